@@ -14,15 +14,15 @@ use objc::msg_send;
 use objc::sel;
 use objc::sel_impl;
 use objc::declare::ClassDecl;
-use objc::runtime::{Class, Object, Sel, objc_retain, object_getClass, BOOL, YES};
+use objc::runtime::{Class, Object, Sel, object_getClass, BOOL, YES};
 use cocoa::base::{id, nil};
 use state::Storage;
 use std::sync::{RwLock, RwLockReadGuard, LockResult, RwLockWriteGuard};
 use crate::{debug_log};
 use matrix_kit::vector_float4;
-use metal_kit::{MetalDevice, MTLResourceCPUCacheModeDefaultCache};
+use metal_kit::{MetalDevice, MetalResourceOptions};
 use metal_kit::MetalRenderPipelineDescriptor;
-use cocoa::foundation::{NSString, NSAutoreleasePool, NSSize};
+use cocoa::foundation::{NSAutoreleasePool, NSSize};
 use metal_kit::MetalRenderPipelineState;
 use metal_kit::MetalCommandQueue;
 use core_animation::{CoreAnimMetalLayer, MTLPixelFormatBGRA8Unorm};
@@ -33,7 +33,6 @@ use metal_kit::MTLPrimitiveTypeTriangle;
 use std::pin::Pin;
 
 extern {
-    fn NSLogv(fmt: id, ...);
     #[allow(non_upper_case_globals)]
     static NSRunLoopCommonModes: id;
 }
@@ -204,12 +203,28 @@ impl MetalViewRust {
     fn make_pipeline(&mut self) {
         let pool = unsafe {NSAutoreleasePool::new(nil) };
         // id<MTLLibrary> library = [device newDefaultLibrary];
-        let library = self.device.new_default_library();
+        let library = match self.device.new_default_library() {
+            Ok(library) => library,
+            Err(error) => {
+                debug_log(format!("Error occurred when loading the default library: {}", error).as_str());
+                unsafe { pool.drain() };
+                return;
+            }
+        };
         //
         // id<MTLFunction> vertexFunc = [library newFunctionWithName:@"vertex_main"];
         // id<MTLFunction> fragmentFunc = [library newFunctionWithName:@"fragment_main"];
-        let vertex_function = library.new_function_with_name("vertex_main");
-        let fragment_function = library.new_function_with_name("fragment_main");
+        let (vertex_function, fragment_function) = match (
+            library.new_function_with_name("vertex_main"),
+            library.new_function_with_name("fragment_main"),
+        ) {
+            (Ok(vertex_function), Ok(fragment_function)) => (vertex_function, fragment_function),
+            (Err(error), _) | (_, Err(error)) => {
+                debug_log(format!("Error occurred when loading shader functions: {}", error).as_str());
+                unsafe { pool.drain() };
+                return;
+            }
+        };
         //
         // MTLRenderPipelineDescriptor *pipelineDescriptor = [MTLRenderPipelineDescriptor new];
         let mut pipeline_descriptor = MetalRenderPipelineDescriptor::new();
@@ -217,8 +232,8 @@ impl MetalViewRust {
         pipeline_descriptor.set_color_attachment_pixel_format(0, MTLPixelFormatBGRA8Unorm);
         // pipelineDescriptor.vertexFunction = vertexFunc;
         // pipelineDescriptor.fragmentFunction = fragmentFunc;
-        pipeline_descriptor.set_vertex_function(vertex_function);
-        pipeline_descriptor.set_fragment_function(fragment_function);
+        pipeline_descriptor.set_vertex_function(&vertex_function);
+        pipeline_descriptor.set_fragment_function(&fragment_function);
         //
         // NSError *error = nil;
         // _pipeline = [device newRenderPipelineStateWithDescriptor:pipelineDescriptor
@@ -230,9 +245,7 @@ impl MetalViewRust {
         // }
         let pipeline_result = self.device.new_render_pipeline_state_with_descriptor(pipeline_descriptor);
         if let Err(error) = pipeline_result {
-            let format = unsafe { NSString::alloc(nil).init_str("Error occurred when creating render pipeline state: %@") };
-            let format = unsafe { objc_retain(format) };
-            unsafe { NSLogv(format, error); }
+            debug_log(format!("Error occurred when creating render pipeline state: {}", error).as_str());
         } else {
             self.pipeline = pipeline_result.unwrap();
             //
@@ -264,7 +277,8 @@ impl MetalViewRust {
         // _vertexBuffer = [device newBufferWithBytes:vertices
         //                                     length:sizeof(vertices)
         //                                    options:MTLResourceOptionCPUCacheModeDefault];
-        self.vertex_buffer = self.device.new_buffer_with_bytes_and_options(&vertices, MTLResourceCPUCacheModeDefaultCache)
+        self.vertex_buffer = self.device.new_buffer_with_bytes_and_options(&vertices, MetalResourceOptions::default())
+            .expect("Unable to make vertex buffer")
     }
     // }
     // /// Makes a display link timer that will fire when the main display syncs
@@ -13,7 +13,9 @@
 //
 
 use state::Storage;
-use std::sync::{RwLock, LockResult, RwLockReadGuard, RwLockWriteGuard};
+use std::sync::{Mutex, RwLock, LockResult, RwLockReadGuard, RwLockWriteGuard};
+use std::ffi::CStr;
+use std::path::{Path, PathBuf};
 use objc::class;
 use objc::msg_send;
 use objc::sel;
@@ -21,13 +23,13 @@ use objc::sel_impl;
 use objc::{Encode, Encoding};
 use objc::declare::ClassDecl;
 use cocoa::base::{id, nil};
-use objc::runtime::{Object, Sel, Class, object_getClass, NO, BOOL, YES};
+use objc::runtime::{Object, Sel, Class, object_getClass, BOOL, YES};
 use core_animation::{CoreAnimMetalLayer, MTLPixelFormat, CoreAnimMetalDrawable, MTLPixelFormatBGRA8Unorm, CoreAnimDisplayLink};
 use crate::mbe_items::RustMBERenderer;
 use cocoa::foundation::{NSInteger, NSTimeInterval, NSUInteger, NSRect, NSSize};
-use metal_kit::{MetalClearColor, MetalRenderPassDescriptor, MetalDevice, MetalTexture, MetalTextureDescriptor, MTLPixelFormatDepth32Float, MTLTextureUsageRenderTarget, MTLStorageModePrivate, MTLStoreActionStore, MTLLoadActionClear, MTLStoreActionDontCare};
+use metal_kit::{MetalClearColor, MetalRenderPassDescriptor, MetalDevice, MetalTexture, MTLPixelFormatDepth32Float, MTLPixelFormatDepth24Unorm_Stencil8, MTLPixelFormatDepth32Float_Stencil8, pixel_format_has_stencil, MTLStoreActionStore, MTLLoadActionClear, MTLStoreActionDontCare, MetalRenderTargetSpec, MetalRenderTargetCache, choose_sample_count};
 use crate::{debug_log};
-use cocoa::foundation::NSAutoreleasePool;
+use cocoa::foundation::{NSAutoreleasePool, NSString};
 use core_graphics::geometry::CGSize;
 
 // THIS PART IS NECESSARY BECAUSE CGRECT DOESN'T IMPLEMENT ENCODING
@@ -97,6 +99,9 @@ use core_graphics::geometry::CGSize;
 // @protocol MetalViewDelegate;
 //
 // @interface MetalView : UIView
+/// The number of samples per pixel we ask for, to smooth the cube's edges.
+/// Devices that cannot do this many get the largest supported count below it.
+const MBE_PREFERRED_SAMPLE_COUNT: NSUInteger = 4;
 //
 /// An implementation of an UIView for Metal, in Rust
 #[derive(Default)]
@@ -146,9 +151,20 @@ pub struct RustMetalView {
     // @property (strong) id<CAMetalDrawable> currentDrawable;
     // @property (assign) NSTimeInterval frameDuration;
     // @property (strong) id<MTLTexture> depthTexture;
-    depth_texture: MetalTexture,
+    depth_texture: MetalRenderTargetCache<MetalTexture>,
+    /// The pixel format of the depth texture,
+    /// which may include a stencil component.
+    depth_pixel_format: MTLPixelFormat,
+    /// The number of samples in each pixel of the color and depth textures.
+    /// Above 1, the drawable's texture is the resolve target of a multisample color texture.
+    sample_count: NSUInteger,
+    /// The multisample color texture, used when the sample count is above 1.
+    multisample_color_texture: MetalRenderTargetCache<MetalTexture>,
     // @property (strong) CADisplayLink *displayLink;
     display_link: CoreAnimDisplayLink,
+    /// Where to save the next frame as a PNG, if a capture has been requested,
+    /// and whether the layer was framebuffer only before the request.
+    frame_capture_request: Mutex<Option<(PathBuf, bool)>>,
     // @end
 }
 //
@@ -235,6 +251,17 @@ impl RustMetalView {
                 set_color_pixel_format as extern "C" fn(&mut Object, Sel, MTLPixelFormat)
             );
             //
+            // /// The pixel format of the depth (and stencil) attachment
+            // @property (nonatomic) MTLPixelFormat depthPixelFormat;
+            class_decl.add_method(
+                sel!(depthPixelFormat),
+                get_depth_pixel_format as extern "C" fn(&Object, Sel) -> MTLPixelFormat,
+            );
+            class_decl.add_method(
+                sel!(setDepthPixelFormat:),
+                set_depth_pixel_format as extern "C" fn(&mut Object, Sel, MTLPixelFormat)
+            );
+            //
             // /// The color to which the color attachment should be cleared at the start of
             // /// a rendering pass
             // @property (nonatomic, assign) MTLClearColor clearColor;
@@ -302,6 +329,10 @@ impl RustMetalView {
                 sel!(displayLinkDidFire:),
                 display_link_did_fire as extern "C" fn(&mut Object, Sel, id),
             );
+            class_decl.add_method(
+                sel!(captureFrameToPath:),
+                capture_frame_to_path as extern "C" fn(&mut Object, Sel, id),
+            );
             class_decl.register();
         }
     }
@@ -315,35 +346,58 @@ impl RustMetalView {
         // if ([self.depthTexture width] != drawableSize.width ||
         //     [self.depthTexture height] != drawableSize.height)
         // {
-        if self.depth_texture.is_null()
-            || (self.depth_texture.get_width() != drawable_size.width as NSUInteger)
-            || (self.depth_texture.get_height() != drawable_size.height as NSUInteger)
-        {
-            // MTLTextureDescriptor *desc = [MTLTextureDescriptor texture2DDescriptorWithPixelFormat:MTLPixelFormatDepth32Float
-            //                                                                                 width:drawableSize.width
-            //                                                                                height:drawableSize.height
-            //                                                                             mipmapped:NO];
-            let mut descriptor = MetalTextureDescriptor::texture_2d_descriptor_with_pixel_format_and_width_and_height_and_mipmapped(
-                MTLPixelFormatDepth32Float,
-                drawable_size.width as _,
-                drawable_size.height as _,
-                NO
-            );
-            // desc.usage = MTLTextureUsageRenderTarget;
-            // desc.storageMode = MTLStorageModePrivate;
-            descriptor.set_usage(MTLTextureUsageRenderTarget);
-            descriptor.set_storage_mode(MTLStorageModePrivate);
-            //
-            // self.depthTexture = [self.metalLayer.device newTextureWithDescriptor:desc];
-            let device = MetalDevice::from(self.metal_layer.get_device());
-            let texture = device.new_texture_with_descriptor(descriptor);
-            self.depth_texture = texture;
-            let debug_message = format!("view depth texture set to {:?}", &self.depth_texture.to_objc());
-            debug_log(debug_message.as_str());
+        //
+        // Multisample depth textures are needed when the color texture is multisampled,
+        // so the check is on the whole specification rather than just the size.
+        let spec = MetalRenderTargetSpec::new(
+            drawable_size.width as _,
+            drawable_size.height as _,
+            self.depth_pixel_format,
+            self.sample_count
+        );
+        if spec.is_empty() {
+            return;
+        }
+        // MTLTextureDescriptor *desc = [MTLTextureDescriptor texture2DDescriptorWithPixelFormat:MTLPixelFormatDepth32Float
+        //                                                                                 width:drawableSize.width
+        //                                                                                height:drawableSize.height
+        //                                                                             mipmapped:NO];
+        // desc.usage = MTLTextureUsageRenderTarget;
+        // desc.storageMode = MTLStorageModePrivate;
+        //
+        // self.depthTexture = [self.metalLayer.device newTextureWithDescriptor:desc];
+        let device = MetalDevice::from(self.metal_layer.get_device());
+        let result = self.depth_texture.update(spec, |spec| {
+            device.new_texture_with_descriptor(spec.new_texture_descriptor())
+        });
+        if let Err(error) = result {
+            debug_log(format!("Unable to make depth texture: {}", error).as_str());
         }
         // }
     }
     // }
+    /// Makes (or drops) the multisample color texture
+    /// to match the drawable size and sample count.
+    fn make_multisample_color_texture(&mut self) {
+        let drawable_size = self.metal_layer.get_drawable_size();
+        let spec = MetalRenderTargetSpec::new(
+            drawable_size.width as _,
+            drawable_size.height as _,
+            self.metal_layer.get_pixel_format(),
+            self.sample_count
+        );
+        if !spec.is_multisampled() || spec.is_empty() {
+            self.multisample_color_texture.clear();
+            return;
+        }
+        let device = MetalDevice::from(self.metal_layer.get_device());
+        let result = self.multisample_color_texture.update(spec, |spec| {
+            device.new_texture_with_descriptor(spec.new_texture_descriptor())
+        });
+        if let Err(error) = result {
+            debug_log(format!("Unable to make multisample color texture: {}", error).as_str());
+        }
+    }
     /// Gets the metal layer.
     #[inline]
     pub fn get_metal_layer(&self) -> &CoreAnimMetalLayer {
@@ -354,10 +408,81 @@ impl RustMetalView {
     pub fn get_current_drawable(&self) -> &CoreAnimMetalDrawable { &self.current_drawable }
     /// Gets a pointer to the depth texture.
     #[inline]
-    pub fn get_depth_texture(&self) -> &MetalTexture { &self.depth_texture }
+    pub fn get_depth_texture(&self) -> &MetalTexture { self.depth_texture.texture() }
+    /// Gets the pixel format of the depth texture.
+    #[inline]
+    pub fn get_depth_pixel_format(&self) -> MTLPixelFormat { self.depth_pixel_format }
+    /// Gets the number of samples in each pixel of the color and depth textures.
+    #[inline]
+    pub fn get_sample_count(&self) -> NSUInteger { self.sample_count }
+    /// Sets the color attachment at index 0 of the given pass descriptor
+    /// to render into the current drawable,
+    /// through the multisample color texture if the sample count is above 1.
+    pub fn set_color_attachment_targets(&self, pass_descriptor: &mut MetalRenderPassDescriptor) {
+        match self.multisample_color_texture.spec() {
+            Some(spec) if spec.sample_count == self.sample_count => {
+                pass_descriptor.set_color_attachments_texture(0, self.multisample_color_texture.texture().to_objc());
+                pass_descriptor.set_color_attachments_resolve_texture(0, self.current_drawable.get_texture());
+                pass_descriptor.set_color_attachments_store_action(0, spec.color_store_action());
+            }
+            _ => {
+                pass_descriptor.set_color_attachments_texture(0, self.current_drawable.get_texture());
+                pass_descriptor.set_color_attachments_store_action(0, MTLStoreActionStore);
+            }
+        }
+    }
+    /// Sets the pixel format of the depth texture,
+    /// e.g. Depth32Float_Stencil8 to get a stencil buffer as well.
+    ///
+    /// Depth24Unorm_Stencil8 falls back to Depth32Float_Stencil8
+    /// on devices that do not support it.
+    /// The depth texture and the delegate's pipeline are remade straight away to match.
+    pub fn set_depth_pixel_format(&mut self, depth_pixel_format: MTLPixelFormat) {
+        let device = MetalDevice::from(self.metal_layer.get_device());
+        self.depth_pixel_format = if depth_pixel_format == MTLPixelFormatDepth24Unorm_Stencil8
+            && !device.is_depth24_stencil8_pixel_format_supported() {
+            MTLPixelFormatDepth32Float_Stencil8
+        } else {
+            depth_pixel_format
+        };
+        self.make_depth_texture();
+        if let Err(error) = self.delegate.remake_pipeline(self.sample_count, self.depth_pixel_format) {
+            debug_log(format!("Unable to remake pipeline: {}", error).as_str());
+        }
+    }
     /// Gets the frame duration.
     #[inline]
     pub fn get_frame_duration(&self) -> NSTimeInterval { self.frame_duration }
+    /// Asks for the next frame to be saved to the given path as a PNG,
+    /// once the GPU has finished drawing it.
+    ///
+    /// The layer stops being framebuffer only until the capture is taken,
+    /// so that drawables can be copied.
+    pub fn request_frame_capture(&mut self, path: &Path) {
+        if let Ok(mut frame_capture_request) = self.frame_capture_request.lock() {
+            let framebuffer_only = match frame_capture_request.take() {
+                Some((_, framebuffer_only)) => framebuffer_only,
+                None => self.metal_layer.get_framebuffer_only(),
+            };
+            self.metal_layer.set_framebuffer_only(false);
+            *frame_capture_request = Some((path.to_path_buf(), framebuffer_only));
+        }
+    }
+    /// Takes the pending frame capture request, if there is one and the current drawable can be copied,
+    /// for the delegate to copy the current drawable into a `TextureReadback`.
+    ///
+    /// The layer goes back to being framebuffer only (if it was) once the request is taken.
+    pub fn take_frame_capture_request(&self) -> Option<PathBuf> {
+        let mut frame_capture_request = self.frame_capture_request.lock().ok()?;
+        frame_capture_request.as_ref()?;
+        // A drawable made before the request may still be framebuffer only; wait for the next one.
+        if MetalTexture::from(self.current_drawable.get_texture()).is_framebuffer_only() {
+            return None;
+        }
+        let (path, framebuffer_only) = frame_capture_request.take()?;
+        self.metal_layer.set_framebuffer_only(framebuffer_only);
+        Some(path)
+    }
     // - (MTLRenderPassDescriptor *)currentRenderPassDescriptor
     // {
     fn current_render_pass_descriptor(&self) -> id {
@@ -368,9 +493,8 @@ impl RustMetalView {
         // passDescriptor.colorAttachments[0].clearColor = self.clearColor;
         // passDescriptor.colorAttachments[0].storeAction = MTLStoreActionStore;
         // passDescriptor.colorAttachments[0].loadAction = MTLLoadActionClear;
-        pass_descriptor.set_color_attachments_texture(0, self.current_drawable.get_texture());
+        self.set_color_attachment_targets(&mut pass_descriptor);
         pass_descriptor.set_color_attachments_clear_color(0, self.clear_color);
-        pass_descriptor.set_color_attachments_store_action(0, MTLStoreActionStore);
         pass_descriptor.set_color_attachments_load_action(0, MTLLoadActionClear);
         //
         // passDescriptor.depthAttachment.texture = self.depthTexture;
@@ -378,11 +502,20 @@ impl RustMetalView {
         // passDescriptor.depthAttachment.loadAction = MTLLoadActionClear;
         // passDescriptor.depthAttachment.storeAction = MTLStoreActionDontCare;
         let mut depth_attachment = pass_descriptor.get_depth_attachment();
-        depth_attachment.set_texture(&self.depth_texture);
+        depth_attachment.set_texture(self.depth_texture.texture());
         depth_attachment.set_clear_depth(1.);
         depth_attachment.set_load_action(MTLLoadActionClear);
         depth_attachment.set_store_action(MTLStoreActionDontCare);
         //
+        // The stencil attachment shares the depth texture, if it has a stencil component.
+        if pixel_format_has_stencil(self.depth_pixel_format) {
+            let mut stencil_attachment = pass_descriptor.get_stencil_attachment();
+            stencil_attachment.set_texture(self.depth_texture.texture());
+            stencil_attachment.set_clear_stencil(0);
+            stencil_attachment.set_load_action(MTLLoadActionClear);
+            stencil_attachment.set_store_action(MTLStoreActionDontCare);
+        }
+        //
         // return passDescriptor;
         pass_descriptor.to_objc()
     }
@@ -514,6 +647,7 @@ extern "C" fn init_with_coder(_self: &mut Object, _sel: Sel, _coder: id) -> id {
         // [self commonInit]; // TOD: brought lines here
         // _preferredFramesPerSecond = 60;
         rust_metal_view.preferred_frames_per_second = 60;
+        rust_metal_view.depth_pixel_format = MTLPixelFormatDepth32Float;
         // _clearColor = MTLClearColorMake(1, 1, 1, 1);
         rust_metal_view.clear_color = MetalClearColor::make(1., 1., 1., 1.);
         //
@@ -527,7 +661,12 @@ extern "C" fn init_with_coder(_self: &mut Object, _sel: Sel, _coder: id) -> id {
         let system_default_device = MetalDevice::create_system_default_device();
         layer.set_device(system_default_device.to_objc());
         rust_metal_view.metal_layer = layer;
-        rust_metal_view.delegate = RustMBERenderer::new().expect("Failed to make renderer");
+        rust_metal_view.sample_count = choose_sample_count(
+            MBE_PREFERRED_SAMPLE_COUNT,
+            |sample_count| system_default_device.supports_texture_sample_count(sample_count)
+        );
+        rust_metal_view.delegate = RustMBERenderer::new(rust_metal_view.sample_count, rust_metal_view.depth_pixel_format)
+            .expect("Failed to make renderer");
     }
     if _self != nil {
         let _self = unsafe {_self.as_mut()}.unwrap();
@@ -632,6 +771,7 @@ extern "C" fn set_frame(_self: &mut Object, _sel: Sel, frame: NSRect) {
     //
     // [self makeDepthTexture];
     rust_metal_view.make_depth_texture();
+    rust_metal_view.make_multisample_color_texture();
     unsafe { pool.drain() }
 }
 // }
@@ -654,6 +794,18 @@ extern "C" fn get_color_pixel_format(_self: &Object, _sel: Sel) -> MTLPixelForma
     rust_metal_view.color_pixel_format
 }
 //
+// - (void)setDepthPixelFormat:(MTLPixelFormat)depthPixelFormat
+extern "C" fn set_depth_pixel_format(_self: &mut Object, _sel: Sel, depth_pixel_format: MTLPixelFormat) {
+    let mut rust_metal_view = get_mut_rust_metal_view(_self).unwrap();
+    rust_metal_view.set_depth_pixel_format(depth_pixel_format)
+}
+//
+// - (MTLPixelFormat)depthPixelFormat
+extern "C" fn get_depth_pixel_format(_self: &Object, _sel: Sel) -> MTLPixelFormat {
+    let rust_metal_view = get_rust_metal_view(_self).unwrap();
+    rust_metal_view.depth_pixel_format
+}
+//
 // - (void)displayLinkDidFire:(CADisplayLink *)displayLink
 // {
 extern "C" fn display_link_did_fire(_self: &mut Object, _sel: Sel, _display_link: id) {
@@ -678,6 +830,20 @@ extern "C" fn display_link_did_fire(_self: &mut Object, _sel: Sel, _display_link
 // }
 //
 //
+// Saves the next frame to the given path as a PNG, for bug reports and regression tests.
+extern "C" fn capture_frame_to_path(_self: &mut Object, _sel: Sel, path: id) {
+    if path == nil {
+        return;
+    }
+    let bytes = unsafe { path.UTF8String() };
+    if bytes.is_null() {
+        return;
+    }
+    let path = unsafe { CStr::from_ptr(bytes) }.to_string_lossy().into_owned();
+    let mut rust_metal_view = get_mut_rust_metal_view(_self).unwrap();
+    rust_metal_view.request_frame_capture(Path::new(&path));
+}
+//
 // - (MTLRenderPassDescriptor *)currentRenderPassDescriptor
 // {
 extern "C" fn get_current_render_pass_descriptor(_self: &Object, _sel: Sel) -> id {
@@ -13,15 +13,15 @@
 //
 
 use crate::mbe_items::mbe_metal_view::{RustMetalViewDelegate, RustMetalView};
-use metal_kit::{MetalDevice, MetalBuffer, MetalCommandQueue, MetalRenderPipelineState, MetalDeviceError, MetalRenderPipelineDescriptor, MTLPixelFormatDepth32Float, MetalDepthStencilDescriptor, MTLCompareFunctionLess, MetalDepthStencilState, MTLResourceCPUCacheModeDefaultCache, MetalClearColor, MetalRenderPassDescriptor, MTLStoreActionStore, MTLLoadActionClear, MTLStoreActionDontCare, MTLWindingCounterClockwise, MTLCullModeBack, MTLPrimitiveTypeTriangle, MTLIndexTypeUInt16, MTLIndexType};
-use cocoa::foundation::{NSInteger, NSTimeInterval};
-use std::os::raw::{c_float,c_void};
-use core_animation::{DispatchSemaphore, MTLPixelFormatBGRA8Unorm, DISPATCH_TIME_FOREVER};
+use metal_kit::{MetalDevice, TypedBuffer, FrameRingBuffer, MetalCommandQueue, MetalRenderPipelineState, MetalError, MetalRenderPipelineDescriptor, MetalDepthStencilDescriptor, MTLCompareFunctionLess, MetalDepthStencilState, MetalResourceOptions, MetalClearColor, MetalRenderPassDescriptor, MTLLoadActionClear, MTLStoreActionDontCare, MTLWindingCounterClockwise, MTLCullModeBack, MTLPrimitiveTypeTriangle, MTLIndexTypeUInt16, MTLIndexType, pixel_format_has_stencil, ExpectedBufferLayout, MTLDataTypeFloat4, MTLDataTypeFloat4x4, MetalTexture, TextureReadback};
+use cocoa::foundation::{NSInteger, NSTimeInterval, NSUInteger};
+use std::mem::offset_of;
+use std::os::raw::c_float;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use core_animation::{DispatchSemaphore, MTLPixelFormat, MTLPixelFormatBGRA8Unorm, DISPATCH_TIME_FOREVER};
 use objc::runtime::YES;
 use crate::debug_log;
 use matrix_kit::{vector_float3, vector_float4, matrix_float4x4};
-use std::mem::size_of;
-use cocoa::base::id;
 
 // Use the Apple versions rather than linking in the Rust stdlib for them.
 extern {
@@ -70,6 +70,8 @@ const MBE_INDEX_TYPE: MTLIndexType = MTLIndexTypeUInt16;
 //     vector_float4 position;
 //     vector_float4 color;
 // } MBEVertex;
+#[derive(Copy, Clone)]
+#[repr(C)]
 struct MBEVertex {
     position: vector_float4,
     color: vector_float4,
@@ -87,6 +89,8 @@ impl From<[f32;8]> for MBEVertex {
 // {
 //     matrix_float4x4 modelViewProjectionMatrix;
 // } MBEUniforms;
+#[derive(Copy, Clone)]
+#[repr(C)]
 struct MBEUniforms {
     model_view_projection_matrix: matrix_float4x4,
 }
@@ -94,15 +98,13 @@ struct MBEUniforms {
 // static inline uint64_t AlignUp(uint64_t n, uint32_t alignment) {
 //     return ((n + alignment - 1) / alignment) * alignment;
 // }
-/// Aligns the given address to the given alignment
-#[inline]
-fn align_up(n: u64, alignment: u32) -> u64 {
-    let alignment = alignment as u64;
-    ((n + alignment - 1) / alignment) * alignment
-}
+// (FrameRingBuffer does the alignment for us.)
 //
 // static const uint32_t MBEBufferAlignment = 256;
-const MBE_BUFFER_ALIGNMENT: u32 = 256;
+const MBE_BUFFER_ALIGNMENT: NSUInteger = 256;
+
+/// The vertex, index and uniform buffers made by `make_buffers`
+type MBEBuffers = (TypedBuffer<MBEVertex>, TypedBuffer<MBEIndex>, FrameRingBuffer<MBEUniforms>);
 //
 // @interface MBERenderer ()
 /// The renderer for our 3D cube
@@ -111,11 +113,12 @@ pub struct RustMBERenderer {
     // @property (strong) id<MTLDevice> device;
     device: MetalDevice,
     // @property (strong) id<MTLBuffer> vertexBuffer;
-    vertex_buffer: MetalBuffer,
+    vertex_buffer: TypedBuffer<MBEVertex>,
     // @property (strong) id<MTLBuffer> indexBuffer;
-    index_buffer: MetalBuffer,
+    index_buffer: TypedBuffer<MBEIndex>,
     // @property (strong) id<MTLBuffer> uniformBuffer;
-    uniform_buffer: MetalBuffer,
+    // (behind a lock, as we draw through a shared reference)
+    uniform_buffer: Mutex<FrameRingBuffer<MBEUniforms>>,
     // @property (strong) id<MTLCommandQueue> commandQueue;
     command_queue: MetalCommandQueue,
     // @property (strong) id<MTLRenderPipelineState> renderPipelineState;
@@ -123,9 +126,9 @@ pub struct RustMBERenderer {
     // @property (strong) id<MTLDepthStencilState> depthStencilState;
     depth_stencil_state: MetalDepthStencilState,
     // @property (strong) dispatch_semaphore_t displaySemaphore;
-    display_semaphore: DispatchSemaphore,
+    display_semaphore: Arc<DispatchSemaphore>,
     // @property (assign) NSInteger bufferIndex;
-    buffer_index: NSInteger,
+    // (the uniform buffer keeps track of its own index)
     // @property (assign) float rotationX, rotationY, time;
     rotation_x: c_float,
     rotation_y: c_float,
@@ -151,8 +154,10 @@ pub struct RustMBERenderer {
 //
 // @implementation MBERenderer
 impl RustMBERenderer {
-    /// Creates a new MBE Renderer
-    pub fn new() -> Result<Self, MetalDeviceError> {
+    /// Creates a new MBE Renderer,
+    /// drawing into textures with the given number of samples per pixel
+    /// and the given depth (and maybe stencil) pixel format.
+    pub fn new(sample_count: NSUInteger, depth_pixel_format: MTLPixelFormat) -> Result<Self, MetalError> {
         // if ((self = [super init]))
         // {
         //     _device = MTLCreateSystemDefaultDevice();
@@ -163,27 +168,26 @@ impl RustMBERenderer {
         //
         // return self;
         let mut device = MetalDevice::create_system_default_device();
-        let display_semaphore = DispatchSemaphore::create(MBE_IN_FLIGHT_BUFFER_COUNT);
+        let display_semaphore = Arc::new(DispatchSemaphore::create(MBE_IN_FLIGHT_BUFFER_COUNT));
         let (
             command_queue,
             depth_stencil_state,
             render_pipeline_state
-        ) = Self::make_pipeline(&mut device)?;
+        ) = Self::make_pipeline(&mut device, sample_count, depth_pixel_format)?;
         let (
             vertex_buffer,
             index_buffer,
             uniform_buffer
-        ) = Self::make_buffers(&mut device);
+        ) = Self::make_buffers(&mut device)?;
         Ok(RustMBERenderer {
             device,
             vertex_buffer,
             index_buffer,
-            uniform_buffer,
+            uniform_buffer: Mutex::new(uniform_buffer),
             command_queue,
             render_pipeline_state,
             depth_stencil_state,
             display_semaphore,
-            buffer_index: 0,
             rotation_x: 0.0,
             rotation_y: 0.0,
             time: 0.0
@@ -205,22 +209,31 @@ impl RustMBERenderer {
     //
     // - (void)makePipeline
     // {
-    fn make_pipeline(device: &mut MetalDevice) -> Result<(MetalCommandQueue, MetalDepthStencilState, MetalRenderPipelineState), MetalDeviceError> {
+    fn make_pipeline(
+        device: &mut MetalDevice,
+        sample_count: NSUInteger,
+        depth_pixel_format: MTLPixelFormat
+    ) -> Result<(MetalCommandQueue, MetalDepthStencilState, MetalRenderPipelineState), MetalError> {
         // self.commandQueue = [self.device newCommandQueue];
         //
         // id<MTLLibrary> library = [self.device newDefaultLibrary];
-        let library = device.new_default_library();
+        let library = device.new_default_library()?;
         //
         // MTLRenderPipelineDescriptor *pipelineDescriptor = [MTLRenderPipelineDescriptor new];
         let mut pipeline_descriptor = MetalRenderPipelineDescriptor::new();
         // pipelineDescriptor.vertexFunction = [library newFunctionWithName:@"vertex_project"];
         // pipelineDescriptor.fragmentFunction = [library newFunctionWithName:@"fragment_flatcolor"];
-        pipeline_descriptor.set_vertex_function(library.new_function_with_name("vertex_project"));
-        pipeline_descriptor.set_fragment_function(library.new_function_with_name("fragment_flatcolor"));
+        pipeline_descriptor.set_vertex_function(&library.new_function_with_name("vertex_project")?);
+        pipeline_descriptor.set_fragment_function(&library.new_function_with_name("fragment_flatcolor")?);
         // pipelineDescriptor.colorAttachments[0].pixelFormat = MTLPixelFormatBGRA8Unorm;
         // pipelineDescriptor.depthAttachmentPixelFormat = MTLPixelFormatDepth32Float;
         pipeline_descriptor.set_color_attachment_pixel_format(0, MTLPixelFormatBGRA8Unorm);
-        pipeline_descriptor.set_depth_attachment_pixel_format(MTLPixelFormatDepth32Float);
+        // (the view's depth texture may have a stencil component too, which the pipeline has to match)
+        pipeline_descriptor.set_depth_attachment_pixel_format(depth_pixel_format);
+        if pixel_format_has_stencil(depth_pixel_format) {
+            pipeline_descriptor.set_stencil_attachment_pixel_format(depth_pixel_format);
+        }
+        pipeline_descriptor.set_sample_count(sample_count);
         //
         // MTLDepthStencilDescriptor *depthStencilDescriptor = [MTLDepthStencilDescriptor new];
         let mut depth_stencil_descriptor = MetalDepthStencilDescriptor::new();
@@ -230,7 +243,7 @@ impl RustMBERenderer {
         // self.depthStencilState = [self.device newDepthStencilStateWithDescriptor:depthStencilDescriptor];
         depth_stencil_descriptor.set_depth_compare_function(MTLCompareFunctionLess);
         depth_stencil_descriptor.set_depth_write_enabled(YES);
-        let depth_stencil_state = device.new_depth_stencil_state_with_descriptor(depth_stencil_descriptor);
+        let depth_stencil_state = device.new_depth_stencil_state_with_descriptor(depth_stencil_descriptor)?;
         //
         // NSError *error = nil;
         // self.renderPipelineState = [self.device newRenderPipelineStateWithDescriptor:pipelineDescriptor
@@ -240,7 +253,13 @@ impl RustMBERenderer {
         // {
         //     NSLog(@"Error occurred when creating render pipeline state: %@", error);
         // }
-        let render_pipeline_state = device.new_render_pipeline_state_with_descriptor(pipeline_descriptor)?;
+        let (render_pipeline_state, reflection) = device.new_render_pipeline_state_with_reflection(pipeline_descriptor)?;
+        // Check the Rust structs match the ones vertex_project reads before drawing with them.
+        reflection.check_vertex_buffer(0, &ExpectedBufferLayout::of::<MBEVertex>()
+            .with_member("position", offset_of!(MBEVertex, position) as NSUInteger, MTLDataTypeFloat4)
+            .with_member("color", offset_of!(MBEVertex, color) as NSUInteger, MTLDataTypeFloat4))?;
+        reflection.check_vertex_buffer(1, &ExpectedBufferLayout::of::<MBEUniforms>()
+            .with_member("modelViewProjectionMatrix", 0, MTLDataTypeFloat4x4))?;
         //
         // self.commandQueue = [self.device newCommandQueue];
         Ok((device.new_command_queue(), depth_stencil_state, render_pipeline_state))
@@ -249,7 +268,7 @@ impl RustMBERenderer {
     //
     // - (void)makeBuffers
     // {
-    fn make_buffers(device: &mut MetalDevice) -> (MetalBuffer, MetalBuffer, MetalBuffer) {
+    fn make_buffers(device: &mut MetalDevice) -> Result<MBEBuffers, MetalError> {
         // static const MBEVertex vertices[] =
         // {
         //     { .position = { -1,  1,  1, 1 }, .color = { 0, 1, 1, 1 } },
@@ -302,38 +321,54 @@ impl RustMBERenderer {
         //                                          length:sizeof(vertices)
         //                                         options:MTLResourceOptionCPUCacheModeDefault];
         // [_vertexBuffer setLabel:@"Vertices"];
-        let mut vertex_buffer = device.new_buffer_with_bytes_and_options(
+        let mut vertex_buffer = TypedBuffer::new_with_slice(
+            device,
             &vertices,
-            MTLResourceCPUCacheModeDefaultCache // same as MTLResourceOptionCPUCacheModeDefault
-        );
-        vertex_buffer.set_label("Vertices");
+            MetalResourceOptions::default() // same as MTLResourceOptionCPUCacheModeDefault
+        )?;
+        vertex_buffer.get_storage_mut().set_label("Vertices");
         //
         // _indexBuffer = [self.device newBufferWithBytes:indices
         //                                         length:sizeof(indices)
         //                                        options:MTLResourceOptionCPUCacheModeDefault];
         // [_indexBuffer setLabel:@"Indices"];
-        let mut index_buffer = device.new_buffer_with_bytes_and_options(
+        let mut index_buffer = TypedBuffer::new_with_slice(
+            device,
             &indices,
-            MTLResourceCPUCacheModeDefaultCache,
-        );
-        index_buffer.set_label("Indices");
+            MetalResourceOptions::default(),
+        )?;
+        index_buffer.get_storage_mut().set_label("Indices");
         //
         // _uniformBuffer = [self.device newBufferWithLength:AlignUp(sizeof(MBEUniforms), MBEBufferAlignment) * MBEInFlightBufferCount
         //                                           options:MTLResourceOptionCPUCacheModeDefault];
         // [_uniformBuffer setLabel:@"Uniforms"];
-        let length = size_of::<MBEUniforms>();
-        let length = align_up(length as _, MBE_BUFFER_ALIGNMENT) * (MBE_IN_FLIGHT_BUFFER_COUNT as u64);
-        let length_str = format!("uniform buffer length = {}", length);
+        let mut uniform_buffer = FrameRingBuffer::new_with_alignment(
+            device,
+            MBE_IN_FLIGHT_BUFFER_COUNT as usize,
+            MBE_BUFFER_ALIGNMENT,
+            MetalResourceOptions::default()
+        )?;
+        let length_str = format!("uniform buffer length = {}", uniform_buffer.get_storage().get_length());
         debug_log(length_str.as_str());
-        let mut uniform_buffer = device.new_buffer_with_length_and_options(
-            length as _,
-            MTLResourceCPUCacheModeDefaultCache
-        );
-        uniform_buffer.set_label("Uniforms");
-        (vertex_buffer, index_buffer, uniform_buffer)
+        uniform_buffer.get_storage_mut().set_label("Uniforms");
+        Ok((vertex_buffer, index_buffer, uniform_buffer))
     }
     // }
     //
+    /// Remakes the pipeline for a new depth pixel format (and sample count),
+    /// so it matches the view's attachments.
+    pub fn remake_pipeline(&mut self, sample_count: NSUInteger, depth_pixel_format: MTLPixelFormat) -> Result<(), MetalError> {
+        let (
+            command_queue,
+            depth_stencil_state,
+            render_pipeline_state
+        ) = Self::make_pipeline(&mut self.device, sample_count, depth_pixel_format)?;
+        self.command_queue = command_queue;
+        self.depth_stencil_state = depth_stencil_state;
+        self.render_pipeline_state = render_pipeline_state;
+        Ok(())
+    }
+    //
     // - (void)updateUniformsForView:(MetalView *)view duration:(NSTimeInterval)duration
     // {
     fn update_uniforms_for_view(&self, view: &RustMetalView, _duration: NSTimeInterval) {
@@ -380,15 +415,13 @@ impl RustMBERenderer {
         };
         //
         // const NSUInteger uniformBufferOffset = AlignUp(sizeof(MBEUniforms), MBEBufferAlignment) * self.bufferIndex;
-        let uniform_buffer_offset = self.uniform_buffer_offset();
         // memcpy([self.uniformBuffer contents] + uniformBufferOffset, &uniforms, sizeof(uniforms));
-        let contents = self.uniform_buffer.get_contents() as *mut u8;
-        //+ let print_string = format!("Contents = {:?}", contents);
-        //+ debug_log(print_string.as_str());
-        unsafe {
-            let write_position = contents.offset(uniform_buffer_offset as isize) as *mut MBEUniforms;
-            write_position.write(uniforms);
-        }
+        self.uniform_buffer().write_current(uniforms);
+    }
+    //
+    /// Locks the uniform buffer; it holds plain values, so a poisoned lock is still usable.
+    fn uniform_buffer(&self) -> MutexGuard<'_, FrameRingBuffer<MBEUniforms>> {
+        self.uniform_buffer.lock().unwrap_or_else(PoisonError::into_inner)
     }
     // }
     //
@@ -403,9 +436,8 @@ impl RustMBERenderer {
         // passDescriptor.colorAttachments[0].clearColor = self.clearColor;
         // passDescriptor.colorAttachments[0].storeAction = MTLStoreActionStore;
         // passDescriptor.colorAttachments[0].loadAction = MTLLoadActionClear;
-        pass_descriptor.set_color_attachments_texture(0, view.get_current_drawable().get_texture());
+        view.set_color_attachment_targets(&mut pass_descriptor);
         pass_descriptor.set_color_attachments_clear_color(0, clear_color);
-        pass_descriptor.set_color_attachments_store_action(0, MTLStoreActionStore);
         pass_descriptor.set_color_attachments_load_action(0, MTLLoadActionClear);
         //
         // passDescriptor.depthAttachment.texture = self.depthTexture;
@@ -418,17 +450,18 @@ impl RustMBERenderer {
         depth_attachment.set_load_action(MTLLoadActionClear);
         depth_attachment.set_store_action(MTLStoreActionDontCare);
         //
+        // The stencil attachment shares the depth texture, if it has a stencil component.
+        if pixel_format_has_stencil(view.get_depth_pixel_format()) {
+            let mut stencil_attachment = pass_descriptor.get_stencil_attachment();
+            stencil_attachment.set_texture(view.get_depth_texture());
+            stencil_attachment.set_clear_stencil(0);
+            stencil_attachment.set_load_action(MTLLoadActionClear);
+            stencil_attachment.set_store_action(MTLStoreActionDontCare);
+        }
+        //
         // return passDescriptor;
         pass_descriptor
     }
-    /// We use this a lot
-    #[inline]
-    fn uniform_buffer_offset(&self) -> u64 {
-        align_up(
-            size_of::<MBEUniforms>() as u64,
-            MBE_BUFFER_ALIGNMENT
-        ) * (self.buffer_index as u64)
-    }
 }
 // }
 //
@@ -472,12 +505,13 @@ impl RustMetalViewDelegate for RustMBERenderer {
         render_pass.set_cull_mode(MTLCullModeBack);
         //
         // const NSUInteger uniformBufferOffset = AlignUp(sizeof(MBEUniforms), MBEBufferAlignment) * self.bufferIndex;
-        let uniform_buffer_offset = self.uniform_buffer_offset();
+        let uniform_buffer = self.uniform_buffer();
+        let uniform_buffer_offset = uniform_buffer.current_offset();
         //
         // [renderPass setVertexBuffer:self.vertexBuffer offset:0 atIndex:0];
         // [renderPass setVertexBuffer:self.uniformBuffer offset:uniformBufferOffset atIndex:1];
-        render_pass.set_vertex_buffer(&self.vertex_buffer, 0, 0);
-        render_pass.set_vertex_buffer(&self.uniform_buffer, uniform_buffer_offset, 1);
+        render_pass.set_vertex_buffer(self.vertex_buffer.get_storage(), 0, 0);
+        render_pass.set_vertex_buffer(uniform_buffer.get_storage(), uniform_buffer_offset, 1);
         //
         // [renderPass drawIndexedPrimitives:MTLPrimitiveTypeTriangle
         //                        indexCount:[self.indexBuffer length] / sizeof(MBEIndex)
@@ -486,15 +520,27 @@ impl RustMetalViewDelegate for RustMBERenderer {
         //                 indexBufferOffset:0];
         render_pass.draw_indexed_primitives_with_count_and_type_and_buffer_and_offset (
             MTLPrimitiveTypeTriangle,
-            self.index_buffer.get_length() / (size_of::<MBEIndex>() as u64),
+            self.index_buffer.len() as NSUInteger,
             MBE_INDEX_TYPE,
-            &self.index_buffer,
+            self.index_buffer.get_storage(),
             0
         );
         //
         // [renderPass endEncoding];
         render_pass.end_encoding();
         //
+        // Copy the finished frame out before it is presented, if a capture was asked for.
+        let frame_capture = view.take_frame_capture_request().and_then(|path| {
+            let drawable_texture = MetalTexture::from(view.get_current_drawable().get_texture());
+            match TextureReadback::encode(&command_buffer, &drawable_texture) {
+                Ok(readback) => Some((readback, path)),
+                Err(error) => {
+                    debug_log(format!("Unable to capture frame: {}", error).as_str());
+                    None
+                }
+            }
+        });
+        //
         // [commandBuffer presentDrawable:view.currentDrawable];
         command_buffer.present_drawable(view.get_current_drawable());
         //
@@ -502,16 +548,22 @@ impl RustMetalViewDelegate for RustMBERenderer {
         //     self.bufferIndex = (self.bufferIndex + 1) % MBEInFlightBufferCount;
         //     dispatch_semaphore_signal(self.displaySemaphore);
         // }];
-        let self_addr = self as *const _ as *const c_void;
-        //+ let self_addr_description = format!("self address: {:?}", self_addr);
-        //+ debug_log(self_addr_description.as_str());
-        command_buffer.attach_completion_function(
-            command_buffer_completion_handler_f,
-            self_addr
-        );
+        let display_semaphore = self.display_semaphore.clone();
+        command_buffer.add_completed_handler(move |_| {
+            display_semaphore.signal();
+            if let Some((readback, path)) = frame_capture {
+                if let Err(error) = readback.write_png(&path) {
+                    debug_log(format!("Unable to save frame capture: {}", error).as_str());
+                }
+            }
+        });
         //
         // [commandBuffer commit];
-        command_buffer.commit()
+        command_buffer.commit();
+        //
+        // The buffer index moves on once the frame is queued, rather than when it completes:
+        // the semaphore stops us coming back round to a slot the GPU is still reading.
+        uniform_buffer.advance();
     }
     // }
     // TOD: moved update code to this fn as we can't update
@@ -527,26 +579,3 @@ impl RustMetalViewDelegate for RustMBERenderer {
     }
 
 }
-
-extern "C" fn command_buffer_completion_handler_f(_source: id, user_data: *const c_void)
-    /*where F:Fn(id)*/
-{
-    // [commandBuffer addCompletedHandler:^(id<MTLCommandBuffer> commandBuffer) {
-    //     self.bufferIndex = (self.bufferIndex + 1) % MBEInFlightBufferCount;
-    //     dispatch_semaphore_signal(self.displaySemaphore);
-    // }];
-    //+ let message = format!("In command buffer completion handler f, user_data = {:?}", user_data);
-    //+ debug_log(message.as_str());
-    let user_data_link_ptr = user_data as *mut RustMBERenderer;
-    match unsafe { user_data_link_ptr.as_mut() } {
-        Some(renderer_p) => {
-            // Note: this really shouldn't at this point. should be just
-            // after you pushed the command onto the queue.
-            renderer_p.buffer_index = (renderer_p.buffer_index + 1) % MBE_IN_FLIGHT_BUFFER_COUNT;
-            //+ let message = format!("display semaphore: {:?}", renderer_p.display_semaphore);
-            //+ debug_log(message.as_str());
-            renderer_p.display_semaphore.signal();
-        }
-        None => ()
-    }
-}
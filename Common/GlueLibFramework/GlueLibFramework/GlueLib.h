@@ -29,6 +29,21 @@ typedef void (*dispatch_function_with_user_data_t)(dispatch_source_t _Nonnull ,
 /// each time the command buffer has finished the command.
 typedef void (*command_buffer_completion_function_with_user_data_t)(id<MTLCommandBuffer> _Nonnull, void *_Nullable);
 
+/// @typedef render_pipeline_completion_function_with_user_data_t
+///
+/// @abstract
+/// The signature of the callback which the device will call
+/// once it has finished compiling a render pipeline state.
+/// Exactly one of the pipeline state and the error is non-nil.
+typedef void (*render_pipeline_completion_function_with_user_data_t)(id<MTLRenderPipelineState> _Nullable, NSError *_Nullable, void *_Nullable);
+
+/// @typedef shared_event_notification_function_with_user_data_t
+///
+/// @abstract
+/// The signature of the callback which a shared event's listener will call
+/// once the event reaches the value it was waiting for.
+typedef void (*shared_event_notification_function_with_user_data_t)(id<MTLSharedEvent> _Nonnull, uint64_t, void *_Nullable);
+
 /*!
  * @function dispatch_source_set_event_handler_f_with_user_data
  *
@@ -64,3 +79,45 @@ void command_buffer_add_completed_handler_f_with_user_data
  command_buffer_completion_function_with_user_data_t _Nullable,
  void *_Nullable
  );
+
+/// @function command_buffer_add_scheduled_handler_f_with_user_data
+///
+/// @abstract
+/// This function allows the caller to pass in arbitrary user data which is passed back
+/// when the command buffer has been scheduled to run on the GPU.
+///
+/// This function is a kludge  to allow Rust classes to set up dispatch callbacks.
+void command_buffer_add_scheduled_handler_f_with_user_data
+(id<MTLCommandBuffer> _Nonnull,
+ command_buffer_completion_function_with_user_data_t _Nullable,
+ void *_Nullable
+ );
+
+/// @function device_new_render_pipeline_state_f_with_user_data
+///
+/// @abstract
+/// This function allows the caller to pass in arbitrary user data which is passed back
+/// when the device has finished compiling the render pipeline state on one of its own threads.
+///
+/// This function is a kludge  to allow Rust classes to set up dispatch callbacks.
+void device_new_render_pipeline_state_f_with_user_data
+(id<MTLDevice> _Nonnull,
+ MTLRenderPipelineDescriptor *_Nonnull,
+ render_pipeline_completion_function_with_user_data_t _Nullable,
+ void *_Nullable
+ );
+
+/// @function shared_event_notify_listener_f_with_user_data
+///
+/// @abstract
+/// This function allows the caller to pass in arbitrary user data which is passed back
+/// when the shared event reaches the given value, on the listener's dispatch queue.
+///
+/// This function is a kludge  to allow Rust classes to set up dispatch callbacks.
+void shared_event_notify_listener_f_with_user_data
+(id<MTLSharedEvent> _Nonnull,
+ MTLSharedEventListener *_Nonnull,
+ uint64_t,
+ shared_event_notification_function_with_user_data_t _Nullable,
+ void *_Nullable
+ );
@@ -34,3 +34,39 @@ void command_buffer_add_completed_handler_f_with_user_data
     handler(command_buffer, user_data);
   }];
 }
+
+void command_buffer_add_scheduled_handler_f_with_user_data
+(id<MTLCommandBuffer> _Nonnull command_buffer,
+ command_buffer_completion_function_with_user_data_t _Nullable handler,
+ void *_Nullable user_data
+ ) {
+  [command_buffer addScheduledHandler:^(id<MTLCommandBuffer> _Nonnull command_buffer) {
+    handler(command_buffer, user_data);
+  }];
+}
+
+void device_new_render_pipeline_state_f_with_user_data
+(id<MTLDevice> _Nonnull device,
+ MTLRenderPipelineDescriptor *_Nonnull descriptor,
+ render_pipeline_completion_function_with_user_data_t _Nullable handler,
+ void *_Nullable user_data
+ ) {
+  [device newRenderPipelineStateWithDescriptor:descriptor
+                             completionHandler:^(id<MTLRenderPipelineState> _Nullable state, NSError * _Nullable error) {
+    handler(state, error, user_data);
+  }];
+}
+
+void shared_event_notify_listener_f_with_user_data
+(id<MTLSharedEvent> _Nonnull event,
+ MTLSharedEventListener *_Nonnull listener,
+ uint64_t value,
+ shared_event_notification_function_with_user_data_t _Nullable handler,
+ void *_Nullable user_data
+ ) {
+  [event notifyListener:listener
+                atValue:value
+                  block:^(id<MTLSharedEvent> _Nonnull event, uint64_t value) {
+    handler(event, value, user_data);
+  }];
+}
@@ -13,7 +13,7 @@ use objc::sel_impl;
 use cocoa::base::{id, nil};
 use cocoa::foundation::NSUInteger;
 use crate::core_animation::core_anim_metal_drawable::CoreAnimMetalDrawable;
-use objc::runtime::{objc_retain, objc_release};
+use objc::runtime::{objc_retain, objc_release, BOOL, NO, YES};
 use core_graphics::geometry::CGSize;
 
 // From System/Library/Frameworks/Metal.framework/Versions/A/Headers/MTLPixelFormat.h:
@@ -60,6 +60,22 @@ impl CoreAnimMetalLayer {
     pub fn set_pixel_format(&mut self, pixel_format: MTLPixelFormat) {
         unsafe { msg_send![self.layer, setPixelFormat:pixel_format] }
     }
+    /// Gets the pixel format of the layer’s textures.
+    pub fn get_pixel_format(&self) -> MTLPixelFormat {
+        unsafe { msg_send![self.layer, pixelFormat] }
+    }
+    /// Sets whether the layer's textures can only be used as render targets.
+    /// Turn this off to sample, read or copy from drawables, e.g. for screenshots,
+    /// at some cost in performance.
+    pub fn set_framebuffer_only(&self, framebuffer_only: bool) {
+        let framebuffer_only = if framebuffer_only { YES } else { NO };
+        unsafe { msg_send![self.layer, setFramebufferOnly:framebuffer_only] }
+    }
+    /// Gets whether the layer's textures can only be used as render targets.
+    pub fn get_framebuffer_only(&self) -> bool {
+        let framebuffer_only: BOOL = unsafe { msg_send![self.layer, framebufferOnly] };
+        framebuffer_only != NO
+    }
     /// Waits until a Metal drawable is available, and then returns it.
     pub fn next_drawable(&self) -> CoreAnimMetalDrawable {
         let drawable:id = unsafe { msg_send![self.layer, nextDrawable] };
@@ -127,6 +127,9 @@ impl From<dispatch_semaphore_t> for DispatchSemaphore {
 impl Drop for DispatchSemaphore {
     fn drop(&mut self) { unsafe { objc_release(self.semaphore) } }
 }
+// Dispatch semaphores are made to be waited on and signalled from different threads.
+unsafe impl Send for DispatchSemaphore {}
+unsafe impl Sync for DispatchSemaphore {}
 impl DispatchSemaphore {
     /// Creates new counting semaphore with an initial value.
     #[inline]
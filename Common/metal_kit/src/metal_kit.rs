@@ -23,12 +23,74 @@ mod metal_texture_descriptor;
 mod metal_render_pass_depth_attachment_descriptor;
 mod metal_depth_stencil_descriptor;
 mod metal_depth_stencil_state;
+mod metal_compile_options;
+mod metal_compiler_diagnostic;
+mod ns_string;
+mod metal_error;
+mod metal_function;
+mod metal_blend_configuration;
+mod metal_stencil_descriptor;
+mod metal_render_pass_stencil_attachment_descriptor;
+mod metal_render_target;
+mod metal_offscreen_target;
+mod metal_draw_arguments;
+mod metal_buffer_storage;
+mod metal_frame_ring_buffer;
+mod metal_typed_buffer;
+mod metal_resource_options;
+mod metal_gpu_timing;
+mod metal_callback;
+mod metal_command_buffer_handler;
+mod metal_debug_group;
+mod metal_capture_manager;
+mod metal_sampler_state;
+mod metal_heap;
+mod metal_argument_layout;
+mod metal_argument_encoder;
+mod metal_indirect_command_buffer;
+mod metal_blit_command_encoder;
+mod metal_pipeline_reflection;
+mod metal_vertex_descriptor;
+mod metal_binary_archive;
+mod metal_pipeline_cache;
+mod metal_pipeline_compilation;
+mod metal_device_capabilities;
+mod metal_fence;
+mod metal_event;
+mod metal_compute_command_encoder;
+mod metal_viewport;
+mod metal_visibility_query_pool;
+mod metal_counter_sample_buffer;
+mod metal_texture_layout;
+mod metal_png_encoder;
+mod metal_texture_readback;
 
 pub use metal_buffer::MetalBuffer;
 pub use metal_clear_colors::MetalClearColor;
 pub use metal_command_buffer::MetalCommandBuffer;
+pub use metal_command_buffer::MTLCommandBufferStatus;
+pub use metal_command_buffer::MTLCommandBufferStatusNotEnqueued;
+pub use metal_command_buffer::MTLCommandBufferStatusEnqueued;
+pub use metal_command_buffer::MTLCommandBufferStatusCommitted;
+pub use metal_command_buffer::MTLCommandBufferStatusScheduled;
+pub use metal_command_buffer::MTLCommandBufferStatusCompleted;
+pub use metal_command_buffer::MTLCommandBufferStatusError;
+pub use metal_command_buffer::MTLCommandBufferError;
+pub use metal_command_buffer::MTLCommandBufferErrorNone;
+pub use metal_command_buffer::MTLCommandBufferErrorInternal;
+pub use metal_command_buffer::MTLCommandBufferErrorTimeout;
+pub use metal_command_buffer::MTLCommandBufferErrorPageFault;
+pub use metal_command_buffer::MTLCommandBufferErrorAccessRevoked;
+pub use metal_command_buffer::MTLCommandBufferErrorNotPermitted;
+pub use metal_command_buffer::MTLCommandBufferErrorOutOfMemory;
+pub use metal_command_buffer::MTLCommandBufferErrorInvalidResource;
+pub use metal_command_buffer::MTLCommandBufferErrorMemoryless;
+pub use metal_command_buffer::MTLCommandBufferErrorDeviceRemoved;
+pub use metal_command_buffer::MTLCommandBufferErrorStackOverflow;
+pub use metal_command_buffer::MTLCommandBufferErrorDomain;
+pub use metal_command_buffer::command_buffer_error_description;
+pub use metal_command_buffer::command_buffer_error_code;
 pub use metal_device::MetalDevice;
-pub use metal_device::MetalDeviceError;
 pub use metal_device::MTLResourceCPUCacheModeDefaultCache;
 pub use metal_device::MTLResourceOptions;
 pub use metal_library::MetalLibrary;
@@ -40,24 +102,394 @@ pub use metal_render_command_encoder::MTLCullMode;
 pub use metal_render_command_encoder::MTLCullModeNone;
 pub use metal_render_command_encoder::MTLCullModeFront;
 pub use metal_render_command_encoder::MTLCullModeBack;
+pub use metal_render_command_encoder::MTLTriangleFillMode;
+pub use metal_render_command_encoder::MTLTriangleFillModeFill;
+pub use metal_render_command_encoder::MTLTriangleFillModeLines;
+pub use metal_render_command_encoder::MTLDepthClipMode;
+pub use metal_render_command_encoder::MTLDepthClipModeClip;
+pub use metal_render_command_encoder::MTLDepthClipModeClamp;
+pub use metal_render_command_encoder::MTLVisibilityResultMode;
+pub use metal_render_command_encoder::MTLVisibilityResultModeDisabled;
+pub use metal_render_command_encoder::MTLVisibilityResultModeBoolean;
+pub use metal_render_command_encoder::MTLVisibilityResultModeCounting;
 pub use metal_render_command_encoder::MTLPrimitiveType;
+pub use metal_render_command_encoder::MTLPrimitiveTypePoint;
+pub use metal_render_command_encoder::MTLPrimitiveTypeLine;
+pub use metal_render_command_encoder::MTLPrimitiveTypeLineStrip;
 pub use metal_render_command_encoder::MTLPrimitiveTypeTriangle;
+pub use metal_render_command_encoder::MTLPrimitiveTypeTriangleStrip;
 pub use metal_render_command_encoder::MTLIndexType;
 pub use metal_render_command_encoder::MTLIndexTypeUInt16;
 pub use metal_render_command_encoder::MTLIndexTypeUInt32;
 pub use metal_render_pass_descriptor::MetalRenderPassDescriptor;
+pub use metal_render_pass_descriptor::MTLLoadAction;
+pub use metal_render_pass_descriptor::MTLLoadActionDontCare;
+pub use metal_render_pass_descriptor::MTLLoadActionLoad;
 pub use metal_render_pass_descriptor::MTLLoadActionClear;
+pub use metal_render_pass_descriptor::MTLStoreAction;
 pub use metal_render_pass_descriptor::MTLStoreActionDontCare;
 pub use metal_render_pass_descriptor::MTLStoreActionStore;
+pub use metal_render_pass_descriptor::MTLStoreActionMultisampleResolve;
+pub use metal_render_pass_descriptor::MTLStoreActionStoreAndMultisampleResolve;
 pub use metal_render_pipeline_descriptor::MetalRenderPipelineDescriptor;
 pub use metal_render_pipeline_state::MetalRenderPipelineState;
 pub use metal_command_queue::MetalCommandQueue;
 pub use metal_texture::MetalTexture;
+pub use metal_texture::MTLOrigin;
+pub use metal_texture::MTLRegion;
+pub use metal_texture::MTLTextureUsageShaderRead;
+pub use metal_texture::MTLTextureUsageShaderWrite;
 pub use metal_texture::MTLTextureUsageRenderTarget;
+pub use metal_texture::MTLTextureUsagePixelFormatView;
 pub use metal_texture_descriptor::MetalTextureDescriptor;
+pub use metal_texture_descriptor::MTLPixelFormatR8Unorm;
+pub use metal_texture_descriptor::MTLPixelFormatR32Float;
+pub use metal_texture_descriptor::MTLPixelFormatRGBA8Unorm;
+pub use metal_texture_descriptor::MTLPixelFormatRGBA8Unorm_sRGB;
+pub use metal_texture_descriptor::MTLPixelFormatBGRA8Unorm;
+pub use metal_texture_descriptor::MTLPixelFormatBGRA8Unorm_sRGB;
+pub use metal_texture_descriptor::MTLPixelFormatRGBA16Float;
+pub use metal_texture_descriptor::MTLPixelFormatRGBA32Float;
+pub use metal_texture_descriptor::MTLPixelFormatDepth16Unorm;
 pub use metal_texture_descriptor::MTLPixelFormatDepth32Float;
+pub use metal_texture_descriptor::MTLPixelFormatStencil8;
+pub use metal_texture_descriptor::MTLPixelFormatDepth24Unorm_Stencil8;
+pub use metal_texture_descriptor::MTLPixelFormatDepth32Float_Stencil8;
+pub use metal_texture_descriptor::MTLPixelFormatX32_Stencil8;
+pub use metal_texture_descriptor::MTLPixelFormatX24_Stencil8;
+pub use metal_texture_descriptor::pixel_format_has_depth;
+pub use metal_texture_descriptor::pixel_format_has_stencil;
+pub use metal_texture_descriptor::MTLTextureType;
+pub use metal_texture_descriptor::MTLTextureType1D;
+pub use metal_texture_descriptor::MTLTextureType1DArray;
+pub use metal_texture_descriptor::MTLTextureType2D;
+pub use metal_texture_descriptor::MTLTextureType2DArray;
+pub use metal_texture_descriptor::MTLTextureType2DMultisample;
+pub use metal_texture_descriptor::MTLTextureTypeCube;
+pub use metal_texture_descriptor::MTLTextureTypeCubeArray;
+pub use metal_texture_descriptor::MTLTextureType3D;
+pub use metal_texture_descriptor::MTLTextureType2DMultisampleArray;
+pub use metal_texture_descriptor::MTLTextureTypeTextureBuffer;
+pub use metal_texture_descriptor::MTLTextureSwizzle;
+pub use metal_texture_descriptor::MTLTextureSwizzleZero;
+pub use metal_texture_descriptor::MTLTextureSwizzleOne;
+pub use metal_texture_descriptor::MTLTextureSwizzleRed;
+pub use metal_texture_descriptor::MTLTextureSwizzleGreen;
+pub use metal_texture_descriptor::MTLTextureSwizzleBlue;
+pub use metal_texture_descriptor::MTLTextureSwizzleAlpha;
+pub use metal_texture_descriptor::MetalTextureSwizzleChannels;
+pub use metal_texture_descriptor::MTLStorageModeShared;
+pub use metal_texture_descriptor::MTLStorageModeManaged;
+pub use metal_texture_descriptor::MTLStorageMode;
 pub use metal_texture_descriptor::MTLStorageModePrivate;
+pub use metal_texture_descriptor::MTLStorageModeMemoryless;
 pub use metal_render_pass_depth_attachment_descriptor::MetalRenderPassDepthAttachment;
+pub use metal_render_pass_stencil_attachment_descriptor::MetalRenderPassStencilAttachment;
 pub use metal_depth_stencil_descriptor::MetalDepthStencilDescriptor;
+pub use metal_depth_stencil_descriptor::MTLCompareFunction;
+pub use metal_depth_stencil_descriptor::MTLCompareFunctionNever;
 pub use metal_depth_stencil_descriptor::MTLCompareFunctionLess;
+pub use metal_depth_stencil_descriptor::MTLCompareFunctionEqual;
+pub use metal_depth_stencil_descriptor::MTLCompareFunctionLessEqual;
+pub use metal_depth_stencil_descriptor::MTLCompareFunctionGreater;
+pub use metal_depth_stencil_descriptor::MTLCompareFunctionNotEqual;
+pub use metal_depth_stencil_descriptor::MTLCompareFunctionGreaterEqual;
+pub use metal_depth_stencil_descriptor::MTLCompareFunctionAlways;
 pub use metal_depth_stencil_state::MetalDepthStencilState;
+pub use metal_compile_options::MetalCompileOptions;
+pub use metal_compile_options::MTLLanguageVersion;
+pub use metal_compile_options::MTLLanguageVersion1_1;
+pub use metal_compile_options::MTLLanguageVersion1_2;
+pub use metal_compile_options::MTLLanguageVersion2_0;
+pub use metal_compile_options::MTLLanguageVersion2_1;
+pub use metal_compile_options::MTLLanguageVersion2_2;
+pub use metal_compile_options::MTLLanguageVersion2_3;
+pub use metal_compile_options::MTLLanguageVersion2_4;
+pub use metal_compile_options::MTLLanguageVersion3_0;
+pub use metal_compile_options::MTLLanguageVersion3_1;
+pub use metal_compiler_diagnostic::MetalCompilerDiagnostic;
+pub use metal_compiler_diagnostic::MetalDiagnosticSeverity;
+pub use metal_error::MetalError;
+pub use metal_error::NSErrorInfo;
+pub use metal_function::MetalFunction;
+pub use metal_blend_configuration::MetalBlendConfiguration;
+pub use metal_blend_configuration::MTLBlendFactor;
+pub use metal_blend_configuration::MTLBlendFactorZero;
+pub use metal_blend_configuration::MTLBlendFactorOne;
+pub use metal_blend_configuration::MTLBlendFactorSourceColor;
+pub use metal_blend_configuration::MTLBlendFactorOneMinusSourceColor;
+pub use metal_blend_configuration::MTLBlendFactorSourceAlpha;
+pub use metal_blend_configuration::MTLBlendFactorOneMinusSourceAlpha;
+pub use metal_blend_configuration::MTLBlendFactorDestinationColor;
+pub use metal_blend_configuration::MTLBlendFactorOneMinusDestinationColor;
+pub use metal_blend_configuration::MTLBlendFactorDestinationAlpha;
+pub use metal_blend_configuration::MTLBlendFactorOneMinusDestinationAlpha;
+pub use metal_blend_configuration::MTLBlendFactorSourceAlphaSaturated;
+pub use metal_blend_configuration::MTLBlendFactorBlendColor;
+pub use metal_blend_configuration::MTLBlendFactorOneMinusBlendColor;
+pub use metal_blend_configuration::MTLBlendFactorBlendAlpha;
+pub use metal_blend_configuration::MTLBlendFactorOneMinusBlendAlpha;
+pub use metal_blend_configuration::MTLBlendFactorSource1Color;
+pub use metal_blend_configuration::MTLBlendFactorOneMinusSource1Color;
+pub use metal_blend_configuration::MTLBlendFactorSource1Alpha;
+pub use metal_blend_configuration::MTLBlendFactorOneMinusSource1Alpha;
+pub use metal_blend_configuration::MTLBlendOperation;
+pub use metal_blend_configuration::MTLBlendOperationAdd;
+pub use metal_blend_configuration::MTLBlendOperationSubtract;
+pub use metal_blend_configuration::MTLBlendOperationReverseSubtract;
+pub use metal_blend_configuration::MTLBlendOperationMin;
+pub use metal_blend_configuration::MTLBlendOperationMax;
+pub use metal_blend_configuration::MTLColorWriteMask;
+pub use metal_blend_configuration::MTLColorWriteMaskNone;
+pub use metal_blend_configuration::MTLColorWriteMaskRed;
+pub use metal_blend_configuration::MTLColorWriteMaskGreen;
+pub use metal_blend_configuration::MTLColorWriteMaskBlue;
+pub use metal_blend_configuration::MTLColorWriteMaskAlpha;
+pub use metal_blend_configuration::MTLColorWriteMaskAll;
+pub use metal_stencil_descriptor::MetalStencilDescriptor;
+pub use metal_stencil_descriptor::MTLStencilOperation;
+pub use metal_stencil_descriptor::MTLStencilOperationKeep;
+pub use metal_stencil_descriptor::MTLStencilOperationZero;
+pub use metal_stencil_descriptor::MTLStencilOperationReplace;
+pub use metal_stencil_descriptor::MTLStencilOperationIncrementClamp;
+pub use metal_stencil_descriptor::MTLStencilOperationDecrementClamp;
+pub use metal_stencil_descriptor::MTLStencilOperationInvert;
+pub use metal_stencil_descriptor::MTLStencilOperationIncrementWrap;
+pub use metal_stencil_descriptor::MTLStencilOperationDecrementWrap;
+pub use metal_render_target::choose_sample_count;
+pub use metal_render_target::MetalRenderTargetSpec;
+pub use metal_render_target::MetalRenderTargetCache;
+pub use metal_offscreen_target::MAX_COLOR_ATTACHMENTS;
+pub use metal_offscreen_target::OffscreenColorAttachment;
+pub use metal_offscreen_target::OffscreenDepthAttachment;
+pub use metal_offscreen_target::OffscreenTargetDescriptor;
+pub use metal_offscreen_target::OffscreenTarget;
+pub use metal_draw_arguments::MTLDrawPrimitivesIndirectArguments;
+pub use metal_draw_arguments::MTLDrawIndexedPrimitivesIndirectArguments;
+pub use metal_draw_arguments::INDIRECT_BUFFER_OFFSET_ALIGNMENT;
+pub use metal_draw_arguments::validate_indirect_buffer_range;
+pub use metal_buffer_storage::BufferStorage;
+pub use metal_buffer_storage::HostBuffer;
+pub use metal_buffer_storage::HOST_BUFFER_ALIGNMENT;
+pub use metal_frame_ring_buffer::FrameRingBuffer;
+pub use metal_frame_ring_buffer::FRAME_RING_BUFFER_DEFAULT_ALIGNMENT;
+pub use metal_frame_ring_buffer::frame_slot_stride;
+pub use metal_typed_buffer::TypedBuffer;
+pub use metal_resource_options::MetalResourceOptions;
+pub use metal_resource_options::MTLCPUCacheMode;
+pub use metal_resource_options::MTLCPUCacheModeDefaultCache;
+pub use metal_resource_options::MTLCPUCacheModeWriteCombined;
+pub use metal_resource_options::MTLHazardTrackingMode;
+pub use metal_resource_options::MTLHazardTrackingModeDefault;
+pub use metal_resource_options::MTLHazardTrackingModeUntracked;
+pub use metal_resource_options::MTLHazardTrackingModeTracked;
+pub use metal_resource_options::MTLResourceCPUCacheModeShift;
+pub use metal_resource_options::MTLResourceCPUCacheModeMask;
+pub use metal_resource_options::MTLResourceStorageModeShift;
+pub use metal_resource_options::MTLResourceStorageModeMask;
+pub use metal_resource_options::MTLResourceHazardTrackingModeShift;
+pub use metal_resource_options::MTLResourceHazardTrackingModeMask;
+pub use metal_gpu_timing::GpuTimingHistory;
+pub use metal_command_buffer_handler::CommandBufferInfo;
+pub use metal_command_buffer_handler::CommandBufferCompletion;
+pub use metal_debug_group::MetalDebugGroups;
+pub use metal_debug_group::DebugGroup;
+pub use metal_capture_manager::MetalCaptureManager;
+pub use metal_capture_manager::MetalCaptureDescriptor;
+pub use metal_capture_manager::MTLCaptureDestination;
+pub use metal_capture_manager::MTLCaptureDestinationDeveloperTools;
+pub use metal_capture_manager::MTLCaptureDestinationGPUTraceDocument;
+pub use metal_capture_manager::GPU_TRACE_EXTENSION;
+pub use metal_capture_manager::validate_gpu_trace_path;
+pub use metal_sampler_state::MetalSamplerDescriptor;
+pub use metal_sampler_state::MetalSamplerState;
+pub use metal_sampler_state::MTLSamplerMinMagFilter;
+pub use metal_sampler_state::MTLSamplerMinMagFilterNearest;
+pub use metal_sampler_state::MTLSamplerMinMagFilterLinear;
+pub use metal_sampler_state::MTLSamplerMipFilter;
+pub use metal_sampler_state::MTLSamplerMipFilterNotMipmapped;
+pub use metal_sampler_state::MTLSamplerMipFilterNearest;
+pub use metal_sampler_state::MTLSamplerMipFilterLinear;
+pub use metal_sampler_state::MTLSamplerAddressMode;
+pub use metal_sampler_state::MTLSamplerAddressModeClampToEdge;
+pub use metal_sampler_state::MTLSamplerAddressModeMirrorClampToEdge;
+pub use metal_sampler_state::MTLSamplerAddressModeRepeat;
+pub use metal_sampler_state::MTLSamplerAddressModeMirrorRepeat;
+pub use metal_sampler_state::MTLSamplerAddressModeClampToZero;
+pub use metal_sampler_state::MTLSamplerAddressModeClampToBorderColor;
+pub use metal_heap::MetalHeap;
+pub use metal_argument_layout::MTLDataType;
+pub use metal_argument_layout::MTLDataTypeFloat;
+pub use metal_argument_layout::MTLDataTypeFloat2;
+pub use metal_argument_layout::MTLDataTypeFloat3;
+pub use metal_argument_layout::MTLDataTypeFloat4;
+pub use metal_argument_layout::MTLDataTypeFloat4x4;
+pub use metal_argument_layout::MTLDataTypeHalf;
+pub use metal_argument_layout::MTLDataTypeInt;
+pub use metal_argument_layout::MTLDataTypeInt2;
+pub use metal_argument_layout::MTLDataTypeInt3;
+pub use metal_argument_layout::MTLDataTypeInt4;
+pub use metal_argument_layout::MTLDataTypeUInt;
+pub use metal_argument_layout::MTLDataTypeUInt2;
+pub use metal_argument_layout::MTLDataTypeUInt3;
+pub use metal_argument_layout::MTLDataTypeUInt4;
+pub use metal_argument_layout::MTLDataTypeBool;
+pub use metal_argument_layout::MTLDataTypeTexture;
+pub use metal_argument_layout::MTLDataTypeSampler;
+pub use metal_argument_layout::MTLDataTypePointer;
+pub use metal_argument_layout::MTLDataTypeIndirectCommandBuffer;
+pub use metal_argument_layout::MTLDataTypeLong;
+pub use metal_argument_layout::MTLDataTypeULong;
+pub use metal_argument_layout::MTLBindingAccess;
+pub use metal_argument_layout::MTLBindingAccessReadOnly;
+pub use metal_argument_layout::MTLBindingAccessReadWrite;
+pub use metal_argument_layout::MTLBindingAccessWriteOnly;
+pub use metal_argument_layout::data_type_size_and_alignment;
+pub use metal_argument_layout::ArgumentField;
+pub use metal_argument_layout::ArgumentLayoutEntry;
+pub use metal_argument_layout::ArgumentLayout;
+pub use metal_argument_encoder::MetalArgumentEncoder;
+pub use metal_argument_encoder::MetalResource;
+pub use metal_argument_encoder::MTLResourceID;
+pub use metal_argument_encoder::MTLArgumentBuffersTier;
+pub use metal_argument_encoder::MTLArgumentBuffersTier1;
+pub use metal_argument_encoder::MTLArgumentBuffersTier2;
+pub use metal_argument_encoder::MTLResourceUsage;
+pub use metal_argument_encoder::MTLResourceUsageRead;
+pub use metal_argument_encoder::MTLResourceUsageWrite;
+pub use metal_argument_encoder::MTLRenderStages;
+pub use metal_argument_encoder::MTLRenderStageVertex;
+pub use metal_argument_encoder::MTLRenderStageFragment;
+pub use metal_indirect_command_buffer::MetalIndirectCommandBuffer;
+pub use metal_indirect_command_buffer::MetalIndirectRenderCommand;
+pub use metal_indirect_command_buffer::IndirectCommandBufferDescriptor;
+pub use metal_indirect_command_buffer::IndirectCommandCounter;
+pub use metal_indirect_command_buffer::MTLIndirectCommandType;
+pub use metal_indirect_command_buffer::MTLIndirectCommandTypeDraw;
+pub use metal_indirect_command_buffer::MTLIndirectCommandTypeDrawIndexed;
+pub use metal_indirect_command_buffer::MTLIndirectCommandTypeDrawPatches;
+pub use metal_indirect_command_buffer::MTLIndirectCommandTypeDrawIndexedPatches;
+pub use metal_indirect_command_buffer::MTLIndirectCommandTypeConcurrentDispatch;
+pub use metal_indirect_command_buffer::MTLIndirectCommandTypeConcurrentDispatchThreads;
+pub use metal_indirect_command_buffer::INDIRECT_COMMAND_MAX_BUFFER_BIND_COUNT;
+pub use metal_blit_command_encoder::MetalBlitCommandEncoder;
+pub use metal_pipeline_reflection::PipelineReflection;
+pub use metal_pipeline_reflection::ArgumentReflection;
+pub use metal_pipeline_reflection::BufferReflection;
+pub use metal_pipeline_reflection::StructMemberReflection;
+pub use metal_pipeline_reflection::ExpectedBufferLayout;
+pub use metal_pipeline_reflection::ExpectedStructMember;
+pub use metal_pipeline_reflection::PipelineLayoutMismatch;
+pub use metal_pipeline_reflection::compare_buffer_layout;
+pub use metal_pipeline_reflection::MTLPipelineOption;
+pub use metal_pipeline_reflection::MTLPipelineOptionNone;
+pub use metal_pipeline_reflection::MTLPipelineOptionBindingInfo;
+pub use metal_pipeline_reflection::MTLPipelineOptionBufferTypeInfo;
+pub use metal_pipeline_reflection::MTLBindingType;
+pub use metal_pipeline_reflection::MTLBindingTypeBuffer;
+pub use metal_pipeline_reflection::MTLBindingTypeThreadgroupMemory;
+pub use metal_pipeline_reflection::MTLBindingTypeTexture;
+pub use metal_pipeline_reflection::MTLBindingTypeSampler;
+pub use metal_vertex_descriptor::MetalVertexDescriptor;
+pub use metal_vertex_descriptor::MetalVertexAttribute;
+pub use metal_vertex_descriptor::MetalVertexBufferLayout;
+pub use metal_vertex_descriptor::MTLVertexFormat;
+pub use metal_vertex_descriptor::MTLVertexFormatInvalid;
+pub use metal_vertex_descriptor::MTLVertexFormatUChar4Normalized;
+pub use metal_vertex_descriptor::MTLVertexFormatHalf2;
+pub use metal_vertex_descriptor::MTLVertexFormatHalf4;
+pub use metal_vertex_descriptor::MTLVertexFormatFloat;
+pub use metal_vertex_descriptor::MTLVertexFormatFloat2;
+pub use metal_vertex_descriptor::MTLVertexFormatFloat3;
+pub use metal_vertex_descriptor::MTLVertexFormatFloat4;
+pub use metal_vertex_descriptor::MTLVertexFormatInt;
+pub use metal_vertex_descriptor::MTLVertexFormatUInt;
+pub use metal_vertex_descriptor::MTLVertexStepFunction;
+pub use metal_vertex_descriptor::MTLVertexStepFunctionConstant;
+pub use metal_vertex_descriptor::MTLVertexStepFunctionPerVertex;
+pub use metal_vertex_descriptor::MTLVertexStepFunctionPerInstance;
+pub use metal_binary_archive::MetalBinaryArchive;
+pub use metal_pipeline_cache::PipelineCache;
+pub use metal_pipeline_cache::PipelineCacheStats;
+pub use metal_pipeline_cache::RenderPipelineKey;
+pub use metal_pipeline_cache::ColorAttachmentKey;
+pub use metal_pipeline_cache::DepthStencilKey;
+pub use metal_pipeline_compilation::PipelineCompilation;
+pub use metal_pipeline_compilation::PipelineBatch;
+pub use metal_pipeline_compilation::PendingPipeline;
+pub use metal_pipeline_compilation::PipelineStatus;
+pub use metal_device_capabilities::DeviceCapabilities;
+pub use metal_device_capabilities::DevicePreference;
+pub use metal_device_capabilities::choose_device;
+pub use metal_device_capabilities::MTLSize;
+pub use metal_device_capabilities::KNOWN_GPU_FAMILIES;
+pub use metal_device_capabilities::KNOWN_TEXTURE_SAMPLE_COUNTS;
+pub use metal_device_capabilities::MTLGPUFamily;
+pub use metal_device_capabilities::MTLGPUFamilyApple1;
+pub use metal_device_capabilities::MTLGPUFamilyApple2;
+pub use metal_device_capabilities::MTLGPUFamilyApple3;
+pub use metal_device_capabilities::MTLGPUFamilyApple4;
+pub use metal_device_capabilities::MTLGPUFamilyApple5;
+pub use metal_device_capabilities::MTLGPUFamilyApple6;
+pub use metal_device_capabilities::MTLGPUFamilyApple7;
+pub use metal_device_capabilities::MTLGPUFamilyApple8;
+pub use metal_device_capabilities::MTLGPUFamilyApple9;
+pub use metal_device_capabilities::MTLGPUFamilyMac2;
+pub use metal_device_capabilities::MTLGPUFamilyCommon1;
+pub use metal_device_capabilities::MTLGPUFamilyCommon2;
+pub use metal_device_capabilities::MTLGPUFamilyCommon3;
+pub use metal_device_capabilities::MTLGPUFamilyMetal3;
+pub use metal_fence::MetalFence;
+pub use metal_event::MetalEventObject;
+pub use metal_event::MetalEvent;
+pub use metal_event::MetalSharedEvent;
+pub use metal_event::MetalSharedEventListener;
+pub use metal_event::EventValueCounter;
+pub use metal_event::EventStep;
+pub use metal_event::EventOrderModel;
+pub use metal_compute_command_encoder::MetalComputeCommandEncoder;
+pub use metal_viewport::MetalViewport;
+pub use metal_viewport::MetalScissorRect;
+pub use metal_visibility_query_pool::VISIBILITY_RESULT_SIZE;
+pub use metal_visibility_query_pool::VisibilityQuery;
+pub use metal_visibility_query_pool::VisibilityQueryPool;
+pub use metal_counter_sample_buffer::MTLCommonCounterSetTimestamp;
+pub use metal_counter_sample_buffer::MTLCommonCounterSetStageUtilization;
+pub use metal_counter_sample_buffer::MTLCommonCounterSetStatistic;
+pub use metal_counter_sample_buffer::MTLCounterErrorValue;
+pub use metal_counter_sample_buffer::MTLCounterDontSample;
+pub use metal_counter_sample_buffer::MTLCounterSamplingPoint;
+pub use metal_counter_sample_buffer::MTLCounterSamplingPointAtStageBoundary;
+pub use metal_counter_sample_buffer::MTLCounterSamplingPointAtDrawBoundary;
+pub use metal_counter_sample_buffer::MTLCounterSamplingPointAtDispatchBoundary;
+pub use metal_counter_sample_buffer::MTLCounterSamplingPointAtTileDispatchBoundary;
+pub use metal_counter_sample_buffer::MTLCounterSamplingPointAtBlitBoundary;
+pub use metal_counter_sample_buffer::MetalCounterSet;
+pub use metal_counter_sample_buffer::MetalCounterSampleBufferDescriptor;
+pub use metal_counter_sample_buffer::MetalCounterSampleBuffer;
+pub use metal_counter_sample_buffer::TimestampPair;
+pub use metal_counter_sample_buffer::TimestampCalibration;
+pub use metal_counter_sample_buffer::GpuPassTiming;
+pub use metal_counter_sample_buffer::GpuPassTimer;
+pub use metal_counter_sample_buffer::GpuPassReport;
+pub use metal_texture_layout::CUBE_FACE_COUNT;
+pub use metal_texture_layout::mipmap_level_count;
+pub use metal_texture_layout::mip_level_size;
+pub use metal_texture_layout::texture_slice_count;
+pub use metal_texture_layout::cube_slice;
+pub use metal_texture_layout::pixel_format_bytes_per_pixel;
+pub use metal_texture_layout::bytes_per_row;
+pub use metal_texture_layout::slice_size_in_bytes;
+pub use metal_texture_layout::texture_size_in_bytes;
+pub use metal_texture_layout::region_within_level;
+pub use metal_png_encoder::PNG_SIGNATURE;
+pub use metal_png_encoder::encode_png_rgba8;
+pub use metal_texture_readback::half_to_f32;
+pub use metal_texture_readback::unorm8_from_f32;
+pub use metal_texture_readback::linear_to_srgb;
+pub use metal_texture_readback::bgra8_to_rgba8;
+pub use metal_texture_readback::rgba16f_to_rgba8;
+pub use metal_texture_readback::can_convert_to_rgba8;
+pub use metal_texture_readback::pixels_to_rgba8;
+pub use metal_texture_readback::RgbaImage;
+pub use metal_texture_readback::TextureReadback;
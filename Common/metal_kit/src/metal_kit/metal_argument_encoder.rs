@@ -0,0 +1,188 @@
+//
+//  metal_argument_encoder.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Thin wrappers for MTLArgumentEncoder,
+//! and the types used to make resources in argument buffers resident
+
+use objc::msg_send;
+use objc::sel;
+use objc::sel_impl;
+use objc::class;
+use cocoa::base::{id, nil};
+use cocoa::foundation::{NSArray, NSUInteger};
+use objc::runtime::{objc_release, objc_retain};
+use std::mem::size_of;
+use crate::metal_kit::metal_argument_layout::ArgumentField;
+use crate::metal_kit::metal_buffer::MetalBuffer;
+use crate::metal_kit::metal_error::MetalError;
+use crate::metal_kit::metal_sampler_state::MetalSamplerState;
+use crate::metal_kit::metal_texture::MetalTexture;
+use crate::metal_kit::ns_string::{get_objc_label, set_objc_label};
+
+// From MTLDevice.h:
+// typedef NS_ENUM(NSUInteger, MTLArgumentBuffersTier) {
+//     MTLArgumentBuffersTier1 = 0,
+//     MTLArgumentBuffersTier2 = 1,
+// } API_AVAILABLE(macos(10.13), ios(11.0));
+/// How much a device can do with argument buffers.
+pub type MTLArgumentBuffersTier = NSUInteger;
+/// Argument buffers have to be filled in with an argument encoder.
+#[allow(non_upper_case_globals)]
+pub const MTLArgumentBuffersTier1: MTLArgumentBuffersTier = 0;
+/// Argument buffers can be written directly, and hold more resources.
+#[allow(non_upper_case_globals)]
+pub const MTLArgumentBuffersTier2: MTLArgumentBuffersTier = 1;
+//
+// From MTLCommandEncoder.h:
+// typedef NS_OPTIONS(NSUInteger, MTLResourceUsage) {
+//     MTLResourceUsageRead   = 1 << 0,
+//     MTLResourceUsageWrite  = 1 << 1,
+// } API_AVAILABLE(macos(10.13), ios(11.0));
+/// How the shaders use a resource they reach through an argument buffer.
+pub type MTLResourceUsage = NSUInteger;
+/// The shaders read from the resource.
+#[allow(non_upper_case_globals)]
+pub const MTLResourceUsageRead: MTLResourceUsage = 1 << 0;
+/// The shaders write to the resource.
+#[allow(non_upper_case_globals)]
+pub const MTLResourceUsageWrite: MTLResourceUsage = 1 << 1;
+//
+// From MTLRenderCommandEncoder.h:
+// typedef NS_OPTIONS(NSUInteger, MTLRenderStages) {
+//     MTLRenderStageVertex   = (1UL << 0),
+//     MTLRenderStageFragment = (1UL << 1),
+// } API_AVAILABLE(macos(10.13), ios(11.0));
+/// The render stages that use a resource.
+pub type MTLRenderStages = NSUInteger;
+/// The vertex stage.
+#[allow(non_upper_case_globals)]
+pub const MTLRenderStageVertex: MTLRenderStages = 1 << 0;
+/// The fragment stage.
+#[allow(non_upper_case_globals)]
+pub const MTLRenderStageFragment: MTLRenderStages = 1 << 1;
+
+// From MTLTypes.h:
+// typedef struct MTLResourceID {
+//     uint64_t _impl;
+// } MTLResourceID;
+/// The ID written into a Tier 2 argument buffer in place of a texture or sampler.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct MTLResourceID {
+    /// The opaque value Metal gives the resource.
+    pub raw: u64,
+}
+
+/// A Metal resource, such as a buffer or texture,
+/// that shaders can reach through an argument buffer.
+pub trait MetalResource {
+    /// Returns the underlying Objective C resource.
+    fn to_objc(&self) -> id;
+}
+impl MetalResource for MetalBuffer {
+    fn to_objc(&self) -> id { MetalBuffer::to_objc(self) }
+}
+impl MetalResource for MetalTexture {
+    fn to_objc(&self) -> id { MetalTexture::to_objc(self) }
+}
+
+/// Creates the array of argument descriptors for the given fields.
+/// The array is autoreleased.
+pub(crate) fn argument_descriptors_from_fields(fields: &[ArgumentField]) -> id {
+    let class = class!(MTLArgumentDescriptor);
+    let descriptors: Vec<id> = fields.iter().map(|field| unsafe {
+        let descriptor: id = msg_send![class, argumentDescriptor];
+        let _:() = msg_send![descriptor, setDataType:field.data_type];
+        let _:() = msg_send![descriptor, setIndex:field.index];
+        let _:() = msg_send![descriptor, setArrayLength:field.array_length];
+        let _:() = msg_send![descriptor, setAccess:field.access];
+        let _:() = msg_send![descriptor, setTextureType:field.texture_type];
+        descriptor
+    }).collect();
+    unsafe { NSArray::arrayWithObjects(nil, &descriptors) }
+}
+
+/// Rust wrapper for an object that writes resources and constants into an argument buffer.
+///
+/// Tier 1 devices need this to fill in argument buffers;
+/// on Tier 2 devices an `ArgumentLayout` and a `#[repr(C)]` struct can be used instead.
+pub struct MetalArgumentEncoder {
+    encoder: id,
+}
+impl Default for MetalArgumentEncoder {
+    fn default() -> Self {
+        MetalArgumentEncoder { encoder: nil }
+    }
+}
+impl From<id> for MetalArgumentEncoder {
+    fn from(encoder: id) -> Self {
+        let encoder = unsafe { objc_retain(encoder) };
+        MetalArgumentEncoder { encoder }
+    }
+}
+impl Drop for MetalArgumentEncoder {
+    fn drop(&mut self) { unsafe { objc_release(self.encoder) } }
+}
+impl MetalArgumentEncoder {
+    /// Wraps an encoder returned from a `new...` method,
+    /// balancing the retain the method has already done for us.
+    pub(crate) fn from_new_object(encoder: id) -> Self {
+        let result = MetalArgumentEncoder::from(encoder);
+        unsafe { objc_release(encoder) };
+        result
+    }
+    /// Returns the underlying Objective C encoder.
+    pub fn to_objc(&self) -> id { self.encoder }
+    /// Sets the label used to identify the encoder in debugging tools.
+    pub fn set_label(&mut self, label: &str) { set_objc_label(self.encoder, label) }
+    /// Gets the label (empty if none was set).
+    pub fn get_label(&self) -> String { get_objc_label(self.encoder) }
+    /// Gets the number of bytes the arguments take up in an argument buffer.
+    pub fn get_encoded_length(&self) -> NSUInteger {
+        unsafe { msg_send![self.encoder, encodedLength] }
+    }
+    /// Gets the alignment in bytes the arguments need in an argument buffer.
+    pub fn get_alignment(&self) -> NSUInteger {
+        unsafe { msg_send![self.encoder, alignment] }
+    }
+    /// Sets the argument buffer, and the offset in it, that the following calls write to.
+    pub fn set_argument_buffer(&mut self, buffer: &MetalBuffer, offset: NSUInteger) {
+        unsafe { msg_send![self.encoder, setArgumentBuffer:buffer.to_objc() offset:offset] }
+    }
+    /// Writes a pointer to the buffer, from the given offset on, at the given argument index.
+    pub fn set_buffer(&mut self, buffer: &MetalBuffer, offset: NSUInteger, index: NSUInteger) {
+        unsafe { msg_send![self.encoder, setBuffer:buffer.to_objc() offset:offset atIndex:index] }
+    }
+    /// Writes the texture at the given argument index.
+    pub fn set_texture(&mut self, texture: &MetalTexture, index: NSUInteger) {
+        unsafe { msg_send![self.encoder, setTexture:texture.to_objc() atIndex:index] }
+    }
+    /// Writes the sampler state at the given argument index.
+    pub fn set_sampler_state(&mut self, sampler: &MetalSamplerState, index: NSUInteger) {
+        unsafe { msg_send![self.encoder, setSamplerState:sampler.to_objc() atIndex:index] }
+    }
+    /// Writes a constant value at the given argument index.
+    ///
+    /// The type has to match the argument's type in the shader.
+    pub fn set_constant<T: Copy>(&mut self, value: T, index: NSUInteger) -> Result<(), MetalError> {
+        let data: *mut u8 = unsafe { msg_send![self.encoder, constantDataAtIndex:index] };
+        if data.is_null() {
+            return Err(MetalError::BufferContentsUnavailable);
+        }
+        unsafe { std::ptr::copy_nonoverlapping(&value as *const T as *const u8, data, size_of::<T>()) };
+        Ok(())
+    }
+    /// Creates an encoder for the argument buffer that the argument at the given index points to.
+    pub fn new_argument_encoder_for_buffer_at_index(&self, index: NSUInteger) -> Result<MetalArgumentEncoder, MetalError> {
+        let encoder: id = unsafe { msg_send![self.encoder, newArgumentEncoderForBufferAtIndex:index] };
+        if encoder == nil {
+            return Err(MetalError::ArgumentEncoderCreation(index));
+        }
+        Ok(Self::from_new_object(encoder))
+    }
+}
@@ -0,0 +1,373 @@
+//
+//  metal_argument_layout.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Where each argument sits in an argument buffer,
+//! so a `#[repr(C)]` Rust struct can be written into it directly
+
+use cocoa::foundation::NSUInteger;
+use std::mem::{align_of, size_of};
+use crate::metal_kit::metal_error::MetalError;
+use crate::metal_kit::metal_texture_descriptor::{MTLTextureType, MTLTextureType2D};
+
+// From MTLArgument.h:
+// typedef NS_ENUM(NSUInteger, MTLDataType) {
+//     MTLDataTypeNone = 0,
+//     MTLDataTypeStruct = 1,
+//     MTLDataTypeArray  = 2,
+//     MTLDataTypeFloat  = 3,
+//     MTLDataTypeFloat2 = 4,
+//     MTLDataTypeFloat3 = 5,
+//     MTLDataTypeFloat4 = 6,
+//     ...
+//     MTLDataTypeFloat4x4 = 15,
+//     MTLDataTypeHalf  = 16,
+//     ...
+//     MTLDataTypeInt  = 29,
+//     MTLDataTypeInt2 = 30,
+//     MTLDataTypeInt3 = 31,
+//     MTLDataTypeInt4 = 32,
+//     MTLDataTypeUInt  = 33,
+//     MTLDataTypeUInt2 = 34,
+//     MTLDataTypeUInt3 = 35,
+//     MTLDataTypeUInt4 = 36,
+//     ...
+//     MTLDataTypeBool  = 53,
+//     ...
+//     MTLDataTypeTexture API_AVAILABLE(macos(10.13), ios(11.0)) = 58,
+//     MTLDataTypeSampler API_AVAILABLE(macos(10.13), ios(11.0)) = 59,
+//     MTLDataTypePointer API_AVAILABLE(macos(10.13), ios(11.0)) = 60,
+//     ...
+//     MTLDataTypeIndirectCommandBuffer API_AVAILABLE(macos(10.14), ios(12.0)) = 80,
+//     MTLDataTypeLong  API_AVAILABLE(macos(14.0), ios(17.0)) = 81,
+//     ...
+//     MTLDataTypeULong  API_AVAILABLE(macos(14.0), ios(17.0)) = 85,
+//     ...
+// } API_AVAILABLE(macos(10.11), ios(8.0));
+/// The type of an argument in an argument buffer.
+pub type MTLDataType = NSUInteger;
+/// A 32-bit float.
+#[allow(non_upper_case_globals)]
+pub const MTLDataTypeFloat: MTLDataType = 3;
+/// A vector of two floats.
+#[allow(non_upper_case_globals)]
+pub const MTLDataTypeFloat2: MTLDataType = 4;
+/// A vector of three floats, padded to the size of four.
+#[allow(non_upper_case_globals)]
+pub const MTLDataTypeFloat3: MTLDataType = 5;
+/// A vector of four floats.
+#[allow(non_upper_case_globals)]
+pub const MTLDataTypeFloat4: MTLDataType = 6;
+/// A 4x4 matrix of floats.
+#[allow(non_upper_case_globals)]
+pub const MTLDataTypeFloat4x4: MTLDataType = 15;
+/// A 16-bit float.
+#[allow(non_upper_case_globals)]
+pub const MTLDataTypeHalf: MTLDataType = 16;
+/// A 32-bit signed integer.
+#[allow(non_upper_case_globals)]
+pub const MTLDataTypeInt: MTLDataType = 29;
+/// A vector of two signed integers.
+#[allow(non_upper_case_globals)]
+pub const MTLDataTypeInt2: MTLDataType = 30;
+/// A vector of three signed integers, padded to the size of four.
+#[allow(non_upper_case_globals)]
+pub const MTLDataTypeInt3: MTLDataType = 31;
+/// A vector of four signed integers.
+#[allow(non_upper_case_globals)]
+pub const MTLDataTypeInt4: MTLDataType = 32;
+/// A 32-bit unsigned integer.
+#[allow(non_upper_case_globals)]
+pub const MTLDataTypeUInt: MTLDataType = 33;
+/// A vector of two unsigned integers.
+#[allow(non_upper_case_globals)]
+pub const MTLDataTypeUInt2: MTLDataType = 34;
+/// A vector of three unsigned integers, padded to the size of four.
+#[allow(non_upper_case_globals)]
+pub const MTLDataTypeUInt3: MTLDataType = 35;
+/// A vector of four unsigned integers.
+#[allow(non_upper_case_globals)]
+pub const MTLDataTypeUInt4: MTLDataType = 36;
+/// A boolean.
+#[allow(non_upper_case_globals)]
+pub const MTLDataTypeBool: MTLDataType = 53;
+/// A texture, written as an `MTLResourceID`.
+#[allow(non_upper_case_globals)]
+pub const MTLDataTypeTexture: MTLDataType = 58;
+/// A sampler, written as an `MTLResourceID`.
+#[allow(non_upper_case_globals)]
+pub const MTLDataTypeSampler: MTLDataType = 59;
+/// A pointer to a buffer, written as its GPU address.
+#[allow(non_upper_case_globals)]
+pub const MTLDataTypePointer: MTLDataType = 60;
+/// An indirect command buffer, written as an `MTLResourceID`.
+#[allow(non_upper_case_globals)]
+pub const MTLDataTypeIndirectCommandBuffer: MTLDataType = 80;
+/// A 64-bit signed integer.
+#[allow(non_upper_case_globals)]
+pub const MTLDataTypeLong: MTLDataType = 81;
+/// A 64-bit unsigned integer.
+#[allow(non_upper_case_globals)]
+pub const MTLDataTypeULong: MTLDataType = 85;
+//
+// typedef NS_ENUM(NSUInteger, MTLBindingAccess) {
+//     MTLBindingAccessReadOnly   = 0,
+//     MTLBindingAccessReadWrite  = 1,
+//     MTLBindingAccessWriteOnly  = 2,
+// } API_AVAILABLE(macos(14.0), ios(17.0));
+/// How a shader may use an argument.
+pub type MTLBindingAccess = NSUInteger;
+/// The shader only reads the argument.
+#[allow(non_upper_case_globals)]
+pub const MTLBindingAccessReadOnly: MTLBindingAccess = 0;
+/// The shader reads and writes the argument.
+#[allow(non_upper_case_globals)]
+pub const MTLBindingAccessReadWrite: MTLBindingAccess = 1;
+/// The shader only writes the argument.
+#[allow(non_upper_case_globals)]
+pub const MTLBindingAccessWriteOnly: MTLBindingAccess = 2;
+
+/// Returns the size and alignment in bytes of one argument of the given type,
+/// following the Metal Shading Language's rules, or None for types we don't lay out.
+#[allow(non_upper_case_globals)]
+pub fn data_type_size_and_alignment(data_type: MTLDataType) -> Option<(NSUInteger, NSUInteger)> {
+    match data_type {
+        MTLDataTypeBool => Some((1, 1)),
+        MTLDataTypeHalf => Some((2, 2)),
+        MTLDataTypeFloat | MTLDataTypeInt | MTLDataTypeUInt => Some((4, 4)),
+        MTLDataTypeFloat2 | MTLDataTypeInt2 | MTLDataTypeUInt2 => Some((8, 8)),
+        MTLDataTypeFloat3 | MTLDataTypeInt3 | MTLDataTypeUInt3 => Some((16, 16)),
+        MTLDataTypeFloat4 | MTLDataTypeInt4 | MTLDataTypeUInt4 => Some((16, 16)),
+        MTLDataTypeFloat4x4 => Some((64, 16)),
+        MTLDataTypeLong | MTLDataTypeULong => Some((8, 8)),
+        MTLDataTypeTexture | MTLDataTypeSampler | MTLDataTypePointer | MTLDataTypeIndirectCommandBuffer => Some((8, 8)),
+        _ => None,
+    }
+}
+
+/// One argument (or array of arguments) in an argument buffer,
+/// as declared with `[[id(n)]]` in the shader.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ArgumentField {
+    /// The argument's `[[id(n)]]`.
+    pub index: NSUInteger,
+    /// The type of each argument.
+    pub data_type: MTLDataType,
+    /// The number of arguments in an array, which take consecutive ids, or 0 for a single argument.
+    pub array_length: NSUInteger,
+    /// How the shader uses the argument.
+    pub access: MTLBindingAccess,
+    /// The type of texture, for texture arguments.
+    pub texture_type: MTLTextureType,
+}
+impl ArgumentField {
+    /// A read-only argument of the given type.
+    pub fn new(index: NSUInteger, data_type: MTLDataType) -> Self {
+        ArgumentField {
+            index,
+            data_type,
+            array_length: 0,
+            access: MTLBindingAccessReadOnly,
+            texture_type: MTLTextureType2D,
+        }
+    }
+    /// A pointer to a buffer.
+    pub fn buffer(index: NSUInteger) -> Self { Self::new(index, MTLDataTypePointer) }
+    /// A texture of the given type.
+    pub fn texture(index: NSUInteger, texture_type: MTLTextureType) -> Self {
+        ArgumentField { texture_type, ..Self::new(index, MTLDataTypeTexture) }
+    }
+    /// A sampler.
+    pub fn sampler(index: NSUInteger) -> Self { Self::new(index, MTLDataTypeSampler) }
+    /// Makes this an array of arguments, taking `array_length` consecutive ids.
+    pub fn with_array_length(self, array_length: NSUInteger) -> Self {
+        ArgumentField { array_length, ..self }
+    }
+    /// Sets how the shader uses the argument.
+    pub fn with_access(self, access: MTLBindingAccess) -> Self {
+        ArgumentField { access, ..self }
+    }
+    /// Gets the number of ids, and so arguments, the field takes.
+    pub fn element_count(&self) -> NSUInteger { self.array_length.max(1) }
+}
+
+/// Where one field sits in an argument buffer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ArgumentLayoutEntry {
+    /// The field laid out.
+    pub field: ArgumentField,
+    /// The offset of its first element, in bytes.
+    pub offset: NSUInteger,
+    /// The size of each element, in bytes.
+    pub element_size: NSUInteger,
+}
+impl ArgumentLayoutEntry {
+    /// Gets the size of the whole field, in bytes.
+    pub fn size(&self) -> NSUInteger { self.element_size * self.field.element_count() }
+}
+
+/// The layout of a Tier 2 argument buffer: fields in `[[id(n)]]` order,
+/// each at the next offset suiting its alignment, as the shader compiler lays out a struct.
+///
+/// Write a matching `#[repr(C)]` struct, using `u64` GPU addresses for buffers
+/// and `MTLResourceID`s for textures and samplers, and check it with `check_struct`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArgumentLayout {
+    entries: Vec<ArgumentLayoutEntry>,
+    encoded_length: NSUInteger,
+    alignment: NSUInteger,
+}
+impl ArgumentLayout {
+    /// Lays out the fields, which may be given in any order.
+    pub fn new(fields: &[ArgumentField]) -> Result<Self, MetalError> {
+        let mut fields = fields.to_vec();
+        fields.sort_by_key(|field| field.index);
+        let mut entries = Vec::with_capacity(fields.len());
+        let mut next_index = 0;
+        let mut offset: NSUInteger = 0;
+        let mut alignment: NSUInteger = 1;
+        for (position, field) in fields.iter().enumerate() {
+            if position > 0 && field.index < next_index {
+                return Err(MetalError::DuplicateArgumentIndex(field.index));
+            }
+            let (element_size, element_alignment) = data_type_size_and_alignment(field.data_type)
+                .ok_or(MetalError::UnsupportedArgumentDataType(field.data_type))?;
+            offset = align_up(offset, element_alignment);
+            let entry = ArgumentLayoutEntry { field: *field, offset, element_size };
+            offset += entry.size();
+            alignment = alignment.max(element_alignment);
+            next_index = field.index + field.element_count();
+            entries.push(entry);
+        }
+        Ok(ArgumentLayout { entries, encoded_length: align_up(offset, alignment), alignment })
+    }
+    /// Gets the fields in id order, with their offsets.
+    pub fn entries(&self) -> &[ArgumentLayoutEntry] { &self.entries }
+    /// Gets the size of the argument buffer in bytes, padded to its alignment.
+    pub fn encoded_length(&self) -> NSUInteger { self.encoded_length }
+    /// Gets the alignment of the argument buffer in bytes.
+    pub fn alignment(&self) -> NSUInteger { self.alignment }
+    /// Gets the offset in bytes of the argument with the given id,
+    /// which may be an element of an array.
+    pub fn offset_of(&self, index: NSUInteger) -> Option<NSUInteger> {
+        self.entries.iter()
+            .find(|entry| index >= entry.field.index && index < entry.field.index + entry.field.element_count())
+            .map(|entry| entry.offset + (index - entry.field.index) * entry.element_size)
+    }
+    /// Checks that a Rust struct is the size and alignment of the argument buffer,
+    /// so it can be written straight into it.
+    pub fn check_struct<T>(&self) -> Result<(), MetalError> {
+        let actual = size_of::<T>() as NSUInteger;
+        if actual != self.encoded_length || (align_of::<T>() as NSUInteger) < self.alignment {
+            return Err(MetalError::ArgumentLayoutMismatch { expected: self.encoded_length, actual });
+        }
+        Ok(())
+    }
+}
+
+/// Rounds the offset up to a multiple of the alignment (a power of two).
+fn align_up(offset: NSUInteger, alignment: NSUInteger) -> NSUInteger {
+    (offset + alignment - 1) & !(alignment - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    /// Matches `struct Material { texture2d<float> base [[id(0)]]; sampler s [[id(1)]];
+    /// constant Light *lights [[id(2)]]; float roughness [[id(3)]]; float4 tint [[id(4)]]; }`.
+    #[repr(C, align(16))]
+    struct Material {
+        base: MTLResourceID,
+        sampler: MTLResourceID,
+        lights: u64,
+        roughness: f32,
+        _padding: f32,
+        tint: [f32; 4],
+    }
+
+    /// The same fields without the padding a float4 needs.
+    #[repr(C)]
+    struct UnpaddedMaterial {
+        base: MTLResourceID,
+        sampler: MTLResourceID,
+        lights: u64,
+        roughness: f32,
+        tint: [f32; 4],
+    }
+
+    fn material_fields() -> Vec<ArgumentField> {
+        vec![
+            ArgumentField::new(4, MTLDataTypeFloat4),
+            ArgumentField::texture(0, MTLTextureType2D),
+            ArgumentField::sampler(1),
+            ArgumentField::buffer(2),
+            ArgumentField::new(3, MTLDataTypeFloat),
+        ]
+    }
+
+    #[test]
+    fn test_fields_are_laid_out_in_id_order_with_padding() {
+        let layout = ArgumentLayout::new(&material_fields()).unwrap();
+        let offsets: Vec<_> = layout.entries().iter().map(|entry| (entry.field.index, entry.offset)).collect();
+        assert_eq!(vec![(0, 0), (1, 8), (2, 16), (3, 24), (4, 32)], offsets);
+        assert_eq!(48, layout.encoded_length());
+        assert_eq!(16, layout.alignment());
+        assert_eq!(Some(24), layout.offset_of(3));
+        assert_eq!(None, layout.offset_of(5));
+    }
+
+    #[test]
+    fn test_arrays_take_consecutive_ids() {
+        let layout = ArgumentLayout::new(&[
+            ArgumentField::texture(0, MTLTextureType2D).with_array_length(4),
+            ArgumentField::new(4, MTLDataTypeUInt),
+        ]).unwrap();
+        assert_eq!(Some(0), layout.offset_of(0));
+        assert_eq!(Some(24), layout.offset_of(3));
+        assert_eq!(Some(32), layout.offset_of(4));
+        assert_eq!(40, layout.encoded_length());
+        assert_eq!(32, layout.entries()[0].size());
+
+        assert_eq!(
+            Err(MetalError::DuplicateArgumentIndex(2)),
+            ArgumentLayout::new(&[ArgumentField::buffer(0).with_array_length(3), ArgumentField::sampler(2)])
+        );
+        assert_eq!(
+            Err(MetalError::UnsupportedArgumentDataType(1)),
+            ArgumentLayout::new(&[ArgumentField::new(0, 1)])
+        );
+    }
+
+    #[test]
+    fn test_rust_struct_must_match_layout() {
+        let layout = ArgumentLayout::new(&material_fields()).unwrap();
+        // Right size, but only 8-byte aligned, so the tint lands at 28 rather than 32.
+        assert_eq!(
+            Err(MetalError::ArgumentLayoutMismatch { expected: 48, actual: 48 }),
+            layout.check_struct::<UnpaddedMaterial>()
+        );
+        assert_eq!(
+            Err(MetalError::ArgumentLayoutMismatch { expected: 48, actual: 40 }),
+            layout.check_struct::<[u64; 5]>()
+        );
+        assert_eq!(Ok(()), layout.check_struct::<Material>());
+
+        let material = Material {
+            base: MTLResourceID { raw: 1 },
+            sampler: MTLResourceID { raw: 2 },
+            lights: 0x1000,
+            roughness: 0.5,
+            _padding: 0.0,
+            tint: [1.0; 4],
+        };
+        let start = &material as *const Material as usize;
+        assert_eq!(layout.offset_of(2), Some((&material.lights as *const u64 as usize - start) as u64));
+        assert_eq!(layout.offset_of(4), Some((material.tint.as_ptr() as usize - start) as u64));
+        assert_eq!(Ok(()), ArgumentLayout::new(&[]).unwrap().check_struct::<()>());
+    }
+}
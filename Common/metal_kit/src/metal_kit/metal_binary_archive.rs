@@ -0,0 +1,86 @@
+//
+//  metal_binary_archive.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Thin wrappers for the methods we use from MTLBinaryArchive
+
+use objc::msg_send;
+use objc::sel;
+use objc::sel_impl;
+use cocoa::base::{id, nil};
+use cocoa::foundation::NSAutoreleasePool;
+use objc::runtime::{objc_release, objc_retain, BOOL, NO};
+use std::path::Path;
+use crate::metal_kit::metal_error::{MetalError, NSErrorInfo};
+use crate::metal_kit::metal_render_pipeline_descriptor::MetalRenderPipelineDescriptor;
+use crate::metal_kit::ns_string::{file_url_from_path, get_objc_label, set_objc_label};
+
+/// Rust wrapper for a container of compiled pipeline functions,
+/// which can be saved to disk so later runs skip compiling them.
+pub struct MetalBinaryArchive {
+    archive: id,
+}
+impl Default for MetalBinaryArchive {
+    fn default() -> Self {
+        MetalBinaryArchive { archive: nil }
+    }
+}
+impl From<id> for MetalBinaryArchive {
+    fn from(archive: id) -> Self {
+        let archive = unsafe { objc_retain(archive) };
+        MetalBinaryArchive { archive }
+    }
+}
+impl Drop for MetalBinaryArchive {
+    fn drop(&mut self) { unsafe { objc_release(self.archive) } }
+}
+impl MetalBinaryArchive {
+    /// Wraps an archive returned from a `new...` method,
+    /// balancing the retain the method has already done for us.
+    pub(crate) fn from_new_object(archive: id) -> Self {
+        let result = MetalBinaryArchive::from(archive);
+        unsafe { objc_release(archive) };
+        result
+    }
+    /// Returns the underlying Objective C archive.
+    pub fn to_objc(&self) -> id { self.archive }
+    /// Sets the label used to identify the archive in debugging tools.
+    pub fn set_label(&mut self, label: &str) { set_objc_label(self.archive, label) }
+    /// Gets the label (empty if none was set).
+    pub fn get_label(&self) -> String { get_objc_label(self.archive) }
+    /// Compiles the functions the descriptor uses, for its attachments and vertex layout,
+    /// and adds them to the archive.
+    pub fn add_render_pipeline_functions(&mut self, descriptor: &MetalRenderPipelineDescriptor) -> Result<(), MetalError> {
+        // - (BOOL)addRenderPipelineFunctionsWithDescriptor:(MTLRenderPipelineDescriptor *)descriptor
+        //                                            error:(NSError * __autoreleasing *)error;
+        let pool = unsafe { NSAutoreleasePool::new(nil) };
+        let mut error: id = nil;
+        let added: BOOL = unsafe { msg_send![self.archive, addRenderPipelineFunctionsWithDescriptor:descriptor.to_objc() error:&mut error] };
+        let result = if added == NO {
+            Err(MetalError::BinaryArchive(NSErrorInfo::from_ns_error_or_unknown(error)))
+        } else {
+            Ok(())
+        };
+        unsafe { pool.drain() };
+        result
+    }
+    /// Writes the archive to the given file, replacing it if it exists.
+    pub fn serialize_to_path(&self, path: &Path) -> Result<(), MetalError> {
+        // - (BOOL)serializeToURL:(NSURL *)url error:(NSError * __autoreleasing *)error;
+        let pool = unsafe { NSAutoreleasePool::new(nil) };
+        let url = file_url_from_path(path);
+        let mut error: id = nil;
+        let written: BOOL = unsafe { msg_send![self.archive, serializeToURL:url error:&mut error] };
+        let result = if written == NO {
+            Err(MetalError::BinaryArchive(NSErrorInfo::from_ns_error_or_unknown(error)))
+        } else {
+            Ok(())
+        };
+        unsafe { pool.drain() };
+        result
+    }
+}
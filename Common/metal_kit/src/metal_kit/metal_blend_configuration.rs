@@ -0,0 +1,357 @@
+//
+//  metal_blend_configuration.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Blending settings for a render pipeline's color attachments,
+//! kept as a plain Rust value until they are applied to a descriptor
+
+use cocoa::foundation::NSUInteger;
+use crate::metal_kit::metal_error::MetalError;
+
+// From MTLRenderPipeline.h:
+// typedef NS_ENUM(NSUInteger, MTLBlendFactor) {
+//     MTLBlendFactorZero = 0,
+//     MTLBlendFactorOne = 1,
+//     MTLBlendFactorSourceColor = 2,
+//     MTLBlendFactorOneMinusSourceColor = 3,
+//     MTLBlendFactorSourceAlpha = 4,
+//     MTLBlendFactorOneMinusSourceAlpha = 5,
+//     MTLBlendFactorDestinationColor = 6,
+//     MTLBlendFactorOneMinusDestinationColor = 7,
+//     MTLBlendFactorDestinationAlpha = 8,
+//     MTLBlendFactorOneMinusDestinationAlpha = 9,
+//     MTLBlendFactorSourceAlphaSaturated = 10,
+//     MTLBlendFactorBlendColor = 11,
+//     MTLBlendFactorOneMinusBlendColor = 12,
+//     MTLBlendFactorBlendAlpha = 13,
+//     MTLBlendFactorOneMinusBlendAlpha = 14,
+//     MTLBlendFactorSource1Color              API_AVAILABLE(macos(10.12), ios(10.11)) = 15,
+//     MTLBlendFactorOneMinusSource1Color      API_AVAILABLE(macos(10.12), ios(10.11)) = 16,
+//     MTLBlendFactorSource1Alpha              API_AVAILABLE(macos(10.12), ios(10.11)) = 17,
+//     MTLBlendFactorOneMinusSource1Alpha      API_AVAILABLE(macos(10.12), ios(10.11)) = 18,
+// } API_AVAILABLE(macos(10.11), ios(8.0));
+/// The source and destination blend factors are often needed to complete specification of a blend operation.
+pub type MTLBlendFactor = NSUInteger;
+/// Blend factor of zero.
+#[allow(non_upper_case_globals)]
+pub const MTLBlendFactorZero: MTLBlendFactor = 0;
+/// Blend factor of one.
+#[allow(non_upper_case_globals)]
+pub const MTLBlendFactorOne: MTLBlendFactor = 1;
+/// Blend factor of source values.
+#[allow(non_upper_case_globals)]
+pub const MTLBlendFactorSourceColor: MTLBlendFactor = 2;
+/// Blend factor of one minus source values.
+#[allow(non_upper_case_globals)]
+pub const MTLBlendFactorOneMinusSourceColor: MTLBlendFactor = 3;
+/// Blend factor of source alpha.
+#[allow(non_upper_case_globals)]
+pub const MTLBlendFactorSourceAlpha: MTLBlendFactor = 4;
+/// Blend factor of one minus source alpha.
+#[allow(non_upper_case_globals)]
+pub const MTLBlendFactorOneMinusSourceAlpha: MTLBlendFactor = 5;
+/// Blend factor of destination values.
+#[allow(non_upper_case_globals)]
+pub const MTLBlendFactorDestinationColor: MTLBlendFactor = 6;
+/// Blend factor of one minus destination values.
+#[allow(non_upper_case_globals)]
+pub const MTLBlendFactorOneMinusDestinationColor: MTLBlendFactor = 7;
+/// Blend factor of destination alpha.
+#[allow(non_upper_case_globals)]
+pub const MTLBlendFactorDestinationAlpha: MTLBlendFactor = 8;
+/// Blend factor of one minus destination alpha.
+#[allow(non_upper_case_globals)]
+pub const MTLBlendFactorOneMinusDestinationAlpha: MTLBlendFactor = 9;
+/// Blend factor of the minimum of either source alpha or one minus destination alpha.
+#[allow(non_upper_case_globals)]
+pub const MTLBlendFactorSourceAlphaSaturated: MTLBlendFactor = 10;
+/// Blend factor of the RGB values set with the encoder's blend color.
+#[allow(non_upper_case_globals)]
+pub const MTLBlendFactorBlendColor: MTLBlendFactor = 11;
+/// Blend factor of one minus the RGB values set with the encoder's blend color.
+#[allow(non_upper_case_globals)]
+pub const MTLBlendFactorOneMinusBlendColor: MTLBlendFactor = 12;
+/// Blend factor of the alpha value set with the encoder's blend color.
+#[allow(non_upper_case_globals)]
+pub const MTLBlendFactorBlendAlpha: MTLBlendFactor = 13;
+/// Blend factor of one minus the alpha value set with the encoder's blend color.
+#[allow(non_upper_case_globals)]
+pub const MTLBlendFactorOneMinusBlendAlpha: MTLBlendFactor = 14;
+/// Blend factor of the second source color (dual-source blending).
+#[allow(non_upper_case_globals)]
+pub const MTLBlendFactorSource1Color: MTLBlendFactor = 15;
+/// Blend factor of one minus the second source color (dual-source blending).
+#[allow(non_upper_case_globals)]
+pub const MTLBlendFactorOneMinusSource1Color: MTLBlendFactor = 16;
+/// Blend factor of the second source alpha (dual-source blending).
+#[allow(non_upper_case_globals)]
+pub const MTLBlendFactorSource1Alpha: MTLBlendFactor = 17;
+/// Blend factor of one minus the second source alpha (dual-source blending).
+#[allow(non_upper_case_globals)]
+pub const MTLBlendFactorOneMinusSource1Alpha: MTLBlendFactor = 18;
+//
+// typedef NS_ENUM(NSUInteger, MTLBlendOperation) {
+//     MTLBlendOperationAdd = 0,
+//     MTLBlendOperationSubtract = 1,
+//     MTLBlendOperationReverseSubtract = 2,
+//     MTLBlendOperationMin = 3,
+//     MTLBlendOperationMax = 4,
+// } API_AVAILABLE(macos(10.11), ios(8.0));
+/// The operation used to combine the weighted source and destination values.
+pub type MTLBlendOperation = NSUInteger;
+/// Add portions of both source and destination pixel values.
+#[allow(non_upper_case_globals)]
+pub const MTLBlendOperationAdd: MTLBlendOperation = 0;
+/// Subtract a portion of the destination pixel values from a portion of the source.
+#[allow(non_upper_case_globals)]
+pub const MTLBlendOperationSubtract: MTLBlendOperation = 1;
+/// Subtract a portion of the source values from a portion of the destination pixel values.
+#[allow(non_upper_case_globals)]
+pub const MTLBlendOperationReverseSubtract: MTLBlendOperation = 2;
+/// Minimum of the source and destination pixel values.
+#[allow(non_upper_case_globals)]
+pub const MTLBlendOperationMin: MTLBlendOperation = 3;
+/// Maximum of the source and destination pixel values.
+#[allow(non_upper_case_globals)]
+pub const MTLBlendOperationMax: MTLBlendOperation = 4;
+//
+// typedef NS_OPTIONS(NSUInteger, MTLColorWriteMask) {
+//     MTLColorWriteMaskNone  = 0,
+//     MTLColorWriteMaskRed   = 0x1 << 3,
+//     MTLColorWriteMaskGreen = 0x1 << 2,
+//     MTLColorWriteMaskBlue  = 0x1 << 1,
+//     MTLColorWriteMaskAlpha = 0x1 << 0,
+//     MTLColorWriteMaskAll   = 0xf
+// } API_AVAILABLE(macos(10.11), ios(8.0));
+/// Values used to specify a mask to permit or restrict writing to color channels of a color value.
+pub type MTLColorWriteMask = NSUInteger;
+/// Do not write any color channels.
+#[allow(non_upper_case_globals)]
+pub const MTLColorWriteMaskNone: MTLColorWriteMask = 0;
+/// Write the red color channel.
+#[allow(non_upper_case_globals)]
+pub const MTLColorWriteMaskRed: MTLColorWriteMask = 0x1 << 3;
+/// Write the green color channel.
+#[allow(non_upper_case_globals)]
+pub const MTLColorWriteMaskGreen: MTLColorWriteMask = 0x1 << 2;
+/// Write the blue color channel.
+#[allow(non_upper_case_globals)]
+pub const MTLColorWriteMaskBlue: MTLColorWriteMask = 0x1 << 1;
+/// Write the alpha color channel.
+#[allow(non_upper_case_globals)]
+pub const MTLColorWriteMaskAlpha: MTLColorWriteMask = 0x1;
+/// Write all color channels.
+#[allow(non_upper_case_globals)]
+pub const MTLColorWriteMaskAll: MTLColorWriteMask = 0xf;
+
+/// The blending settings for one color attachment of a render pipeline.
+///
+/// The blended color is
+/// `source * source_factor <operation> destination * destination_factor`,
+/// worked out separately for the RGB and the alpha channels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MetalBlendConfiguration {
+    /// Whether blending is done at all.
+    /// If false, the fragment color replaces the attachment color.
+    pub blending_enabled: bool,
+    /// How the weighted RGB values are combined.
+    pub rgb_blend_operation: MTLBlendOperation,
+    /// How the weighted alpha values are combined.
+    pub alpha_blend_operation: MTLBlendOperation,
+    /// The weight given to the fragment's RGB values.
+    pub source_rgb_blend_factor: MTLBlendFactor,
+    /// The weight given to the fragment's alpha value.
+    pub source_alpha_blend_factor: MTLBlendFactor,
+    /// The weight given to the attachment's RGB values.
+    pub destination_rgb_blend_factor: MTLBlendFactor,
+    /// The weight given to the attachment's alpha value.
+    pub destination_alpha_blend_factor: MTLBlendFactor,
+    /// Which channels of the attachment are written.
+    pub write_mask: MTLColorWriteMask,
+}
+impl Default for MetalBlendConfiguration {
+    fn default() -> Self { MetalBlendConfiguration::opaque() }
+}
+
+impl MetalBlendConfiguration {
+    /// Creates an enabled configuration that uses the same factors and operation
+    /// for the RGB and alpha channels.
+    pub fn with_factors(
+        source_factor: MTLBlendFactor,
+        destination_factor: MTLBlendFactor,
+        operation: MTLBlendOperation
+    ) -> Self {
+        MetalBlendConfiguration {
+            blending_enabled: true,
+            rgb_blend_operation: operation,
+            alpha_blend_operation: operation,
+            source_rgb_blend_factor: source_factor,
+            source_alpha_blend_factor: source_factor,
+            destination_rgb_blend_factor: destination_factor,
+            destination_alpha_blend_factor: destination_factor,
+            write_mask: MTLColorWriteMaskAll,
+        }
+    }
+    /// No blending: the fragment color replaces the attachment color.
+    /// (These are Metal's own defaults.)
+    pub fn opaque() -> Self {
+        MetalBlendConfiguration {
+            blending_enabled: false,
+            ..Self::with_factors(MTLBlendFactorOne, MTLBlendFactorZero, MTLBlendOperationAdd)
+        }
+    }
+    /// Conventional transparency for fragments whose color
+    /// is not premultiplied by their alpha.
+    pub fn alpha() -> Self {
+        MetalBlendConfiguration {
+            source_rgb_blend_factor: MTLBlendFactorSourceAlpha,
+            ..Self::premultiplied_alpha()
+        }
+    }
+    /// Transparency for fragments whose color is already premultiplied by their alpha.
+    pub fn premultiplied_alpha() -> Self {
+        Self::with_factors(MTLBlendFactorOne, MTLBlendFactorOneMinusSourceAlpha, MTLBlendOperationAdd)
+    }
+    /// Adds the fragment color (weighted by its alpha) to the attachment,
+    /// e.g. for glows and particles.
+    pub fn additive() -> Self {
+        MetalBlendConfiguration {
+            source_rgb_blend_factor: MTLBlendFactorSourceAlpha,
+            ..Self::with_factors(MTLBlendFactorOne, MTLBlendFactorOne, MTLBlendOperationAdd)
+        }
+    }
+    /// Multiplies the attachment color by the fragment color, e.g. for tinting and shadows.
+    pub fn multiply() -> Self {
+        MetalBlendConfiguration {
+            source_alpha_blend_factor: MTLBlendFactorDestinationAlpha,
+            ..Self::with_factors(MTLBlendFactorDestinationColor, MTLBlendFactorZero, MTLBlendOperationAdd)
+        }
+    }
+    /// Returns this configuration with only the given channels written.
+    pub fn with_write_mask(self, write_mask: MTLColorWriteMask) -> Self {
+        MetalBlendConfiguration { write_mask, ..self }
+    }
+    /// Returns true if the configuration reads from the second fragment output,
+    /// which needs dual-source blending.
+    pub fn uses_dual_source_blending(&self) -> bool {
+        [
+            self.source_rgb_blend_factor,
+            self.source_alpha_blend_factor,
+            self.destination_rgb_blend_factor,
+            self.destination_alpha_blend_factor,
+        ].iter().any(|factor| (MTLBlendFactorSource1Color..=MTLBlendFactorOneMinusSource1Alpha).contains(factor))
+    }
+    /// Checks that every setting is one Metal understands.
+    pub fn validate(&self) -> Result<(), MetalError> {
+        for operation in [self.rgb_blend_operation, self.alpha_blend_operation].iter() {
+            if *operation > MTLBlendOperationMax {
+                return Err(MetalError::InvalidBlendOperation(*operation));
+            }
+        }
+        for factor in [
+            self.source_rgb_blend_factor,
+            self.source_alpha_blend_factor,
+            self.destination_rgb_blend_factor,
+            self.destination_alpha_blend_factor,
+        ].iter() {
+            if *factor > MTLBlendFactorOneMinusSource1Alpha {
+                return Err(MetalError::InvalidBlendFactor(*factor));
+            }
+        }
+        if self.write_mask & !MTLColorWriteMaskAll != 0 {
+            return Err(MetalError::InvalidColorWriteMask(self.write_mask));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_presets_are_valid_and_distinct() {
+        let presets = [
+            MetalBlendConfiguration::opaque(),
+            MetalBlendConfiguration::alpha(),
+            MetalBlendConfiguration::premultiplied_alpha(),
+            MetalBlendConfiguration::additive(),
+            MetalBlendConfiguration::multiply(),
+        ];
+        for preset in presets.iter() {
+            assert_eq!(Ok(()), preset.validate());
+            assert!(!preset.uses_dual_source_blending());
+        }
+        let distinct: HashSet<MetalBlendConfiguration> = presets.iter().cloned().collect();
+        assert_eq!(presets.len(), distinct.len());
+    }
+
+    #[test]
+    fn test_preset_factors() {
+        let opaque = MetalBlendConfiguration::default();
+        assert!(!opaque.blending_enabled);
+        assert_eq!(MetalBlendConfiguration::opaque(), opaque);
+
+        let alpha = MetalBlendConfiguration::alpha();
+        assert!(alpha.blending_enabled);
+        assert_eq!(MTLBlendFactorSourceAlpha, alpha.source_rgb_blend_factor);
+        assert_eq!(MTLBlendFactorOne, alpha.source_alpha_blend_factor);
+        assert_eq!(MTLBlendFactorOneMinusSourceAlpha, alpha.destination_rgb_blend_factor);
+        assert_eq!(MTLBlendFactorOneMinusSourceAlpha, alpha.destination_alpha_blend_factor);
+
+        let premultiplied = MetalBlendConfiguration::premultiplied_alpha();
+        assert_eq!(MTLBlendFactorOne, premultiplied.source_rgb_blend_factor);
+
+        let additive = MetalBlendConfiguration::additive();
+        assert_eq!(MTLBlendFactorOne, additive.destination_rgb_blend_factor);
+        assert_eq!(MTLBlendOperationAdd, additive.rgb_blend_operation);
+
+        let multiply = MetalBlendConfiguration::multiply();
+        assert_eq!(MTLBlendFactorDestinationColor, multiply.source_rgb_blend_factor);
+        assert_eq!(MTLBlendFactorDestinationAlpha, multiply.source_alpha_blend_factor);
+        assert_eq!(MTLBlendFactorZero, multiply.destination_rgb_blend_factor);
+    }
+
+    #[test]
+    fn test_write_mask() {
+        let color_only = MetalBlendConfiguration::alpha()
+            .with_write_mask(MTLColorWriteMaskRed | MTLColorWriteMaskGreen | MTLColorWriteMaskBlue);
+        assert_eq!(0xe, color_only.write_mask);
+        assert_ne!(MetalBlendConfiguration::alpha(), color_only);
+        assert_eq!(Ok(()), color_only.validate());
+    }
+
+    #[test]
+    fn test_validation_rejects_unknown_values() {
+        let bad_operation = MetalBlendConfiguration {
+            alpha_blend_operation: 5,
+            ..MetalBlendConfiguration::alpha()
+        };
+        assert_eq!(Err(MetalError::InvalidBlendOperation(5)), bad_operation.validate());
+
+        let bad_factor = MetalBlendConfiguration {
+            destination_rgb_blend_factor: 19,
+            ..MetalBlendConfiguration::alpha()
+        };
+        assert_eq!(Err(MetalError::InvalidBlendFactor(19)), bad_factor.validate());
+
+        let bad_mask = MetalBlendConfiguration::alpha().with_write_mask(0x10);
+        assert_eq!(Err(MetalError::InvalidColorWriteMask(0x10)), bad_mask.validate());
+    }
+
+    #[test]
+    fn test_dual_source_detection() {
+        let dual_source = MetalBlendConfiguration::with_factors(
+            MTLBlendFactorOne,
+            MTLBlendFactorOneMinusSource1Color,
+            MTLBlendOperationAdd
+        );
+        assert!(dual_source.uses_dual_source_blending());
+        assert_eq!(Ok(()), dual_source.validate());
+    }
+}
@@ -0,0 +1,140 @@
+//
+//  metal_blit_command_encoder.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Thin wrappers for methods we use from MTLBlitCommandEncoder
+
+use objc::msg_send;
+use objc::sel;
+use objc::sel_impl;
+use cocoa::base::{id, nil};
+use cocoa::foundation::NSUInteger;
+use objc::runtime::{objc_release, objc_retain, NO, YES};
+use std::ops::Range;
+use crate::metal_kit::metal_debug_group::MetalDebugGroups;
+use crate::metal_kit::metal_error::MetalError;
+use crate::metal_kit::metal_fence::MetalFence;
+use crate::metal_kit::metal_buffer::MetalBuffer;
+use crate::metal_kit::metal_device_capabilities::MTLSize;
+use crate::metal_kit::metal_texture::{MTLOrigin, MetalTexture};
+use crate::metal_kit::metal_counter_sample_buffer::MetalCounterSampleBuffer;
+use crate::metal_kit::metal_indirect_command_buffer::{ns_range, MetalIndirectCommandBuffer};
+use crate::metal_kit::ns_string::{get_objc_label, ns_string_from_str, set_objc_label};
+
+/// Rust wrapper for an encoder of copy and maintenance commands
+/// run on the GPU between passes.
+pub struct MetalBlitCommandEncoder {
+    encoder: id,
+}
+impl Default for MetalBlitCommandEncoder {
+    fn default() -> Self {
+        MetalBlitCommandEncoder { encoder: nil }
+    }
+}
+impl From<id> for MetalBlitCommandEncoder {
+    fn from(encoder: id) -> Self {
+        let encoder = unsafe { objc_retain(encoder) };
+        MetalBlitCommandEncoder { encoder }
+    }
+}
+impl Drop for MetalBlitCommandEncoder {
+    fn drop(&mut self) { unsafe { objc_release(self.encoder) } }
+}
+impl MetalBlitCommandEncoder {
+    /// Returns the underlying Objective C encoder.
+    pub fn to_objc(&self) -> id { self.encoder }
+    /// Sets the label used to identify the encoder in debugging tools.
+    pub fn set_label(&mut self, label: &str) { set_objc_label(self.encoder, label) }
+    /// Gets the label (empty if none was set).
+    pub fn get_label(&self) -> String { get_objc_label(self.encoder) }
+    /// Encodes a command that resets a range of commands in an indirect command buffer,
+    /// e.g. before a kernel encodes new ones into it.
+    pub fn reset_commands_in_buffer(
+        &mut self,
+        buffer: &mut MetalIndirectCommandBuffer,
+        range: Range<NSUInteger>
+    ) -> Result<(), MetalError> {
+        buffer.counter_mut().reset(&range)?;
+        unsafe { msg_send![self.encoder, resetCommandsInBuffer:buffer.to_objc() withRange:ns_range(&range)] }
+        Ok(())
+    }
+    /// Encodes a command that removes redundant state changes from a range of commands
+    /// in an indirect command buffer.
+    pub fn optimize_indirect_command_buffer(
+        &mut self,
+        buffer: &MetalIndirectCommandBuffer,
+        range: Range<NSUInteger>
+    ) -> Result<(), MetalError> {
+        buffer.get_counter().check_range(&range)?;
+        unsafe { msg_send![self.encoder, optimizeIndirectCommandBuffer:buffer.to_objc() withRange:ns_range(&range)] }
+        Ok(())
+    }
+    /// Updates the fence once the GPU has finished the commands encoded so far.
+    pub fn update_fence(&mut self, fence: &MetalFence) {
+        unsafe { msg_send![self.encoder, updateFence:fence.to_objc()] }
+    }
+    /// Makes the following commands wait until another pass has updated the fence.
+    pub fn wait_for_fence(&mut self, fence: &MetalFence) {
+        unsafe { msg_send![self.encoder, waitForFence:fence.to_objc()] }
+    }
+    /// Encodes a command that writes the counter set's values into the sample buffer at the index,
+    /// optionally waiting for the commands before it to finish first.
+    ///
+    /// The device has to support sampling at `MTLCounterSamplingPointAtBlitBoundary`; see `supports_counter_sampling`.
+    pub fn sample_counters_in_buffer(&mut self, sample_buffer: &MetalCounterSampleBuffer, sample_index: NSUInteger, barrier: bool) {
+        let barrier = if barrier { YES } else { NO };
+        unsafe { msg_send![self.encoder, sampleCountersInBuffer:sample_buffer.to_objc() atSampleIndex:sample_index withBarrier:barrier] }
+    }
+    /// Encodes a command that copies pixels from a slice and mipmap level of a texture
+    /// into a buffer, `bytes_per_row` apart, with each depth plane `bytes_per_image` apart.
+    #[allow(clippy::too_many_arguments)]
+    pub fn copy_from_texture_to_buffer(
+        &mut self,
+        texture: &MetalTexture,
+        source_slice: NSUInteger,
+        source_level: NSUInteger,
+        source_origin: MTLOrigin,
+        source_size: MTLSize,
+        buffer: &MetalBuffer,
+        destination_offset: NSUInteger,
+        destination_bytes_per_row: NSUInteger,
+        destination_bytes_per_image: NSUInteger
+    ) {
+        unsafe { msg_send![self.encoder, copyFromTexture:texture.to_objc()
+                                             sourceSlice:source_slice
+                                             sourceLevel:source_level
+                                            sourceOrigin:source_origin
+                                              sourceSize:source_size
+                                                toBuffer:buffer.to_objc()
+                                       destinationOffset:destination_offset
+                                  destinationBytesPerRow:destination_bytes_per_row
+                                destinationBytesPerImage:destination_bytes_per_image] }
+    }
+    /// Declares that all command generation from the encoder is completed.
+    pub fn end_encoding(&mut self) {
+        unsafe { msg_send![self.encoder, endEncoding] }
+    }
+}
+impl MetalDebugGroups for MetalBlitCommandEncoder {
+    fn push_debug_group(&mut self, name: &str) {
+        let name = ns_string_from_str(name);
+        unsafe {
+            let _:() = msg_send![self.encoder, pushDebugGroup:name];
+            objc_release(name);
+        }
+    }
+    fn pop_debug_group(&mut self) {
+        unsafe { msg_send![self.encoder, popDebugGroup] }
+    }
+    fn insert_debug_signpost(&mut self, name: &str) {
+        let name = ns_string_from_str(name);
+        unsafe {
+            let _:() = msg_send![self.encoder, insertDebugSignpost:name];
+            objc_release(name);
+        }
+    }
+}
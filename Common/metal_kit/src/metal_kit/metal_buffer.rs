@@ -13,8 +13,9 @@ use objc::sel;
 use objc::sel_impl;
 use cocoa::base::{id, nil};
 use objc::runtime::{objc_release, objc_retain};
-use cocoa::foundation::{NSString, NSUInteger};
+use cocoa::foundation::{NSRange, NSUInteger};
 use std::os::raw::c_void;
+use crate::metal_kit::ns_string::{get_objc_label, set_objc_label};
 
 /// Rust wrapper for MTLBuffer
 pub struct MetalBuffer {
@@ -40,9 +41,15 @@ impl MetalBuffer {
     pub fn to_objc(&self) -> id { self.buffer }
     /// Sets the buffer label
     #[inline]
-    pub fn set_label(&mut self, label: &str) {
-        let label = unsafe { NSString::alloc(nil).init_str(label) };
-        unsafe { msg_send![self.buffer, setLabel:label] }
+    pub fn set_label(&mut self, label: &str) { set_objc_label(self.buffer, label) }
+    /// Gets the buffer label (empty if none was set).
+    #[inline]
+    pub fn get_label(&self) -> String { get_objc_label(self.buffer) }
+    /// Gets the address of the buffer on the GPU,
+    /// to write into a Tier 2 argument buffer in place of the buffer.
+    #[inline]
+    pub fn get_gpu_address(&self) -> u64 {
+        unsafe { msg_send![self.buffer, gpuAddress] }
     }
     /// Gets the system address of the buffer’s storage allocation.
     #[inline]
@@ -54,4 +61,15 @@ impl MetalBuffer {
     pub fn get_length(&self) -> NSUInteger {
         unsafe { msg_send![self.buffer, length] }
     }
+    /// Gets the location and access permissions of the buffer's memory.
+    #[inline]
+    pub fn get_storage_mode(&self) -> NSUInteger {
+        unsafe { msg_send![self.buffer, storageMode] }
+    }
+    /// Informs the GPU that the CPU has modified a section of a managed buffer.
+    #[inline]
+    pub fn did_modify_range(&self, location: NSUInteger, length: NSUInteger) {
+        let range = NSRange::new(location, length);
+        unsafe { msg_send![self.buffer, didModifyRange:range] }
+    }
 }
@@ -0,0 +1,95 @@
+//
+//  metal_buffer_storage.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! The CPU-visible memory behind a buffer,
+//! either a Metal buffer or a plain block of host memory
+
+use cocoa::foundation::NSUInteger;
+use std::alloc::{alloc_zeroed, dealloc, handle_alloc_error, Layout};
+use std::cell::RefCell;
+use std::ops::Range;
+use crate::metal_kit::metal_buffer::MetalBuffer;
+use crate::metal_kit::metal_texture_descriptor::MTLStorageModeManaged;
+
+/// Memory the CPU can write values into before the GPU reads them.
+///
+/// `MetalBuffer` is the real thing;
+/// `HostBuffer` lets the bookkeeping built on top be tested without a GPU.
+pub trait BufferStorage {
+    /// Gets the address of the first byte, or null if there is no memory.
+    fn contents(&self) -> *mut u8;
+    /// Gets the length of the memory in bytes.
+    fn length(&self) -> NSUInteger;
+    /// Tells the GPU that the CPU has written to the given bytes.
+    fn did_modify_range(&self, location: NSUInteger, length: NSUInteger);
+}
+
+impl BufferStorage for MetalBuffer {
+    fn contents(&self) -> *mut u8 { self.get_contents() as *mut u8 }
+    fn length(&self) -> NSUInteger { self.get_length() }
+    /// Only managed buffers keep a separate GPU copy that needs to be told.
+    fn did_modify_range(&self, location: NSUInteger, length: NSUInteger) {
+        if length > 0 && self.get_storage_mode() == MTLStorageModeManaged {
+            MetalBuffer::did_modify_range(self, location, length)
+        }
+    }
+}
+
+/// Metal buffers start on a page boundary, so host buffers are at least this well aligned.
+pub const HOST_BUFFER_ALIGNMENT: usize = 256;
+
+/// A zero-filled block of host memory standing in for a Metal buffer.
+///
+/// It remembers the ranges it is told were modified,
+/// so tests can check what a managed buffer would have been told.
+#[derive(Debug)]
+pub struct HostBuffer {
+    contents: *mut u8,
+    length: usize,
+    modified_ranges: RefCell<Vec<Range<NSUInteger>>>,
+}
+impl Default for HostBuffer {
+    fn default() -> Self { HostBuffer::new(0) }
+}
+impl Drop for HostBuffer {
+    fn drop(&mut self) {
+        unsafe { dealloc(self.contents, Self::layout(self.length)) }
+    }
+}
+impl HostBuffer {
+    /// Allocates a zero-filled buffer of the given length in bytes.
+    pub fn new(length: usize) -> Self {
+        let layout = Self::layout(length);
+        let contents = unsafe { alloc_zeroed(layout) };
+        if contents.is_null() {
+            handle_alloc_error(layout);
+        }
+        HostBuffer { contents, length, modified_ranges: RefCell::new(Vec::new()) }
+    }
+    /// Gets the contents of the buffer.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.contents, self.length) }
+    }
+    /// Returns the ranges reported modified since the last call, oldest first.
+    pub fn take_modified_ranges(&self) -> Vec<Range<NSUInteger>> {
+        self.modified_ranges.take()
+    }
+    /// Always allocate at least one byte, as zero-sized allocations are not allowed.
+    fn layout(length: usize) -> Layout {
+        Layout::from_size_align(length.max(1), HOST_BUFFER_ALIGNMENT).expect("host buffer too large")
+    }
+}
+impl BufferStorage for HostBuffer {
+    fn contents(&self) -> *mut u8 { self.contents }
+    fn length(&self) -> NSUInteger { self.length as NSUInteger }
+    fn did_modify_range(&self, location: NSUInteger, length: NSUInteger) {
+        if length > 0 {
+            self.modified_ranges.borrow_mut().push(location..location + length);
+        }
+    }
+}
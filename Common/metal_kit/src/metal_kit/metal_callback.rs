@@ -0,0 +1,198 @@
+//
+//  metal_callback.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Rust closures passed through GlueLib as user data, for Metal to call on its own threads,
+//! and the futures they complete
+
+use std::future::Future;
+use std::os::raw::c_void;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// A callback waiting to be called, boxed twice so it fits through a thin `void *`.
+type BoxedCallback<A> = Box<dyn FnOnce(A) + Send + 'static>;
+
+/// Moves the callback onto the heap, giving the pointer to pass as user data.
+/// Ownership passes to whoever calls `run_callback` with it.
+pub(crate) fn callback_into_user_data<A, F>(callback: F) -> *const c_void
+    where F: FnOnce(A) + Send + 'static {
+    let callback: BoxedCallback<A> = Box::new(callback);
+    Box::into_raw(Box::new(callback)) as *const c_void
+}
+
+/// Takes back ownership of a callback made by `callback_into_user_data`, calls it and frees it.
+///
+/// A panic in the callback is caught here, as it must not unwind into Metal.
+///
+/// # Safety
+/// `user_data` must have come from `callback_into_user_data` with the same `A`,
+/// and not been run before.
+pub(crate) unsafe fn run_callback<A>(user_data: *const c_void, argument: A) {
+    let callback = Box::from_raw(user_data as *mut BoxedCallback<A>);
+    let _ = catch_unwind(AssertUnwindSafe(move || callback(argument)));
+}
+
+/// What the future and the callback that completes it share.
+#[derive(Debug)]
+struct CompletionState<T> {
+    value: Option<T>,
+    complete: bool,
+    waker: Option<Waker>,
+}
+
+/// A future that resolves to the value a callback is given, once Metal has called it.
+///
+/// It stays complete after the value has been taken.
+#[derive(Debug)]
+pub(crate) struct Completion<T> {
+    state: Arc<Mutex<CompletionState<T>>>,
+}
+impl<T: Send + 'static> Completion<T> {
+    /// Creates a future together with the callback that completes it.
+    pub(crate) fn new() -> (Self, impl FnOnce(T) + Send + 'static) {
+        let state = Arc::new(Mutex::new(CompletionState { value: None, complete: false, waker: None }));
+        let callback_state = state.clone();
+        let callback = move |value: T| {
+            let waker = {
+                let mut state = callback_state.lock().unwrap_or_else(|error| error.into_inner());
+                state.value = Some(value);
+                state.complete = true;
+                state.waker.take()
+            };
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        };
+        (Completion { state }, callback)
+    }
+}
+impl<T> Completion<T> {
+    /// Returns true once the callback has run, whether or not the value has been taken.
+    pub(crate) fn is_complete(&self) -> bool {
+        self.state.lock().map(|state| state.complete).unwrap_or(true)
+    }
+    /// Takes the value if the callback has run, without waiting.
+    pub(crate) fn try_take(&mut self) -> Option<T> {
+        self.state.lock().unwrap_or_else(|error| error.into_inner()).value.take()
+    }
+}
+impl<T> Future for Completion<T> {
+    type Output = T;
+    fn poll(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap_or_else(|error| error.into_inner());
+        match state.value.take() {
+            Some(value) => Poll::Ready(value),
+            None => {
+                state.waker = Some(context.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// A waker that does nothing, for tests that poll by hand.
+#[cfg(test)]
+pub(crate) fn noop_waker() -> Waker {
+    use std::task::{RawWaker, RawWakerVTable};
+    fn clone(data: *const ()) -> RawWaker { RawWaker::new(data, &VTABLE) }
+    fn noop(_: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+    unsafe { Waker::from_raw(RawWaker::new(std::ptr::null(), &VTABLE)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{callback_into_user_data, run_callback, Completion};
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    /// Counts how many times it is dropped.
+    struct DropCounter(Arc<AtomicUsize>);
+    impl Drop for DropCounter {
+        fn drop(&mut self) { self.0.fetch_add(1, Ordering::SeqCst); }
+    }
+
+    /// A waker that counts how many times it is woken.
+    fn counting_waker(wakes: &Arc<AtomicUsize>) -> Waker {
+        fn clone(data: *const ()) -> RawWaker {
+            unsafe { Arc::increment_strong_count(data as *const AtomicUsize) };
+            RawWaker::new(data, &VTABLE)
+        }
+        fn wake(data: *const ()) {
+            wake_by_ref(data);
+            drop_waker(data);
+        }
+        fn wake_by_ref(data: *const ()) {
+            unsafe { &*(data as *const AtomicUsize) }.fetch_add(1, Ordering::SeqCst);
+        }
+        fn drop_waker(data: *const ()) {
+            unsafe { Arc::decrement_strong_count(data as *const AtomicUsize) };
+        }
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake_by_ref, drop_waker);
+        let data = Arc::into_raw(wakes.clone()) as *const ();
+        unsafe { Waker::from_raw(RawWaker::new(data, &VTABLE)) }
+    }
+
+    #[test]
+    fn test_callback_runs_and_is_freed_exactly_once() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let drops = Arc::new(AtomicUsize::new(0));
+        let counter = DropCounter(drops.clone());
+        let callback_calls = calls.clone();
+        let user_data = callback_into_user_data(move |value: u64| {
+            let _counter = &counter;
+            assert_eq!(7, value);
+            callback_calls.fetch_add(1, Ordering::SeqCst);
+        });
+        assert_eq!(0, drops.load(Ordering::SeqCst));
+
+        // Simulate Metal calling the callback from its own thread.
+        let user_data = user_data as usize;
+        std::thread::spawn(move || unsafe {
+            run_callback::<u64>(user_data as *const _, 7)
+        }).join().unwrap();
+        assert_eq!(1, calls.load(Ordering::SeqCst));
+        assert_eq!(1, drops.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_panicking_callback_is_still_freed() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let counter = DropCounter(drops.clone());
+        let user_data = callback_into_user_data(move |_: u64| {
+            let _counter = &counter;
+            panic!("callback failed");
+        });
+        unsafe { run_callback::<u64>(user_data, 7) };
+        assert_eq!(1, drops.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_completion_stays_complete_once_taken() {
+        let (mut completion, callback) = Completion::<&str>::new();
+        let wakes = Arc::new(AtomicUsize::new(0));
+        let waker = counting_waker(&wakes);
+        let mut context = Context::from_waker(&waker);
+        assert!(!completion.is_complete());
+        assert_eq!(Poll::Pending, Pin::new(&mut completion).poll(&mut context));
+        assert_eq!(None, completion.try_take());
+
+        let user_data = callback_into_user_data(callback);
+        unsafe { run_callback(user_data, "done") };
+        assert_eq!(1, wakes.load(Ordering::SeqCst));
+        assert!(completion.is_complete());
+        assert_eq!(Poll::Ready("done"), Pin::new(&mut completion).poll(&mut context));
+        assert!(completion.is_complete());
+        assert_eq!(None, completion.try_take());
+    }
+}
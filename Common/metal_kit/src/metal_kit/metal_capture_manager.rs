@@ -0,0 +1,209 @@
+//
+//  metal_capture_manager.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Thin wrappers for MTLCaptureManager and MTLCaptureDescriptor,
+//! to capture frames from code rather than from Xcode's capture button
+
+use objc::msg_send;
+use objc::sel;
+use objc::sel_impl;
+use objc::class;
+use cocoa::base::{id, nil};
+use cocoa::foundation::{NSAutoreleasePool, NSInteger};
+use objc::runtime::{objc_release, objc_retain, BOOL, NO};
+use std::path::Path;
+use crate::metal_kit::metal_command_queue::MetalCommandQueue;
+use crate::metal_kit::metal_device::MetalDevice;
+use crate::metal_kit::metal_error::{MetalError, NSErrorInfo};
+use crate::metal_kit::ns_string::file_url_from_path;
+
+// From MTLCaptureManager.h:
+// typedef NS_ENUM(NSInteger, MTLCaptureDestination) {
+//     MTLCaptureDestinationDeveloperTools = 1,
+//     MTLCaptureDestinationGPUTraceDocument,
+// } API_AVAILABLE(macos(10.15), ios(13.0));
+/// Where a capture is sent.
+pub type MTLCaptureDestination = NSInteger;
+/// Sends the capture to Xcode, which has to be attached to the app.
+#[allow(non_upper_case_globals)]
+pub const MTLCaptureDestinationDeveloperTools: MTLCaptureDestination = 1;
+/// Writes the capture to a `.gputrace` document, which Xcode can open later.
+///
+/// Apps have to opt in, by setting `MetalCaptureEnabled` in their Info.plist
+/// or `MTL_CAPTURE_ENABLED=1` in their environment.
+#[allow(non_upper_case_globals)]
+pub const MTLCaptureDestinationGPUTraceDocument: MTLCaptureDestination = 2;
+
+/// The file extension Metal insists on for GPU trace documents.
+pub const GPU_TRACE_EXTENSION: &str = "gputrace";
+
+/// Checks that a capture can be written to the given path:
+/// it has to end in `.gputrace` and not exist yet.
+pub fn validate_gpu_trace_path(path: &Path) -> Result<(), MetalError> {
+    let is_gpu_trace = path.extension().is_some_and(|extension| extension == GPU_TRACE_EXTENSION);
+    if !is_gpu_trace || path.exists() {
+        return Err(MetalError::InvalidCaptureOutputPath(path.display().to_string()));
+    }
+    Ok(())
+}
+
+/// Rust wrapper for the settings of a capture:
+/// what to capture and where to send it.
+pub struct MetalCaptureDescriptor {
+    descriptor: id,
+}
+impl Default for MetalCaptureDescriptor {
+    fn default() -> Self {
+        MetalCaptureDescriptor { descriptor: nil }
+    }
+}
+impl From<id> for MetalCaptureDescriptor {
+    fn from(descriptor: id) -> Self {
+        let descriptor = unsafe { objc_retain(descriptor) };
+        MetalCaptureDescriptor { descriptor }
+    }
+}
+impl Drop for MetalCaptureDescriptor {
+    fn drop(&mut self) { unsafe { objc_release(self.descriptor) } }
+}
+impl MetalCaptureDescriptor {
+    /// Creates a descriptor that sends captures to Xcode.
+    pub fn new() -> Self {
+        let class = class!(MTLCaptureDescriptor);
+        let descriptor: id = unsafe { msg_send![class, new] };
+        // Balance the retain done by `new`, as `from` retains too.
+        let result = MetalCaptureDescriptor::from(descriptor);
+        unsafe { objc_release(descriptor) };
+        result
+    }
+    /// Returns the underlying objective c descriptor
+    pub fn to_objc(&self) -> id { self.descriptor }
+    /// Captures all the work the device does.
+    pub fn set_capture_device(&mut self, device: &MetalDevice) {
+        unsafe { msg_send![self.descriptor, setCaptureObject:device.to_objc()] }
+    }
+    /// Captures only the work sent through the command queue.
+    pub fn set_capture_command_queue(&mut self, command_queue: &MetalCommandQueue) {
+        unsafe { msg_send![self.descriptor, setCaptureObject:command_queue.to_objc()] }
+    }
+    /// Sets where the capture is sent.
+    pub fn set_destination(&mut self, destination: MTLCaptureDestination) {
+        unsafe { msg_send![self.descriptor, setDestination:destination] }
+    }
+    /// Gets where the capture is sent.
+    pub fn get_destination(&self) -> MTLCaptureDestination {
+        unsafe { msg_send![self.descriptor, destination] }
+    }
+    /// Writes the capture to a GPU trace document at the given path,
+    /// which has to end in `.gputrace` and not exist yet.
+    pub fn set_output_path(&mut self, path: &Path) -> Result<(), MetalError> {
+        validate_gpu_trace_path(path)?;
+        unsafe {
+            let pool = NSAutoreleasePool::new(nil);
+            let _:() = msg_send![self.descriptor, setOutputURL:file_url_from_path(path)];
+            pool.drain();
+        }
+        self.set_destination(MTLCaptureDestinationGPUTraceDocument);
+        Ok(())
+    }
+}
+
+/// Rust wrapper for the app's capture manager,
+/// which starts and stops frame captures.
+pub struct MetalCaptureManager {
+    manager: id,
+}
+impl Default for MetalCaptureManager {
+    fn default() -> Self {
+        MetalCaptureManager { manager: nil }
+    }
+}
+impl From<id> for MetalCaptureManager {
+    fn from(manager: id) -> Self {
+        let manager = unsafe { objc_retain(manager) };
+        MetalCaptureManager { manager }
+    }
+}
+impl Drop for MetalCaptureManager {
+    fn drop(&mut self) { unsafe { objc_release(self.manager) } }
+}
+impl MetalCaptureManager {
+    /// Gets the capture manager shared by the whole app.
+    pub fn shared() -> Self {
+        let class = class!(MTLCaptureManager);
+        let manager: id = unsafe { msg_send![class, sharedCaptureManager] };
+        MetalCaptureManager::from(manager)
+    }
+    /// Returns the underlying objective c capture manager
+    pub fn to_objc(&self) -> id { self.manager }
+    /// Returns true if captures can be sent to the given destination,
+    /// e.g. false for GPU trace documents unless the app has opted in.
+    pub fn supports_destination(&self, destination: MTLCaptureDestination) -> bool {
+        let supported: BOOL = unsafe { msg_send![self.manager, supportsDestination:destination] };
+        supported != NO
+    }
+    /// Starts capturing the work described by the descriptor,
+    /// until `stop_capture` is called.
+    pub fn start_capture(&self, descriptor: &MetalCaptureDescriptor) -> Result<(), MetalError> {
+        let destination = descriptor.get_destination();
+        if !self.supports_destination(destination) {
+            return Err(MetalError::CaptureDestinationNotSupported(destination));
+        }
+        let mut error: id = nil;
+        let started: BOOL = unsafe {
+            msg_send![self.manager, startCaptureWithDescriptor:descriptor.to_objc() error:&mut error]
+        };
+        if started == NO {
+            return Err(MetalError::CaptureStart(NSErrorInfo::from_ns_error_or_unknown(error)));
+        }
+        Ok(())
+    }
+    /// Starts capturing everything the device does into a GPU trace document at the given path.
+    pub fn start_capture_to_file(&self, device: &MetalDevice, path: &Path) -> Result<(), MetalError> {
+        let mut descriptor = MetalCaptureDescriptor::new();
+        descriptor.set_capture_device(device);
+        descriptor.set_output_path(path)?;
+        self.start_capture(&descriptor)
+    }
+    /// Stops the capture in progress, writing out its document if it has one.
+    pub fn stop_capture(&self) {
+        unsafe { msg_send![self.manager, stopCapture] }
+    }
+    /// Returns true while a capture is in progress.
+    pub fn is_capturing(&self) -> bool {
+        let capturing: BOOL = unsafe { msg_send![self.manager, isCapturing] };
+        capturing != NO
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_output_path_must_be_a_new_gpu_trace() {
+        let directory = std::env::temp_dir();
+        let new = directory.join(format!("metal_kit_capture_new_{}.gputrace", std::process::id()));
+        assert_eq!(Ok(()), validate_gpu_trace_path(&new));
+        assert_eq!(
+            Err(MetalError::InvalidCaptureOutputPath(String::from("frame.trace"))),
+            validate_gpu_trace_path(Path::new("frame.trace"))
+        );
+        assert!(validate_gpu_trace_path(Path::new("gputrace")).is_err());
+
+        let existing = directory.join(format!("metal_kit_capture_test_{}.gputrace", std::process::id()));
+        std::fs::write(&existing, b"").unwrap();
+        let result = validate_gpu_trace_path(&existing);
+        std::fs::remove_file(&existing).unwrap();
+        assert_eq!(
+            "Invalid capture output path (must be a new .gputrace file): ".to_string() + &existing.display().to_string(),
+            result.unwrap_err().to_string()
+        );
+    }
+}
@@ -21,7 +21,7 @@ use objc::{Encode, Encoding};
 // } MTLClearColor;
 /// An RGBA value used for a color pixel.
 #[repr(C)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct MetalClearColor {
     red: c_double,
     green: c_double,
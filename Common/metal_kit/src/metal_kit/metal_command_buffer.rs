@@ -12,11 +12,136 @@ use objc::msg_send;
 use objc::sel;
 use objc::sel_impl;
 use cocoa::base::id;
+use cocoa::foundation::{NSInteger, NSUInteger};
 use crate::metal_kit::metal_render_pass_descriptor::MetalRenderPassDescriptor;
 use crate::metal_kit::metal_render_command_encoder::MetalRenderCommandEncoder;
 use objc::runtime::{objc_retain, objc_release};
 use core_animation::CoreAnimMetalDrawable;
 use std::os::raw::c_void;
+use crate::metal_kit::metal_error::{MetalError, NSErrorInfo};
+use crate::metal_kit::metal_command_buffer_handler::{
+    command_buffer_handler_trampoline,
+    handler_into_user_data,
+    CommandBufferCompletion,
+    CommandBufferInfo,
+};
+use crate::metal_kit::metal_debug_group::MetalDebugGroups;
+use crate::metal_kit::metal_blit_command_encoder::MetalBlitCommandEncoder;
+use crate::metal_kit::metal_compute_command_encoder::MetalComputeCommandEncoder;
+use crate::metal_kit::metal_event::MetalEventObject;
+use crate::metal_kit::ns_string::{get_objc_label, ns_string_from_str, set_objc_label};
+
+// From MTLCommandBuffer.h:
+// typedef NS_ENUM(NSUInteger, MTLCommandBufferStatus) {
+//     MTLCommandBufferStatusNotEnqueued = 0,
+//     MTLCommandBufferStatusEnqueued = 1,
+//     MTLCommandBufferStatusCommitted = 2,
+//     MTLCommandBufferStatusScheduled = 3,
+//     MTLCommandBufferStatusCompleted = 4,
+//     MTLCommandBufferStatusError = 5,
+// } API_AVAILABLE(macos(10.11), ios(8.0));
+/// How far a command buffer has got towards being executed.
+pub type MTLCommandBufferStatus = NSUInteger;
+/// The command buffer is not enqueued yet.
+#[allow(non_upper_case_globals)]
+pub const MTLCommandBufferStatusNotEnqueued: MTLCommandBufferStatus = 0;
+/// The command buffer is enqueued.
+#[allow(non_upper_case_globals)]
+pub const MTLCommandBufferStatusEnqueued: MTLCommandBufferStatus = 1;
+/// The command buffer is committed for execution.
+#[allow(non_upper_case_globals)]
+pub const MTLCommandBufferStatusCommitted: MTLCommandBufferStatus = 2;
+/// The command buffer is scheduled to run on the GPU.
+#[allow(non_upper_case_globals)]
+pub const MTLCommandBufferStatusScheduled: MTLCommandBufferStatus = 3;
+/// The GPU finished executing the command buffer successfully.
+#[allow(non_upper_case_globals)]
+pub const MTLCommandBufferStatusCompleted: MTLCommandBufferStatus = 4;
+/// Execution of the command buffer was aborted because of an error.
+#[allow(non_upper_case_globals)]
+pub const MTLCommandBufferStatusError: MTLCommandBufferStatus = 5;
+
+// typedef NS_ENUM(NSUInteger, MTLCommandBufferError)
+// {
+//     MTLCommandBufferErrorNone = 0,
+//     MTLCommandBufferErrorInternal = 1,
+//     MTLCommandBufferErrorTimeout = 2,
+//     MTLCommandBufferErrorPageFault = 3,
+//     MTLCommandBufferErrorAccessRevoked = 4,
+//     MTLCommandBufferErrorNotPermitted = 7,
+//     MTLCommandBufferErrorOutOfMemory = 8,
+//     MTLCommandBufferErrorInvalidResource = 9,
+//     MTLCommandBufferErrorMemoryless API_AVAILABLE(ios(10.0)) API_UNAVAILABLE(macos, macCatalyst) = 10,
+//     MTLCommandBufferErrorDeviceRemoved API_AVAILABLE(macos(10.13), macCatalyst(13.0)) API_UNAVAILABLE(ios) = 11,
+//     MTLCommandBufferErrorStackOverflow API_AVAILABLE(macos(12.0), ios(15.0)) = 12,
+// } API_AVAILABLE(macos(10.11), ios(8.0));
+/// The domain of the errors that stop a command buffer.
+#[allow(non_upper_case_globals)]
+pub const MTLCommandBufferErrorDomain: &str = "MTLCommandBufferErrorDomain";
+/// The error codes in `MTLCommandBufferErrorDomain`.
+pub type MTLCommandBufferError = NSInteger;
+/// No error.
+#[allow(non_upper_case_globals)]
+pub const MTLCommandBufferErrorNone: MTLCommandBufferError = 0;
+/// An internal error that doesn't fit into the other categories.
+#[allow(non_upper_case_globals)]
+pub const MTLCommandBufferErrorInternal: MTLCommandBufferError = 1;
+/// Execution took too long.
+#[allow(non_upper_case_globals)]
+pub const MTLCommandBufferErrorTimeout: MTLCommandBufferError = 2;
+/// Execution caused a page fault.
+#[allow(non_upper_case_globals)]
+pub const MTLCommandBufferErrorPageFault: MTLCommandBufferError = 3;
+/// The process has lost access to the GPU.
+#[allow(non_upper_case_globals)]
+pub const MTLCommandBufferErrorAccessRevoked: MTLCommandBufferError = 4;
+/// The process may not do the requested work.
+#[allow(non_upper_case_globals)]
+pub const MTLCommandBufferErrorNotPermitted: MTLCommandBufferError = 7;
+/// The GPU ran out of memory.
+#[allow(non_upper_case_globals)]
+pub const MTLCommandBufferErrorOutOfMemory: MTLCommandBufferError = 8;
+/// The command buffer used a resource that is not valid.
+#[allow(non_upper_case_globals)]
+pub const MTLCommandBufferErrorInvalidResource: MTLCommandBufferError = 9;
+/// A memoryless render target ran out of tile memory.
+#[allow(non_upper_case_globals)]
+pub const MTLCommandBufferErrorMemoryless: MTLCommandBufferError = 10;
+/// The GPU was removed from the system.
+#[allow(non_upper_case_globals)]
+pub const MTLCommandBufferErrorDeviceRemoved: MTLCommandBufferError = 11;
+/// A shader ran out of stack space.
+#[allow(non_upper_case_globals)]
+pub const MTLCommandBufferErrorStackOverflow: MTLCommandBufferError = 12;
+
+/// Gets a short description of a command buffer error code.
+#[allow(non_upper_case_globals)]
+pub fn command_buffer_error_description(code: MTLCommandBufferError) -> &'static str {
+    match code {
+        MTLCommandBufferErrorNone => "no error",
+        MTLCommandBufferErrorInternal => "internal error",
+        MTLCommandBufferErrorTimeout => "timed out",
+        MTLCommandBufferErrorPageFault => "page fault",
+        MTLCommandBufferErrorAccessRevoked => "GPU access revoked",
+        MTLCommandBufferErrorNotPermitted => "not permitted",
+        MTLCommandBufferErrorOutOfMemory => "out of memory",
+        MTLCommandBufferErrorInvalidResource => "invalid resource",
+        MTLCommandBufferErrorMemoryless => "out of memoryless tile memory",
+        MTLCommandBufferErrorDeviceRemoved => "GPU removed",
+        MTLCommandBufferErrorStackOverflow => "stack overflow",
+        _ => "unknown error",
+    }
+}
+
+/// Gets the command buffer error code of the given error,
+/// or None if the error is not in `MTLCommandBufferErrorDomain`.
+pub fn command_buffer_error_code(error: &NSErrorInfo) -> Option<MTLCommandBufferError> {
+    if error.domain == MTLCommandBufferErrorDomain {
+        Some(error.code)
+    } else {
+        None
+    }
+}
 
 // From GlueLib.h:
 // /// @typedef command_buffer_completion_function_with_user_data_t
@@ -49,6 +174,16 @@ extern {
         completion_function: command_buffer_completion_function_with_user_data_t,
         user_data: *const c_void
     );
+    // void command_buffer_add_scheduled_handler_f_with_user_data
+    // (id<MTLCommandBuffer> _Nonnull,
+    //  command_buffer_completion_function_with_user_data_t _Nullable,
+    //  void *_Nullable
+    //  );
+    fn command_buffer_add_scheduled_handler_f_with_user_data(
+        command_buffer: id,
+        scheduled_function: command_buffer_completion_function_with_user_data_t,
+        user_data: *const c_void
+    );
 }
 
 /// Rust wrapper of a container
@@ -74,6 +209,28 @@ impl MetalCommandBuffer {
         let encoder:id = unsafe { msg_send![self.buffer, renderCommandEncoderWithDescriptor:descriptor_id] };
         MetalRenderCommandEncoder::from(encoder)
     }
+    /// Creates an object to encode copy and maintenance commands into the command buffer.
+    #[inline]
+    pub fn blit_command_encoder(&self) -> MetalBlitCommandEncoder {
+        let encoder: id = unsafe { msg_send![self.buffer, blitCommandEncoder] };
+        MetalBlitCommandEncoder::from(encoder)
+    }
+    /// Creates an object to encode a compute pass into the command buffer.
+    #[inline]
+    pub fn compute_command_encoder(&self) -> MetalComputeCommandEncoder {
+        let encoder: id = unsafe { msg_send![self.buffer, computeCommandEncoder] };
+        MetalComputeCommandEncoder::from(encoder)
+    }
+    /// Encodes a command that sets the event to the value
+    /// once the GPU has finished the work before it.
+    pub fn encode_signal_event<E: MetalEventObject>(&mut self, event: &E, value: u64) {
+        unsafe { msg_send![self.buffer, encodeSignalEvent:event.to_objc() value:value] }
+    }
+    /// Encodes a command that holds back the work after it
+    /// until the event reaches the value.
+    pub fn encode_wait_for_event<E: MetalEventObject>(&mut self, event: &E, value: u64) {
+        unsafe { msg_send![self.buffer, encodeWaitForEvent:event.to_objc() value:value] }
+    }
     /// Registers a drawable presentation to occur as soon as possible.
     #[inline]
     pub fn present_drawable(&mut self, drawable: &CoreAnimMetalDrawable) {
@@ -85,7 +242,9 @@ impl MetalCommandBuffer {
         unsafe { msg_send![self.buffer, commit] }
     }
     /// Attaches a command to be executed
-    /// when the buffer completes operation
+    /// when the buffer completes operation.
+    ///
+    /// `add_completed_handler` is safer: it takes a Rust closure.
     #[inline]
     pub fn attach_completion_function(
         &self,
@@ -98,5 +257,122 @@ impl MetalCommandBuffer {
             user_data
         )}
     }
-
+    /// Attaches a command to be executed
+    /// when the buffer has been scheduled to run on the GPU.
+    #[inline]
+    pub fn attach_scheduled_function(
+        &self,
+        scheduled_function: command_buffer_completion_function_with_user_data_t,
+        user_data: *const c_void
+    ) {
+        unsafe { command_buffer_add_scheduled_handler_f_with_user_data(
+            self.buffer,
+            scheduled_function,
+            user_data
+        )}
+    }
+    /// Registers a closure to run, on a Metal thread, once the GPU has finished the command buffer.
+    ///
+    /// Handlers must be added before the command buffer is committed.
+    /// The closure is called once and then freed.
+    pub fn add_completed_handler<F>(&self, handler: F)
+        where F: FnOnce(&CommandBufferInfo) + Send + 'static {
+        self.attach_completion_function(command_buffer_handler_trampoline, handler_into_user_data(handler))
+    }
+    /// Registers a closure to run, on a Metal thread, once the command buffer is scheduled.
+    ///
+    /// Handlers must be added before the command buffer is committed.
+    /// The closure is called once and then freed.
+    pub fn add_scheduled_handler<F>(&self, handler: F)
+        where F: FnOnce(&CommandBufferInfo) + Send + 'static {
+        self.attach_scheduled_function(command_buffer_handler_trampoline, handler_into_user_data(handler))
+    }
+    /// Returns a future that resolves once the GPU has finished the command buffer.
+    ///
+    /// Call this before the command buffer is committed, and await it afterwards.
+    pub fn completed(&self) -> CommandBufferCompletion {
+        let (completion, handler) = CommandBufferCompletion::new();
+        self.add_completed_handler(handler);
+        completion
+    }
+    /// Returns the underlying objective c command buffer
+    #[inline]
+    pub fn to_objc(&self) -> id { self.buffer }
+    /// Sets the label used to identify the command buffer in debugging tools.
+    pub fn set_label(&mut self, label: &str) { set_objc_label(self.buffer, label) }
+    /// Gets the command buffer's label (empty if none was set).
+    pub fn get_label(&self) -> String { get_objc_label(self.buffer) }
+    /// Gets how far the command buffer has got towards being executed.
+    #[inline]
+    pub fn status(&self) -> MTLCommandBufferStatus {
+        unsafe { msg_send![self.buffer, status] }
+    }
+    /// Gets the error that stopped the command buffer,
+    /// or None unless the status is `MTLCommandBufferStatusError`.
+    ///
+    /// A command buffer that failed without giving a reason gets an unknown error.
+    pub fn error(&self) -> Option<MetalError> {
+        if self.status() != MTLCommandBufferStatusError {
+            return None;
+        }
+        let error: id = unsafe { msg_send![self.buffer, error] };
+        Some(MetalError::CommandBufferExecution(NSErrorInfo::from_ns_error_or_unknown(error)))
+    }
+    /// Gets the host time in seconds when the GPU started running the command buffer
+    /// (0 until it has).
+    #[inline]
+    pub fn gpu_start_time(&self) -> f64 {
+        unsafe { msg_send![self.buffer, GPUStartTime] }
+    }
+    /// Gets the host time in seconds when the GPU finished running the command buffer
+    /// (0 until it has).
+    #[inline]
+    pub fn gpu_end_time(&self) -> f64 {
+        unsafe { msg_send![self.buffer, GPUEndTime] }
+    }
+    /// Gets the host time in seconds when the CPU started scheduling the command buffer.
+    #[inline]
+    pub fn kernel_start_time(&self) -> f64 {
+        unsafe { msg_send![self.buffer, kernelStartTime] }
+    }
+    /// Gets the host time in seconds when the CPU finished scheduling the command buffer.
+    #[inline]
+    pub fn kernel_end_time(&self) -> f64 {
+        unsafe { msg_send![self.buffer, kernelEndTime] }
+    }
+    /// Blocks the calling thread until the command buffer is scheduled.
+    #[inline]
+    pub fn wait_until_scheduled(&self) {
+        unsafe { msg_send![self.buffer, waitUntilScheduled] }
+    }
+    /// Blocks the calling thread until the GPU finishes executing the command buffer.
+    #[inline]
+    pub fn wait_until_completed(&self) {
+        unsafe { msg_send![self.buffer, waitUntilCompleted] }
+    }
+    /// Waits for the GPU to finish, then reports whether the command buffer succeeded.
+    pub fn wait_until_completed_with_result(&self) -> Result<(), MetalError> {
+        self.wait_until_completed();
+        match self.error() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+impl MetalDebugGroups for MetalCommandBuffer {
+    fn push_debug_group(&mut self, name: &str) {
+        let name = ns_string_from_str(name);
+        unsafe {
+            let _:() = msg_send![self.buffer, pushDebugGroup:name];
+            objc_release(name);
+        }
+    }
+    fn pop_debug_group(&mut self) {
+        unsafe { msg_send![self.buffer, popDebugGroup] }
+    }
+    /// Command buffers have no signposts, so this marks the point with an empty group.
+    fn insert_debug_signpost(&mut self, name: &str) {
+        self.push_debug_group(name);
+        self.pop_debug_group();
+    }
 }
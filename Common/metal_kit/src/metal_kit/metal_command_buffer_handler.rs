@@ -0,0 +1,155 @@
+//
+//  metal_command_buffer_handler.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Rust closures and futures run when a command buffer is scheduled or completes
+
+use cocoa::base::id;
+use std::future::Future;
+use std::os::raw::c_void;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use crate::metal_kit::metal_callback::{callback_into_user_data, run_callback, Completion};
+use crate::metal_kit::metal_command_buffer::{MetalCommandBuffer, MTLCommandBufferStatus};
+use crate::metal_kit::metal_error::MetalError;
+
+/// What a command buffer handler gets told about the command buffer,
+/// copied out so it can be kept or sent to another thread.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandBufferInfo {
+    /// The command buffer's label.
+    pub label: String,
+    /// The status when the handler ran.
+    pub status: MTLCommandBufferStatus,
+    /// The error that stopped the command buffer, if any.
+    pub error: Option<MetalError>,
+    /// The host time in seconds when the GPU started running the command buffer.
+    pub gpu_start_time: f64,
+    /// The host time in seconds when the GPU finished running the command buffer.
+    pub gpu_end_time: f64,
+    /// The host time in seconds when the CPU started scheduling the command buffer.
+    pub kernel_start_time: f64,
+    /// The host time in seconds when the CPU finished scheduling the command buffer.
+    pub kernel_end_time: f64,
+}
+impl CommandBufferInfo {
+    /// Copies the current state of the command buffer.
+    pub fn from_command_buffer(command_buffer: &MetalCommandBuffer) -> Self {
+        CommandBufferInfo {
+            label: command_buffer.get_label(),
+            status: command_buffer.status(),
+            error: command_buffer.error(),
+            gpu_start_time: command_buffer.gpu_start_time(),
+            gpu_end_time: command_buffer.gpu_end_time(),
+            kernel_start_time: command_buffer.kernel_start_time(),
+            kernel_end_time: command_buffer.kernel_end_time(),
+        }
+    }
+    /// Gets how long the GPU spent on the command buffer, in seconds.
+    pub fn gpu_duration(&self) -> f64 { self.gpu_end_time - self.gpu_start_time }
+    /// Turns the error, if any, into a result.
+    pub fn result(&self) -> Result<(), MetalError> {
+        match &self.error {
+            Some(error) => Err(error.clone()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Gives the user data to pass with `command_buffer_handler_trampoline` for the handler.
+pub(crate) fn handler_into_user_data<F>(handler: F) -> *const c_void
+    where F: FnOnce(&CommandBufferInfo) + Send + 'static {
+    callback_into_user_data(move |info: CommandBufferInfo| handler(&info))
+}
+
+/// Called by the command buffer (through GlueLib) with the user data from `handler_into_user_data`.
+/// Metal calls each handler exactly once, so the handler is freed exactly once.
+pub(crate) extern "C" fn command_buffer_handler_trampoline(command_buffer: id, user_data: *const c_void) {
+    let command_buffer = MetalCommandBuffer::from(command_buffer);
+    let info = CommandBufferInfo::from_command_buffer(&command_buffer);
+    unsafe { run_callback(user_data, info) }
+}
+
+/// A future that resolves when its command buffer completes,
+/// made by `MetalCommandBuffer::completed`.
+#[derive(Debug)]
+pub struct CommandBufferCompletion {
+    completion: Completion<CommandBufferInfo>,
+}
+impl CommandBufferCompletion {
+    /// Creates a future together with the handler that completes it.
+    pub(crate) fn new() -> (Self, impl FnOnce(&CommandBufferInfo) + Send + 'static) {
+        let (completion, complete) = Completion::new();
+        (CommandBufferCompletion { completion }, move |info: &CommandBufferInfo| complete(info.clone()))
+    }
+    /// Returns true once the command buffer has completed.
+    pub fn is_complete(&self) -> bool { self.completion.is_complete() }
+}
+impl Future for CommandBufferCompletion {
+    type Output = CommandBufferInfo;
+    fn poll(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.get_mut().completion).poll(context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::handler_into_user_data;
+    use crate::*;
+    use crate::metal_kit::metal_callback::{noop_waker, run_callback};
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    fn info(status: MTLCommandBufferStatus) -> CommandBufferInfo {
+        CommandBufferInfo {
+            label: String::from("Frame"),
+            status,
+            error: None,
+            gpu_start_time: 10.0,
+            gpu_end_time: 10.25,
+            kernel_start_time: 9.5,
+            kernel_end_time: 9.75,
+        }
+    }
+
+    #[test]
+    fn test_handler_is_given_the_info() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let user_data = handler_into_user_data(move |info: &CommandBufferInfo| {
+            sender.send((info.status, info.gpu_duration())).unwrap();
+        });
+        unsafe { run_callback(user_data, info(MTLCommandBufferStatusCompleted)) };
+        assert_eq!(Ok((MTLCommandBufferStatusCompleted, 0.25)), receiver.try_recv());
+    }
+
+    #[test]
+    fn test_completed_future_resolves_after_callback() {
+        let (mut completion, handler) = CommandBufferCompletion::new();
+        let waker = noop_waker();
+        let mut context = Context::from_waker(&waker);
+        assert!(!completion.is_complete());
+        assert_eq!(Poll::Pending, Pin::new(&mut completion).poll(&mut context));
+
+        let user_data = handler_into_user_data(handler);
+        let mut failed = info(MTLCommandBufferStatusError);
+        failed.error = Some(MetalError::CommandBufferExecution(
+            NSErrorInfo::new("MTLCommandBufferErrorDomain", MTLCommandBufferErrorPageFault, "Page fault")
+        ));
+        unsafe { run_callback(user_data, failed) };
+        assert!(completion.is_complete());
+
+        match Pin::new(&mut completion).poll(&mut context) {
+            Poll::Ready(info) => {
+                assert_eq!(MTLCommandBufferStatusError, info.status);
+                assert!(info.result().is_err());
+            }
+            Poll::Pending => panic!("completion should be ready"),
+        }
+        assert!(completion.is_complete());
+    }
+}
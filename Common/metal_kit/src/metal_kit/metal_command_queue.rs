@@ -14,6 +14,7 @@ use objc::sel_impl;
 use cocoa::base::{id, nil};
 use crate::metal_kit::metal_command_buffer::MetalCommandBuffer;
 use objc::runtime::{objc_retain, objc_release};
+use crate::metal_kit::ns_string::{get_objc_label, set_objc_label};
 
 /// Rust wrapper for a queue that organizes command buffers
 /// to be executed by a GPU.
@@ -37,6 +38,12 @@ impl Drop for MetalCommandQueue {
     fn drop(&mut self) { unsafe { objc_release(self.command_queue) } }
 }
 impl MetalCommandQueue {
+    /// Sets the label used to identify the command queue in debugging tools.
+    pub fn set_label(&mut self, label: &str) { set_objc_label(self.command_queue, label) }
+    /// Gets the label (empty if none was set).
+    pub fn get_label(&self) -> String { get_objc_label(self.command_queue) }
+    /// Returns the underlying objective c command queue
+    pub fn to_objc(&self) -> id { self.command_queue }
     /// Creates a command buffer.
     pub fn command_buffer(&self) -> MetalCommandBuffer {
         let command_buffer:id = unsafe { msg_send![self.command_queue, commandBuffer] };
@@ -0,0 +1,132 @@
+//
+//  metal_compile_options.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Thin wrappers for methods we use from MTLCompileOptions
+
+use objc::class;
+use objc::msg_send;
+use objc::sel;
+use objc::sel_impl;
+use cocoa::base::{id, nil};
+use objc::runtime::{objc_retain, objc_release, BOOL};
+use cocoa::foundation::{NSUInteger, NSAutoreleasePool};
+use crate::metal_kit::ns_string::ns_string_from_str;
+
+// From MTLLibrary.h:
+// typedef NS_ENUM(NSUInteger, MTLLanguageVersion) {
+//     MTLLanguageVersion1_0 API_DEPRECATED("Use a newer language standard", ios(9.0, 16.0)) API_UNAVAILABLE(macos, macCatalyst) = (1 << 16),
+//     MTLLanguageVersion1_1 API_AVAILABLE(macos(10.11), ios(9.0)) = (1 << 16) + 1,
+//     MTLLanguageVersion1_2 API_AVAILABLE(macos(10.12), ios(10.0)) = (1 << 16) + 2,
+//     MTLLanguageVersion2_0 API_AVAILABLE(macos(10.13), ios(11.0)) = (2 << 16),
+//     MTLLanguageVersion2_1 API_AVAILABLE(macos(10.14), ios(12.0)) = (2 << 16) + 1,
+//     MTLLanguageVersion2_2 API_AVAILABLE(macos(10.15), ios(13.0)) = (2 << 16) + 2,
+//     MTLLanguageVersion2_3 API_AVAILABLE(macos(11.0), ios(14.0)) = (2 << 16) + 3,
+//     MTLLanguageVersion2_4 API_AVAILABLE(macos(12.0), ios(15.0)) = (2 << 16) + 4,
+//     MTLLanguageVersion3_0 API_AVAILABLE(macos(13.0), ios(16.0)) = (3 << 16) + 0,
+//     MTLLanguageVersion3_1 API_AVAILABLE(macos(14.0), ios(17.0)) = (3 << 16) + 1,
+// } API_AVAILABLE(macos(10.11), ios(9.0));
+/// The version of the Metal shading language to compile against.
+pub type MTLLanguageVersion = NSUInteger;
+/// Version 1.1 of the Metal shading language.
+#[allow(non_upper_case_globals)]
+pub const MTLLanguageVersion1_1: MTLLanguageVersion = (1 << 16) + 1;
+/// Version 1.2 of the Metal shading language.
+#[allow(non_upper_case_globals)]
+pub const MTLLanguageVersion1_2: MTLLanguageVersion = (1 << 16) + 2;
+/// Version 2.0 of the Metal shading language.
+#[allow(non_upper_case_globals)]
+pub const MTLLanguageVersion2_0: MTLLanguageVersion = 2 << 16;
+/// Version 2.1 of the Metal shading language.
+#[allow(non_upper_case_globals)]
+pub const MTLLanguageVersion2_1: MTLLanguageVersion = (2 << 16) + 1;
+/// Version 2.2 of the Metal shading language.
+#[allow(non_upper_case_globals)]
+pub const MTLLanguageVersion2_2: MTLLanguageVersion = (2 << 16) + 2;
+/// Version 2.3 of the Metal shading language.
+#[allow(non_upper_case_globals)]
+pub const MTLLanguageVersion2_3: MTLLanguageVersion = (2 << 16) + 3;
+/// Version 2.4 of the Metal shading language.
+#[allow(non_upper_case_globals)]
+pub const MTLLanguageVersion2_4: MTLLanguageVersion = (2 << 16) + 4;
+/// Version 3.0 of the Metal shading language.
+#[allow(non_upper_case_globals)]
+pub const MTLLanguageVersion3_0: MTLLanguageVersion = 3 << 16;
+/// Version 3.1 of the Metal shading language.
+#[allow(non_upper_case_globals)]
+pub const MTLLanguageVersion3_1: MTLLanguageVersion = (3 << 16) + 1;
+
+/// Rust wrapper for the compilation settings
+/// used when building a library from Metal source code.
+pub struct MetalCompileOptions {
+    options: id,
+}
+impl Default for MetalCompileOptions {
+    fn default() -> Self {
+        MetalCompileOptions { options: nil }
+    }
+}
+impl From<id> for MetalCompileOptions {
+    fn from(options: id) -> Self {
+        let options = unsafe { objc_retain(options) };
+        MetalCompileOptions { options }
+    }
+}
+impl Drop for MetalCompileOptions {
+    fn drop(&mut self) { unsafe { objc_release(self.options) } }
+}
+
+impl MetalCompileOptions {
+    /// Creates a new set of compile options with the system defaults.
+    pub fn new() -> Self {
+        let class = class!(MTLCompileOptions);
+        let options: id = unsafe { msg_send![class, new] };
+        let result = MetalCompileOptions::from(options);
+        unsafe { objc_release(options) };
+        result
+    }
+    /// Returns the underlying Objective C object.
+    pub fn to_objc(&self) -> id { self.options }
+    /// Sets the language version used to interpret the source code.
+    pub fn set_language_version(&mut self, language_version: MTLLanguageVersion) {
+        unsafe { msg_send![self.options, setLanguageVersion:language_version] }
+    }
+    /// Gets the language version used to interpret the source code.
+    pub fn get_language_version(&self) -> MTLLanguageVersion {
+        unsafe { msg_send![self.options, languageVersion] }
+    }
+    /// Sets a Boolean value that indicates whether the compiler
+    /// can perform optimizations for floating-point arithmetic
+    /// that may violate the IEEE 754 standard.
+    pub fn set_fast_math_enabled(&mut self, enabled: BOOL) {
+        unsafe { msg_send![self.options, setFastMathEnabled:enabled] }
+    }
+    /// Gets whether fast-math optimizations are enabled.
+    pub fn get_fast_math_enabled(&self) -> BOOL {
+        unsafe { msg_send![self.options, fastMathEnabled] }
+    }
+    /// Sets the preprocessor macros to define before compiling the source,
+    /// given as (name, value) pairs.
+    ///
+    /// Any macros set previously are replaced.
+    pub fn set_preprocessor_macros(&mut self, macros: &[(&str, &str)]) {
+        unsafe {
+            let pool = NSAutoreleasePool::new(nil);
+            let dictionary_class = class!(NSMutableDictionary);
+            let dictionary: id = msg_send![dictionary_class, dictionary];
+            for (name, value) in macros {
+                let name = ns_string_from_str(name);
+                let value = ns_string_from_str(value);
+                let _:() = msg_send![dictionary, setObject:value forKey:name];
+                objc_release(name);
+                objc_release(value);
+            }
+            let _:() = msg_send![self.options, setPreprocessorMacros:dictionary];
+            pool.drain();
+        }
+    }
+}
@@ -0,0 +1,159 @@
+//
+//  metal_compiler_diagnostic.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Rust versions of the diagnostics reported by the Metal shader compiler
+
+use std::fmt::{Display, Formatter};
+
+/// How serious a compiler diagnostic is.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MetalDiagnosticSeverity {
+    /// The source could not be compiled.
+    Error,
+    /// The source compiled, but contains something suspicious.
+    Warning,
+    /// Extra information attached to a preceding error or warning.
+    Note,
+}
+impl Display for MetalDiagnosticSeverity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MetalDiagnosticSeverity::Error => write!(f, "error"),
+            MetalDiagnosticSeverity::Warning => write!(f, "warning"),
+            MetalDiagnosticSeverity::Note => write!(f, "note"),
+        }
+    }
+}
+
+/// One diagnostic reported by the Metal shader compiler,
+/// e.g. `program_source:3:5: error: use of undeclared identifier 'foo'`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetalCompilerDiagnostic {
+    /// The file the diagnostic refers to
+    /// (`program_source` for source passed in as a string).
+    pub file: String,
+    /// The (1-based) line the diagnostic refers to.
+    pub line: usize,
+    /// The (1-based) column the diagnostic refers to.
+    pub column: usize,
+    /// How serious the diagnostic is.
+    pub severity: MetalDiagnosticSeverity,
+    /// The compiler's description of the problem.
+    pub message: String,
+}
+impl Display for MetalCompilerDiagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}: {}: {}", self.file, self.line, self.column, self.severity, self.message)
+    }
+}
+
+impl MetalCompilerDiagnostic {
+    /// Parses a single line of compiler output.
+    ///
+    /// Returns None if the line is not a diagnostic
+    /// (e.g. it is a source excerpt or a caret line).
+    pub fn parse_line(line: &str) -> Option<Self> {
+        // Format is <file>:<line>:<column>: <severity>: <message>
+        // The file name may itself contain colons, so we split from the right
+        // of the severity marker.
+        let (location, severity, message) = [
+            (": error: ", MetalDiagnosticSeverity::Error),
+            (": warning: ", MetalDiagnosticSeverity::Warning),
+            (": note: ", MetalDiagnosticSeverity::Note),
+        ].iter()
+            .filter_map(|(marker, severity)| {
+                line.find(marker).map(|position| (
+                    &line[..position],
+                    *severity,
+                    &line[position + marker.len()..]
+                ))
+            })
+            .min_by_key(|(location, _, _)| location.len())?;
+        let mut parts = location.rsplitn(3, ':');
+        let column = parts.next()?.trim().parse().ok()?;
+        let line_number = parts.next()?.trim().parse().ok()?;
+        let file = parts.next()?.trim();
+        if file.is_empty() {
+            return None;
+        }
+        Some(MetalCompilerDiagnostic {
+            file: file.to_string(),
+            line: line_number,
+            column,
+            severity,
+            message: message.trim().to_string(),
+        })
+    }
+    /// Extracts all the diagnostics from the compiler's output,
+    /// in the order in which they were reported.
+    pub fn parse_compiler_output(output: &str) -> Vec<Self> {
+        output.lines().filter_map(Self::parse_line).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{MetalCompilerDiagnostic, MetalDiagnosticSeverity};
+
+    // Captured from the localizedDescription of a failed newLibraryWithSource:options:error:
+    const CAPTURED_OUTPUT: &str = "Compilation failed: \n\
+\n\
+program_source:4:5: warning: unused variable 'unused' [-Wunused-variable]\n\
+    float unused = 1.0;\n\
+    ^\n\
+program_source:9:12: error: use of undeclared identifier 'positon'; did you mean 'position'?\n\
+    return positon;\n\
+           ^~~~~~~\n\
+           position\n\
+program_source:7:12: note: 'position' declared here\n\
+    float4 position = in.position;\n\
+           ^\n";
+
+    #[test]
+    fn test_parse_captured_output() {
+        let diagnostics = MetalCompilerDiagnostic::parse_compiler_output(CAPTURED_OUTPUT);
+        assert_eq!(3, diagnostics.len());
+
+        assert_eq!("program_source", diagnostics[0].file);
+        assert_eq!(4, diagnostics[0].line);
+        assert_eq!(5, diagnostics[0].column);
+        assert_eq!(MetalDiagnosticSeverity::Warning, diagnostics[0].severity);
+        assert_eq!("unused variable 'unused' [-Wunused-variable]", diagnostics[0].message);
+
+        assert_eq!(9, diagnostics[1].line);
+        assert_eq!(12, diagnostics[1].column);
+        assert_eq!(MetalDiagnosticSeverity::Error, diagnostics[1].severity);
+        assert_eq!("use of undeclared identifier 'positon'; did you mean 'position'?", diagnostics[1].message);
+
+        assert_eq!(MetalDiagnosticSeverity::Note, diagnostics[2].severity);
+        assert_eq!(7, diagnostics[2].line);
+    }
+
+    #[test]
+    fn test_parse_line_ignores_excerpts() {
+        assert_eq!(None, MetalCompilerDiagnostic::parse_line("    return positon;"));
+        assert_eq!(None, MetalCompilerDiagnostic::parse_line("           ^~~~~~~"));
+        assert_eq!(None, MetalCompilerDiagnostic::parse_line("Compilation failed: "));
+        assert_eq!(None, MetalCompilerDiagnostic::parse_line("program_source:x:3: error: bad"));
+    }
+
+    #[test]
+    fn test_parse_line_with_colons_in_path() {
+        let diagnostic = MetalCompilerDiagnostic::parse_line(
+            "/Users/me/Shaders: v2/Shaders.metal:12:3: error: expected ';' after expression"
+        ).unwrap();
+        assert_eq!("/Users/me/Shaders: v2/Shaders.metal", diagnostic.file);
+        assert_eq!(12, diagnostic.line);
+        assert_eq!(3, diagnostic.column);
+        assert_eq!("expected ';' after expression", diagnostic.message);
+        assert_eq!(
+            "/Users/me/Shaders: v2/Shaders.metal:12:3: error: expected ';' after expression",
+            diagnostic.to_string()
+        );
+    }
+}
@@ -0,0 +1,86 @@
+//
+//  metal_compute_command_encoder.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Thin wrappers for methods we use from MTLComputeCommandEncoder
+
+use objc::msg_send;
+use objc::sel;
+use objc::sel_impl;
+use cocoa::base::{id, nil};
+use cocoa::foundation::NSUInteger;
+use objc::runtime::{objc_release, objc_retain, NO, YES};
+use crate::metal_kit::metal_debug_group::MetalDebugGroups;
+use crate::metal_kit::metal_fence::MetalFence;
+use crate::metal_kit::metal_counter_sample_buffer::MetalCounterSampleBuffer;
+use crate::metal_kit::ns_string::{get_objc_label, ns_string_from_str, set_objc_label};
+
+/// Rust wrapper for an encoder of compute passes.
+pub struct MetalComputeCommandEncoder {
+    encoder: id,
+}
+impl Default for MetalComputeCommandEncoder {
+    fn default() -> Self {
+        MetalComputeCommandEncoder { encoder: nil }
+    }
+}
+impl From<id> for MetalComputeCommandEncoder {
+    fn from(encoder: id) -> Self {
+        let encoder = unsafe { objc_retain(encoder) };
+        MetalComputeCommandEncoder { encoder }
+    }
+}
+impl Drop for MetalComputeCommandEncoder {
+    fn drop(&mut self) { unsafe { objc_release(self.encoder) } }
+}
+impl MetalComputeCommandEncoder {
+    /// Returns the underlying Objective C encoder.
+    pub fn to_objc(&self) -> id { self.encoder }
+    /// Sets the label used to identify the encoder in debugging tools.
+    pub fn set_label(&mut self, label: &str) { set_objc_label(self.encoder, label) }
+    /// Gets the label (empty if none was set).
+    pub fn get_label(&self) -> String { get_objc_label(self.encoder) }
+    /// Updates the fence once the GPU has finished the commands encoded so far.
+    pub fn update_fence(&mut self, fence: &MetalFence) {
+        unsafe { msg_send![self.encoder, updateFence:fence.to_objc()] }
+    }
+    /// Makes the following commands wait until another pass has updated the fence.
+    pub fn wait_for_fence(&mut self, fence: &MetalFence) {
+        unsafe { msg_send![self.encoder, waitForFence:fence.to_objc()] }
+    }
+    /// Encodes a command that writes the counter set's values into the sample buffer at the index,
+    /// optionally waiting for the commands before it to finish first.
+    ///
+    /// The device has to support sampling at `MTLCounterSamplingPointAtDispatchBoundary`; see `supports_counter_sampling`.
+    pub fn sample_counters_in_buffer(&mut self, sample_buffer: &MetalCounterSampleBuffer, sample_index: NSUInteger, barrier: bool) {
+        let barrier = if barrier { YES } else { NO };
+        unsafe { msg_send![self.encoder, sampleCountersInBuffer:sample_buffer.to_objc() atSampleIndex:sample_index withBarrier:barrier] }
+    }
+    /// Declares that all command generation from the encoder is completed.
+    pub fn end_encoding(&mut self) {
+        unsafe { msg_send![self.encoder, endEncoding] }
+    }
+}
+impl MetalDebugGroups for MetalComputeCommandEncoder {
+    fn push_debug_group(&mut self, name: &str) {
+        let name = ns_string_from_str(name);
+        unsafe {
+            let _:() = msg_send![self.encoder, pushDebugGroup:name];
+            objc_release(name);
+        }
+    }
+    fn pop_debug_group(&mut self) {
+        unsafe { msg_send![self.encoder, popDebugGroup] }
+    }
+    fn insert_debug_signpost(&mut self, name: &str) {
+        let name = ns_string_from_str(name);
+        unsafe {
+            let _:() = msg_send![self.encoder, insertDebugSignpost:name];
+            objc_release(name);
+        }
+    }
+}
@@ -0,0 +1,404 @@
+//
+//  metal_counter_sample_buffer.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Thin wrappers for GPU counter sets and counter sample buffers,
+//! and the bookkeeping that turns sampled timestamps into per-pass GPU times
+
+use objc::class;
+use objc::msg_send;
+use objc::sel;
+use objc::sel_impl;
+use cocoa::base::{id, nil};
+use cocoa::foundation::{NSAutoreleasePool, NSUInteger};
+use objc::runtime::{objc_release, objc_retain};
+use std::ops::Range;
+use crate::metal_kit::metal_error::MetalError;
+use crate::metal_kit::metal_indirect_command_buffer::ns_range;
+use crate::metal_kit::metal_texture_descriptor::MTLStorageMode;
+use crate::metal_kit::ns_string::{get_objc_label, rust_string_from_ns_string, set_objc_label};
+
+// From System/Library/Frameworks/Metal.framework/Headers/MTLCounters.h:
+// MTL_EXTERN MTLCommonCounterSet const MTLCommonCounterSetTimestamp;
+// MTL_EXTERN MTLCommonCounterSet const MTLCommonCounterSetStageUtilization;
+// MTL_EXTERN MTLCommonCounterSet const MTLCommonCounterSetStatistic;
+/// The name of the counter set holding GPU timestamps.
+#[allow(non_upper_case_globals)]
+pub const MTLCommonCounterSetTimestamp: &str = "timestamp";
+/// The name of the counter set holding the time spent in each pipeline stage.
+#[allow(non_upper_case_globals)]
+pub const MTLCommonCounterSetStageUtilization: &str = "stageutilization";
+/// The name of the counter set holding counts such as vertices and fragments processed.
+#[allow(non_upper_case_globals)]
+pub const MTLCommonCounterSetStatistic: &str = "statistic";
+//
+// #define MTLCounterErrorValue ((uint64_t)-1)
+/// The value of a sample the GPU could not take.
+#[allow(non_upper_case_globals)]
+pub const MTLCounterErrorValue: u64 = u64::MAX;
+//
+// #define MTLCounterDontSample ((NSUInteger)-1)
+/// The sample index that tells Metal not to take a sample.
+#[allow(non_upper_case_globals)]
+pub const MTLCounterDontSample: NSUInteger = NSUInteger::MAX;
+//
+// typedef NS_ENUM(NSUInteger, MTLCounterSamplingPoint) {
+//     MTLCounterSamplingPointAtStageBoundary,
+//     MTLCounterSamplingPointAtDrawBoundary,
+//     MTLCounterSamplingPointAtDispatchBoundary,
+//     MTLCounterSamplingPointAtTileDispatchBoundary,
+//     MTLCounterSamplingPointAtBlitBoundary
+// } API_AVAILABLE(macos(11.0), ios(14.0));
+/// Where in the encoded work a device can take counter samples.
+pub type MTLCounterSamplingPoint = NSUInteger;
+/// At the start and end of each pass' vertex and fragment stages (Apple GPUs).
+#[allow(non_upper_case_globals)]
+pub const MTLCounterSamplingPointAtStageBoundary: MTLCounterSamplingPoint = 0;
+/// Between draws in a render pass.
+#[allow(non_upper_case_globals)]
+pub const MTLCounterSamplingPointAtDrawBoundary: MTLCounterSamplingPoint = 1;
+/// Between dispatches in a compute pass.
+#[allow(non_upper_case_globals)]
+pub const MTLCounterSamplingPointAtDispatchBoundary: MTLCounterSamplingPoint = 2;
+/// Between tile dispatches in a render pass.
+#[allow(non_upper_case_globals)]
+pub const MTLCounterSamplingPointAtTileDispatchBoundary: MTLCounterSamplingPoint = 3;
+/// Between blits in a blit pass.
+#[allow(non_upper_case_globals)]
+pub const MTLCounterSamplingPointAtBlitBoundary: MTLCounterSamplingPoint = 4;
+
+/// Rust wrapper for a group of counters that a device can sample together.
+pub struct MetalCounterSet {
+    set: id,
+}
+impl Default for MetalCounterSet {
+    fn default() -> Self {
+        MetalCounterSet { set: nil }
+    }
+}
+impl From<id> for MetalCounterSet {
+    fn from(set: id) -> Self {
+        let set = unsafe { objc_retain(set) };
+        MetalCounterSet { set }
+    }
+}
+impl Drop for MetalCounterSet {
+    fn drop(&mut self) { unsafe { objc_release(self.set) } }
+}
+impl MetalCounterSet {
+    /// Returns the underlying Objective C counter set.
+    pub fn to_objc(&self) -> id { self.set }
+    /// Gets the name of the set, e.g. `MTLCommonCounterSetTimestamp`.
+    pub fn get_name(&self) -> String {
+        let name: id = unsafe { msg_send![self.set, name] };
+        rust_string_from_ns_string(name)
+    }
+    /// Gets the names of the counters in the set.
+    pub fn get_counter_names(&self) -> Vec<String> {
+        let counters: id = unsafe { msg_send![self.set, counters] };
+        if counters == nil {
+            return Vec::new();
+        }
+        let count: NSUInteger = unsafe { msg_send![counters, count] };
+        (0..count).map(|index| {
+            let counter: id = unsafe { msg_send![counters, objectAtIndex:index] };
+            let name: id = unsafe { msg_send![counter, name] };
+            rust_string_from_ns_string(name)
+        }).collect()
+    }
+}
+
+/// Rust wrapper for the settings a counter sample buffer is made with.
+pub struct MetalCounterSampleBufferDescriptor {
+    descriptor: id,
+}
+impl Default for MetalCounterSampleBufferDescriptor {
+    fn default() -> Self {
+        MetalCounterSampleBufferDescriptor { descriptor: nil }
+    }
+}
+impl From<id> for MetalCounterSampleBufferDescriptor {
+    fn from(descriptor: id) -> Self {
+        let descriptor = unsafe { objc_retain(descriptor) };
+        MetalCounterSampleBufferDescriptor { descriptor }
+    }
+}
+impl Drop for MetalCounterSampleBufferDescriptor {
+    fn drop(&mut self) { unsafe { objc_release(self.descriptor) } }
+}
+impl MetalCounterSampleBufferDescriptor {
+    /// Creates a descriptor for a buffer of the given number of samples from the counter set.
+    pub fn new(counter_set: &MetalCounterSet, sample_count: NSUInteger) -> Self {
+        let class = class!(MTLCounterSampleBufferDescriptor);
+        let descriptor: id = unsafe {
+            let descriptor: id = msg_send![class, alloc];
+            msg_send![descriptor, init]
+        };
+        let mut result = MetalCounterSampleBufferDescriptor::from(descriptor);
+        unsafe { objc_release(descriptor) };
+        result.set_counter_set(counter_set);
+        result.set_sample_count(sample_count);
+        result
+    }
+    /// Returns the underlying Objective C descriptor.
+    pub fn to_objc(&self) -> id { self.descriptor }
+    /// Sets the counter set the buffer samples.
+    pub fn set_counter_set(&mut self, counter_set: &MetalCounterSet) {
+        unsafe { msg_send![self.descriptor, setCounterSet:counter_set.to_objc()] }
+    }
+    /// Sets the number of samples the buffer holds.
+    pub fn set_sample_count(&mut self, sample_count: NSUInteger) {
+        unsafe { msg_send![self.descriptor, setSampleCount:sample_count] }
+    }
+    /// Sets where the buffer is kept; shared (the default) or private.
+    pub fn set_storage_mode(&mut self, storage_mode: MTLStorageMode) {
+        unsafe { msg_send![self.descriptor, setStorageMode:storage_mode] }
+    }
+    /// Sets the label the buffer is made with.
+    pub fn set_label(&mut self, label: &str) { set_objc_label(self.descriptor, label) }
+}
+
+/// Rust wrapper for a buffer that encoders write GPU counter samples into.
+pub struct MetalCounterSampleBuffer {
+    buffer: id,
+}
+impl Default for MetalCounterSampleBuffer {
+    fn default() -> Self {
+        MetalCounterSampleBuffer { buffer: nil }
+    }
+}
+impl From<id> for MetalCounterSampleBuffer {
+    fn from(buffer: id) -> Self {
+        let buffer = unsafe { objc_retain(buffer) };
+        MetalCounterSampleBuffer { buffer }
+    }
+}
+impl Drop for MetalCounterSampleBuffer {
+    fn drop(&mut self) { unsafe { objc_release(self.buffer) } }
+}
+impl MetalCounterSampleBuffer {
+    /// Wraps a buffer returned from a `new...` method,
+    /// balancing the retain the method has already done for us.
+    pub(crate) fn from_new_object(buffer: id) -> Self {
+        let result = MetalCounterSampleBuffer::from(buffer);
+        unsafe { objc_release(buffer) };
+        result
+    }
+    /// Returns the underlying Objective C buffer.
+    pub fn to_objc(&self) -> id { self.buffer }
+    /// Gets the label the buffer was made with (empty if none was set).
+    pub fn get_label(&self) -> String { get_objc_label(self.buffer) }
+    /// Gets the number of samples the buffer holds.
+    pub fn get_sample_count(&self) -> NSUInteger {
+        unsafe { msg_send![self.buffer, sampleCount] }
+    }
+    /// Copies the given samples out of the buffer, in the counter set's result layout.
+    ///
+    /// Only call this once the command buffers that took the samples have completed.
+    pub fn resolve_counter_range(&self, range: Range<NSUInteger>) -> Result<Vec<u8>, MetalError> {
+        let count = self.get_sample_count();
+        if range.start > range.end || range.end > count {
+            return Err(MetalError::BufferIndexOutOfBounds { index: range.end as usize, count: count as usize });
+        }
+        let pool = unsafe { NSAutoreleasePool::new(nil) };
+        let data: id = unsafe { msg_send![self.buffer, resolveCounterRange:ns_range(&range)] };
+        let result = if data == nil {
+            Err(MetalError::CounterResolve)
+        } else {
+            let bytes: *const u8 = unsafe { msg_send![data, bytes] };
+            let length: NSUInteger = unsafe { msg_send![data, length] };
+            if length == 0 || bytes.is_null() {
+                // Empty data has no bytes to point at.
+                Ok(Vec::new())
+            } else {
+                Ok(unsafe { std::slice::from_raw_parts(bytes, length as usize) }.to_vec())
+            }
+        };
+        unsafe { pool.drain() };
+        result
+    }
+    /// Copies the given samples out of a buffer sampling `MTLCommonCounterSetTimestamp`,
+    /// as GPU timestamps (`MTLCounterErrorValue` where no sample was taken).
+    pub fn resolve_timestamps(&self, range: Range<NSUInteger>) -> Result<Vec<u64>, MetalError> {
+        let bytes = self.resolve_counter_range(range)?;
+        Ok(bytes.chunks_exact(8).map(|sample| {
+            let mut timestamp = [0u8; 8];
+            timestamp.copy_from_slice(sample);
+            u64::from_ne_bytes(timestamp)
+        }).collect())
+    }
+}
+
+/// A CPU and a GPU timestamp taken at the same moment, as given by `sample_timestamps`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TimestampPair {
+    /// The CPU time, in nanoseconds.
+    pub cpu: u64,
+    /// The GPU time, in ticks of the GPU's clock.
+    pub gpu: u64,
+}
+
+/// Converts GPU timestamps to CPU nanoseconds,
+/// using two timestamp pairs taken before and after the work being timed.
+///
+/// GPU clocks tick at a rate of their own (only Apple GPUs tick in nanoseconds),
+/// so the rate is worked out from how far each clock moved between the pairs.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TimestampCalibration {
+    start: TimestampPair,
+    nanoseconds_per_tick: f64,
+}
+impl TimestampCalibration {
+    /// Works out the calibration from pairs taken at two different moments.
+    ///
+    /// Returns None unless both clocks moved forward between the pairs.
+    pub fn new(start: TimestampPair, end: TimestampPair) -> Option<Self> {
+        if end.cpu <= start.cpu || end.gpu <= start.gpu {
+            return None;
+        }
+        let nanoseconds_per_tick = (end.cpu - start.cpu) as f64 / (end.gpu - start.gpu) as f64;
+        Some(TimestampCalibration { start, nanoseconds_per_tick })
+    }
+    /// Gets the number of CPU nanoseconds per tick of the GPU's clock.
+    pub fn nanoseconds_per_tick(&self) -> f64 { self.nanoseconds_per_tick }
+    /// Converts a number of GPU ticks to nanoseconds.
+    pub fn ticks_to_nanoseconds(&self, ticks: u64) -> f64 { ticks as f64 * self.nanoseconds_per_tick }
+    /// Converts a GPU timestamp to the CPU time it happened at, in nanoseconds.
+    pub fn gpu_to_cpu_nanoseconds(&self, gpu: u64) -> f64 {
+        let ticks = gpu as f64 - self.start.gpu as f64;
+        self.start.cpu as f64 + ticks * self.nanoseconds_per_tick
+    }
+}
+
+/// The GPU time one named pass took.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuPassTiming {
+    /// The pass name.
+    pub name: String,
+    /// How long the pass ran on the GPU, in nanoseconds.
+    pub nanoseconds: f64,
+}
+
+/// Hands out a pair of timestamp sample indices for each named pass of a frame,
+/// and turns the resolved timestamps into a report of how long each pass took.
+///
+/// Sample at the first index before a pass' work and at the second after it,
+/// either with `sample_counters_in_buffer` or a render pass' sample buffer attachment.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GpuPassTimer {
+    passes: Vec<String>,
+}
+impl GpuPassTimer {
+    /// A timer with no passes.
+    pub fn new() -> Self { Self::default() }
+    /// Adds a pass, returning the sample indices to take its start and end timestamps at.
+    pub fn add_pass(&mut self, name: &str) -> (NSUInteger, NSUInteger) {
+        let start = 2 * self.passes.len() as NSUInteger;
+        self.passes.push(name.to_string());
+        (start, start + 1)
+    }
+    /// Gets the number of samples the timer's passes need.
+    pub fn sample_count(&self) -> NSUInteger { 2 * self.passes.len() as NSUInteger }
+    /// Forgets the passes, ready for the next frame.
+    pub fn clear(&mut self) { self.passes.clear() }
+    /// Works out how long each pass took from the resolved timestamps, in the order the passes were added.
+    ///
+    /// Passes whose samples are missing, failed or go backwards are left out.
+    pub fn report(&self, timestamps: &[u64], calibration: &TimestampCalibration) -> GpuPassReport {
+        let passes = self.passes.iter().enumerate()
+            .filter_map(|(pass, name)| {
+                let start = *timestamps.get(2 * pass)?;
+                let end = *timestamps.get(2 * pass + 1)?;
+                if start == 0 || start == MTLCounterErrorValue || end == MTLCounterErrorValue || end < start {
+                    return None;
+                }
+                Some(GpuPassTiming { name: name.clone(), nanoseconds: calibration.ticks_to_nanoseconds(end - start) })
+            })
+            .collect();
+        GpuPassReport { passes }
+    }
+}
+
+/// The GPU times of a frame's named passes.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct GpuPassReport {
+    /// The passes, in the order they were added to the timer.
+    pub passes: Vec<GpuPassTiming>,
+}
+impl GpuPassReport {
+    /// Gets the time of the named pass, in nanoseconds.
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.passes.iter().find(|pass| pass.name == name).map(|pass| pass.nanoseconds)
+    }
+    /// Gets the total time of the passes, in nanoseconds.
+    pub fn total_nanoseconds(&self) -> f64 { self.passes.iter().map(|pass| pass.nanoseconds).sum() }
+    /// Gets the slowest pass.
+    pub fn slowest(&self) -> Option<&GpuPassTiming> {
+        self.passes.iter().max_by(|a, b| a.nanoseconds.total_cmp(&b.nanoseconds))
+    }
+    /// Formats the report as one `name: milliseconds` line per pass, for logging.
+    pub fn to_text(&self) -> String {
+        self.passes.iter()
+            .map(|pass| format!("{}: {:.3} ms\n", pass.name, pass.nanoseconds / 1_000_000.0))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn close(expected: f64, actual: f64) -> bool { (expected - actual).abs() < 1e-6 }
+
+    #[test]
+    fn test_calibration_converts_ticks_to_nanoseconds() {
+        // A GPU clock running at 24 MHz, so about 41.667 ns per tick.
+        let start = TimestampPair { cpu: 1_000_000_000, gpu: 5_000 };
+        let end = TimestampPair { cpu: 1_001_000_000, gpu: 5_000 + 24_000 };
+        let calibration = TimestampCalibration::new(start, end).unwrap();
+        assert!(close(1_000_000.0 / 24_000.0, calibration.nanoseconds_per_tick()));
+        assert!(close(1_000.0, calibration.ticks_to_nanoseconds(24)));
+        assert!(close(1_000_000_000.0, calibration.gpu_to_cpu_nanoseconds(5_000)));
+        assert!(close(1_000_500_000.0, calibration.gpu_to_cpu_nanoseconds(17_000)));
+        // Timestamps from before the first pair are extrapolated backwards.
+        assert!(close(999_999_000.0, calibration.gpu_to_cpu_nanoseconds(4_976)));
+    }
+
+    #[test]
+    fn test_calibration_needs_both_clocks_to_move() {
+        let start = TimestampPair { cpu: 100, gpu: 100 };
+        assert_eq!(None, TimestampCalibration::new(start, start));
+        assert_eq!(None, TimestampCalibration::new(start, TimestampPair { cpu: 200, gpu: 100 }));
+        assert_eq!(None, TimestampCalibration::new(start, TimestampPair { cpu: 50, gpu: 200 }));
+        let nanosecond_clock = TimestampCalibration::new(start, TimestampPair { cpu: 300, gpu: 300 }).unwrap();
+        assert!(close(1.0, nanosecond_clock.nanoseconds_per_tick()));
+    }
+
+    #[test]
+    fn test_pass_timer_reports_each_pass() {
+        let mut timer = GpuPassTimer::new();
+        assert_eq!((0, 1), timer.add_pass("shadows"));
+        assert_eq!((2, 3), timer.add_pass("gbuffer"));
+        assert_eq!((4, 5), timer.add_pass("lighting"));
+        assert_eq!(6, timer.sample_count());
+        let calibration = TimestampCalibration::new(
+            TimestampPair { cpu: 0, gpu: 0 },
+            TimestampPair { cpu: 2_000, gpu: 1_000 }
+        ).unwrap();
+        let timestamps = [100, 600, 700, 2_700, MTLCounterErrorValue, 3_000];
+        let report = timer.report(&timestamps, &calibration);
+        assert_eq!(2, report.passes.len());
+        assert_eq!(Some(1_000.0), report.get("shadows"));
+        assert_eq!(Some(4_000.0), report.get("gbuffer"));
+        assert_eq!(None, report.get("lighting"));
+        assert_eq!(5_000.0, report.total_nanoseconds());
+        assert_eq!("gbuffer", report.slowest().unwrap().name);
+        assert_eq!("shadows: 0.001 ms\ngbuffer: 0.004 ms\n", report.to_text());
+        timer.clear();
+        assert_eq!(0, timer.sample_count());
+    }
+}
@@ -0,0 +1,143 @@
+//
+//  metal_debug_group.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Named groups of commands and signposts, as shown in Xcode's frame capture,
+//! with a guard that pops each group it pushes
+
+use std::ops::{Deref, DerefMut};
+
+/// Something that can mark nested groups of the commands it records, and single points among them,
+/// i.e. a command buffer or a command encoder.
+pub trait MetalDebugGroups {
+    /// Starts a named group of commands.
+    fn push_debug_group(&mut self, name: &str);
+    /// Ends the group of commands most recently started.
+    fn pop_debug_group(&mut self);
+    /// Marks a single point in the commands, e.g. where a pass changes.
+    fn insert_debug_signpost(&mut self, name: &str);
+    /// Starts a named group of commands that ends when the returned guard is dropped.
+    ///
+    /// The guard derefs to `self`, so commands can be recorded through it,
+    /// and further groups nested inside it.
+    fn debug_group(&mut self, name: &str) -> DebugGroup<'_, Self> where Self: Sized {
+        DebugGroup::new(self, name)
+    }
+}
+
+/// A debug group that is popped when it goes out of scope,
+/// so pushes and pops stay balanced even on early returns.
+///
+/// Drop the guard before ending the encoder, as Metal wants
+/// every group an encoder pushes popped before `end_encoding`.
+pub struct DebugGroup<'a, T: MetalDebugGroups> {
+    target: &'a mut T,
+}
+impl<'a, T: MetalDebugGroups> DebugGroup<'a, T> {
+    /// Pushes a group with the given name onto the target.
+    pub fn new(target: &'a mut T, name: &str) -> Self {
+        target.push_debug_group(name);
+        DebugGroup { target }
+    }
+}
+impl<T: MetalDebugGroups> Deref for DebugGroup<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T { self.target }
+}
+impl<T: MetalDebugGroups> DerefMut for DebugGroup<'_, T> {
+    fn deref_mut(&mut self) -> &mut T { self.target }
+}
+impl<T: MetalDebugGroups> Drop for DebugGroup<'_, T> {
+    fn drop(&mut self) { self.target.pop_debug_group() }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    /// Records the groups pushed and popped, and how deep they go.
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<String>,
+        depth: usize,
+    }
+    impl Recorder {
+        fn draw(&mut self) { self.events.push(format!("draw at depth {}", self.depth)) }
+    }
+    impl MetalDebugGroups for Recorder {
+        fn push_debug_group(&mut self, name: &str) {
+            self.depth += 1;
+            self.events.push(format!("push {}", name));
+        }
+        fn pop_debug_group(&mut self) {
+            assert!(self.depth > 0, "popped a group that was never pushed");
+            self.depth -= 1;
+            self.events.push(String::from("pop"));
+        }
+        fn insert_debug_signpost(&mut self, name: &str) {
+            self.events.push(format!("signpost {} at depth {}", name, self.depth));
+        }
+    }
+
+    #[test]
+    fn test_guard_pops_when_dropped() {
+        let mut recorder = Recorder::default();
+        {
+            let mut group = recorder.debug_group("Shadows");
+            group.draw();
+            group.insert_debug_signpost("Cascade 1");
+        }
+        recorder.draw();
+        assert_eq!(
+            vec!["push Shadows", "draw at depth 1", "signpost Cascade 1 at depth 1", "pop", "draw at depth 0"],
+            recorder.events
+        );
+    }
+
+    #[test]
+    fn test_nested_guards_pop_in_reverse_order() {
+        let mut recorder = Recorder::default();
+        {
+            let mut frame = recorder.debug_group("Frame");
+            {
+                let mut opaque = frame.debug_group("Opaque");
+                opaque.draw();
+            }
+            let mut transparent = frame.debug_group("Transparent");
+            transparent.draw();
+        }
+        assert_eq!(0, recorder.depth);
+        assert_eq!(
+            vec!["push Frame", "push Opaque", "draw at depth 2", "pop", "push Transparent", "draw at depth 2", "pop", "pop"],
+            recorder.events
+        );
+    }
+
+    #[test]
+    fn test_guard_pops_on_early_return_and_panic() {
+        fn draw_if(recorder: &mut Recorder, draw: bool) -> Option<()> {
+            let mut group = recorder.debug_group("Maybe");
+            if !draw {
+                return None;
+            }
+            group.draw();
+            Some(())
+        }
+        let mut recorder = Recorder::default();
+        assert_eq!(None, draw_if(&mut recorder, false));
+        assert_eq!(Some(()), draw_if(&mut recorder, true));
+        assert_eq!(0, recorder.depth);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _group = recorder.debug_group("Panics");
+            panic!("draw failed");
+        }));
+        assert!(result.is_err());
+        assert_eq!(0, recorder.depth);
+        assert_eq!(Some("pop"), recorder.events.last().map(String::as_str));
+    }
+}
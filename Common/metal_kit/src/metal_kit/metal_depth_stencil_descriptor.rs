@@ -15,16 +15,47 @@ use objc::sel_impl;
 use cocoa::base::{id, nil};
 use objc::runtime::{objc_retain, objc_release, BOOL};
 use cocoa::foundation::NSUInteger;
+use crate::metal_kit::metal_stencil_descriptor::MetalStencilDescriptor;
+use crate::metal_kit::ns_string::{get_objc_label, set_objc_label};
 
 // From MTLDepthStencil.h:
 // typedef NS_ENUM(NSUInteger, MTLCompareFunction) {
-// ...
+//     MTLCompareFunctionNever = 0,
 //     MTLCompareFunctionLess = 1,
-// ...
-// }
+//     MTLCompareFunctionEqual = 2,
+//     MTLCompareFunctionLessEqual = 3,
+//     MTLCompareFunctionGreater = 4,
+//     MTLCompareFunctionNotEqual = 5,
+//     MTLCompareFunctionGreaterEqual = 6,
+//     MTLCompareFunctionAlways = 7,
+// } API_AVAILABLE(macos(10.11), ios(8.0));
+/// Options used to specify how a sample compare operation
+/// should be performed on a depth or stencil value.
+pub type MTLCompareFunction = NSUInteger;
+/// A new value never passes the comparison test.
+#[allow(non_upper_case_globals)]
+pub const MTLCompareFunctionNever:MTLCompareFunction = 0;
 /// A new value passes the comparison test if it is less than the existing value.
 #[allow(non_upper_case_globals)]
-pub const MTLCompareFunctionLess:NSUInteger = 1;
+pub const MTLCompareFunctionLess:MTLCompareFunction = 1;
+/// A new value passes the comparison test if it is equal to the existing value.
+#[allow(non_upper_case_globals)]
+pub const MTLCompareFunctionEqual:MTLCompareFunction = 2;
+/// A new value passes the comparison test if it is less than or equal to the existing value.
+#[allow(non_upper_case_globals)]
+pub const MTLCompareFunctionLessEqual:MTLCompareFunction = 3;
+/// A new value passes the comparison test if it is greater than the existing value.
+#[allow(non_upper_case_globals)]
+pub const MTLCompareFunctionGreater:MTLCompareFunction = 4;
+/// A new value passes the comparison test if it is not equal to the existing value.
+#[allow(non_upper_case_globals)]
+pub const MTLCompareFunctionNotEqual:MTLCompareFunction = 5;
+/// A new value passes the comparison test if it is greater than or equal to the existing value.
+#[allow(non_upper_case_globals)]
+pub const MTLCompareFunctionGreaterEqual:MTLCompareFunction = 6;
+/// A new value always passes the comparison test.
+#[allow(non_upper_case_globals)]
+pub const MTLCompareFunctionAlways:MTLCompareFunction = 7;
 
 /// Rust wrapper around an object that configures new MTLDepthStencilState objects.
 pub struct MetalDepthStencilDescriptor {
@@ -43,6 +74,10 @@ impl Drop for MetalDepthStencilDescriptor {
     fn drop(&mut self) { unsafe { objc_release(self.descriptor) } }
 }
 impl MetalDepthStencilDescriptor {
+    /// Sets the label used to identify the depth stencil state made from this descriptor in debugging tools.
+    pub fn set_label(&mut self, label: &str) { set_objc_label(self.descriptor, label) }
+    /// Gets the label (empty if none was set).
+    pub fn get_label(&self) -> String { get_objc_label(self.descriptor) }
     /// Returns the underlying Objective C object.
     pub fn to_objc(&self) -> id { self.descriptor }
     /// Returns a new MetalDepthStencilDescriptor
@@ -62,4 +97,25 @@ impl MetalDepthStencilDescriptor {
     pub fn set_depth_write_enabled(&mut self, enabled: BOOL) {
         unsafe { msg_send![self.descriptor, setDepthWriteEnabled:enabled] }
     }
-}
\ No newline at end of file
+    /// Sets the stencil test and operations used for front-facing primitives.
+    pub fn set_front_face_stencil(&mut self, stencil: &MetalStencilDescriptor) {
+        let stencil = stencil.new_objc();
+        unsafe {
+            let _:() = msg_send![self.descriptor, setFrontFaceStencil:stencil];
+            objc_release(stencil);
+        }
+    }
+    /// Sets the stencil test and operations used for back-facing primitives.
+    pub fn set_back_face_stencil(&mut self, stencil: &MetalStencilDescriptor) {
+        let stencil = stencil.new_objc();
+        unsafe {
+            let _:() = msg_send![self.descriptor, setBackFaceStencil:stencil];
+            objc_release(stencil);
+        }
+    }
+    /// Sets the same stencil test and operations for front- and back-facing primitives.
+    pub fn set_stencil(&mut self, stencil: &MetalStencilDescriptor) {
+        self.set_front_face_stencil(stencil);
+        self.set_back_face_stencil(stencil);
+    }
+}
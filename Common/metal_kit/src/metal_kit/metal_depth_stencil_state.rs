@@ -11,6 +11,7 @@
 
 use cocoa::base::{id, nil};
 use objc::runtime::{objc_retain, objc_release};
+use crate::metal_kit::ns_string::get_objc_label;
 
 //  metal_depth_stencil_state.rs
 //
@@ -37,6 +38,9 @@ impl Drop for MetalDepthStencilState {
     fn drop(&mut self) { unsafe { objc_release(self.state) } }
 }
 impl MetalDepthStencilState {
+    /// Gets the label used to identify the depth stencil state in debugging tools,
+    /// copied from the descriptor it was made from (empty if none was set).
+    pub fn get_label(&self) -> String { get_objc_label(self.state) }
     /// Returns the underlying Objective C object.
     pub fn to_objc(&self) -> id { self.state }
 }
\ No newline at end of file
@@ -12,22 +12,95 @@ use objc::msg_send;
 use objc::sel;
 use objc::sel_impl;
 use cocoa::base::{id, nil};
-use objc::runtime::{objc_retain, objc_release};
+use objc::runtime::{objc_retain, objc_release, BOOL, NO};
 use crate::metal_kit::metal_library::MetalLibrary;
 use crate::metal_kit::metal_render_pipeline_descriptor::MetalRenderPipelineDescriptor;
 use crate::metal_kit::metal_render_pipeline_state::MetalRenderPipelineState;
-use std::error::Error;
-use std::fmt::{Display, Formatter};
 use crate::metal_kit::metal_command_queue::MetalCommandQueue;
 use crate::metal_kit::metal_buffer::MetalBuffer;
-use cocoa::foundation::NSUInteger;
+use cocoa::foundation::{NSUInteger, NSAutoreleasePool};
 use crate::{MetalTextureDescriptor, MetalTexture, MetalDepthStencilDescriptor, MetalDepthStencilState};
+use crate::metal_kit::metal_compile_options::MetalCompileOptions;
+use crate::metal_kit::metal_compiler_diagnostic::MetalCompilerDiagnostic;
+use crate::metal_kit::metal_error::{MetalError, NSErrorInfo};
+use crate::metal_kit::ns_string::{file_url_from_path, ns_string_from_str, rust_string_from_ns_string};
+use crate::metal_kit::metal_binary_archive::MetalBinaryArchive;
+use crate::metal_kit::metal_argument_encoder::{argument_descriptors_from_fields, MetalArgumentEncoder, MTLArgumentBuffersTier};
+use crate::metal_kit::metal_argument_layout::ArgumentLayout;
+use crate::metal_kit::metal_argument_layout::ArgumentField;
+use crate::metal_kit::metal_heap::MetalHeap;
+use crate::metal_kit::metal_fence::MetalFence;
+use crate::metal_kit::metal_event::{MetalEvent, MetalSharedEvent};
+use crate::metal_kit::metal_counter_sample_buffer::{
+    MetalCounterSampleBuffer,
+    MetalCounterSampleBufferDescriptor,
+    MetalCounterSet,
+    MTLCounterSamplingPoint,
+    TimestampPair
+};
+use crate::metal_kit::metal_pipeline_reflection::{
+    PipelineReflection,
+    MTLPipelineOptionBindingInfo,
+    MTLPipelineOptionBufferTypeInfo,
+};
+use crate::metal_kit::metal_indirect_command_buffer::{IndirectCommandBufferDescriptor, MetalIndirectCommandBuffer};
+use crate::metal_kit::metal_resource_options::MetalResourceOptions;
+use crate::metal_kit::metal_sampler_state::{MetalSamplerDescriptor, MetalSamplerState};
+use objc::class;
+use std::os::raw::c_void;
+use std::ptr::null;
+use std::path::Path;
+use std::sync::Arc;
+use crate::metal_kit::metal_device_capabilities::{
+    choose_device,
+    DeviceCapabilities,
+    DevicePreference,
+    MTLGPUFamily,
+    MTLSize,
+    KNOWN_GPU_FAMILIES,
+    KNOWN_TEXTURE_SAMPLE_COUNTS,
+};
+use crate::metal_kit::metal_callback::callback_into_user_data;
+use crate::metal_kit::metal_pipeline_compilation::{
+    render_pipeline_handler_trampoline,
+    PipelineBatch,
+    PipelineCompilation,
+};
 
 #[link(name="Metal", kind="framework")]
 extern {
     // From System/Library/Frameworks/Metal.framework/Versions/A/Headers/MTLDevice.h:
     // MTL_EXTERN id <MTLDevice> __nullable MTLCreateSystemDefaultDevice(void) API_AVAILABLE(macos(10.11), ios(8.0)) NS_RETURNS_RETAINED;
     fn MTLCreateSystemDefaultDevice() -> id;
+    // MTL_EXTERN NSArray <id<MTLDevice>> *MTLCopyAllDevices(void) API_AVAILABLE(macos(10.11)) API_UNAVAILABLE(ios) NS_RETURNS_RETAINED;
+    #[cfg(target_os = "macos")]
+    fn MTLCopyAllDevices() -> id;
+}
+#[link(name="GlueLib", kind="dylib")]
+extern {
+    // From GlueLib.h:
+    // void device_new_render_pipeline_state_f_with_user_data
+    // (id<MTLDevice> _Nonnull,
+    //  MTLRenderPipelineDescriptor *_Nonnull,
+    //  render_pipeline_completion_function_with_user_data_t _Nullable,
+    //  void *_Nullable
+    //  );
+    fn device_new_render_pipeline_state_f_with_user_data(
+        device: id,
+        descriptor: id,
+        completion_function: extern "C" fn(state: id, error: id, user_data: *const c_void),
+        user_data: *const c_void
+    );
+}
+extern {
+    // From usr/include/dispatch/data.h:
+    // dispatch_data_t dispatch_data_create(const void *buffer,
+    //     size_t size,
+    //     dispatch_queue_t _Nullable queue,
+    //     dispatch_block_t _Nullable destructor);
+    //
+    // A nil destructor (DISPATCH_DATA_DESTRUCTOR_DEFAULT) makes dispatch copy the buffer.
+    fn dispatch_data_create(buffer: *const c_void, size: usize, queue: id, destructor: *const c_void) -> id;
 }
 //
 // typedef enum MTLResourceOptions : NSUInteger {
@@ -47,21 +120,6 @@ pub type MTLResourceOptions = NSUInteger;
 #[allow(non_upper_case_globals)]
 pub const MTLResourceCPUCacheModeDefaultCache:NSUInteger = 0; // I think we've got a good chance of this always remaining 0.
 
-/// The error returned if the system failed
-/// to create a render pipeline state
-#[derive(Debug)]
-pub enum MetalDeviceError {
-    /// The system returned the given Objective C error
-    /// when attempting to create a render pipeline state
-    RenderPipelineStateCreationError(id),
-}
-impl Display for MetalDeviceError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Render pipeline state creation error")
-    }
-}
-impl Error for MetalDeviceError {}
-
 /// Rust wrapper for the Metal interface to a GPU
 /// that you use to draw graphics or do parallel computation.
 pub struct MetalDevice {
@@ -89,15 +147,224 @@ impl MetalDevice {
         let device:id = unsafe { MTLCreateSystemDefaultDevice() };
         MetalDevice::from(device)
     }
+    /// Returns every Metal device in the system.
+    #[cfg(target_os = "macos")]
+    pub fn copy_all_devices() -> Vec<MetalDevice> {
+        let devices = unsafe { MTLCopyAllDevices() };
+        if devices == nil {
+            return Vec::new();
+        }
+        let count: NSUInteger = unsafe { msg_send![devices, count] };
+        let result = (0..count).map(|index| {
+            let device: id = unsafe { msg_send![devices, objectAtIndex:index] };
+            MetalDevice::from(device)
+        }).collect();
+        unsafe { objc_release(devices) };
+        result
+    }
+    /// Returns every Metal device in the system.
+    ///
+    /// iOS devices only ever have the one GPU.
+    #[cfg(not(target_os = "macos"))]
+    pub fn copy_all_devices() -> Vec<MetalDevice> {
+        let device = Self::create_system_default_device();
+        if device.to_objc() == nil { Vec::new() } else { vec![device] }
+    }
+    /// Returns the first device of the kind asked for,
+    /// or any device if none is that kind (see `choose_device`).
+    pub fn select_device(preference: DevicePreference) -> Option<MetalDevice> {
+        let mut devices = Self::copy_all_devices();
+        let capabilities: Vec<DeviceCapabilities> = devices.iter().map(|device| device.get_capabilities()).collect();
+        choose_device(&capabilities, preference).map(|index| devices.swap_remove(index))
+    }
     /// Returns the underlying objective c device
     pub fn to_objc(&self) -> id { self.device }
+    /// Gets the ID the system uses for the GPU, stable across launches.
+    pub fn get_registry_id(&self) -> u64 {
+        unsafe { msg_send![self.device, registryID] }
+    }
+    /// Returns true for a GPU that trades performance for battery life.
+    #[cfg(target_os = "macos")]
+    pub fn is_low_power(&self) -> bool {
+        let low_power: BOOL = unsafe { msg_send![self.device, isLowPower] };
+        low_power != NO
+    }
+    /// Returns true for a GPU that trades performance for battery life.
+    ///
+    /// Only macOS reports this, so this is always false on iOS.
+    #[cfg(not(target_os = "macos"))]
+    pub fn is_low_power(&self) -> bool { false }
+    /// Returns true for a GPU with no display attached.
+    #[cfg(target_os = "macos")]
+    pub fn is_headless(&self) -> bool {
+        let headless: BOOL = unsafe { msg_send![self.device, isHeadless] };
+        headless != NO
+    }
+    /// Returns true for a GPU with no display attached.
+    ///
+    /// Only macOS reports this, so this is always false on iOS.
+    #[cfg(not(target_os = "macos"))]
+    pub fn is_headless(&self) -> bool { false }
+    /// Returns true for an external GPU that can be unplugged.
+    #[cfg(target_os = "macos")]
+    pub fn is_removable(&self) -> bool {
+        let removable: BOOL = unsafe { msg_send![self.device, isRemovable] };
+        removable != NO
+    }
+    /// Returns true for an external GPU that can be unplugged.
+    ///
+    /// Only macOS reports this, so this is always false on iOS.
+    #[cfg(not(target_os = "macos"))]
+    pub fn is_removable(&self) -> bool { false }
+    /// Returns true if the CPU and GPU share memory.
+    pub fn has_unified_memory(&self) -> bool {
+        let unified: BOOL = unsafe { msg_send![self.device, hasUnifiedMemory] };
+        unified != NO
+    }
+    /// Returns true if the device supports the features of the GPU family.
+    pub fn supports_family(&self, family: MTLGPUFamily) -> bool {
+        let supported: BOOL = unsafe { msg_send![self.device, supportsFamily:family] };
+        supported != NO
+    }
+    /// Gets the most memory, in bytes, the app should use on the GPU without hurting performance.
+    pub fn get_recommended_max_working_set_size(&self) -> u64 {
+        unsafe { msg_send![self.device, recommendedMaxWorkingSetSize] }
+    }
+    /// Gets the most threads a threadgroup can have in each dimension.
+    pub fn get_max_threads_per_threadgroup(&self) -> MTLSize {
+        unsafe { msg_send![self.device, maxThreadsPerThreadgroup] }
+    }
+    /// Gets the largest buffer the device can make, in bytes.
+    pub fn get_max_buffer_length(&self) -> NSUInteger {
+        unsafe { msg_send![self.device, maxBufferLength] }
+    }
+    /// Copies what the device can do into a plain value.
+    pub fn get_capabilities(&self) -> DeviceCapabilities {
+        DeviceCapabilities {
+            name: self.get_name(),
+            registry_id: self.get_registry_id(),
+            is_low_power: self.is_low_power(),
+            is_headless: self.is_headless(),
+            is_removable: self.is_removable(),
+            has_unified_memory: self.has_unified_memory(),
+            gpu_families: KNOWN_GPU_FAMILIES.iter().copied().filter(|family| self.supports_family(*family)).collect(),
+            recommended_max_working_set_size: self.get_recommended_max_working_set_size(),
+            max_threads_per_threadgroup: self.get_max_threads_per_threadgroup(),
+            max_buffer_length: self.get_max_buffer_length(),
+            argument_buffers_tier: self.get_argument_buffers_support(),
+            texture_sample_counts: KNOWN_TEXTURE_SAMPLE_COUNTS.iter().copied()
+                .filter(|count| self.supports_texture_sample_count(*count)).collect(),
+        }
+    }
+    /// Gets the name of the GPU, as debugging tools show it (devices have no label).
+    pub fn get_name(&self) -> String {
+        let name: id = unsafe { msg_send![self.device, name] };
+        rust_string_from_ns_string(name)
+    }
+    /// Returns true if the device can create textures with the given number of samples per pixel.
+    pub fn supports_texture_sample_count(&self, sample_count: NSUInteger) -> bool {
+        let supported: BOOL = unsafe { msg_send![self.device, supportsTextureSampleCount:sample_count] };
+        supported != NO
+    }
+    /// Returns true if the device supports the Depth24Unorm_Stencil8 pixel format.
+    ///
+    /// The format is only ever available on macOS, so this is always false on iOS.
+    #[cfg(target_os = "macos")]
+    pub fn is_depth24_stencil8_pixel_format_supported(&self) -> bool {
+        let supported: BOOL = unsafe { msg_send![self.device, isDepth24Stencil8PixelFormatSupported] };
+        supported != NO
+    }
+    /// Returns true if the device supports the Depth24Unorm_Stencil8 pixel format.
+    ///
+    /// The format is only ever available on macOS, so this is always false on iOS.
+    #[cfg(not(target_os = "macos"))]
+    pub fn is_depth24_stencil8_pixel_format_supported(&self) -> bool { false }
+    /// Gets how much the device can do with argument buffers.
+    pub fn get_argument_buffers_support(&self) -> MTLArgumentBuffersTier {
+        unsafe { msg_send![self.device, argumentBuffersSupport] }
+    }
     /// Creates a library object containing the functions in the app’s default Metal library.
-    pub fn new_default_library(&self) -> MetalLibrary {
+    pub fn new_default_library(&self) -> Result<MetalLibrary, MetalError> {
         let library: id = unsafe { msg_send![self.device, newDefaultLibrary] };
-        MetalLibrary::from(library)
+        if library == nil {
+            Err(MetalError::DefaultLibraryNotFound)
+        } else {
+            Ok(Self::library_from_new_object(library))
+        }
+    }
+    /// Synchronously creates a library object by compiling the given Metal source code.
+    ///
+    /// If the compiler rejects the source, the error carries
+    /// the compiler's diagnostics, with their lines and columns.
+    pub fn new_library_with_source(&self, source: &str, options: &MetalCompileOptions) -> Result<MetalLibrary, MetalError> {
+        // - (nullable id <MTLLibrary>)newLibraryWithSource:(NSString *)source
+        //                                          options:(nullable MTLCompileOptions *)options
+        //                                            error:(__autoreleasing NSError **)error;
+        let pool = unsafe { NSAutoreleasePool::new(nil) };
+        let source = ns_string_from_str(source);
+        let mut error: id = nil;
+        let library: id = unsafe { msg_send![self.device, newLibraryWithSource:source options:options.to_objc() error:&mut error] };
+        unsafe { objc_release(source) };
+        let result = if library == nil {
+            let error = NSErrorInfo::from_ns_error_or_unknown(error);
+            let diagnostics = MetalCompilerDiagnostic::parse_compiler_output(&error.localized_description);
+            Err(MetalError::LibraryCompilation { error, diagnostics })
+        } else {
+            Ok(Self::library_from_new_object(library))
+        };
+        unsafe { pool.drain() };
+        result
+    }
+    /// Creates a library object from the contents of a compiled `.metallib` file,
+    /// e.g. one embedded in the binary with `include_bytes!`.
+    pub fn new_library_with_data(&self, bytes: &[u8]) -> Result<MetalLibrary, MetalError> {
+        // - (nullable id <MTLLibrary>)newLibraryWithData:(dispatch_data_t)data error:(__autoreleasing NSError **)error;
+        let pool = unsafe { NSAutoreleasePool::new(nil) };
+        let data = unsafe { dispatch_data_create(bytes.as_ptr() as *const c_void, bytes.len(), nil, null()) };
+        let mut error: id = nil;
+        let library: id = unsafe { msg_send![self.device, newLibraryWithData:data error:&mut error] };
+        unsafe { objc_release(data) };
+        let result = if library == nil {
+            Err(MetalError::LibraryCreation(NSErrorInfo::from_ns_error_or_unknown(error)))
+        } else {
+            Ok(Self::library_from_new_object(library))
+        };
+        unsafe { pool.drain() };
+        result
+    }
+    /// Creates a library object from the `.metallib` file at the given NSURL.
+    pub fn new_library_with_url(&self, url: id) -> Result<MetalLibrary, MetalError> {
+        // - (nullable id <MTLLibrary>)newLibraryWithURL:(NSURL *)url error:(__autoreleasing NSError **)error;
+        if url == nil {
+            return Err(MetalError::LibraryCreation(NSErrorInfo::from_ns_error_or_unknown(nil)));
+        }
+        let pool = unsafe { NSAutoreleasePool::new(nil) };
+        let mut error: id = nil;
+        let library: id = unsafe { msg_send![self.device, newLibraryWithURL:url error:&mut error] };
+        let result = if library == nil {
+            Err(MetalError::LibraryCreation(NSErrorInfo::from_ns_error_or_unknown(error)))
+        } else {
+            Ok(Self::library_from_new_object(library))
+        };
+        unsafe { pool.drain() };
+        result
+    }
+    /// Creates a library object from the `.metallib` file at the given path.
+    pub fn new_library_with_file(&self, path: &Path) -> Result<MetalLibrary, MetalError> {
+        let pool = unsafe { NSAutoreleasePool::new(nil) };
+        let result = self.new_library_with_url(file_url_from_path(path));
+        unsafe { pool.drain() };
+        result
+    }
+    /// Wraps a library returned from a `new...` method,
+    /// balancing the retain the method has already done for us.
+    fn library_from_new_object(library: id) -> MetalLibrary {
+        let result = MetalLibrary::from(library);
+        unsafe { objc_release(library) };
+        result
     }
     /// Synchronously creates a render pipeline state object and associated reflection information.
-    pub fn new_render_pipeline_state_with_descriptor(&mut self, descriptor: MetalRenderPipelineDescriptor) -> Result<MetalRenderPipelineState, MetalDeviceError> {
+    pub fn new_render_pipeline_state_with_descriptor(&mut self, descriptor: MetalRenderPipelineDescriptor) -> Result<MetalRenderPipelineState, MetalError> {
         //
         // NSError *error = nil;
         // _pipeline = [device newRenderPipelineStateWithDescriptor:pipelineDescriptor
@@ -107,14 +374,89 @@ impl MetalDevice {
         // {
         //     NSLog(@"Error occurred when creating render pipeline state: %@", error);
         // }
-        let error:id = nil;
-        let pipeline_state:id = unsafe { msg_send![self.device, newRenderPipelineStateWithDescriptor:descriptor error:&error] };
+        let mut error:id = nil;
+        let pipeline_state:id = unsafe { msg_send![self.device, newRenderPipelineStateWithDescriptor:descriptor.to_objc() error:&mut error] };
         if pipeline_state == nil {
-            Err(MetalDeviceError::RenderPipelineStateCreationError(error))
+            Err(MetalError::RenderPipelineStateCreation(NSErrorInfo::from_ns_error_or_unknown(error)))
+        } else {
+            let result = MetalRenderPipelineState::from(pipeline_state);
+            unsafe { objc_release(pipeline_state) };
+            Ok(result)
+        }
+    }
+    /// Synchronously creates a render pipeline state,
+    /// also reporting the arguments its functions take and the layout of their buffers.
+    pub fn new_render_pipeline_state_with_reflection(
+        &mut self,
+        descriptor: MetalRenderPipelineDescriptor
+    ) -> Result<(MetalRenderPipelineState, PipelineReflection), MetalError> {
+        // - (nullable id <MTLRenderPipelineState>)newRenderPipelineStateWithDescriptor:(MTLRenderPipelineDescriptor *)descriptor
+        //     options:(MTLPipelineOption)options
+        //     reflection:(MTLAutoreleasedRenderPipelineReflection * __nullable)reflection
+        //     error:(__autoreleasing NSError **)error;
+        let pool = unsafe { NSAutoreleasePool::new(nil) };
+        let options = MTLPipelineOptionBindingInfo | MTLPipelineOptionBufferTypeInfo;
+        let mut reflection: id = nil;
+        let mut error: id = nil;
+        let pipeline_state: id = unsafe {
+            msg_send![self.device, newRenderPipelineStateWithDescriptor:descriptor.to_objc()
+                                                                options:options
+                                                             reflection:&mut reflection
+                                                                  error:&mut error]
+        };
+        let result = if pipeline_state == nil {
+            Err(MetalError::RenderPipelineStateCreation(NSErrorInfo::from_ns_error_or_unknown(error)))
         } else {
-            Ok(MetalRenderPipelineState::from(pipeline_state))
+            let state = MetalRenderPipelineState::from(pipeline_state);
+            unsafe { objc_release(pipeline_state) };
+            Ok((state, PipelineReflection::from_objc(reflection)))
+        };
+        unsafe { pool.drain() };
+        result
+    }
+    /// Starts compiling a render pipeline state on one of Metal's threads,
+    /// returning straight away.
+    ///
+    /// The closure is called once, on a Metal thread, with the pipeline state or the error.
+    pub fn new_render_pipeline_state_async<F>(&self, descriptor: &MetalRenderPipelineDescriptor, handler: F)
+        where F: FnOnce(Result<MetalRenderPipelineState, MetalError>) + Send + 'static {
+        // - (void)newRenderPipelineStateWithDescriptor:(MTLRenderPipelineDescriptor *)descriptor
+        //                            completionHandler:(MTLNewRenderPipelineStateCompletionHandler)completionHandler;
+        unsafe { device_new_render_pipeline_state_f_with_user_data(
+            self.device,
+            descriptor.to_objc(),
+            render_pipeline_handler_trampoline,
+            callback_into_user_data(handler)
+        )}
+    }
+    /// Starts compiling a render pipeline state on one of Metal's threads,
+    /// returning a future that resolves to it.
+    ///
+    /// Wrap the future in a `PendingPipeline` to draw only once it is ready.
+    pub fn compile_render_pipeline_state(&self, descriptor: &MetalRenderPipelineDescriptor) -> PipelineCompilation {
+        let (compilation, handler) = PipelineCompilation::new();
+        self.new_render_pipeline_state_async(descriptor, handler);
+        compilation
+    }
+    /// Starts compiling every render pipeline state in the list at once, returning straight away.
+    ///
+    /// The closure is called once for each descriptor, with its index in the list,
+    /// on whichever Metal thread compiled it, so calls can overlap and come in any order.
+    pub fn new_render_pipeline_states_async<F>(&self, descriptors: &[MetalRenderPipelineDescriptor], handler: F)
+        where F: Fn(usize, Result<MetalRenderPipelineState, MetalError>) + Send + Sync + 'static {
+        let handler = Arc::new(handler);
+        for (index, descriptor) in descriptors.iter().enumerate() {
+            let handler = handler.clone();
+            self.new_render_pipeline_state_async(descriptor, move |result| handler(index, result));
         }
     }
+    /// Starts compiling every render pipeline state in the list at once,
+    /// returning a future that resolves to all of them, in the same order as the descriptors.
+    pub fn compile_render_pipeline_states(&self, descriptors: &[MetalRenderPipelineDescriptor]) -> PipelineBatch {
+        let (batch, handler) = PipelineBatch::new(descriptors.len());
+        self.new_render_pipeline_states_async(descriptors, handler);
+        batch
+    }
     /// Creates a command submission queue.
     pub fn new_command_queue(&mut self ) -> MetalCommandQueue {
         let command_queue:id = unsafe { msg_send![self.device, newCommandQueue] };
@@ -122,26 +464,220 @@ impl MetalDevice {
     }
     // - (id<MTLBuffer>)newBufferWithBytes:(const void *)pointer length:(NSUInteger)length options:(MTLResourceOptions)options;
     /// Allocates a new buffer of a given length and initializes its contents by copying existing data into it.
-    pub fn new_buffer_with_bytes_and_options<T>(&mut self, pointer: &[T], options: MTLResourceOptions) -> MetalBuffer {
+    ///
+    /// Metal cannot make empty buffers, so the data must take up at least one byte.
+    pub fn new_buffer_with_bytes_and_options<T>(&mut self, pointer: &[T], options: MetalResourceOptions) -> Result<MetalBuffer, MetalError> {
         let length = std::mem::size_of_val(pointer) as NSUInteger;
         //+ debug_log(&format!("Length of buffer is {} bytes", length));
-        let data_ptr = pointer.first().unwrap() as *const _;
-        let buffer:id = unsafe { msg_send![self.device, newBufferWithBytes:data_ptr length:length options:options] };
-        MetalBuffer::from(buffer)
+        if length == 0 {
+            return Err(MetalError::EmptyBuffer);
+        }
+        let data_ptr = pointer.as_ptr() as *const c_void;
+        let buffer:id = unsafe { msg_send![self.device, newBufferWithBytes:data_ptr length:length options:options.to_raw()] };
+        if buffer == nil {
+            return Err(MetalError::BufferCreation(length));
+        }
+        Ok(MetalBuffer::from(buffer))
     }
     /// Allocates a new zero-filled buffer of a given length.
-    pub fn new_buffer_with_length_and_options(&mut self, length: NSUInteger, options: MTLResourceOptions) -> MetalBuffer {
-        let buffer:id = unsafe { msg_send![self.device, newBufferWithLength:length options:options] };
+    pub fn new_buffer_with_length_and_options(&mut self, length: NSUInteger, options: MetalResourceOptions) -> MetalBuffer {
+        let buffer:id = unsafe { msg_send![self.device, newBufferWithLength:length options:options.to_raw()] };
         MetalBuffer::from(buffer)
     }
     /// Creates a texture on the heap with the given properties.
-    pub fn new_texture_with_descriptor(&self, descriptor: MetalTextureDescriptor) -> MetalTexture {
-        let texture:id = unsafe { msg_send![self.device, newTextureWithDescriptor:descriptor] };
-        MetalTexture::from(texture)
+    pub fn new_texture_with_descriptor(&self, descriptor: MetalTextureDescriptor) -> Result<MetalTexture, MetalError> {
+        let texture:id = unsafe { msg_send![self.device, newTextureWithDescriptor:descriptor.to_objc()] };
+        if texture == nil {
+            Err(MetalError::TextureCreation {
+                width: descriptor.get_width(),
+                height: descriptor.get_height(),
+                pixel_format: descriptor.get_pixel_format(),
+            })
+        } else {
+            let result = MetalTexture::from(texture);
+            unsafe { objc_release(texture) };
+            Ok(result)
+        }
     }
     /// Creates a new object that contains depth and stencil test state.
-    pub fn new_depth_stencil_state_with_descriptor(&self, descriptor: MetalDepthStencilDescriptor) -> MetalDepthStencilState {
-        let state:id = unsafe { msg_send![self.device, newDepthStencilStateWithDescriptor:descriptor] };
-        MetalDepthStencilState::from(state)
+    pub fn new_depth_stencil_state_with_descriptor(&self, descriptor: MetalDepthStencilDescriptor) -> Result<MetalDepthStencilState, MetalError> {
+        let state:id = unsafe { msg_send![self.device, newDepthStencilStateWithDescriptor:descriptor.to_objc()] };
+        if state == nil {
+            Err(MetalError::DepthStencilStateCreation)
+        } else {
+            let result = MetalDepthStencilState::from(state);
+            unsafe { objc_release(state) };
+            Ok(result)
+        }
+    }
+    /// Creates a sampler state with the settings in the descriptor.
+    pub fn new_sampler_state_with_descriptor(&self, descriptor: &MetalSamplerDescriptor) -> Result<MetalSamplerState, MetalError> {
+        let state: id = unsafe { msg_send![self.device, newSamplerStateWithDescriptor:descriptor.to_objc()] };
+        if state == nil {
+            Err(MetalError::SamplerStateCreation)
+        } else {
+            let result = MetalSamplerState::from(state);
+            unsafe { objc_release(state) };
+            Ok(result)
+        }
+    }
+    /// Creates a heap of the given size, whose resources all use the given options.
+    pub fn new_heap_with_size_and_options(&self, size: NSUInteger, options: MetalResourceOptions) -> Result<MetalHeap, MetalError> {
+        let class = class!(MTLHeapDescriptor);
+        let heap: id = unsafe {
+            let descriptor: id = msg_send![class, new];
+            let _:() = msg_send![descriptor, setSize:size];
+            let _:() = msg_send![descriptor, setResourceOptions:options.to_raw()];
+            let heap: id = msg_send![self.device, newHeapWithDescriptor:descriptor];
+            objc_release(descriptor);
+            heap
+        };
+        if heap == nil {
+            Err(MetalError::HeapCreation(size))
+        } else {
+            let result = MetalHeap::from(heap);
+            unsafe { objc_release(heap) };
+            Ok(result)
+        }
+    }
+    /// Creates a fence to order passes within a command queue.
+    pub fn new_fence(&self) -> Result<MetalFence, MetalError> {
+        let fence: id = unsafe { msg_send![self.device, newFence] };
+        if fence == nil {
+            return Err(MetalError::FenceCreation);
+        }
+        Ok(MetalFence::from_new_object(fence))
+    }
+    /// Creates an event to order work between command queues on this device.
+    pub fn new_event(&self) -> Result<MetalEvent, MetalError> {
+        let event: id = unsafe { msg_send![self.device, newEvent] };
+        if event == nil {
+            return Err(MetalError::EventCreation);
+        }
+        Ok(MetalEvent::from_new_object(event))
     }
+    /// Creates an event whose value the CPU can also read, set and be told about.
+    pub fn new_shared_event(&self) -> Result<MetalSharedEvent, MetalError> {
+        let event: id = unsafe { msg_send![self.device, newSharedEvent] };
+        if event == nil {
+            return Err(MetalError::EventCreation);
+        }
+        Ok(MetalSharedEvent::from_new_object(event))
+    }
+    /// Returns the counter sets the device can sample.
+    pub fn get_counter_sets(&self) -> Vec<MetalCounterSet> {
+        let sets: id = unsafe { msg_send![self.device, counterSets] };
+        if sets == nil {
+            return Vec::new();
+        }
+        let count: NSUInteger = unsafe { msg_send![sets, count] };
+        (0..count).map(|index| {
+            let set: id = unsafe { msg_send![sets, objectAtIndex:index] };
+            MetalCounterSet::from(set)
+        }).collect()
+    }
+    /// Returns the counter set with the given name, e.g. `MTLCommonCounterSetTimestamp`,
+    /// if the device can sample it.
+    pub fn find_counter_set(&self, name: &str) -> Option<MetalCounterSet> {
+        self.get_counter_sets().into_iter().find(|set| set.get_name() == name)
+    }
+    /// Returns true if the device can take counter samples at the given point.
+    pub fn supports_counter_sampling(&self, sampling_point: MTLCounterSamplingPoint) -> bool {
+        let supported: BOOL = unsafe { msg_send![self.device, supportsCounterSampling:sampling_point] };
+        supported != NO
+    }
+    /// Creates a buffer for the GPU to write counter samples into.
+    pub fn new_counter_sample_buffer(
+        &self,
+        descriptor: &MetalCounterSampleBufferDescriptor
+    ) -> Result<MetalCounterSampleBuffer, MetalError> {
+        // - (nullable id<MTLCounterSampleBuffer>) newCounterSampleBufferWithDescriptor:(MTLCounterSampleBufferDescriptor*)descriptor
+        //                                                                        error:(NSError**)error;
+        let pool = unsafe { NSAutoreleasePool::new(nil) };
+        let mut error: id = nil;
+        let buffer: id = unsafe { msg_send![self.device, newCounterSampleBufferWithDescriptor:descriptor.to_objc() error:&mut error] };
+        let result = if buffer == nil {
+            Err(MetalError::CounterSampleBufferCreation(NSErrorInfo::from_ns_error_or_unknown(error)))
+        } else {
+            Ok(MetalCounterSampleBuffer::from_new_object(buffer))
+        };
+        unsafe { pool.drain() };
+        result
+    }
+    /// Takes a CPU and a GPU timestamp at the same moment,
+    /// for converting GPU timestamps with a `TimestampCalibration`.
+    pub fn sample_timestamps(&self) -> TimestampPair {
+        let mut cpu: u64 = 0;
+        let mut gpu: u64 = 0;
+        unsafe { msg_send![self.device, sampleTimestamps:&mut cpu gpuTimestamp:&mut gpu] }
+        TimestampPair { cpu, gpu }
+    }
+    /// Creates a binary archive, empty or loaded from the given file.
+    ///
+    /// Pipelines whose descriptors list the archive reuse the functions in it
+    /// rather than compiling them again.
+    pub fn new_binary_archive(&self, path: Option<&Path>) -> Result<MetalBinaryArchive, MetalError> {
+        // - (nullable id <MTLBinaryArchive>)newBinaryArchiveWithDescriptor:(MTLBinaryArchiveDescriptor*)descriptor
+        //                                                             error:(NSError**)error;
+        let pool = unsafe { NSAutoreleasePool::new(nil) };
+        let class = class!(MTLBinaryArchiveDescriptor);
+        let mut error: id = nil;
+        let archive: id = unsafe {
+            let descriptor: id = msg_send![class, new];
+            if let Some(path) = path {
+                let _:() = msg_send![descriptor, setUrl:file_url_from_path(path)];
+            }
+            let archive: id = msg_send![self.device, newBinaryArchiveWithDescriptor:descriptor error:&mut error];
+            objc_release(descriptor);
+            archive
+        };
+        let result = if archive == nil {
+            Err(MetalError::BinaryArchive(NSErrorInfo::from_ns_error_or_unknown(error)))
+        } else {
+            Ok(MetalBinaryArchive::from_new_object(archive))
+        };
+        unsafe { pool.drain() };
+        result
+    }
+    /// Creates an argument encoder for an argument buffer holding the given fields.
+    ///
+    /// The fields are checked as `ArgumentLayout` would lay them out.
+    pub fn new_argument_encoder_with_arguments(&self, fields: &[ArgumentField]) -> Result<MetalArgumentEncoder, MetalError> {
+        ArgumentLayout::new(fields)?;
+        let pool = unsafe { NSAutoreleasePool::new(nil) };
+        let descriptors = argument_descriptors_from_fields(fields);
+        let encoder: id = unsafe { msg_send![self.device, newArgumentEncoderWithArguments:descriptors] };
+        unsafe { pool.drain() };
+        if encoder == nil {
+            Err(MetalError::ArgumentEncoderCreationFromArguments(fields.len()))
+        } else {
+            Ok(MetalArgumentEncoder::from_new_object(encoder))
+        }
+    }
+
+    /// Creates an indirect command buffer with room for `max_command_count` commands.
+    pub fn new_indirect_command_buffer_with_descriptor(
+        &self,
+        descriptor: &IndirectCommandBufferDescriptor,
+        max_command_count: NSUInteger,
+        options: MetalResourceOptions
+    ) -> Result<MetalIndirectCommandBuffer, MetalError> {
+        descriptor.validate()?;
+        if max_command_count == 0 {
+            return Err(MetalError::IndirectCommandBufferCreation(max_command_count));
+        }
+        let objc_descriptor = descriptor.new_objc_descriptor();
+        let buffer: id = unsafe {
+            msg_send![self.device, newIndirectCommandBufferWithDescriptor:objc_descriptor
+                                                          maxCommandCount:max_command_count
+                                                                  options:options.to_raw()]
+        };
+        unsafe { objc_release(objc_descriptor) };
+        if buffer == nil {
+            Err(MetalError::IndirectCommandBufferCreation(max_command_count))
+        } else {
+            Ok(MetalIndirectCommandBuffer::from_new_object(buffer, *descriptor))
+        }
+    }
+
 }
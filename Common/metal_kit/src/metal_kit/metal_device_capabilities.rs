@@ -0,0 +1,352 @@
+//
+//  metal_device_capabilities.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! What a GPU can do, copied into a plain Rust value
+//! that can be saved, loaded from fixtures and used to pick a device
+
+use cocoa::foundation::{NSInteger, NSUInteger};
+use std::str::FromStr;
+use crate::metal_kit::metal_argument_encoder::{MTLArgumentBuffersTier, MTLArgumentBuffersTier1};
+use crate::metal_kit::metal_error::MetalError;
+use crate::metal_kit::metal_render_target::choose_sample_count;
+
+// From MTLDevice.h:
+// typedef NS_ENUM(NSInteger, MTLGPUFamily) {
+//     MTLGPUFamilyApple1 = 1001,
+//     ...
+//     MTLGPUFamilyApple9 = 1009,
+//     MTLGPUFamilyMac2 = 2002,
+//     MTLGPUFamilyCommon1 = 3001,
+//     MTLGPUFamilyCommon2 = 3002,
+//     MTLGPUFamilyCommon3 = 3003,
+//     MTLGPUFamilyMetal3 = 5001,
+// } API_AVAILABLE(macos(10.15), ios(13.0));
+/// A set of features that GPUs share.
+pub type MTLGPUFamily = NSInteger;
+/// Apple GPU family 1 (A7).
+#[allow(non_upper_case_globals)]
+pub const MTLGPUFamilyApple1: MTLGPUFamily = 1001;
+/// Apple GPU family 2 (A8).
+#[allow(non_upper_case_globals)]
+pub const MTLGPUFamilyApple2: MTLGPUFamily = 1002;
+/// Apple GPU family 3 (A9, A10).
+#[allow(non_upper_case_globals)]
+pub const MTLGPUFamilyApple3: MTLGPUFamily = 1003;
+/// Apple GPU family 4 (A11).
+#[allow(non_upper_case_globals)]
+pub const MTLGPUFamilyApple4: MTLGPUFamily = 1004;
+/// Apple GPU family 5 (A12).
+#[allow(non_upper_case_globals)]
+pub const MTLGPUFamilyApple5: MTLGPUFamily = 1005;
+/// Apple GPU family 6 (A13).
+#[allow(non_upper_case_globals)]
+pub const MTLGPUFamilyApple6: MTLGPUFamily = 1006;
+/// Apple GPU family 7 (A14, M1).
+#[allow(non_upper_case_globals)]
+pub const MTLGPUFamilyApple7: MTLGPUFamily = 1007;
+/// Apple GPU family 8 (A15, A16, M2).
+#[allow(non_upper_case_globals)]
+pub const MTLGPUFamilyApple8: MTLGPUFamily = 1008;
+/// Apple GPU family 9 (A17, M3).
+#[allow(non_upper_case_globals)]
+pub const MTLGPUFamilyApple9: MTLGPUFamily = 1009;
+/// Mac GPU family 2.
+#[allow(non_upper_case_globals)]
+pub const MTLGPUFamilyMac2: MTLGPUFamily = 2002;
+/// Features every Metal GPU has.
+#[allow(non_upper_case_globals)]
+pub const MTLGPUFamilyCommon1: MTLGPUFamily = 3001;
+/// Common GPU family 2.
+#[allow(non_upper_case_globals)]
+pub const MTLGPUFamilyCommon2: MTLGPUFamily = 3002;
+/// Common GPU family 3.
+#[allow(non_upper_case_globals)]
+pub const MTLGPUFamilyCommon3: MTLGPUFamily = 3003;
+/// GPUs that support Metal 3.
+#[allow(non_upper_case_globals)]
+pub const MTLGPUFamilyMetal3: MTLGPUFamily = 5001;
+
+/// The families `MetalDevice::get_capabilities` asks about.
+pub const KNOWN_GPU_FAMILIES: [MTLGPUFamily; 14] = [
+    MTLGPUFamilyApple1, MTLGPUFamilyApple2, MTLGPUFamilyApple3, MTLGPUFamilyApple4,
+    MTLGPUFamilyApple5, MTLGPUFamilyApple6, MTLGPUFamilyApple7, MTLGPUFamilyApple8,
+    MTLGPUFamilyApple9, MTLGPUFamilyMac2, MTLGPUFamilyCommon1, MTLGPUFamilyCommon2,
+    MTLGPUFamilyCommon3, MTLGPUFamilyMetal3,
+];
+
+/// The sample counts `MetalDevice::get_capabilities` asks about.
+pub const KNOWN_TEXTURE_SAMPLE_COUNTS: [NSUInteger; 4] = [1, 2, 4, 8];
+
+// From MTLTypes.h:
+// typedef struct {
+//     NSUInteger width, height, depth;
+// } MTLSize;
+/// The dimensions of a grid, such as a threadgroup.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct MTLSize {
+    /// The number of elements in the x dimension.
+    pub width: NSUInteger,
+    /// The number of elements in the y dimension.
+    pub height: NSUInteger,
+    /// The number of elements in the z dimension.
+    pub depth: NSUInteger,
+}
+
+/// The kind of GPU to pick when there is more than one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DevicePreference {
+    /// A GPU that is not low-power, e.g. a discrete or external GPU.
+    HighPerformance,
+    /// A low-power GPU, e.g. an integrated one, to save battery.
+    LowPower,
+    /// A GPU with no display attached, e.g. for compute or offscreen rendering.
+    Headless,
+    /// An external GPU that can be unplugged.
+    Removable,
+}
+
+/// What a GPU can do, as plain values that can be kept, compared and written to a fixture.
+///
+/// `MetalDevice::get_capabilities` fills it in from a real device;
+/// tests can build one directly or load one with `from_text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceCapabilities {
+    /// The name of the GPU.
+    pub name: String,
+    /// The ID the system uses for the GPU, stable across launches.
+    pub registry_id: u64,
+    /// True for a GPU that trades performance for battery life.
+    pub is_low_power: bool,
+    /// True for a GPU with no display attached.
+    pub is_headless: bool,
+    /// True for an external GPU that can be unplugged.
+    pub is_removable: bool,
+    /// True if the CPU and GPU share memory.
+    pub has_unified_memory: bool,
+    /// The known GPU families the device supports.
+    pub gpu_families: Vec<MTLGPUFamily>,
+    /// The most memory, in bytes, the app should use on the GPU without hurting performance.
+    pub recommended_max_working_set_size: u64,
+    /// The most threads a threadgroup can have in each dimension.
+    pub max_threads_per_threadgroup: MTLSize,
+    /// The largest buffer the device can make, in bytes.
+    pub max_buffer_length: NSUInteger,
+    /// How much the device can do with argument buffers.
+    pub argument_buffers_tier: MTLArgumentBuffersTier,
+    /// The known sample counts the device can make textures with.
+    pub texture_sample_counts: Vec<NSUInteger>,
+}
+impl Default for DeviceCapabilities {
+    /// The least any Metal device can do.
+    fn default() -> Self {
+        DeviceCapabilities {
+            name: String::new(),
+            registry_id: 0,
+            is_low_power: false,
+            is_headless: false,
+            is_removable: false,
+            has_unified_memory: false,
+            gpu_families: vec![MTLGPUFamilyCommon1],
+            recommended_max_working_set_size: 0,
+            max_threads_per_threadgroup: MTLSize { width: 512, height: 512, depth: 512 },
+            max_buffer_length: 256 * 1024 * 1024,
+            argument_buffers_tier: MTLArgumentBuffersTier1,
+            texture_sample_counts: vec![1],
+        }
+    }
+}
+impl DeviceCapabilities {
+    /// Returns true if the device supports the GPU family.
+    pub fn supports_family(&self, family: MTLGPUFamily) -> bool {
+        self.gpu_families.contains(&family)
+    }
+    /// Returns true if the device can make textures with the given number of samples per pixel.
+    pub fn supports_texture_sample_count(&self, sample_count: NSUInteger) -> bool {
+        self.texture_sample_counts.contains(&sample_count)
+    }
+    /// Gets the largest supported sample count no greater than the one requested.
+    pub fn choose_sample_count(&self, requested: NSUInteger) -> NSUInteger {
+        choose_sample_count(requested, |count| self.supports_texture_sample_count(count))
+    }
+    /// Returns true if the device is the kind asked for.
+    pub fn matches(&self, preference: DevicePreference) -> bool {
+        match preference {
+            DevicePreference::HighPerformance => !self.is_low_power,
+            DevicePreference::LowPower => self.is_low_power,
+            DevicePreference::Headless => self.is_headless,
+            DevicePreference::Removable => self.is_removable,
+        }
+    }
+    /// Writes the capabilities as `key = value` lines, which `from_text` reads back.
+    pub fn to_text(&self) -> String {
+        fn list<T: ToString>(values: &[T]) -> String {
+            values.iter().map(|value| value.to_string()).collect::<Vec<_>>().join(" ")
+        }
+        let threads = &self.max_threads_per_threadgroup;
+        [
+            format!("name = {}", self.name),
+            format!("registry_id = {}", self.registry_id),
+            format!("low_power = {}", self.is_low_power),
+            format!("headless = {}", self.is_headless),
+            format!("removable = {}", self.is_removable),
+            format!("unified_memory = {}", self.has_unified_memory),
+            format!("gpu_families = {}", list(&self.gpu_families)),
+            format!("recommended_max_working_set_size = {}", self.recommended_max_working_set_size),
+            format!("max_threads_per_threadgroup = {} {} {}", threads.width, threads.height, threads.depth),
+            format!("max_buffer_length = {}", self.max_buffer_length),
+            format!("argument_buffers_tier = {}", self.argument_buffers_tier),
+            format!("texture_sample_counts = {}", list(&self.texture_sample_counts)),
+        ].iter().map(|line| format!("{}\n", line)).collect()
+    }
+    /// Reads capabilities written by `to_text`, e.g. from a test fixture.
+    ///
+    /// Blank lines and lines starting with `#` are skipped,
+    /// and keys that are missing keep their default values.
+    pub fn from_text(text: &str) -> Result<Self, MetalError> {
+        let mut capabilities = DeviceCapabilities::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |reason: &str| MetalError::InvalidDeviceCapabilities(format!("line {}: {}", number + 1, reason));
+            let (key, value) = line.split_once('=').ok_or_else(|| invalid("expected key = value"))?;
+            let value = value.trim();
+            let bad_value = || invalid(&format!("bad value for {}: {}", key.trim(), value));
+            match key.trim() {
+                "name" => capabilities.name = value.to_string(),
+                "registry_id" => capabilities.registry_id = parse(value).ok_or_else(bad_value)?,
+                "low_power" => capabilities.is_low_power = parse(value).ok_or_else(bad_value)?,
+                "headless" => capabilities.is_headless = parse(value).ok_or_else(bad_value)?,
+                "removable" => capabilities.is_removable = parse(value).ok_or_else(bad_value)?,
+                "unified_memory" => capabilities.has_unified_memory = parse(value).ok_or_else(bad_value)?,
+                "gpu_families" => capabilities.gpu_families = parse_list(value).ok_or_else(bad_value)?,
+                "recommended_max_working_set_size" =>
+                    capabilities.recommended_max_working_set_size = parse(value).ok_or_else(bad_value)?,
+                "max_threads_per_threadgroup" => {
+                    match parse_list::<NSUInteger>(value).as_deref() {
+                        Some([width, height, depth]) =>
+                            capabilities.max_threads_per_threadgroup = MTLSize { width: *width, height: *height, depth: *depth },
+                        _ => return Err(bad_value()),
+                    }
+                }
+                "max_buffer_length" => capabilities.max_buffer_length = parse(value).ok_or_else(bad_value)?,
+                "argument_buffers_tier" => capabilities.argument_buffers_tier = parse(value).ok_or_else(bad_value)?,
+                "texture_sample_counts" => capabilities.texture_sample_counts = parse_list(value).ok_or_else(bad_value)?,
+                other => return Err(invalid(&format!("unknown key {}", other))),
+            }
+        }
+        Ok(capabilities)
+    }
+}
+
+/// Parses one value, giving `None` if it is malformed.
+fn parse<T: FromStr>(value: &str) -> Option<T> { value.parse().ok() }
+
+/// Parses a space separated list, giving `None` if any value is malformed.
+fn parse_list<T: FromStr>(value: &str) -> Option<Vec<T>> {
+    value.split_whitespace().map(parse).collect()
+}
+
+/// Picks the device of the kind asked for, returning its index.
+///
+/// The first match wins. If nothing matches, the first device is used,
+/// as any GPU is better than none; an empty list gives `None`.
+pub fn choose_device(devices: &[DeviceCapabilities], preference: DevicePreference) -> Option<usize> {
+    if devices.is_empty() {
+        return None;
+    }
+    Some(devices.iter().position(|device| device.matches(preference)).unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    /// A MacBook Pro with an integrated and a discrete GPU, plus an eGPU.
+    fn dual_gpu_mac() -> Vec<DeviceCapabilities> {
+        let integrated = DeviceCapabilities {
+            name: String::from("Intel(R) UHD Graphics 630"),
+            registry_id: 4294968615,
+            is_low_power: true,
+            has_unified_memory: true,
+            gpu_families: vec![MTLGPUFamilyMac2, MTLGPUFamilyCommon1, MTLGPUFamilyCommon2, MTLGPUFamilyCommon3],
+            recommended_max_working_set_size: 1_610_612_736,
+            max_threads_per_threadgroup: MTLSize { width: 1024, height: 1024, depth: 1024 },
+            max_buffer_length: 1_073_741_824,
+            texture_sample_counts: vec![1, 2, 4, 8],
+            ..Default::default()
+        };
+        let discrete = DeviceCapabilities {
+            name: String::from("AMD Radeon Pro 5500M"),
+            registry_id: 4294968700,
+            is_low_power: false,
+            has_unified_memory: false,
+            argument_buffers_tier: MTLArgumentBuffersTier2,
+            ..integrated.clone()
+        };
+        let external = DeviceCapabilities {
+            name: String::from("AMD Radeon RX 6800 XT"),
+            registry_id: 4294968900,
+            is_removable: true,
+            is_headless: true,
+            ..discrete.clone()
+        };
+        vec![integrated, discrete, external]
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        for device in dual_gpu_mac() {
+            assert_eq!(Ok(device.clone()), DeviceCapabilities::from_text(&device.to_text()));
+        }
+        let fixture = "\
+            # An iPhone with an A15\n\
+            name = Apple A15 GPU\n\
+            unified_memory = true\n\
+            gpu_families = 1001 1002 1003 1004 1005 1006 1007 1008 3001 3002 3003 5001\n\
+            max_threads_per_threadgroup = 1024 1024 1024\n\
+            texture_sample_counts = 1 2 4\n";
+        let phone = DeviceCapabilities::from_text(fixture).unwrap();
+        assert_eq!("Apple A15 GPU", phone.name);
+        assert!(phone.supports_family(MTLGPUFamilyApple8));
+        assert!(!phone.supports_family(MTLGPUFamilyApple9));
+        assert_eq!(MTLArgumentBuffersTier1, phone.argument_buffers_tier);
+    }
+
+    #[test]
+    fn test_malformed_text_is_rejected() {
+        let errors = [
+            ("name Apple", "line 1: expected key = value"),
+            ("\nlow_power = maybe", "line 2: bad value for low_power: maybe"),
+            ("max_threads_per_threadgroup = 1024 1024", "line 1: bad value for max_threads_per_threadgroup: 1024 1024"),
+            ("texture_sample_counts = 1 two", "line 1: bad value for texture_sample_counts: 1 two"),
+            ("colour = blue", "line 1: unknown key colour"),
+        ];
+        for (text, reason) in errors.iter() {
+            assert_eq!(Err(MetalError::InvalidDeviceCapabilities(reason.to_string())), DeviceCapabilities::from_text(text));
+        }
+    }
+
+    #[test]
+    fn test_choose_device_and_sample_count() {
+        let devices = dual_gpu_mac();
+        assert_eq!(Some(0), choose_device(&devices, DevicePreference::LowPower));
+        assert_eq!(Some(1), choose_device(&devices, DevicePreference::HighPerformance));
+        assert_eq!(Some(2), choose_device(&devices, DevicePreference::Removable));
+        assert_eq!(Some(2), choose_device(&devices, DevicePreference::Headless));
+        // A single integrated GPU is still used when a discrete one was asked for.
+        assert_eq!(Some(0), choose_device(&devices[..1], DevicePreference::HighPerformance));
+        assert_eq!(None, choose_device(&[], DevicePreference::LowPower));
+
+        let phone = DeviceCapabilities { texture_sample_counts: vec![1, 2, 4], ..Default::default() };
+        assert_eq!(4, phone.choose_sample_count(8));
+        assert_eq!(1, DeviceCapabilities::default().choose_sample_count(4));
+    }
+}
@@ -0,0 +1,124 @@
+//
+//  metal_draw_arguments.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Rust versions of the argument structs read from a buffer by indirect draw calls
+
+use cocoa::foundation::NSUInteger;
+use std::mem::size_of;
+use crate::metal_kit::metal_error::MetalError;
+
+// From MTLRenderCommandEncoder.h:
+// typedef struct {
+//     uint32_t vertexCount;
+//     uint32_t instanceCount;
+//     uint32_t vertexStart;
+//     uint32_t baseInstance;
+// } MTLDrawPrimitivesIndirectArguments;
+/// The layout of the arguments an indirect, non-indexed draw call reads from its buffer.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct MTLDrawPrimitivesIndirectArguments {
+    /// The number of vertices in each instance.
+    pub vertex_count: u32,
+    /// The number of instances to draw.
+    pub instance_count: u32,
+    /// The first vertex to draw.
+    pub vertex_start: u32,
+    /// The first instance to draw.
+    pub base_instance: u32,
+}
+
+// typedef struct {
+//     uint32_t indexCount;
+//     uint32_t instanceCount;
+//     uint32_t indexStart;
+//     int32_t  baseVertex;
+//     uint32_t baseInstance;
+// } MTLDrawIndexedPrimitivesIndirectArguments;
+/// The layout of the arguments an indirect, indexed draw call reads from its buffer.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct MTLDrawIndexedPrimitivesIndirectArguments {
+    /// The number of indices read from the index buffer for each instance.
+    pub index_count: u32,
+    /// The number of instances to draw.
+    pub instance_count: u32,
+    /// The first index to read.
+    pub index_start: u32,
+    /// The value added to each index before reading a vertex.
+    pub base_vertex: i32,
+    /// The first instance to draw.
+    pub base_instance: u32,
+}
+
+/// Metal requires indirect buffer offsets to be a multiple of this many bytes (a power of two).
+pub const INDIRECT_BUFFER_OFFSET_ALIGNMENT: NSUInteger = 4;
+
+/// Checks that a buffer of the given length holds a whole `T`
+/// (one of the indirect argument structs) at the given offset,
+/// and that the offset is suitably aligned.
+pub fn validate_indirect_buffer_range<T>(buffer_length: NSUInteger, offset: NSUInteger) -> Result<(), MetalError> {
+    if offset & (INDIRECT_BUFFER_OFFSET_ALIGNMENT - 1) != 0 {
+        return Err(MetalError::MisalignedIndirectBufferOffset(offset));
+    }
+    let required = size_of::<T>() as NSUInteger;
+    match offset.checked_add(required) {
+        Some(end) if end <= buffer_length => Ok(()),
+        _ => Err(MetalError::IndirectBufferTooSmall { offset, required, length: buffer_length }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use cocoa::foundation::NSUInteger;
+    use std::mem::{align_of, size_of};
+
+    #[test]
+    fn test_argument_layouts_match_metal() {
+        assert_eq!(16, size_of::<MTLDrawPrimitivesIndirectArguments>());
+        assert_eq!(4, align_of::<MTLDrawPrimitivesIndirectArguments>());
+        assert_eq!(20, size_of::<MTLDrawIndexedPrimitivesIndirectArguments>());
+        assert_eq!(4, align_of::<MTLDrawIndexedPrimitivesIndirectArguments>());
+
+        let arguments = MTLDrawIndexedPrimitivesIndirectArguments {
+            index_count: 36,
+            instance_count: 2,
+            index_start: 6,
+            base_vertex: -4,
+            base_instance: 1,
+        };
+        let words: [u32; 5] = unsafe { std::mem::transmute(arguments) };
+        assert_eq!([36, 2, 6, (-4i32) as u32, 1], words);
+    }
+
+    #[test]
+    fn test_buffer_range_validation() {
+        type Arguments = MTLDrawPrimitivesIndirectArguments;
+        assert_eq!(Ok(()), validate_indirect_buffer_range::<Arguments>(16, 0));
+        assert_eq!(Ok(()), validate_indirect_buffer_range::<Arguments>(64, 48));
+        assert_eq!(
+            Err(MetalError::IndirectBufferTooSmall { offset: 52, required: 16, length: 64 }),
+            validate_indirect_buffer_range::<Arguments>(64, 52)
+        );
+        assert_eq!(
+            Err(MetalError::MisalignedIndirectBufferOffset(2)),
+            validate_indirect_buffer_range::<Arguments>(64, 2)
+        );
+        assert_eq!(
+            Err(MetalError::IndirectBufferTooSmall { offset: 0, required: 20, length: 16 }),
+            validate_indirect_buffer_range::<MTLDrawIndexedPrimitivesIndirectArguments>(16, 0)
+        );
+        // No overflow near the top of the address space.
+        let offset = NSUInteger::MAX - 3;
+        assert_eq!(
+            Err(MetalError::IndirectBufferTooSmall { offset, required: 16, length: NSUInteger::MAX }),
+            validate_indirect_buffer_range::<Arguments>(NSUInteger::MAX, offset)
+        );
+    }
+}
@@ -0,0 +1,544 @@
+//
+//  metal_error.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! The Rust error returned by fallible metal_kit calls,
+//! with NSError details copied out into owned Rust values
+
+use objc::msg_send;
+use objc::sel;
+use objc::sel_impl;
+use cocoa::base::{id, nil};
+use cocoa::foundation::{NSInteger, NSUInteger};
+use core_animation::MTLPixelFormat;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+use crate::metal_kit::metal_command_buffer::{command_buffer_error_code, command_buffer_error_description};
+use crate::metal_kit::metal_compiler_diagnostic::MetalCompilerDiagnostic;
+use crate::metal_kit::ns_string::rust_string_from_ns_string;
+use crate::metal_kit::metal_pipeline_reflection::PipelineLayoutMismatch;
+use crate::metal_kit::metal_device_capabilities::MTLSize;
+use crate::metal_kit::metal_texture::MTLRegion;
+
+extern {
+    // From Foundation.framework/Headers/NSError.h:
+    // FOUNDATION_EXPORT NSErrorUserInfoKey const NSUnderlyingErrorKey;
+    #[allow(non_upper_case_globals)]
+    static NSUnderlyingErrorKey: id;
+}
+
+/// An owned copy of the contents of an NSError,
+/// including the chain of underlying errors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NSErrorInfo {
+    /// The error domain, e.g. `MTLLibraryErrorDomain`.
+    pub domain: String,
+    /// The error code within the domain.
+    pub code: NSInteger,
+    /// The localized description of the error.
+    pub localized_description: String,
+    /// The error that caused this one, if any.
+    pub underlying_error: Option<Box<NSErrorInfo>>,
+}
+impl NSErrorInfo {
+    /// Creates an error description from its parts.
+    pub fn new(domain: &str, code: NSInteger, localized_description: &str) -> Self {
+        NSErrorInfo {
+            domain: domain.to_string(),
+            code,
+            localized_description: localized_description.to_string(),
+            underlying_error: None,
+        }
+    }
+    /// Returns this error with the given error attached as its cause.
+    pub fn with_underlying_error(mut self, underlying_error: NSErrorInfo) -> Self {
+        self.underlying_error = Some(Box::new(underlying_error));
+        self
+    }
+    /// Copies the contents of the given NSError
+    /// (and of the errors under it) into Rust values.
+    ///
+    /// Returns None if the error is nil.
+    pub fn from_ns_error(error: id) -> Option<Self> {
+        if error == nil {
+            return None;
+        }
+        let domain: id = unsafe { msg_send![error, domain] };
+        let code: NSInteger = unsafe { msg_send![error, code] };
+        let description: id = unsafe { msg_send![error, localizedDescription] };
+        let user_info: id = unsafe { msg_send![error, userInfo] };
+        let underlying_error: id = if user_info == nil {
+            nil
+        } else {
+            unsafe { msg_send![user_info, objectForKey:NSUnderlyingErrorKey] }
+        };
+        Some(NSErrorInfo {
+            domain: rust_string_from_ns_string(domain),
+            code,
+            localized_description: rust_string_from_ns_string(description),
+            underlying_error: Self::from_ns_error(underlying_error).map(Box::new),
+        })
+    }
+    /// Copies the contents of the given NSError as `from_ns_error` does,
+    /// but describes a nil error as unknown
+    /// (some calls can fail without giving a reason).
+    pub(crate) fn from_ns_error_or_unknown(error: id) -> Self {
+        Self::from_ns_error(error).unwrap_or_else(|| NSErrorInfo::new("", 0, "Unknown error"))
+    }
+    /// Iterates over this error and the errors under it, outermost first.
+    pub fn chain(&self) -> impl Iterator<Item = &NSErrorInfo> {
+        std::iter::successors(Some(self), |error| error.underlying_error.as_deref())
+    }
+}
+impl Display for NSErrorInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({} {})", self.localized_description, self.domain, self.code)
+    }
+}
+impl Error for NSErrorInfo {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.underlying_error.as_deref().map(|error| error as &(dyn Error + 'static))
+    }
+}
+
+/// The error returned if a Metal call fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetalError {
+    /// The app has no default library, or it could not be loaded.
+    DefaultLibraryNotFound,
+    /// The shader compiler rejected the source code
+    /// passed to `new_library_with_source`.
+    LibraryCompilation {
+        /// The error reported by the compiler.
+        error: NSErrorInfo,
+        /// The individual errors, warnings and notes
+        /// found in the compiler's description.
+        diagnostics: Vec<MetalCompilerDiagnostic>,
+    },
+    /// The system could not load a library from the given data or file.
+    LibraryCreation(NSErrorInfo),
+    /// The library has no function with the given name.
+    FunctionNotFound(String),
+    /// The system could not create a render pipeline state.
+    RenderPipelineStateCreation(NSErrorInfo),
+    /// The system could not create a depth stencil state.
+    DepthStencilStateCreation,
+    /// The system could not create a texture with the given
+    /// (width, height, pixel format).
+    TextureCreation {
+        /// The requested width in pixels.
+        width: NSUInteger,
+        /// The requested height in pixels.
+        height: NSUInteger,
+        /// The requested pixel format.
+        pixel_format: MTLPixelFormat,
+    },
+    /// A blend configuration names a blend operation Metal does not have.
+    InvalidBlendOperation(NSUInteger),
+    /// A blend configuration names a blend factor Metal does not have.
+    InvalidBlendFactor(NSUInteger),
+    /// A color write mask has bits set other than the four channel bits.
+    InvalidColorWriteMask(NSUInteger),
+    /// A render target was given more color attachments (this many) than Metal allows.
+    TooManyColorAttachments(usize),
+    /// A color attachment was given a depth, stencil or invalid pixel format.
+    InvalidColorAttachmentPixelFormat(MTLPixelFormat),
+    /// A depth attachment was given a pixel format with neither depth nor stencil.
+    InvalidDepthAttachmentPixelFormat(MTLPixelFormat),
+    /// A render target was given a zero width or height.
+    InvalidRenderTargetSize {
+        /// The requested width in pixels.
+        width: NSUInteger,
+        /// The requested height in pixels.
+        height: NSUInteger,
+    },
+    /// A render target has neither color nor depth attachments.
+    NoRenderTargetAttachments,
+    /// An indirect buffer offset is not a multiple of 4 bytes.
+    MisalignedIndirectBufferOffset(NSUInteger),
+    /// An indirect buffer does not hold a whole set of draw arguments at the given offset.
+    IndirectBufferTooSmall {
+        /// The offset of the arguments in the buffer.
+        offset: NSUInteger,
+        /// The size of the arguments in bytes.
+        required: NSUInteger,
+        /// The length of the buffer in bytes.
+        length: NSUInteger,
+    },
+    /// The system could not create a buffer of the given length in bytes.
+    BufferCreation(NSUInteger),
+    /// A buffer alignment is not a power of two,
+    /// or is smaller than the alignment of the values stored in the buffer.
+    InvalidBufferAlignment(NSUInteger),
+    /// A ring buffer was asked for no slots.
+    InvalidSlotCount(usize),
+    /// A buffer is shorter than the values it has to hold.
+    BufferTooSmall {
+        /// The number of bytes needed.
+        required: NSUInteger,
+        /// The length of the buffer in bytes.
+        length: NSUInteger,
+    },
+    /// The byte length of the given number of values does not fit in memory.
+    BufferLengthOverflow(usize),
+    /// A buffer was asked to hold no bytes, which Metal cannot create.
+    EmptyBuffer,
+    /// The CPU cannot see a buffer's contents, e.g. because its storage is private.
+    BufferContentsUnavailable,
+    /// The GPU could not execute a command buffer.
+    /// If the error is in `MTLCommandBufferErrorDomain`, its code is one of the `MTLCommandBufferError` values.
+    CommandBufferExecution(NSErrorInfo),
+    /// Resource options with an unknown mode, or modes that cannot be used together.
+    InvalidResourceOptions(NSUInteger),
+    /// A CPU cache, storage or hazard tracking mode (named here) that Metal does not have.
+    InvalidResourceMode {
+        /// The kind of mode.
+        name: String,
+        /// The mode asked for.
+        mode: NSUInteger,
+    },
+    /// An element index (or the end of a range of elements) is past the end of a buffer.
+    BufferIndexOutOfBounds {
+        /// The index asked for.
+        index: usize,
+        /// The number of elements in the buffer.
+        count: usize,
+    },
+    /// A capture was asked to go somewhere the capture manager cannot send it.
+    /// The value is the `MTLCaptureDestination`.
+    CaptureDestinationNotSupported(NSInteger),
+    /// A capture could not be started, e.g. because one is already in progress.
+    CaptureStart(NSErrorInfo),
+    /// A capture file path that does not end in `.gputrace`, or that already exists.
+    InvalidCaptureOutputPath(String),
+    /// The device could not create a sampler state.
+    SamplerStateCreation,
+    /// The device could not create a heap of the given size in bytes.
+    HeapCreation(NSUInteger),
+    /// An argument encoder could not be created for the buffer at the given index.
+    ArgumentEncoderCreation(NSUInteger),
+    /// An argument encoder could not be created for an argument buffer with the given number of fields.
+    ArgumentEncoderCreationFromArguments(usize),
+    /// Two arguments in an argument buffer share an id.
+    DuplicateArgumentIndex(NSUInteger),
+    /// An argument type that we cannot lay out in an argument buffer.
+    UnsupportedArgumentDataType(NSUInteger),
+    /// A Rust struct whose size or alignment doesn't match the argument buffer it is written to.
+    ArgumentLayoutMismatch {
+        /// The length of the argument buffer in bytes.
+        expected: NSUInteger,
+        /// The size of the Rust struct in bytes.
+        actual: NSUInteger,
+    },
+    /// An indirect command buffer with no command types, unknown ones, or render and compute mixed.
+    InvalidIndirectCommandTypes(NSUInteger),
+    /// More buffer bindings than an indirect command can hold.
+    InvalidIndirectBufferBindCount(NSUInteger),
+    /// An indirect command tried to set state (named here) that it inherits from its encoder.
+    IndirectCommandInheritedState(String),
+    /// A command index (or the end of a range of commands) is past the end of an indirect command buffer.
+    IndirectCommandIndexOutOfBounds {
+        /// The index asked for.
+        index: NSUInteger,
+        /// The number of commands the buffer has room for.
+        count: NSUInteger,
+    },
+    /// The device could not create an indirect command buffer with room for the given number of commands.
+    IndirectCommandBufferCreation(NSUInteger),
+    /// A pipeline function's buffer argument doesn't match the Rust type meant for it.
+    PipelineLayoutMismatch {
+        /// The function, "vertex" or "fragment".
+        stage: String,
+        /// The buffer index.
+        index: NSUInteger,
+        /// How the buffer differs.
+        mismatches: Vec<PipelineLayoutMismatch>,
+    },
+    /// A binary archive could not be created, added to or written out.
+    BinaryArchive(NSErrorInfo),
+    /// Device capabilities text that could not be read, with the line and reason.
+    InvalidDeviceCapabilities(String),
+    /// The device could not create a fence.
+    FenceCreation,
+    /// The device could not create an event.
+    EventCreation,
+    /// Signals and waits that would run work out of order or never finish.
+    EventOrdering(String),
+    /// Every visibility query slot of the current frame has been handed out.
+    VisibilityQueryPoolExhausted(usize),
+    /// A frame's visibility results were needed while the GPU may still be writing them.
+    VisibilityFrameInFlight(usize),
+    /// A visibility query was kept past its frame, whose slots have since been handed out again.
+    StaleVisibilityQuery(usize),
+    /// The device could not create a counter sample buffer.
+    CounterSampleBufferCreation(NSErrorInfo),
+    /// The samples in a counter sample buffer could not be resolved.
+    CounterResolve,
+    /// The system could not create a view of a texture with the given pixel format.
+    TextureViewCreation(MTLPixelFormat),
+    /// The CPU cannot see a texture's contents, because its storage is private or memoryless,
+    /// or because it is framebuffer only.
+    TextureContentsUnavailable,
+    /// A texture region that is not one pixel deep, or does not fit in its mipmap level.
+    /// The byte size of a texture of the given size does not fit in memory.
+    TextureSizeOverflow(MTLSize),
+    /// A region outside the bounds of the texture's mipmap level.
+    InvalidTextureRegion {
+        /// The region asked for.
+        region: MTLRegion,
+        /// The mipmap level asked for.
+        mipmap_level: NSUInteger,
+        /// The size of that level (zero if the texture has no such level).
+        level_size: MTLSize,
+    },
+    /// Pixels of the given format cannot be read back or converted.
+    UnsupportedPixelFormat(MTLPixelFormat),
+    /// An image could not be written, with the path and reason.
+    ImageExport(String),
+    /// A texture view asked for mipmap levels or slices the texture does not have.
+    InvalidTextureViewRange {
+        /// The mipmap levels asked for.
+        levels: Range<NSUInteger>,
+        /// The slices asked for.
+        slices: Range<NSUInteger>,
+        /// The number of mipmap levels in the texture.
+        level_count: NSUInteger,
+        /// The number of slices in the texture.
+        slice_count: NSUInteger,
+    },
+}
+impl MetalError {
+    /// Gets the NSError details behind this error, if the system gave us any.
+    pub fn ns_error(&self) -> Option<&NSErrorInfo> {
+        match self {
+            MetalError::LibraryCompilation { error, .. } => Some(error),
+            MetalError::LibraryCreation(error) => Some(error),
+            MetalError::RenderPipelineStateCreation(error) => Some(error),
+            MetalError::CommandBufferExecution(error) => Some(error),
+            MetalError::CaptureStart(error) => Some(error),
+            MetalError::BinaryArchive(error) => Some(error),
+            MetalError::CounterSampleBufferCreation(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+impl Display for MetalError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MetalError::DefaultLibraryNotFound =>
+                write!(f, "Default library not found"),
+            MetalError::LibraryCompilation { error, .. } =>
+                write!(f, "Library compilation error: {}", error),
+            MetalError::LibraryCreation(error) =>
+                write!(f, "Library creation error: {}", error),
+            MetalError::FunctionNotFound(name) =>
+                write!(f, "Function not found: {}", name),
+            MetalError::RenderPipelineStateCreation(error) =>
+                write!(f, "Render pipeline state creation error: {}", error),
+            MetalError::DepthStencilStateCreation =>
+                write!(f, "Depth stencil state creation error"),
+            MetalError::TextureCreation { width, height, pixel_format } =>
+                write!(f, "Texture creation error: {}x{} pixel format {}", width, height, pixel_format),
+            MetalError::InvalidBlendOperation(operation) =>
+                write!(f, "Invalid blend operation: {}", operation),
+            MetalError::InvalidBlendFactor(factor) =>
+                write!(f, "Invalid blend factor: {}", factor),
+            MetalError::InvalidColorWriteMask(mask) =>
+                write!(f, "Invalid color write mask: {:#x}", mask),
+            MetalError::TooManyColorAttachments(count) =>
+                write!(f, "Too many color attachments: {} (at most 8)", count),
+            MetalError::InvalidColorAttachmentPixelFormat(pixel_format) =>
+                write!(f, "Invalid color attachment pixel format {}", pixel_format),
+            MetalError::InvalidDepthAttachmentPixelFormat(pixel_format) =>
+                write!(f, "Invalid depth attachment pixel format {}", pixel_format),
+            MetalError::InvalidRenderTargetSize { width, height } =>
+                write!(f, "Invalid render target size: {}x{}", width, height),
+            MetalError::NoRenderTargetAttachments =>
+                write!(f, "Render target has no attachments"),
+            MetalError::MisalignedIndirectBufferOffset(offset) =>
+                write!(f, "Indirect buffer offset {} is not a multiple of 4", offset),
+            MetalError::IndirectBufferTooSmall { offset, required, length } =>
+                write!(f, "Indirect buffer of {} bytes is too small for {} bytes at offset {}", length, required, offset),
+            MetalError::BufferCreation(length) =>
+                write!(f, "Buffer creation error: {} bytes", length),
+            MetalError::InvalidBufferAlignment(alignment) =>
+                write!(f, "Invalid buffer alignment: {}", alignment),
+            MetalError::InvalidSlotCount(count) =>
+                write!(f, "Invalid ring buffer slot count: {}", count),
+            MetalError::BufferTooSmall { required, length } =>
+                write!(f, "Buffer of {} bytes is too small for {} bytes", length, required),
+            MetalError::BufferLengthOverflow(count) =>
+                write!(f, "Buffer length overflow for {} values", count),
+            MetalError::EmptyBuffer =>
+                write!(f, "Buffer creation error: Metal buffers cannot be empty"),
+            MetalError::BufferContentsUnavailable =>
+                write!(f, "Buffer contents are not accessible to the CPU"),
+            MetalError::CommandBufferExecution(error) => match command_buffer_error_code(error) {
+                Some(code) =>
+                    write!(f, "Command buffer execution error ({}): {}", command_buffer_error_description(code), error),
+                None =>
+                    write!(f, "Command buffer execution error: {}", error),
+            },
+            MetalError::InvalidResourceOptions(options) =>
+                write!(f, "Invalid resource options: {:#x}", options),
+            MetalError::InvalidResourceMode { name, mode } =>
+                write!(f, "Invalid {} mode: {}", name, mode),
+            MetalError::BufferIndexOutOfBounds { index, count } =>
+                write!(f, "Buffer index {} out of bounds for {} elements", index, count),
+            MetalError::CaptureDestinationNotSupported(destination) =>
+                write!(f, "Capture destination not supported: {}", destination),
+            MetalError::CaptureStart(error) =>
+                write!(f, "Capture start error: {}", error),
+            MetalError::InvalidCaptureOutputPath(path) =>
+                write!(f, "Invalid capture output path (must be a new .gputrace file): {}", path),
+            MetalError::SamplerStateCreation =>
+                write!(f, "Sampler state creation error"),
+            MetalError::HeapCreation(size) =>
+                write!(f, "Heap creation error: {} bytes", size),
+            MetalError::ArgumentEncoderCreation(index) =>
+                write!(f, "Argument encoder creation error for buffer index {}", index),
+            MetalError::ArgumentEncoderCreationFromArguments(count) =>
+                write!(f, "Argument encoder creation error for {} arguments", count),
+            MetalError::DuplicateArgumentIndex(index) =>
+                write!(f, "Duplicate argument index: {}", index),
+            MetalError::UnsupportedArgumentDataType(data_type) =>
+                write!(f, "Unsupported argument data type: {}", data_type),
+            MetalError::ArgumentLayoutMismatch { expected, actual } =>
+                write!(f, "Argument buffer of {} bytes does not match a struct of {} bytes (or its alignment)", expected, actual),
+            MetalError::InvalidIndirectCommandTypes(command_types) =>
+                write!(f, "Invalid indirect command types: {:#x}", command_types),
+            MetalError::InvalidIndirectBufferBindCount(count) =>
+                write!(f, "Invalid indirect command buffer bind count: {} (at most 31)", count),
+            MetalError::IndirectCommandInheritedState(state) =>
+                write!(f, "Indirect command inherits its {} from the encoder", state),
+            MetalError::IndirectCommandIndexOutOfBounds { index, count } =>
+                write!(f, "Indirect command index {} out of bounds for {} commands", index, count),
+            MetalError::IndirectCommandBufferCreation(count) =>
+                write!(f, "Indirect command buffer creation error: {} commands", count),
+            MetalError::PipelineLayoutMismatch { stage, index, mismatches } => {
+                let mismatches: Vec<String> = mismatches.iter().map(|mismatch| mismatch.to_string()).collect();
+                write!(f, "Pipeline {} buffer {} does not match: {}", stage, index, mismatches.join("; "))
+            }
+            MetalError::BinaryArchive(error) =>
+                write!(f, "Binary archive error: {}", error),
+            MetalError::InvalidDeviceCapabilities(reason) =>
+                write!(f, "Invalid device capabilities: {}", reason),
+            MetalError::FenceCreation =>
+                write!(f, "Fence creation error"),
+            MetalError::EventCreation =>
+                write!(f, "Event creation error"),
+            MetalError::EventOrdering(reason) =>
+                write!(f, "Event ordering error: {}", reason),
+            MetalError::VisibilityQueryPoolExhausted(count) =>
+                write!(f, "All {} visibility queries of the frame are in use", count),
+            MetalError::VisibilityFrameInFlight(frame) =>
+                write!(f, "Visibility results of frame {} are still in flight", frame),
+            MetalError::StaleVisibilityQuery(frame) =>
+                write!(f, "Visibility query of frame {} has been recycled", frame),
+            MetalError::CounterSampleBufferCreation(error) =>
+                write!(f, "Counter sample buffer creation error: {}", error),
+            MetalError::CounterResolve =>
+                write!(f, "Counter samples could not be resolved"),
+            MetalError::TextureViewCreation(pixel_format) =>
+                write!(f, "Texture view creation error: pixel format {}", pixel_format),
+            MetalError::TextureContentsUnavailable =>
+                write!(f, "Texture contents are not accessible to the CPU"),
+            MetalError::TextureSizeOverflow(size) =>
+                write!(f, "Texture size overflow for {}x{}x{}", size.width, size.height, size.depth),
+            MetalError::InvalidTextureRegion { region, mipmap_level, level_size } =>
+                write!(f, "Texture region {:?} does not fit in mipmap level {} of size {}x{}x{}",
+                       region, mipmap_level, level_size.width, level_size.height, level_size.depth),
+            MetalError::UnsupportedPixelFormat(pixel_format) =>
+                write!(f, "Unsupported pixel format {}", pixel_format),
+            MetalError::ImageExport(reason) =>
+                write!(f, "Image export error: {}", reason),
+            MetalError::InvalidTextureViewRange { levels, slices, level_count, slice_count } =>
+                write!(f, "Texture view of levels {:?} and slices {:?} is outside the texture's {} levels and {} slices",
+                       levels, slices, level_count, slice_count),
+        }
+    }
+}
+impl Error for MetalError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.ns_error().map(|error| error as &(dyn Error + 'static))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{MetalError, NSErrorInfo};
+    use std::error::Error;
+
+    fn assert_send_sync<T: Send + Sync + 'static>(_: &T) {}
+
+    fn pipeline_error() -> MetalError {
+        let underlying = NSErrorInfo::new(
+            "AGXMetalG13X",
+            3,
+            "Fragment input(s) `user(locn1)` mismatching vertex shader output type(s) or not written by vertex shader"
+        );
+        MetalError::RenderPipelineStateCreation(
+            NSErrorInfo::new("CompilerError", 2, "Link failed").with_underlying_error(underlying)
+        )
+    }
+
+    #[test]
+    fn test_error_is_send_and_sync() {
+        let error = pipeline_error();
+        assert_send_sync(&error);
+        let handle = std::thread::spawn(move || error.to_string());
+        assert_eq!(
+            "Render pipeline state creation error: Link failed (CompilerError 2)",
+            handle.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_error_chain() {
+        let error = pipeline_error();
+        let ns_error = error.ns_error().unwrap();
+        let domains: Vec<&str> = ns_error.chain().map(|error| error.domain.as_str()).collect();
+        assert_eq!(vec!["CompilerError", "AGXMetalG13X"], domains);
+
+        let source = error.source().unwrap();
+        assert_eq!("Link failed (CompilerError 2)", source.to_string());
+        let cause = source.source().unwrap();
+        assert!(cause.to_string().starts_with("Fragment input(s)"));
+        assert!(cause.source().is_none());
+    }
+
+    #[test]
+    fn test_errors_without_ns_error() {
+        let error = MetalError::FunctionNotFound(String::from("vertex_project"));
+        assert_eq!(None, error.ns_error());
+        assert!(error.source().is_none());
+        assert_eq!("Function not found: vertex_project", error.to_string());
+        let error = MetalError::TextureCreation { width: 640, height: 480, pixel_format: 252 };
+        assert_eq!("Texture creation error: 640x480 pixel format 252", error.to_string());
+    }
+
+    #[test]
+    fn test_command_buffer_error_names_the_code() {
+        let error = MetalError::CommandBufferExecution(
+            NSErrorInfo::new("MTLCommandBufferErrorDomain", 2, "Execution timed out")
+        );
+        assert_eq!(
+            "Command buffer execution error (timed out): Execution timed out (MTLCommandBufferErrorDomain 2)",
+            error.to_string()
+        );
+        assert_eq!(2, error.ns_error().unwrap().code);
+        // Codes from other domains are not command buffer error codes.
+        let error = MetalError::CommandBufferExecution(
+            NSErrorInfo::new("IOGPUCommandQueueErrorDomain", 2, "Caused GPU hang")
+        );
+        assert_eq!(
+            "Command buffer execution error: Caused GPU hang (IOGPUCommandQueueErrorDomain 2)",
+            error.to_string()
+        );
+    }
+}
@@ -0,0 +1,368 @@
+//
+//  metal_event.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Thin wrappers for MTLEvent and MTLSharedEvent,
+//! and a plain Rust model of how their values order work across command queues
+
+use objc::class;
+use objc::msg_send;
+use objc::sel;
+use objc::sel_impl;
+use cocoa::base::{id, nil};
+use objc::runtime::{objc_release, objc_retain, BOOL, NO};
+use std::collections::HashMap;
+use std::os::raw::c_void;
+use crate::metal_kit::metal_callback::{callback_into_user_data, run_callback};
+use crate::metal_kit::metal_error::MetalError;
+use crate::metal_kit::ns_string::{get_objc_label, set_objc_label};
+
+#[allow(non_camel_case_types)]
+type shared_event_notification_function_with_user_data_t = extern "C" fn(event: id, value: u64, user_data: *const c_void);
+#[link(name="GlueLib", kind="dylib")]
+extern {
+    // From GlueLib.h:
+    // void shared_event_notify_listener_f_with_user_data
+    // (id<MTLSharedEvent> _Nonnull,
+    //  MTLSharedEventListener *_Nonnull,
+    //  uint64_t,
+    //  shared_event_notification_function_with_user_data_t _Nullable,
+    //  void *_Nullable
+    //  );
+    fn shared_event_notify_listener_f_with_user_data(
+        event: id,
+        listener: id,
+        value: u64,
+        notification_function: shared_event_notification_function_with_user_data_t,
+        user_data: *const c_void
+    );
+}
+
+/// Called by the listener (through GlueLib) with the user data made in `notify_listener`.
+/// Metal calls each notification block once, so the handler is freed exactly once.
+extern "C" fn shared_event_notification_trampoline(_event: id, value: u64, user_data: *const c_void) {
+    unsafe { run_callback::<u64>(user_data, value) }
+}
+
+/// A Metal event, shared or not, that command buffers can signal and wait for.
+pub trait MetalEventObject {
+    /// Returns the underlying Objective C event.
+    fn to_objc(&self) -> id;
+}
+
+/// Rust wrapper for an event that orders work between command queues on the same device.
+pub struct MetalEvent {
+    event: id,
+}
+impl Default for MetalEvent {
+    fn default() -> Self {
+        MetalEvent { event: nil }
+    }
+}
+impl From<id> for MetalEvent {
+    fn from(event: id) -> Self {
+        let event = unsafe { objc_retain(event) };
+        MetalEvent { event }
+    }
+}
+impl Drop for MetalEvent {
+    fn drop(&mut self) { unsafe { objc_release(self.event) } }
+}
+impl MetalEvent {
+    /// Wraps an event returned from a `new...` method,
+    /// balancing the retain the method has already done for us.
+    pub(crate) fn from_new_object(event: id) -> Self {
+        let result = MetalEvent::from(event);
+        unsafe { objc_release(event) };
+        result
+    }
+    /// Sets the label used to identify the event in debugging tools.
+    pub fn set_label(&mut self, label: &str) { set_objc_label(self.event, label) }
+    /// Gets the label (empty if none was set).
+    pub fn get_label(&self) -> String { get_objc_label(self.event) }
+}
+impl MetalEventObject for MetalEvent {
+    fn to_objc(&self) -> id { self.event }
+}
+
+/// Rust wrapper for an event whose value the CPU can also read, set and be told about.
+pub struct MetalSharedEvent {
+    event: id,
+}
+impl Default for MetalSharedEvent {
+    fn default() -> Self {
+        MetalSharedEvent { event: nil }
+    }
+}
+impl From<id> for MetalSharedEvent {
+    fn from(event: id) -> Self {
+        let event = unsafe { objc_retain(event) };
+        MetalSharedEvent { event }
+    }
+}
+impl Drop for MetalSharedEvent {
+    fn drop(&mut self) { unsafe { objc_release(self.event) } }
+}
+impl MetalSharedEvent {
+    /// Wraps an event returned from a `new...` method,
+    /// balancing the retain the method has already done for us.
+    pub(crate) fn from_new_object(event: id) -> Self {
+        let result = MetalSharedEvent::from(event);
+        unsafe { objc_release(event) };
+        result
+    }
+    /// Sets the label used to identify the event in debugging tools.
+    pub fn set_label(&mut self, label: &str) { set_objc_label(self.event, label) }
+    /// Gets the label (empty if none was set).
+    pub fn get_label(&self) -> String { get_objc_label(self.event) }
+    /// Gets the value the event was last signalled with.
+    pub fn get_signaled_value(&self) -> u64 {
+        unsafe { msg_send![self.event, signaledValue] }
+    }
+    /// Signals the event from the CPU, releasing any command buffers waiting for the value.
+    pub fn set_signaled_value(&mut self, value: u64) {
+        unsafe { msg_send![self.event, setSignaledValue:value] }
+    }
+    /// Blocks the calling thread until the event reaches the value, or the timeout passes.
+    ///
+    /// Returns true if the event reached the value.
+    pub fn wait_until_signaled_value(&self, value: u64, timeout_ms: u64) -> bool {
+        let signaled: BOOL = unsafe { msg_send![self.event, waitUntilSignaledValue:value timeoutMS:timeout_ms] };
+        signaled != NO
+    }
+    /// Registers a closure to run, on the listener's dispatch queue,
+    /// once the event reaches the value (straight away if it already has).
+    ///
+    /// The closure is given the value the event reached, and is called once and then freed.
+    pub fn notify_listener<F>(&self, listener: &MetalSharedEventListener, value: u64, handler: F)
+        where F: FnOnce(u64) + Send + 'static {
+        unsafe { shared_event_notify_listener_f_with_user_data(
+            self.event,
+            listener.to_objc(),
+            value,
+            shared_event_notification_trampoline,
+            callback_into_user_data::<u64, F>(handler)
+        )}
+    }
+}
+impl MetalEventObject for MetalSharedEvent {
+    fn to_objc(&self) -> id { self.event }
+}
+
+/// Rust wrapper for the dispatch queue that shared event notifications run on.
+pub struct MetalSharedEventListener {
+    listener: id,
+}
+impl Default for MetalSharedEventListener {
+    fn default() -> Self {
+        MetalSharedEventListener { listener: nil }
+    }
+}
+impl From<id> for MetalSharedEventListener {
+    fn from(listener: id) -> Self {
+        let listener = unsafe { objc_retain(listener) };
+        MetalSharedEventListener { listener }
+    }
+}
+impl Drop for MetalSharedEventListener {
+    fn drop(&mut self) { unsafe { objc_release(self.listener) } }
+}
+impl MetalSharedEventListener {
+    /// Creates a listener with its own dispatch queue.
+    pub fn new() -> Self {
+        let class = class!(MTLSharedEventListener);
+        let listener: id = unsafe {
+            let listener: id = msg_send![class, alloc];
+            msg_send![listener, init]
+        };
+        let result = MetalSharedEventListener::from(listener);
+        unsafe { objc_release(listener) };
+        result
+    }
+    /// Returns the underlying Objective C listener.
+    pub fn to_objc(&self) -> id { self.listener }
+}
+
+/// Hands out the values one event is signalled with,
+/// so each signal comes after the one before.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct EventValueCounter {
+    last: u64,
+}
+impl EventValueCounter {
+    /// A counter whose first value is one more than `start`,
+    /// e.g. the event's current signalled value.
+    pub fn new(start: u64) -> Self { EventValueCounter { last: start } }
+    /// Gets the last value handed out.
+    pub fn last(&self) -> u64 { self.last }
+    /// Hands out the next value.
+    pub fn next_value(&mut self) -> u64 {
+        self.last += 1;
+        self.last
+    }
+}
+
+/// One step of the work submitted to a command queue, for `EventOrderModel`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventStep {
+    /// Sets the event to the value once the work before it is done.
+    Signal {
+        /// The event, numbered by the caller.
+        event: usize,
+        /// The value.
+        value: u64,
+    },
+    /// Holds back the rest of the queue until the event reaches the value.
+    Wait {
+        /// The event, numbered by the caller.
+        event: usize,
+        /// The value.
+        value: u64,
+    },
+    /// Work done on the GPU, named so its place in the order can be checked.
+    Work(String),
+}
+impl EventStep {
+    /// A step of work with the given name.
+    pub fn work(name: &str) -> Self { EventStep::Work(name.to_string()) }
+}
+
+/// A plain model of command queues ordered by events, which starts at zero,
+/// used to check that signals and waits put passes in the intended order
+/// and cannot deadlock.
+///
+/// Work in one queue runs in order; a queue stops at a wait
+/// until some queue has signalled the event with at least the value waited for.
+#[derive(Debug, Default, Clone)]
+pub struct EventOrderModel {
+    queues: Vec<Vec<EventStep>>,
+}
+impl EventOrderModel {
+    /// A model with no queues.
+    pub fn new() -> Self { Self::default() }
+    /// Adds a queue running the steps in order, returning its index.
+    pub fn add_queue(&mut self, steps: Vec<EventStep>) -> usize {
+        self.queues.push(steps);
+        self.queues.len() - 1
+    }
+    /// Runs every queue as far as it can, returning the names of the work
+    /// in an order the GPU could run it in.
+    ///
+    /// Fails if a signal does not raise its event's value, which would release waits too early,
+    /// or if queues are left waiting for signals that never come.
+    pub fn run(&self) -> Result<Vec<String>, MetalError> {
+        let mut values: HashMap<usize, u64> = HashMap::new();
+        let mut positions = vec![0; self.queues.len()];
+        let mut order = Vec::new();
+        loop {
+            let mut progressed = false;
+            for (queue, steps) in self.queues.iter().enumerate() {
+                while let Some(step) = steps.get(positions[queue]) {
+                    match step {
+                        EventStep::Signal { event, value } => {
+                            let current = values.entry(*event).or_insert(0);
+                            if *value <= *current {
+                                return Err(MetalError::EventOrdering(format!(
+                                    "queue {} signals event {} with {}, which is not above {}",
+                                    queue, event, value, current
+                                )));
+                            }
+                            *current = *value;
+                        }
+                        EventStep::Wait { event, value } => {
+                            if values.get(event).copied().unwrap_or(0) < *value {
+                                break;
+                            }
+                        }
+                        EventStep::Work(name) => order.push(name.clone()),
+                    }
+                    positions[queue] += 1;
+                    progressed = true;
+                }
+            }
+            if positions.iter().zip(&self.queues).all(|(position, steps)| *position == steps.len()) {
+                return Ok(order);
+            }
+            if !progressed {
+                let blocked: Vec<String> = self.queues.iter().enumerate()
+                    .filter_map(|(queue, steps)| match steps.get(positions[queue]) {
+                        Some(EventStep::Wait { event, value }) => Some(format!(
+                            "queue {} waits for event {} to reach {} (it is at {})",
+                            queue, event, value, values.get(event).copied().unwrap_or(0)
+                        )),
+                        _ => None,
+                    })
+                    .collect();
+                return Err(MetalError::EventOrdering(format!("deadlock: {}", blocked.join("; "))));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn position(order: &[String], name: &str) -> usize {
+        order.iter().position(|step| step == name).unwrap()
+    }
+
+    #[test]
+    fn test_event_values_increase() {
+        let mut values = EventValueCounter::new(5);
+        assert_eq!(5, values.last());
+        assert_eq!(6, values.next_value());
+        assert_eq!(7, values.next_value());
+        assert_eq!(7, values.last());
+        assert_eq!(1, EventValueCounter::default().next_value());
+    }
+
+    #[test]
+    fn test_waits_order_work_across_queues() {
+        const SHADOWS: usize = 0;
+        let mut model = EventOrderModel::new();
+        // The main queue is added first, so without the wait it would run first.
+        model.add_queue(vec![
+            EventStep::work("gbuffer"),
+            EventStep::Wait { event: SHADOWS, value: 1 },
+            EventStep::work("lighting"),
+            EventStep::Wait { event: SHADOWS, value: 2 },
+            EventStep::work("composite"),
+        ]);
+        model.add_queue(vec![
+            EventStep::work("shadow map"),
+            EventStep::Signal { event: SHADOWS, value: 1 },
+            EventStep::work("shadow blur"),
+            EventStep::Signal { event: SHADOWS, value: 2 },
+        ]);
+        let order = model.run().unwrap();
+        assert_eq!(5, order.len());
+        assert!(position(&order, "shadow map") < position(&order, "lighting"));
+        assert!(position(&order, "shadow blur") < position(&order, "composite"));
+        assert!(position(&order, "gbuffer") < position(&order, "lighting"));
+    }
+
+    #[test]
+    fn test_deadlocks_and_stale_signals_are_reported() {
+        let mut model = EventOrderModel::new();
+        model.add_queue(vec![EventStep::Wait { event: 0, value: 1 }, EventStep::Signal { event: 1, value: 1 }]);
+        model.add_queue(vec![EventStep::Wait { event: 1, value: 1 }, EventStep::Signal { event: 0, value: 1 }]);
+        assert_eq!(
+            Err(MetalError::EventOrdering(String::from(
+                "deadlock: queue 0 waits for event 0 to reach 1 (it is at 0); queue 1 waits for event 1 to reach 1 (it is at 0)"
+            ))),
+            model.run()
+        );
+
+        let mut model = EventOrderModel::new();
+        model.add_queue(vec![EventStep::Signal { event: 0, value: 2 }, EventStep::Signal { event: 0, value: 2 }]);
+        assert_eq!(
+            Err(MetalError::EventOrdering(String::from("queue 0 signals event 0 with 2, which is not above 2"))),
+            model.run()
+        );
+    }
+}
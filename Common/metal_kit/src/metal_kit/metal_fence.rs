@@ -0,0 +1,51 @@
+//
+//  metal_fence.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Thin wrappers for MTLFence
+
+use cocoa::base::{id, nil};
+use objc::runtime::{objc_release, objc_retain};
+use crate::metal_kit::ns_string::{get_objc_label, set_objc_label};
+
+/// Rust wrapper for a fence that orders passes within one command queue.
+///
+/// One encoder updates the fence once its work is done,
+/// and later encoders wait for it before touching the same resources.
+/// Use events to order work across command queues.
+pub struct MetalFence {
+    fence: id,
+}
+impl Default for MetalFence {
+    fn default() -> Self {
+        MetalFence { fence: nil }
+    }
+}
+impl From<id> for MetalFence {
+    fn from(fence: id) -> Self {
+        let fence = unsafe { objc_retain(fence) };
+        MetalFence { fence }
+    }
+}
+impl Drop for MetalFence {
+    fn drop(&mut self) { unsafe { objc_release(self.fence) } }
+}
+impl MetalFence {
+    /// Wraps a fence returned from a `new...` method,
+    /// balancing the retain the method has already done for us.
+    pub(crate) fn from_new_object(fence: id) -> Self {
+        let result = MetalFence::from(fence);
+        unsafe { objc_release(fence) };
+        result
+    }
+    /// Returns the underlying Objective C fence.
+    pub fn to_objc(&self) -> id { self.fence }
+    /// Sets the label used to identify the fence in debugging tools.
+    pub fn set_label(&mut self, label: &str) { set_objc_label(self.fence, label) }
+    /// Gets the label (empty if none was set).
+    pub fn get_label(&self) -> String { get_objc_label(self.fence) }
+}
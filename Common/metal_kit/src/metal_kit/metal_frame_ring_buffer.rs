@@ -0,0 +1,242 @@
+//
+//  metal_frame_ring_buffer.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! A buffer split into one slot per frame in flight,
+//! so the CPU can fill in the next frame's values while the GPU reads the last ones
+
+use cocoa::base::nil;
+use cocoa::foundation::NSUInteger;
+use std::marker::PhantomData;
+use std::mem::{align_of, size_of};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::metal_kit::metal_buffer::MetalBuffer;
+use crate::metal_kit::metal_buffer_storage::BufferStorage;
+use crate::metal_kit::metal_device::MetalDevice;
+use crate::metal_kit::metal_error::MetalError;
+use crate::metal_kit::metal_resource_options::MetalResourceOptions;
+
+/// Constant buffer offsets have to be a multiple of 256 bytes on macOS,
+/// so that is the default distance between slots.
+pub const FRAME_RING_BUFFER_DEFAULT_ALIGNMENT: NSUInteger = 256;
+
+/// Returns the distance in bytes between slots holding a `T`:
+/// its size rounded up to the alignment (which must be a power of two).
+pub fn frame_slot_stride<T>(alignment: NSUInteger) -> NSUInteger {
+    let size = (size_of::<T>() as NSUInteger).max(1);
+    (size + alignment - 1) & !(alignment - 1)
+}
+
+/// A ring of `slot_count` aligned slots, each holding one `T`.
+///
+/// Each frame writes its values into the current slot and binds the buffer at `current_offset()`.
+/// Writing needs `&mut self`, so there is only ever one writer;
+/// a renderer that draws through `&self` can keep the ring buffer in a `Mutex`.
+/// Once a frame has been committed, `advance()` moves on to the next slot;
+/// it only needs `&self`, so it can be called from a draw call taking `&self`.
+/// The caller still has to stop the CPU getting more than `slot_count` frames ahead,
+/// e.g. with a semaphore signalled from each command buffer's completed handler.
+pub struct FrameRingBuffer<T: Copy, S: BufferStorage = MetalBuffer> {
+    storage: S,
+    slot_count: usize,
+    slot_stride: NSUInteger,
+    current_slot: AtomicUsize,
+    element: PhantomData<T>,
+}
+impl<T: Copy, S: BufferStorage + Default> Default for FrameRingBuffer<T, S> {
+    /// An empty ring buffer, with no slots.
+    fn default() -> Self {
+        FrameRingBuffer {
+            storage: S::default(),
+            slot_count: 0,
+            slot_stride: frame_slot_stride::<T>(FRAME_RING_BUFFER_DEFAULT_ALIGNMENT),
+            current_slot: AtomicUsize::new(0),
+            element: PhantomData,
+        }
+    }
+}
+impl<T: Copy> FrameRingBuffer<T> {
+    /// Creates a Metal buffer with `slot_count` slots 256 bytes apart.
+    pub fn new(device: &mut MetalDevice, slot_count: usize, options: MetalResourceOptions) -> Result<Self, MetalError> {
+        Self::new_with_alignment(device, slot_count, FRAME_RING_BUFFER_DEFAULT_ALIGNMENT, options)
+    }
+    /// Creates a Metal buffer with `slot_count` slots, each starting on a multiple of `alignment`.
+    pub fn new_with_alignment(
+        device: &mut MetalDevice,
+        slot_count: usize,
+        alignment: NSUInteger,
+        options: MetalResourceOptions
+    ) -> Result<Self, MetalError> {
+        let length = Self::required_length(slot_count, alignment)?;
+        let buffer = device.new_buffer_with_length_and_options(length, options);
+        if buffer.to_objc() == nil {
+            return Err(MetalError::BufferCreation(length));
+        }
+        Self::with_storage(buffer, slot_count, alignment)
+    }
+}
+impl<T: Copy, S: BufferStorage> FrameRingBuffer<T, S> {
+    /// Returns the number of bytes needed for `slot_count` slots at the given alignment.
+    pub fn required_length(slot_count: usize, alignment: NSUInteger) -> Result<NSUInteger, MetalError> {
+        if !alignment.is_power_of_two() || alignment < align_of::<T>() as NSUInteger {
+            return Err(MetalError::InvalidBufferAlignment(alignment));
+        }
+        if slot_count == 0 {
+            return Err(MetalError::InvalidSlotCount(slot_count));
+        }
+        Ok(frame_slot_stride::<T>(alignment) * slot_count as NSUInteger)
+    }
+    /// Splits existing storage into `slot_count` slots, each starting on a multiple of `alignment`.
+    pub fn with_storage(storage: S, slot_count: usize, alignment: NSUInteger) -> Result<Self, MetalError> {
+        let required = Self::required_length(slot_count, alignment)?;
+        let length = storage.length();
+        if length < required || storage.contents().is_null() {
+            return Err(MetalError::BufferTooSmall { required, length });
+        }
+        if storage.contents() as usize & (alignment as usize - 1) != 0 {
+            return Err(MetalError::InvalidBufferAlignment(alignment));
+        }
+        Ok(FrameRingBuffer {
+            storage,
+            slot_count,
+            slot_stride: frame_slot_stride::<T>(alignment),
+            current_slot: AtomicUsize::new(0),
+            element: PhantomData,
+        })
+    }
+    /// Gets the storage, e.g. to bind the Metal buffer to an encoder.
+    pub fn get_storage(&self) -> &S { &self.storage }
+    /// Gets the storage mutably, e.g. to set the buffer's label.
+    pub fn get_storage_mut(&mut self) -> &mut S { &mut self.storage }
+    /// Gets the number of slots.
+    pub fn get_slot_count(&self) -> usize { self.slot_count }
+    /// Gets the distance in bytes between the starts of adjacent slots.
+    pub fn get_slot_stride(&self) -> NSUInteger { self.slot_stride }
+    /// Gets the index of the slot the current frame writes into.
+    pub fn current_slot(&self) -> usize { self.current_slot.load(Ordering::Acquire) }
+    /// Gets the offset in bytes of the given slot.
+    pub fn slot_offset(&self, slot: usize) -> NSUInteger { self.slot_stride * slot as NSUInteger }
+    /// Gets the offset in bytes to bind the buffer at for the current frame.
+    pub fn current_offset(&self) -> NSUInteger { self.slot_offset(self.current_slot()) }
+    /// Gets the value in the current slot to fill in.
+    ///
+    /// # Panics
+    /// Panics if the ring buffer has no slots.
+    pub fn current_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.current_pointer() }
+    }
+    /// Writes the value into the current slot,
+    /// telling a managed buffer which bytes changed.
+    ///
+    /// # Panics
+    /// Panics if the ring buffer has no slots.
+    pub fn write_current(&mut self, value: T) {
+        unsafe { self.current_pointer().write(value) }
+        self.storage.did_modify_range(self.current_offset(), size_of::<T>() as NSUInteger);
+    }
+    /// Moves on to the next slot, wrapping round after the last one,
+    /// and returns its index.
+    /// Call this once a frame's command buffer has been committed.
+    pub fn advance(&self) -> usize {
+        if self.slot_count == 0 {
+            return 0;
+        }
+        let count = self.slot_count;
+        let previous = self.current_slot.fetch_update(Ordering::AcqRel, Ordering::Acquire, |slot| {
+            Some((slot + 1) % count)
+        }).unwrap_or_default();
+        (previous + 1) % count
+    }
+    /// Works out where the current slot starts.
+    fn current_pointer(&self) -> *mut T {
+        assert!(self.slot_count > 0, "frame ring buffer has no slots");
+        unsafe { self.storage.contents().add(self.current_offset() as usize) as *mut T }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use cocoa::foundation::NSUInteger;
+
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    #[repr(C, align(16))]
+    struct Uniforms {
+        matrix: [f32; 16],
+    }
+
+    fn ring(slot_count: usize, alignment: NSUInteger) -> FrameRingBuffer<Uniforms, HostBuffer> {
+        let length = FrameRingBuffer::<Uniforms, HostBuffer>::required_length(slot_count, alignment).unwrap();
+        FrameRingBuffer::with_storage(HostBuffer::new(length as usize), slot_count, alignment).unwrap()
+    }
+
+    #[test]
+    fn test_slot_stride_rounds_up_to_alignment() {
+        assert_eq!(256, frame_slot_stride::<Uniforms>(256));
+        assert_eq!(64, frame_slot_stride::<Uniforms>(16));
+        assert_eq!(512, frame_slot_stride::<[u8; 257]>(256));
+        assert_eq!(256, frame_slot_stride::<()>(256));
+        assert_eq!(
+            Ok(768),
+            FrameRingBuffer::<Uniforms, HostBuffer>::required_length(3, FRAME_RING_BUFFER_DEFAULT_ALIGNMENT)
+        );
+    }
+
+    #[test]
+    fn test_offsets_cycle_through_slots() {
+        let ring = ring(3, 256);
+        assert_eq!(3, ring.get_slot_count());
+        assert_eq!(0, ring.current_offset());
+        assert_eq!(1, ring.advance());
+        assert_eq!(256, ring.current_offset());
+        assert_eq!(2, ring.advance());
+        assert_eq!(512, ring.current_offset());
+        assert_eq!(0, ring.advance());
+        assert_eq!(0, ring.current_offset());
+    }
+
+    #[test]
+    fn test_writes_land_in_their_own_slot() {
+        let mut ring = ring(2, 256);
+        ring.write_current(Uniforms { matrix: [1.0; 16] });
+        ring.advance();
+        ring.current_mut().matrix = [2.0; 16];
+
+        let bytes = ring.get_storage().as_bytes();
+        let float_at = |offset: usize| f32::from_ne_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]]);
+        assert_eq!(1.0, float_at(0));
+        assert_eq!(1.0, float_at(60));
+        // Padding between the value and the next slot is untouched.
+        assert_eq!(0.0, float_at(64));
+        assert_eq!(2.0, float_at(256));
+        assert_eq!(2.0, float_at(316));
+        assert_eq!(vec![0..64], ring.get_storage().take_modified_ranges());
+
+        ring.advance();
+        assert_eq!([1.0; 16], ring.current_mut().matrix);
+    }
+
+    #[test]
+    fn test_invalid_set_ups_are_rejected() {
+        type Ring = FrameRingBuffer<Uniforms, HostBuffer>;
+        assert_eq!(Err(MetalError::InvalidBufferAlignment(100)), Ring::required_length(3, 100).map(|_| ()));
+        assert_eq!(Err(MetalError::InvalidBufferAlignment(8)), Ring::required_length(3, 8).map(|_| ()));
+        assert_eq!(Err(MetalError::InvalidSlotCount(0)), Ring::required_length(0, 256).map(|_| ()));
+        assert_eq!(
+            Err(MetalError::BufferTooSmall { required: 768, length: 512 }),
+            Ring::with_storage(HostBuffer::new(512), 3, 256).map(|_| ())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "no slots")]
+    fn test_empty_ring_cannot_be_written() {
+        let mut ring: FrameRingBuffer<Uniforms, HostBuffer> = FrameRingBuffer::default();
+        assert_eq!(0, ring.advance());
+        ring.write_current(Uniforms { matrix: [0.0; 16] });
+    }
+}
@@ -0,0 +1,61 @@
+//
+//  metal_function.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Thin wrappers for methods we use from MTLFunction
+
+use objc::msg_send;
+use objc::sel;
+use objc::sel_impl;
+use cocoa::base::{id, nil};
+use objc::runtime::{objc_retain, objc_release};
+use crate::metal_kit::ns_string::rust_string_from_ns_string;
+use crate::metal_kit::ns_string::{get_objc_label, set_objc_label};
+use cocoa::foundation::NSUInteger;
+use crate::metal_kit::metal_argument_encoder::MetalArgumentEncoder;
+use crate::metal_kit::metal_error::MetalError;
+
+/// Rust wrapper for a public shader function in a Metal library.
+pub struct MetalFunction {
+    function: id,
+}
+impl Default for MetalFunction {
+    fn default() -> Self {
+        MetalFunction { function: nil }
+    }
+}
+impl From<id> for MetalFunction {
+    fn from(function: id) -> Self {
+        let function = unsafe { objc_retain(function) };
+        MetalFunction { function }
+    }
+}
+impl Drop for MetalFunction {
+    fn drop(&mut self) { unsafe { objc_release(self.function) } }
+}
+
+impl MetalFunction {
+    /// Sets the label used to identify the function in debugging tools.
+    pub fn set_label(&mut self, label: &str) { set_objc_label(self.function, label) }
+    /// Gets the label (empty if none was set).
+    pub fn get_label(&self) -> String { get_objc_label(self.function) }
+    /// Returns the underlying Objective C function.
+    pub fn to_objc(&self) -> id { self.function }
+    /// Gets the name of the function.
+    pub fn get_name(&self) -> String {
+        let name: id = unsafe { msg_send![self.function, name] };
+        rust_string_from_ns_string(name)
+    }
+    /// Creates an encoder for the argument buffer the function takes at the given buffer index.
+    pub fn new_argument_encoder_with_buffer_index(&self, index: NSUInteger) -> Result<MetalArgumentEncoder, MetalError> {
+        let encoder: id = unsafe { msg_send![self.function, newArgumentEncoderWithBufferIndex:index] };
+        if encoder == nil {
+            return Err(MetalError::ArgumentEncoderCreation(index));
+        }
+        Ok(MetalArgumentEncoder::from_new_object(encoder))
+    }
+}
@@ -0,0 +1,155 @@
+//
+//  metal_gpu_timing.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! A rolling history of how long the GPU spent on each frame
+
+use std::collections::VecDeque;
+use crate::metal_kit::metal_command_buffer::MetalCommandBuffer;
+
+/// The GPU times of the last few frames,
+/// with the statistics a frame-time overlay or log wants.
+///
+/// Times are in seconds, as given by `gpu_start_time` and `gpu_end_time`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuTimingHistory {
+    durations: VecDeque<f64>,
+    capacity: usize,
+}
+impl Default for GpuTimingHistory {
+    /// Two seconds' worth of frames at 60 frames per second.
+    fn default() -> Self { GpuTimingHistory::new(120) }
+}
+impl GpuTimingHistory {
+    /// Creates an empty history holding up to `capacity` frames (at least one).
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        GpuTimingHistory { durations: VecDeque::with_capacity(capacity), capacity }
+    }
+    /// Records a frame that ran on the GPU between the two host times,
+    /// returning its duration.
+    ///
+    /// Frames that never ran (a zero start time) or whose times make no sense are skipped.
+    pub fn record(&mut self, gpu_start_time: f64, gpu_end_time: f64) -> Option<f64> {
+        if !(gpu_start_time > 0.0 && gpu_end_time >= gpu_start_time) {
+            return None;
+        }
+        let duration = gpu_end_time - gpu_start_time;
+        self.record_duration(duration);
+        Some(duration)
+    }
+    /// Records the GPU time of a completed command buffer, returning its duration.
+    pub fn record_command_buffer(&mut self, command_buffer: &MetalCommandBuffer) -> Option<f64> {
+        self.record(command_buffer.gpu_start_time(), command_buffer.gpu_end_time())
+    }
+    /// Records a frame duration directly, dropping the oldest frame if the history is full.
+    pub fn record_duration(&mut self, duration: f64) {
+        if self.durations.len() == self.capacity {
+            self.durations.pop_front();
+        }
+        self.durations.push_back(duration);
+    }
+    /// Gets the most frames the history holds.
+    pub fn capacity(&self) -> usize { self.capacity }
+    /// Gets the number of frames recorded (up to the capacity).
+    pub fn len(&self) -> usize { self.durations.len() }
+    /// Returns true if no frames have been recorded.
+    pub fn is_empty(&self) -> bool { self.durations.is_empty() }
+    /// Forgets all recorded frames.
+    pub fn clear(&mut self) { self.durations.clear() }
+    /// Gets the duration of the most recent frame.
+    pub fn latest(&self) -> Option<f64> { self.durations.back().copied() }
+    /// Gets the mean duration of the recorded frames.
+    pub fn average(&self) -> Option<f64> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(self.durations.iter().sum::<f64>() / self.len() as f64)
+    }
+    /// Gets the shortest recorded duration.
+    pub fn min(&self) -> Option<f64> {
+        self.durations.iter().copied().reduce(f64::min)
+    }
+    /// Gets the longest recorded duration.
+    pub fn max(&self) -> Option<f64> {
+        self.durations.iter().copied().reduce(f64::max)
+    }
+    /// Gets the duration that `percentile` percent of the recorded frames are no longer than,
+    /// using the nearest-rank method (so the result is always a recorded duration).
+    ///
+    /// `percentile` is clamped to 0...100; 50 gives the median and 100 the maximum.
+    pub fn percentile(&self, percentile: f64) -> Option<f64> {
+        if self.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<f64> = self.durations.iter().copied().collect();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let percentile = if percentile.is_nan() { 0.0 } else { percentile.clamp(0.0, 100.0) };
+        let rank = (percentile / 100.0 * sorted.len() as f64).ceil() as usize;
+        Some(sorted[rank.max(1) - 1])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn close(expected: f64, actual: Option<f64>) -> bool {
+        actual.is_some_and(|actual| (expected - actual).abs() < 1e-9)
+    }
+
+    #[test]
+    fn test_records_durations_from_timestamps() {
+        let mut history = GpuTimingHistory::new(4);
+        assert!(history.is_empty());
+        assert_eq!(None, history.average());
+        assert!(close(0.004, history.record(100.0, 100.004)));
+        // Not run yet, or times that go backwards, are skipped.
+        assert_eq!(None, history.record(0.0, 0.0));
+        assert_eq!(None, history.record(100.010, 100.008));
+        assert_eq!(None, history.record(f64::NAN, 100.0));
+        assert_eq!(1, history.len());
+        assert!(close(0.004, history.latest()));
+    }
+
+    #[test]
+    fn test_rolling_statistics() {
+        let mut history = GpuTimingHistory::new(4);
+        for duration in [0.010, 0.002, 0.004, 0.006].iter() {
+            history.record_duration(*duration);
+        }
+        assert!(close(0.0055, history.average()));
+        assert!(close(0.002, history.min()));
+        assert!(close(0.010, history.max()));
+
+        // The oldest frame rolls off.
+        history.record_duration(0.008);
+        assert_eq!(4, history.len());
+        assert!(close(0.005, history.average()));
+        assert!(close(0.008, history.max()));
+        assert!(close(0.008, history.latest()));
+
+        history.clear();
+        assert_eq!(None, history.max());
+        assert_eq!(None, history.percentile(50.0));
+    }
+
+    #[test]
+    fn test_percentiles_use_nearest_rank() {
+        let mut history = GpuTimingHistory::new(10);
+        for frame in (1..=10).rev() {
+            history.record_duration(frame as f64 / 1000.0);
+        }
+        assert!(close(0.005, history.percentile(50.0)));
+        assert!(close(0.009, history.percentile(90.0)));
+        assert!(close(0.010, history.percentile(99.0)));
+        assert!(close(0.010, history.percentile(100.0)));
+        assert!(close(0.001, history.percentile(0.0)));
+        assert!(close(0.001, history.percentile(-5.0)));
+        assert!(close(0.010, history.percentile(250.0)));
+    }
+}
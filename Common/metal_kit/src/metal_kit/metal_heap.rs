@@ -0,0 +1,78 @@
+//
+//  metal_heap.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Thin wrappers for the methods we use from MTLHeap
+
+use objc::msg_send;
+use objc::sel;
+use objc::sel_impl;
+use cocoa::base::{id, nil};
+use cocoa::foundation::NSUInteger;
+use objc::runtime::{objc_release, objc_retain};
+use crate::metal_kit::metal_buffer::MetalBuffer;
+use crate::metal_kit::metal_error::MetalError;
+use crate::metal_kit::metal_resource_options::MetalResourceOptions;
+use crate::metal_kit::ns_string::{get_objc_label, set_objc_label};
+
+/// Rust wrapper for a block of GPU memory that buffers can be made in.
+///
+/// Resources made in a heap can be made resident for an argument buffer
+/// all at once, with `MetalRenderCommandEncoder::use_heap`.
+pub struct MetalHeap {
+    heap: id,
+}
+impl Default for MetalHeap {
+    fn default() -> Self {
+        MetalHeap { heap: nil }
+    }
+}
+impl From<id> for MetalHeap {
+    fn from(heap: id) -> Self {
+        let heap = unsafe { objc_retain(heap) };
+        MetalHeap { heap }
+    }
+}
+impl Drop for MetalHeap {
+    fn drop(&mut self) { unsafe { objc_release(self.heap) } }
+}
+impl MetalHeap {
+    /// Returns the underlying Objective C heap.
+    pub fn to_objc(&self) -> id { self.heap }
+    /// Sets the label used to identify the heap in debugging tools.
+    pub fn set_label(&mut self, label: &str) { set_objc_label(self.heap, label) }
+    /// Gets the label (empty if none was set).
+    pub fn get_label(&self) -> String { get_objc_label(self.heap) }
+    /// Gets the size of the heap, in bytes.
+    pub fn get_size(&self) -> NSUInteger {
+        unsafe { msg_send![self.heap, size] }
+    }
+    /// Gets the number of bytes taken up by the resources in the heap.
+    pub fn get_used_size(&self) -> NSUInteger {
+        unsafe { msg_send![self.heap, usedSize] }
+    }
+    /// Gets the largest resource, in bytes, that can still be made in the heap at the given alignment.
+    pub fn get_max_available_size_with_alignment(&self, alignment: NSUInteger) -> NSUInteger {
+        unsafe { msg_send![self.heap, maxAvailableSizeWithAlignment:alignment] }
+    }
+    /// Makes a buffer in the heap.
+    ///
+    /// The storage and cache modes in the options have to match the heap's.
+    pub fn new_buffer_with_length_and_options(
+        &self,
+        length: NSUInteger,
+        options: MetalResourceOptions
+    ) -> Result<MetalBuffer, MetalError> {
+        let buffer: id = unsafe { msg_send![self.heap, newBufferWithLength:length options:options.to_raw()] };
+        if buffer == nil {
+            return Err(MetalError::BufferCreation(length));
+        }
+        let result = MetalBuffer::from(buffer);
+        unsafe { objc_release(buffer) };
+        Ok(result)
+    }
+}
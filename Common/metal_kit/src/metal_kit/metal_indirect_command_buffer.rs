@@ -0,0 +1,446 @@
+//
+//  metal_indirect_command_buffer.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Thin wrappers for MTLIndirectCommandBuffer and MTLIndirectRenderCommand,
+//! for draws encoded once (or by a kernel) and replayed each frame
+
+use objc::msg_send;
+use objc::sel;
+use objc::sel_impl;
+use objc::class;
+use cocoa::base::{id, nil, NO, YES};
+use cocoa::foundation::{NSRange, NSUInteger};
+use objc::runtime::{objc_release, objc_retain};
+use std::ops::Range;
+use crate::metal_kit::metal_argument_encoder::MTLResourceID;
+use crate::metal_kit::metal_buffer::MetalBuffer;
+use crate::metal_kit::metal_error::MetalError;
+use crate::metal_kit::metal_render_command_encoder::{MTLIndexType, MTLPrimitiveType};
+use crate::metal_kit::metal_render_pipeline_state::MetalRenderPipelineState;
+use crate::metal_kit::ns_string::{get_objc_label, set_objc_label};
+
+// From MTLIndirectCommandBuffer.h:
+// typedef NS_OPTIONS(NSUInteger, MTLIndirectCommandType) {
+//     MTLIndirectCommandTypeDraw                = (1 << 0),
+//     MTLIndirectCommandTypeDrawIndexed         = (1 << 1),
+//     MTLIndirectCommandTypeDrawPatches         = (1 << 2),
+//     MTLIndirectCommandTypeDrawIndexedPatches  = (1 << 3),
+//     MTLIndirectCommandTypeConcurrentDispatch  = (1 << 5),
+//     MTLIndirectCommandTypeConcurrentDispatchThreads = (1 << 6),
+// } API_AVAILABLE(macos(10.14), ios(12.0));
+/// The kinds of command an indirect command buffer can hold.
+pub type MTLIndirectCommandType = NSUInteger;
+/// Non-indexed draws.
+#[allow(non_upper_case_globals)]
+pub const MTLIndirectCommandTypeDraw: MTLIndirectCommandType = 1 << 0;
+/// Indexed draws.
+#[allow(non_upper_case_globals)]
+pub const MTLIndirectCommandTypeDrawIndexed: MTLIndirectCommandType = 1 << 1;
+/// Non-indexed tessellated draws.
+#[allow(non_upper_case_globals)]
+pub const MTLIndirectCommandTypeDrawPatches: MTLIndirectCommandType = 1 << 2;
+/// Indexed tessellated draws.
+#[allow(non_upper_case_globals)]
+pub const MTLIndirectCommandTypeDrawIndexedPatches: MTLIndirectCommandType = 1 << 3;
+/// Compute dispatches of threadgroups.
+#[allow(non_upper_case_globals)]
+pub const MTLIndirectCommandTypeConcurrentDispatch: MTLIndirectCommandType = 1 << 5;
+/// Compute dispatches of threads.
+#[allow(non_upper_case_globals)]
+pub const MTLIndirectCommandTypeConcurrentDispatchThreads: MTLIndirectCommandType = 1 << 6;
+
+/// The command types that draw.
+const RENDER_COMMAND_TYPES: MTLIndirectCommandType = MTLIndirectCommandTypeDraw
+    | MTLIndirectCommandTypeDrawIndexed
+    | MTLIndirectCommandTypeDrawPatches
+    | MTLIndirectCommandTypeDrawIndexedPatches;
+/// The command types that dispatch compute work.
+const COMPUTE_COMMAND_TYPES: MTLIndirectCommandType = MTLIndirectCommandTypeConcurrentDispatch
+    | MTLIndirectCommandTypeConcurrentDispatchThreads;
+
+/// The most buffers a render stage can have bound.
+pub const INDIRECT_COMMAND_MAX_BUFFER_BIND_COUNT: NSUInteger = 31;
+
+/// What an indirect command buffer holds, and what its commands inherit
+/// from the encoder that executes them.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct IndirectCommandBufferDescriptor {
+    /// The kinds of command it can hold: render or compute, not both.
+    pub command_types: MTLIndirectCommandType,
+    /// Whether the commands use the encoder's pipeline state rather than setting their own.
+    pub inherit_pipeline_state: bool,
+    /// Whether the commands use the encoder's buffers rather than setting their own.
+    pub inherit_buffers: bool,
+    /// The most vertex buffers a command can set, when not inherited.
+    pub max_vertex_buffer_bind_count: NSUInteger,
+    /// The most fragment buffers a command can set, when not inherited.
+    pub max_fragment_buffer_bind_count: NSUInteger,
+}
+impl Default for IndirectCommandBufferDescriptor {
+    /// Draws, indexed or not, inheriting everything from the encoder.
+    fn default() -> Self {
+        IndirectCommandBufferDescriptor {
+            command_types: MTLIndirectCommandTypeDraw | MTLIndirectCommandTypeDrawIndexed,
+            inherit_pipeline_state: true,
+            inherit_buffers: true,
+            max_vertex_buffer_bind_count: 0,
+            max_fragment_buffer_bind_count: 0,
+        }
+    }
+}
+impl IndirectCommandBufferDescriptor {
+    /// Checks the settings make sense before handing them to Metal.
+    pub fn validate(&self) -> Result<(), MetalError> {
+        let known = RENDER_COMMAND_TYPES | COMPUTE_COMMAND_TYPES;
+        let mixed = self.command_types & RENDER_COMMAND_TYPES != 0 && self.command_types & COMPUTE_COMMAND_TYPES != 0;
+        if self.command_types == 0 || self.command_types & !known != 0 || mixed {
+            return Err(MetalError::InvalidIndirectCommandTypes(self.command_types));
+        }
+        for count in [self.max_vertex_buffer_bind_count, self.max_fragment_buffer_bind_count].iter() {
+            if *count > INDIRECT_COMMAND_MAX_BUFFER_BIND_COUNT {
+                return Err(MetalError::InvalidIndirectBufferBindCount(*count));
+            }
+        }
+        Ok(())
+    }
+    /// Checks a command may set its own pipeline state.
+    pub fn check_set_pipeline_state(&self) -> Result<(), MetalError> {
+        if self.inherit_pipeline_state {
+            return Err(MetalError::IndirectCommandInheritedState(String::from("pipeline state")));
+        }
+        Ok(())
+    }
+    /// Checks a command may set a vertex buffer at the given index.
+    pub fn check_set_vertex_buffer(&self, index: NSUInteger) -> Result<(), MetalError> {
+        self.check_set_buffer(index, self.max_vertex_buffer_bind_count)
+    }
+    /// Checks a command may set a fragment buffer at the given index.
+    pub fn check_set_fragment_buffer(&self, index: NSUInteger) -> Result<(), MetalError> {
+        self.check_set_buffer(index, self.max_fragment_buffer_bind_count)
+    }
+    /// Checks the buffer can hold commands of the given type.
+    pub fn check_command_type(&self, command_type: MTLIndirectCommandType) -> Result<(), MetalError> {
+        if self.command_types & command_type == 0 {
+            return Err(MetalError::InvalidIndirectCommandTypes(command_type));
+        }
+        Ok(())
+    }
+    fn check_set_buffer(&self, index: NSUInteger, bind_count: NSUInteger) -> Result<(), MetalError> {
+        if self.inherit_buffers {
+            return Err(MetalError::IndirectCommandInheritedState(String::from("buffers")));
+        }
+        if index >= bind_count {
+            return Err(MetalError::InvalidIndirectBufferBindCount(index + 1));
+        }
+        Ok(())
+    }
+    /// Creates the Objective C descriptor, which the caller releases.
+    pub(crate) fn new_objc_descriptor(&self) -> id {
+        let class = class!(MTLIndirectCommandBufferDescriptor);
+        unsafe {
+            let descriptor: id = msg_send![class, new];
+            let _:() = msg_send![descriptor, setCommandTypes:self.command_types];
+            let _:() = msg_send![descriptor, setInheritPipelineState:if self.inherit_pipeline_state { YES } else { NO }];
+            let _:() = msg_send![descriptor, setInheritBuffers:if self.inherit_buffers { YES } else { NO }];
+            let _:() = msg_send![descriptor, setMaxVertexBufferBindCount:self.max_vertex_buffer_bind_count];
+            let _:() = msg_send![descriptor, setMaxFragmentBufferBindCount:self.max_fragment_buffer_bind_count];
+            descriptor
+        }
+    }
+}
+
+/// Keeps track of how many commands of an indirect command buffer have been encoded,
+/// so only those are executed.
+///
+/// Commands are counted up to the highest index encoded,
+/// as `execute_commands_in_buffer` takes a contiguous range.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct IndirectCommandCounter {
+    size: NSUInteger,
+    encoded: NSUInteger,
+}
+impl IndirectCommandCounter {
+    /// Counts commands in a buffer with room for `size` of them.
+    pub fn new(size: NSUInteger) -> Self {
+        IndirectCommandCounter { size, encoded: 0 }
+    }
+    /// Gets the number of commands the buffer has room for.
+    pub fn size(&self) -> NSUInteger { self.size }
+    /// Gets the number of commands from the start of the buffer up to the last one encoded.
+    pub fn encoded_count(&self) -> NSUInteger { self.encoded }
+    /// Gets the range of commands to execute.
+    pub fn encoded_range(&self) -> Range<NSUInteger> { 0..self.encoded }
+    /// Notes that the command at the given index is being encoded.
+    pub fn claim(&mut self, index: NSUInteger) -> Result<(), MetalError> {
+        if index >= self.size {
+            return Err(MetalError::IndirectCommandIndexOutOfBounds { index, count: self.size });
+        }
+        self.encoded = self.encoded.max(index + 1);
+        Ok(())
+    }
+    /// Claims the command after the last one encoded, returning its index.
+    pub fn claim_next(&mut self) -> Result<NSUInteger, MetalError> {
+        let index = self.encoded;
+        self.claim(index)?;
+        Ok(index)
+    }
+    /// Checks a range of commands lies inside the buffer.
+    pub fn check_range(&self, range: &Range<NSUInteger>) -> Result<(), MetalError> {
+        if range.start > range.end || range.end > self.size {
+            return Err(MetalError::IndirectCommandIndexOutOfBounds { index: range.end.max(range.start), count: self.size });
+        }
+        Ok(())
+    }
+    /// Notes that a range of commands has been reset.
+    /// Resetting the last commands encoded shortens the encoded range.
+    pub fn reset(&mut self, range: &Range<NSUInteger>) -> Result<(), MetalError> {
+        self.check_range(range)?;
+        if range.end >= self.encoded && range.start < self.encoded {
+            self.encoded = range.start;
+        }
+        Ok(())
+    }
+}
+
+/// Converts a range of commands to the NSRange Metal takes.
+pub(crate) fn ns_range(range: &Range<NSUInteger>) -> NSRange {
+    NSRange::new(range.start, range.end - range.start)
+}
+
+/// Rust wrapper for a buffer of draw commands that can be encoded once and executed many times.
+pub struct MetalIndirectCommandBuffer {
+    buffer: id,
+    descriptor: IndirectCommandBufferDescriptor,
+    counter: IndirectCommandCounter,
+}
+impl Default for MetalIndirectCommandBuffer {
+    fn default() -> Self {
+        MetalIndirectCommandBuffer {
+            buffer: nil,
+            descriptor: IndirectCommandBufferDescriptor::default(),
+            counter: IndirectCommandCounter::default(),
+        }
+    }
+}
+impl Drop for MetalIndirectCommandBuffer {
+    fn drop(&mut self) { unsafe { objc_release(self.buffer) } }
+}
+impl MetalIndirectCommandBuffer {
+    /// Wraps a buffer returned from a `new...` method with the descriptor it was made from,
+    /// taking over the retain the method has already done for us.
+    pub(crate) fn from_new_object(buffer: id, descriptor: IndirectCommandBufferDescriptor) -> Self {
+        let size: NSUInteger = unsafe { msg_send![buffer, size] };
+        MetalIndirectCommandBuffer { buffer, descriptor, counter: IndirectCommandCounter::new(size) }
+    }
+    /// Returns the underlying Objective C indirect command buffer.
+    pub fn to_objc(&self) -> id { self.buffer }
+    /// Sets the label used to identify the buffer in debugging tools.
+    pub fn set_label(&mut self, label: &str) { set_objc_label(self.buffer, label) }
+    /// Gets the label (empty if none was set).
+    pub fn get_label(&self) -> String { get_objc_label(self.buffer) }
+    /// Gets the descriptor the buffer was made from.
+    pub fn get_descriptor(&self) -> &IndirectCommandBufferDescriptor { &self.descriptor }
+    /// Gets the number of commands the buffer has room for.
+    pub fn get_size(&self) -> NSUInteger { self.counter.size() }
+    /// Gets the count of commands encoded from the CPU.
+    pub fn get_counter(&self) -> &IndirectCommandCounter { &self.counter }
+    /// Gets the ID to write into a Tier 2 argument buffer,
+    /// for a kernel to encode commands into the buffer.
+    pub fn get_gpu_resource_id(&self) -> MTLResourceID {
+        unsafe { msg_send![self.buffer, gpuResourceID] }
+    }
+    /// Gets the command at the given index, to encode a draw into from the CPU.
+    pub fn indirect_render_command_at(&mut self, index: NSUInteger) -> Result<MetalIndirectRenderCommand, MetalError> {
+        self.descriptor.check_command_type(RENDER_COMMAND_TYPES)?;
+        self.counter.claim(index)?;
+        let command: id = unsafe { msg_send![self.buffer, indirectRenderCommandAtIndex:index] };
+        Ok(MetalIndirectRenderCommand::new(command, self.descriptor))
+    }
+    /// Gets the command after the last one encoded.
+    pub fn next_indirect_render_command(&mut self) -> Result<MetalIndirectRenderCommand, MetalError> {
+        self.descriptor.check_command_type(RENDER_COMMAND_TYPES)?;
+        let index = self.counter.claim_next()?;
+        let command: id = unsafe { msg_send![self.buffer, indirectRenderCommandAtIndex:index] };
+        Ok(MetalIndirectRenderCommand::new(command, self.descriptor))
+    }
+    /// Resets a range of commands from the CPU, so they do nothing when executed.
+    pub fn reset(&mut self, range: Range<NSUInteger>) -> Result<(), MetalError> {
+        self.counter.reset(&range)?;
+        unsafe { msg_send![self.buffer, resetWithRange:ns_range(&range)] }
+        Ok(())
+    }
+    /// Notes that a blit encoder is resetting a range of commands.
+    pub(crate) fn counter_mut(&mut self) -> &mut IndirectCommandCounter { &mut self.counter }
+}
+
+/// Rust wrapper for one draw command in an indirect command buffer.
+///
+/// Each setter is checked against what the buffer's descriptor allows.
+pub struct MetalIndirectRenderCommand {
+    command: id,
+    descriptor: IndirectCommandBufferDescriptor,
+}
+impl Drop for MetalIndirectRenderCommand {
+    fn drop(&mut self) { unsafe { objc_release(self.command) } }
+}
+impl MetalIndirectRenderCommand {
+    fn new(command: id, descriptor: IndirectCommandBufferDescriptor) -> Self {
+        let command = unsafe { objc_retain(command) };
+        MetalIndirectRenderCommand { command, descriptor }
+    }
+    /// Returns the underlying Objective C command.
+    pub fn to_objc(&self) -> id { self.command }
+    /// Sets the pipeline state the draw uses.
+    ///
+    /// The pipeline has to have been made with `set_support_indirect_command_buffers`.
+    pub fn set_render_pipeline_state(&mut self, pipeline: &MetalRenderPipelineState) -> Result<(), MetalError> {
+        self.descriptor.check_set_pipeline_state()?;
+        unsafe { msg_send![self.command, setRenderPipelineState:pipeline.to_objc()] }
+        Ok(())
+    }
+    /// Sets a buffer for the vertex function.
+    pub fn set_vertex_buffer(&mut self, buffer: &MetalBuffer, offset: NSUInteger, index: NSUInteger) -> Result<(), MetalError> {
+        self.descriptor.check_set_vertex_buffer(index)?;
+        unsafe { msg_send![self.command, setVertexBuffer:buffer.to_objc() offset:offset atIndex:index] }
+        Ok(())
+    }
+    /// Sets a buffer for the fragment function.
+    pub fn set_fragment_buffer(&mut self, buffer: &MetalBuffer, offset: NSUInteger, index: NSUInteger) -> Result<(), MetalError> {
+        self.descriptor.check_set_fragment_buffer(index)?;
+        unsafe { msg_send![self.command, setFragmentBuffer:buffer.to_objc() offset:offset atIndex:index] }
+        Ok(())
+    }
+    /// Encodes a non-indexed draw.
+    pub fn draw_primitives(
+        &mut self,
+        primitive_type: MTLPrimitiveType,
+        vertex_start: NSUInteger,
+        vertex_count: NSUInteger,
+        instance_count: NSUInteger,
+        base_instance: NSUInteger
+    ) -> Result<(), MetalError> {
+        self.descriptor.check_command_type(MTLIndirectCommandTypeDraw)?;
+        unsafe { msg_send![self.command,
+            drawPrimitives:primitive_type
+               vertexStart:vertex_start
+               vertexCount:vertex_count
+             instanceCount:instance_count
+              baseInstance:base_instance
+        ] }
+        Ok(())
+    }
+    /// Encodes an indexed draw.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_indexed_primitives(
+        &mut self,
+        primitive_type: MTLPrimitiveType,
+        index_count: NSUInteger,
+        index_type: MTLIndexType,
+        index_buffer: &MetalBuffer,
+        index_buffer_offset: NSUInteger,
+        instance_count: NSUInteger,
+        base_vertex: isize,
+        base_instance: NSUInteger
+    ) -> Result<(), MetalError> {
+        self.descriptor.check_command_type(MTLIndirectCommandTypeDrawIndexed)?;
+        unsafe { msg_send![self.command,
+            drawIndexedPrimitives:primitive_type
+                       indexCount:index_count
+                        indexType:index_type
+                      indexBuffer:index_buffer.to_objc()
+                indexBufferOffset:index_buffer_offset
+                    instanceCount:instance_count
+                       baseVertex:base_vertex
+                     baseInstance:base_instance
+        ] }
+        Ok(())
+    }
+    /// Clears the command, so it does nothing when executed.
+    pub fn reset(&mut self) {
+        unsafe { msg_send![self.command, reset] }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn own_bindings() -> IndirectCommandBufferDescriptor {
+        IndirectCommandBufferDescriptor {
+            command_types: MTLIndirectCommandTypeDrawIndexed,
+            inherit_pipeline_state: false,
+            inherit_buffers: false,
+            max_vertex_buffer_bind_count: 2,
+            max_fragment_buffer_bind_count: 1,
+        }
+    }
+
+    #[test]
+    fn test_descriptor_validation() {
+        assert_eq!(Ok(()), IndirectCommandBufferDescriptor::default().validate());
+        assert_eq!(Ok(()), own_bindings().validate());
+        let invalid = |command_types| IndirectCommandBufferDescriptor { command_types, ..own_bindings() }.validate();
+        assert_eq!(Err(MetalError::InvalidIndirectCommandTypes(0)), invalid(0));
+        assert_eq!(Err(MetalError::InvalidIndirectCommandTypes(1 << 4)), invalid(1 << 4));
+        assert_eq!(
+            Err(MetalError::InvalidIndirectCommandTypes(0x21)),
+            invalid(MTLIndirectCommandTypeDraw | MTLIndirectCommandTypeConcurrentDispatch)
+        );
+        assert_eq!(
+            Err(MetalError::InvalidIndirectBufferBindCount(32)),
+            IndirectCommandBufferDescriptor { max_fragment_buffer_bind_count: 32, ..own_bindings() }.validate()
+        );
+    }
+
+    #[test]
+    fn test_commands_only_set_what_is_not_inherited() {
+        let inherited = IndirectCommandBufferDescriptor::default();
+        assert_eq!(
+            Err(MetalError::IndirectCommandInheritedState(String::from("pipeline state"))),
+            inherited.check_set_pipeline_state()
+        );
+        assert_eq!(
+            Err(MetalError::IndirectCommandInheritedState(String::from("buffers"))),
+            inherited.check_set_vertex_buffer(0)
+        );
+
+        let own = own_bindings();
+        assert_eq!(Ok(()), own.check_set_pipeline_state());
+        assert_eq!(Ok(()), own.check_set_vertex_buffer(1));
+        assert_eq!(Err(MetalError::InvalidIndirectBufferBindCount(3)), own.check_set_vertex_buffer(2));
+        assert_eq!(Err(MetalError::InvalidIndirectBufferBindCount(2)), own.check_set_fragment_buffer(1));
+        assert_eq!(Ok(()), own.check_command_type(MTLIndirectCommandTypeDrawIndexed));
+        assert_eq!(
+            Err(MetalError::InvalidIndirectCommandTypes(MTLIndirectCommandTypeDraw)),
+            own.check_command_type(MTLIndirectCommandTypeDraw)
+        );
+    }
+
+    #[test]
+    fn test_counter_tracks_encoded_range() {
+        let mut counter = IndirectCommandCounter::new(4);
+        assert_eq!(0..0, counter.encoded_range());
+        assert_eq!(Ok(0), counter.claim_next());
+        assert_eq!(Ok(1), counter.claim_next());
+        // Encoding further on leaves a gap that is executed as empty commands.
+        assert_eq!(Ok(()), counter.claim(3));
+        assert_eq!(0..4, counter.encoded_range());
+        assert_eq!(Err(MetalError::IndirectCommandIndexOutOfBounds { index: 4, count: 4 }), counter.claim_next());
+
+        assert_eq!(Ok(()), counter.check_range(&(1..4)));
+        assert_eq!(Err(MetalError::IndirectCommandIndexOutOfBounds { index: 5, count: 4 }), counter.check_range(&(2..5)));
+
+        // Resetting the middle leaves the count; resetting the tail shortens it.
+        assert_eq!(Ok(()), counter.reset(&(1..2)));
+        assert_eq!(4, counter.encoded_count());
+        assert_eq!(Ok(()), counter.reset(&(2..4)));
+        assert_eq!(0..2, counter.encoded_range());
+        assert_eq!(Ok(()), counter.reset(&(0..4)));
+        assert_eq!(Ok(0), counter.claim_next());
+    }
+}
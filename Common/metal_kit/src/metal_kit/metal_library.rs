@@ -14,6 +14,9 @@ use objc::sel_impl;
 use cocoa::base::{id, nil};
 use objc::runtime::{objc_retain, objc_release};
 use cocoa::foundation::{NSAutoreleasePool, NSString};
+use crate::metal_kit::metal_function::MetalFunction;
+use crate::metal_kit::metal_error::MetalError;
+use crate::metal_kit::ns_string::{get_objc_label, set_objc_label};
 
 /// Rust wrapper for a collection of Metal shader functions.
 pub struct MetalLibrary {
@@ -35,15 +38,26 @@ impl Drop for MetalLibrary {
 }
 
 impl MetalLibrary {
+    /// Sets the label used to identify the library in debugging tools.
+    pub fn set_label(&mut self, label: &str) { set_objc_label(self.library, label) }
+    /// Gets the label (empty if none was set).
+    pub fn get_label(&self) -> String { get_objc_label(self.library) }
     /// Creates an object that represents a shader function in the library.
-    pub fn new_function_with_name(&self, name: &str) -> id {
+    pub fn new_function_with_name(&self, name: &str) -> Result<MetalFunction, MetalError> {
         // id<MTLFunction> vertexFunc = [library newFunctionWithName:@"vertex_main"];
         unsafe {
             let pool =  NSAutoreleasePool::new(nil);
             let function_name = NSString::alloc(nil).init_str(name);
-            let function: id = objc_retain(msg_send![self.library, newFunctionWithName:function_name]);
+            let function: id = msg_send![self.library, newFunctionWithName:function_name];
+            objc_release(function_name);
             pool.drain();
-            function
+            if function == nil {
+                Err(MetalError::FunctionNotFound(name.to_string()))
+            } else {
+                let result = MetalFunction::from(function);
+                objc_release(function);
+                Ok(result)
+            }
         }
     }
 }
@@ -0,0 +1,494 @@
+//
+//  metal_offscreen_target.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Textures to render into other than the view's drawable,
+//! e.g. G-buffers, shadow maps and intermediate post-processing images
+
+use cocoa::foundation::NSUInteger;
+use core_animation::MTLPixelFormat;
+use std::os::raw::c_double;
+use crate::metal_kit::metal_clear_colors::MetalClearColor;
+use crate::metal_kit::metal_device::MetalDevice;
+use crate::metal_kit::metal_error::MetalError;
+use crate::metal_kit::metal_render_pass_descriptor::{
+    MetalRenderPassDescriptor,
+    MTLLoadAction,
+    MTLLoadActionClear,
+    MTLStoreAction,
+    MTLStoreActionDontCare,
+    MTLStoreActionStore,
+};
+use crate::metal_kit::metal_render_target::{MetalRenderTargetCache, MetalRenderTargetSpec};
+use crate::metal_kit::metal_texture::{MetalTexture, MTLTextureUsageRenderTarget, MTLTextureUsageShaderRead};
+use crate::metal_kit::metal_texture_descriptor::{
+    pixel_format_has_depth,
+    pixel_format_has_stencil,
+    MTLPixelFormatX24_Stencil8,
+    MTLPixelFormatX32_Stencil8,
+};
+
+/// The largest number of color attachments a render pass can have.
+pub const MAX_COLOR_ATTACHMENTS: usize = 8;
+
+/// How one color texture of an offscreen target is made and used by a render pass.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct OffscreenColorAttachment {
+    /// The pixel format of the texture.
+    pub pixel_format: MTLPixelFormat,
+    /// What happens to the texture at the start of the pass.
+    pub load_action: MTLLoadAction,
+    /// What happens to the texture at the end of the pass.
+    ///
+    /// When the target is multisampled, store means resolve into the attachment's resolve texture,
+    /// which later passes can sample; use `MTLStoreActionStoreAndMultisampleResolve` to keep the samples too.
+    pub store_action: MTLStoreAction,
+    /// The color the texture is cleared to, if the load action is clear.
+    pub clear_color: MetalClearColor,
+}
+impl OffscreenColorAttachment {
+    /// Creates an attachment that is cleared to transparent black
+    /// at the start of the pass and stored at the end,
+    /// so a later pass can read it.
+    pub fn new(pixel_format: MTLPixelFormat) -> Self {
+        OffscreenColorAttachment {
+            pixel_format,
+            load_action: MTLLoadActionClear,
+            store_action: MTLStoreActionStore,
+            clear_color: MetalClearColor::default(),
+        }
+    }
+}
+
+/// How the depth (and/or stencil) texture of an offscreen target is made and used by a render pass.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct OffscreenDepthAttachment {
+    /// The pixel format of the texture; it may have a stencil component.
+    pub pixel_format: MTLPixelFormat,
+    /// What happens to the texture at the start of the pass.
+    pub load_action: MTLLoadAction,
+    /// What happens to the texture at the end of the pass.
+    pub store_action: MTLStoreAction,
+    /// The depth the texture is cleared to, if the load action is clear.
+    pub clear_depth: c_double,
+    /// The stencil value the texture is cleared to, if it has a stencil component.
+    pub clear_stencil: u32,
+}
+impl OffscreenDepthAttachment {
+    /// Creates an attachment that is cleared to the far plane
+    /// at the start of the pass and thrown away at the end.
+    /// (Set the store action to store for shadow maps.)
+    pub fn new(pixel_format: MTLPixelFormat) -> Self {
+        OffscreenDepthAttachment {
+            pixel_format,
+            load_action: MTLLoadActionClear,
+            store_action: MTLStoreActionDontCare,
+            clear_depth: 1.,
+            clear_stencil: 0,
+        }
+    }
+}
+
+/// The size, sample count and attachments of an offscreen target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OffscreenTargetDescriptor {
+    width: NSUInteger,
+    height: NSUInteger,
+    sample_count: NSUInteger,
+    color_attachments: Vec<OffscreenColorAttachment>,
+    depth_attachment: Option<OffscreenDepthAttachment>,
+}
+impl OffscreenTargetDescriptor {
+    /// Creates a descriptor for a target of the given size with no attachments.
+    pub fn new(width: NSUInteger, height: NSUInteger) -> Self {
+        OffscreenTargetDescriptor {
+            width,
+            height,
+            sample_count: 1,
+            color_attachments: Vec::new(),
+            depth_attachment: None,
+        }
+    }
+    /// Gets the width in pixels.
+    pub fn get_width(&self) -> NSUInteger { self.width }
+    /// Gets the height in pixels.
+    pub fn get_height(&self) -> NSUInteger { self.height }
+    /// Gets the number of samples in each pixel.
+    pub fn get_sample_count(&self) -> NSUInteger { self.sample_count }
+    /// Sets the number of samples in each pixel of every attachment.
+    pub fn set_sample_count(&mut self, sample_count: NSUInteger) {
+        self.sample_count = sample_count.max(1);
+    }
+    /// Adds a color attachment, returning its index in the render pass.
+    pub fn add_color_attachment(&mut self, attachment: OffscreenColorAttachment) -> Result<usize, MetalError> {
+        if self.color_attachments.len() == MAX_COLOR_ATTACHMENTS {
+            return Err(MetalError::TooManyColorAttachments(self.color_attachments.len() + 1));
+        }
+        let format = attachment.pixel_format;
+        if format == 0 || pixel_format_has_depth(format) || pixel_format_has_stencil(format) {
+            return Err(MetalError::InvalidColorAttachmentPixelFormat(format));
+        }
+        self.color_attachments.push(attachment);
+        Ok(self.color_attachments.len() - 1)
+    }
+    /// Gets the color attachments, in index order.
+    pub fn get_color_attachments(&self) -> &[OffscreenColorAttachment] { &self.color_attachments }
+    /// Gets the color attachment at the given index, to change its actions or clear color.
+    pub fn get_color_attachment_mut(&mut self, index: usize) -> Option<&mut OffscreenColorAttachment> {
+        self.color_attachments.get_mut(index)
+    }
+    /// Sets (or replaces) the depth attachment.
+    ///
+    /// The X32_Stencil8 and X24_Stencil8 formats are only for views of depth stencil textures,
+    /// so they cannot be used here.
+    pub fn set_depth_attachment(&mut self, attachment: OffscreenDepthAttachment) -> Result<(), MetalError> {
+        let format = attachment.pixel_format;
+        let view_only = format == MTLPixelFormatX32_Stencil8 || format == MTLPixelFormatX24_Stencil8;
+        if view_only || (!pixel_format_has_depth(format) && !pixel_format_has_stencil(format)) {
+            return Err(MetalError::InvalidDepthAttachmentPixelFormat(format));
+        }
+        self.depth_attachment = Some(attachment);
+        Ok(())
+    }
+    /// Gets the depth attachment, if there is one.
+    pub fn get_depth_attachment(&self) -> Option<&OffscreenDepthAttachment> { self.depth_attachment.as_ref() }
+    /// Removes the depth attachment.
+    pub fn remove_depth_attachment(&mut self) { self.depth_attachment = None; }
+    /// Checks that textures can be made: the target needs a size and at least one attachment.
+    pub fn validate(&self) -> Result<(), MetalError> {
+        if self.width == 0 || self.height == 0 {
+            return Err(MetalError::InvalidRenderTargetSize { width: self.width, height: self.height });
+        }
+        if self.color_attachments.is_empty() && self.depth_attachment.is_none() {
+            return Err(MetalError::NoRenderTargetAttachments);
+        }
+        Ok(())
+    }
+    /// Gets the specification of the texture for the color attachment at the given index.
+    pub fn color_texture_spec(&self, index: usize) -> Option<MetalRenderTargetSpec> {
+        self.color_attachments.get(index).map(|attachment| {
+            MetalRenderTargetSpec::new(self.width, self.height, attachment.pixel_format, self.sample_count)
+        })
+    }
+    /// Gets the specification of the single-sample texture that the color attachment at the given index
+    /// is resolved into, if the target is multisampled.
+    pub fn resolve_texture_spec(&self, index: usize) -> Option<MetalRenderTargetSpec> {
+        self.color_texture_spec(index)
+            .filter(|spec| spec.is_multisampled())
+            .map(|spec| MetalRenderTargetSpec::new(spec.width, spec.height, spec.pixel_format, 1))
+    }
+    /// Gets the specification of the depth texture, if there is one.
+    pub fn depth_texture_spec(&self) -> Option<MetalRenderTargetSpec> {
+        self.depth_attachment.map(|attachment| {
+            MetalRenderTargetSpec::new(self.width, self.height, attachment.pixel_format, self.sample_count)
+        })
+    }
+}
+
+/// A set of textures, up to eight color and one depth,
+/// that render passes can draw into instead of the view's drawable.
+///
+/// When the target is multisampled, each color attachment also gets a single-sample resolve texture,
+/// which is what later passes sample (see `get_resolved_color_texture`).
+/// The textures are only remade when the descriptor changes (e.g. on `resize`).
+/// The texture type is generic so the bookkeeping can be exercised without a GPU.
+pub struct OffscreenTarget<T = MetalTexture> {
+    descriptor: OffscreenTargetDescriptor,
+    color_textures: Vec<MetalRenderTargetCache<T>>,
+    resolve_textures: Vec<MetalRenderTargetCache<T>>,
+    depth_texture: MetalRenderTargetCache<T>,
+}
+impl<T: Default> OffscreenTarget<T> {
+    /// Creates a target with the given attachments.
+    /// No textures are made until `make_textures` is called.
+    pub fn new(descriptor: OffscreenTargetDescriptor) -> Result<Self, MetalError> {
+        descriptor.validate()?;
+        let color_textures = descriptor.color_attachments.iter().map(|_| MetalRenderTargetCache::default()).collect();
+        let resolve_textures = descriptor.color_attachments.iter().map(|_| MetalRenderTargetCache::default()).collect();
+        Ok(OffscreenTarget { descriptor, color_textures, resolve_textures, depth_texture: MetalRenderTargetCache::default() })
+    }
+    /// Gets the descriptor the target was made with.
+    pub fn get_descriptor(&self) -> &OffscreenTargetDescriptor { &self.descriptor }
+    /// Gets the number of color attachments.
+    pub fn color_attachment_count(&self) -> usize { self.color_textures.len() }
+    /// Changes the size of every texture; they are remade by the next call to `make_textures`.
+    pub fn resize(&mut self, width: NSUInteger, height: NSUInteger) -> Result<(), MetalError> {
+        let mut descriptor = self.descriptor.clone();
+        descriptor.width = width;
+        descriptor.height = height;
+        descriptor.validate()?;
+        self.descriptor = descriptor;
+        Ok(())
+    }
+    /// Makes sure every texture matches the descriptor,
+    /// calling `create` for each one that has to be (re)made.
+    ///
+    /// Returns the number of textures made.
+    pub fn make_textures<E, F>(&mut self, mut create: F) -> Result<usize, E>
+        where F: FnMut(&MetalRenderTargetSpec) -> Result<T, E> {
+        let mut made = 0;
+        for (index, cache) in self.color_textures.iter_mut().enumerate() {
+            let spec = self.descriptor.color_texture_spec(index).unwrap();
+            if cache.update(spec, &mut create)? {
+                made += 1;
+            }
+        }
+        for (index, cache) in self.resolve_textures.iter_mut().enumerate() {
+            match self.descriptor.resolve_texture_spec(index) {
+                Some(spec) => if cache.update(spec, &mut create)? {
+                    made += 1;
+                },
+                None => cache.clear(),
+            }
+        }
+        match self.descriptor.depth_texture_spec() {
+            Some(spec) => if self.depth_texture.update(spec, &mut create)? {
+                made += 1;
+            },
+            None => self.depth_texture.clear(),
+        }
+        Ok(made)
+    }
+    /// Gets the texture for the color attachment at the given index,
+    /// if it has been made.
+    pub fn get_color_texture(&self, index: usize) -> Option<&T> {
+        self.color_textures.get(index)
+            .filter(|cache| cache.spec().is_some())
+            .map(|cache| cache.texture())
+    }
+    /// Gets the resolve texture for the color attachment at the given index,
+    /// if the target is multisampled and it has been made.
+    pub fn get_resolve_texture(&self, index: usize) -> Option<&T> {
+        self.resolve_textures.get(index)
+            .filter(|cache| cache.spec().is_some())
+            .map(|cache| cache.texture())
+    }
+    /// Gets the single-sample texture holding the result of the color attachment at the given index,
+    /// for later passes to sample: the resolve texture if the target is multisampled,
+    /// otherwise the color texture itself.
+    pub fn get_resolved_color_texture(&self, index: usize) -> Option<&T> {
+        if self.descriptor.sample_count > 1 {
+            self.get_resolve_texture(index)
+        } else {
+            self.get_color_texture(index)
+        }
+    }
+    /// Gets the depth texture, if there is a depth attachment and it has been made.
+    pub fn get_depth_texture(&self) -> Option<&T> {
+        self.depth_texture.spec().map(|_| self.depth_texture.texture())
+    }
+    /// Returns true if every texture matches the descriptor.
+    pub fn is_complete(&self) -> bool {
+        let colors_complete = self.color_textures.iter().enumerate().all(|(index, cache)| {
+            !cache.needs_rebuild(&self.descriptor.color_texture_spec(index).unwrap())
+        });
+        let resolves_complete = self.resolve_textures.iter().enumerate().all(|(index, cache)| {
+            match self.descriptor.resolve_texture_spec(index) {
+                Some(spec) => !cache.needs_rebuild(&spec),
+                None => true,
+            }
+        });
+        let depth_complete = match self.descriptor.depth_texture_spec() {
+            Some(spec) => !self.depth_texture.needs_rebuild(&spec),
+            None => true,
+        };
+        colors_complete && resolves_complete && depth_complete
+    }
+}
+impl OffscreenTarget<MetalTexture> {
+    /// Makes any missing or out-of-date textures on the given device.
+    /// The textures can be both rendered into and sampled by later passes.
+    pub fn make_textures_on_device(&mut self, device: &MetalDevice) -> Result<usize, MetalError> {
+        self.make_textures(|spec| {
+            let mut descriptor = spec.new_texture_descriptor();
+            descriptor.set_usage(MTLTextureUsageRenderTarget | MTLTextureUsageShaderRead);
+            device.new_texture_with_descriptor(descriptor)
+        })
+    }
+    /// Creates a render pass descriptor that renders into this target's textures,
+    /// with each attachment's load and store actions and clear value.
+    /// Multisampled color attachments that are stored are resolved into their resolve textures.
+    pub fn new_render_pass_descriptor(&self) -> MetalRenderPassDescriptor {
+        let mut pass_descriptor = MetalRenderPassDescriptor::render_pass_descriptor();
+        for (index, attachment) in self.descriptor.color_attachments.iter().enumerate() {
+            let index = index as NSUInteger;
+            if let Some(texture) = self.get_color_texture(index as usize) {
+                pass_descriptor.set_color_attachments_texture(index, texture.to_objc());
+            }
+            let mut store_action = attachment.store_action;
+            if let (Some(spec), Some(resolve_texture)) = (
+                self.descriptor.resolve_texture_spec(index as usize),
+                self.get_resolve_texture(index as usize)
+            ) {
+                pass_descriptor.set_color_attachments_resolve_texture(index, resolve_texture.to_objc());
+                if store_action == MTLStoreActionStore {
+                    store_action = spec.color_store_action();
+                }
+            }
+            pass_descriptor.set_color_attachments_load_action(index, attachment.load_action);
+            pass_descriptor.set_color_attachments_store_action(index, store_action);
+            pass_descriptor.set_color_attachments_clear_color(index, attachment.clear_color);
+        }
+        if let (Some(attachment), Some(texture)) = (self.descriptor.depth_attachment, self.get_depth_texture()) {
+            if pixel_format_has_depth(attachment.pixel_format) {
+                let mut depth_attachment = pass_descriptor.get_depth_attachment();
+                depth_attachment.set_texture(texture);
+                depth_attachment.set_clear_depth(attachment.clear_depth);
+                depth_attachment.set_load_action(attachment.load_action);
+                depth_attachment.set_store_action(attachment.store_action);
+            }
+            if pixel_format_has_stencil(attachment.pixel_format) {
+                let mut stencil_attachment = pass_descriptor.get_stencil_attachment();
+                stencil_attachment.set_texture(texture);
+                stencil_attachment.set_clear_stencil(attachment.clear_stencil);
+                stencil_attachment.set_load_action(attachment.load_action);
+                stencil_attachment.set_store_action(attachment.store_action);
+            }
+        }
+        pass_descriptor
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use cocoa::foundation::NSUInteger;
+    use core_animation::{MTLPixelFormat, MTLPixelFormatBGRA8Unorm};
+
+    // From MTLPixelFormat.h
+    const RGBA16_FLOAT: MTLPixelFormat = 115;
+    const RG16_FLOAT: MTLPixelFormat = 65;
+
+    fn g_buffer() -> OffscreenTargetDescriptor {
+        let mut descriptor = OffscreenTargetDescriptor::new(1024, 768);
+        descriptor.add_color_attachment(OffscreenColorAttachment::new(MTLPixelFormatBGRA8Unorm)).unwrap();
+        descriptor.add_color_attachment(OffscreenColorAttachment::new(RGBA16_FLOAT)).unwrap();
+        descriptor.add_color_attachment(OffscreenColorAttachment::new(RG16_FLOAT)).unwrap();
+        descriptor.set_depth_attachment(OffscreenDepthAttachment::new(MTLPixelFormatDepth32Float)).unwrap();
+        descriptor
+    }
+
+    #[test]
+    fn test_attachment_indices_and_specs() {
+        let descriptor = g_buffer();
+        assert_eq!(3, descriptor.get_color_attachments().len());
+        assert_eq!(Some(MetalRenderTargetSpec::new(1024, 768, RGBA16_FLOAT, 1)), descriptor.color_texture_spec(1));
+        assert_eq!(None, descriptor.color_texture_spec(3));
+        assert_eq!(MTLPixelFormatDepth32Float, descriptor.depth_texture_spec().unwrap().pixel_format);
+
+        let mut descriptor = descriptor;
+        descriptor.set_sample_count(4);
+        assert_eq!(4, descriptor.color_texture_spec(0).unwrap().sample_count);
+        assert_eq!(4, descriptor.depth_texture_spec().unwrap().sample_count);
+        descriptor.set_sample_count(0);
+        assert_eq!(1, descriptor.get_sample_count());
+    }
+
+    #[test]
+    fn test_at_most_eight_color_attachments() {
+        let mut descriptor = OffscreenTargetDescriptor::new(64, 64);
+        for index in 0..MAX_COLOR_ATTACHMENTS {
+            assert_eq!(Ok(index), descriptor.add_color_attachment(OffscreenColorAttachment::new(RGBA16_FLOAT)));
+        }
+        assert_eq!(
+            Err(MetalError::TooManyColorAttachments(9)),
+            descriptor.add_color_attachment(OffscreenColorAttachment::new(RGBA16_FLOAT))
+        );
+    }
+
+    #[test]
+    fn test_format_checks() {
+        let mut descriptor = OffscreenTargetDescriptor::new(64, 64);
+        assert_eq!(
+            Err(MetalError::InvalidColorAttachmentPixelFormat(MTLPixelFormatDepth32Float)),
+            descriptor.add_color_attachment(OffscreenColorAttachment::new(MTLPixelFormatDepth32Float))
+        );
+        assert_eq!(
+            Err(MetalError::InvalidColorAttachmentPixelFormat(0)),
+            descriptor.add_color_attachment(OffscreenColorAttachment::new(0))
+        );
+        assert_eq!(
+            Err(MetalError::InvalidDepthAttachmentPixelFormat(RGBA16_FLOAT)),
+            descriptor.set_depth_attachment(OffscreenDepthAttachment::new(RGBA16_FLOAT))
+        );
+        assert_eq!(
+            Err(MetalError::InvalidDepthAttachmentPixelFormat(MTLPixelFormatX32_Stencil8)),
+            descriptor.set_depth_attachment(OffscreenDepthAttachment::new(MTLPixelFormatX32_Stencil8))
+        );
+        assert_eq!(Ok(()), descriptor.set_depth_attachment(OffscreenDepthAttachment::new(MTLPixelFormatStencil8)));
+        assert_eq!(Ok(()), descriptor.validate());
+
+        descriptor.remove_depth_attachment();
+        assert_eq!(Err(MetalError::NoRenderTargetAttachments), descriptor.validate());
+        assert_eq!(
+            Err(MetalError::InvalidRenderTargetSize { width: 0, height: 64 }),
+            OffscreenTargetDescriptor::new(0, 64).validate()
+        );
+    }
+
+    #[test]
+    fn test_per_attachment_actions() {
+        let mut descriptor = g_buffer();
+        let albedo = descriptor.get_color_attachment_mut(0).unwrap();
+        albedo.load_action = MTLLoadActionLoad;
+        albedo.clear_color = MetalClearColor::make(0.5, 0.5, 0.5, 1.);
+        let attachments = descriptor.get_color_attachments();
+        assert_eq!(MTLLoadActionLoad, attachments[0].load_action);
+        assert_eq!(MTLLoadActionClear, attachments[1].load_action);
+        assert_eq!(MetalClearColor::default(), attachments[1].clear_color);
+        assert_eq!(MTLStoreActionStore, attachments[2].store_action);
+        assert_eq!(MTLStoreActionDontCare, descriptor.get_depth_attachment().unwrap().store_action);
+    }
+
+    #[test]
+    fn test_textures_made_and_remade() {
+        let mut target: OffscreenTarget<u64> = OffscreenTarget::new(g_buffer()).unwrap();
+        assert_eq!(3, target.color_attachment_count());
+        assert!(!target.is_complete());
+        assert_eq!(None, target.get_color_texture(0));
+        assert_eq!(None, target.get_depth_texture());
+
+        let create = |spec: &MetalRenderTargetSpec| Ok::<u64, MetalError>((spec.pixel_format << 32) | spec.width);
+        assert_eq!(Ok(4), target.make_textures(create));
+        assert!(target.is_complete());
+        assert_eq!(Some(&((RGBA16_FLOAT << 32) | 1024)), target.get_color_texture(1));
+        assert_eq!(Ok(0), target.make_textures(create));
+
+        target.resize(512, 384).unwrap();
+        assert!(!target.is_complete());
+        assert_eq!(Ok(4), target.make_textures(create));
+        assert_eq!(Some(&((MTLPixelFormatDepth32Float << 32) | 512)), target.get_depth_texture());
+
+        assert_eq!(
+            Err(MetalError::InvalidRenderTargetSize { width: 0, height: 0 }),
+            target.resize(0, 0)
+        );
+        assert_eq!(512, target.get_descriptor().get_width());
+        assert_eq!(None, target.get_resolve_texture(0));
+        assert_eq!(target.get_color_texture(0), target.get_resolved_color_texture(0));
+    }
+
+    #[test]
+    fn test_multisampled_targets_get_resolve_textures() {
+        let mut descriptor = g_buffer();
+        descriptor.set_sample_count(4);
+        assert_eq!(Some(MetalRenderTargetSpec::new(1024, 768, RGBA16_FLOAT, 1)), descriptor.resolve_texture_spec(1));
+        assert_eq!(MTLStoreActionMultisampleResolve, descriptor.color_texture_spec(1).unwrap().color_store_action());
+        assert_eq!(None, g_buffer().resolve_texture_spec(1));
+
+        let mut target: OffscreenTarget<(MTLPixelFormat, NSUInteger)> = OffscreenTarget::new(descriptor).unwrap();
+        let create = |spec: &MetalRenderTargetSpec| Ok::<_, MetalError>((spec.pixel_format, spec.sample_count));
+        // Three multisample color textures, three resolve textures and a multisample depth texture.
+        assert_eq!(Ok(7), target.make_textures(create));
+        assert!(target.is_complete());
+        assert_eq!(Some(&(RG16_FLOAT, 4)), target.get_color_texture(2));
+        assert_eq!(Some(&(RG16_FLOAT, 1)), target.get_resolve_texture(2));
+        assert_eq!(Some(&(RG16_FLOAT, 1)), target.get_resolved_color_texture(2));
+        assert_eq!(Some(&(MTLPixelFormatDepth32Float, 4)), target.get_depth_texture());
+        assert_eq!(Ok(0), target.make_textures(create));
+    }
+}
@@ -0,0 +1,422 @@
+//
+//  metal_pipeline_cache.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! A cache of render pipeline and depth stencil states,
+//! keyed by plain Rust snapshots of the descriptors they are made from
+
+use cocoa::base::{YES, NO};
+use cocoa::foundation::NSUInteger;
+use core_animation::MTLPixelFormat;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use crate::metal_kit::metal_binary_archive::MetalBinaryArchive;
+use crate::metal_kit::metal_blend_configuration::MetalBlendConfiguration;
+use crate::metal_kit::metal_depth_stencil_descriptor::{MetalDepthStencilDescriptor, MTLCompareFunction, MTLCompareFunctionAlways};
+use crate::metal_kit::metal_depth_stencil_state::MetalDepthStencilState;
+use crate::metal_kit::metal_device::MetalDevice;
+use crate::metal_kit::metal_error::MetalError;
+use crate::metal_kit::metal_library::MetalLibrary;
+use crate::metal_kit::metal_render_pipeline_descriptor::MetalRenderPipelineDescriptor;
+use crate::metal_kit::metal_render_pipeline_state::MetalRenderPipelineState;
+use crate::metal_kit::metal_stencil_descriptor::MetalStencilDescriptor;
+use crate::metal_kit::metal_vertex_descriptor::MetalVertexDescriptor;
+
+/// The settings for one color attachment of a render pipeline.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ColorAttachmentKey {
+    /// The pixel format of the attachment's texture.
+    pub pixel_format: MTLPixelFormat,
+    /// How the fragment color is blended with the attachment's color.
+    pub blending: MetalBlendConfiguration,
+}
+
+/// Everything that goes into a `MetalRenderPipelineDescriptor`,
+/// as a value that can be compared and hashed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RenderPipelineKey {
+    /// The name of the vertex function in the library.
+    pub vertex_function: String,
+    /// The name of the fragment function in the library, if the pipeline has one.
+    pub fragment_function: Option<String>,
+    /// The color attachments, from index 0 on.
+    pub color_attachments: Vec<ColorAttachmentKey>,
+    /// The pixel format of the depth attachment, if any.
+    pub depth_pixel_format: Option<MTLPixelFormat>,
+    /// The pixel format of the stencil attachment, if any.
+    pub stencil_pixel_format: Option<MTLPixelFormat>,
+    /// The number of samples in each pixel of the attachments.
+    pub sample_count: NSUInteger,
+    /// How `[[stage_in]]` vertex attributes are read, if the vertex function uses them.
+    pub vertex_descriptor: Option<MetalVertexDescriptor>,
+    /// Whether the pipeline can be used by commands in an indirect command buffer.
+    pub support_indirect_command_buffers: bool,
+}
+impl RenderPipelineKey {
+    /// A single-sampled pipeline with the given functions and no attachments yet.
+    pub fn new(vertex_function: &str, fragment_function: Option<&str>) -> Self {
+        RenderPipelineKey {
+            vertex_function: vertex_function.to_string(),
+            fragment_function: fragment_function.map(|name| name.to_string()),
+            color_attachments: Vec::new(),
+            depth_pixel_format: None,
+            stencil_pixel_format: None,
+            sample_count: 1,
+            vertex_descriptor: None,
+            support_indirect_command_buffers: false,
+        }
+    }
+    /// Adds a color attachment at the next index.
+    pub fn with_color_attachment(mut self, pixel_format: MTLPixelFormat, blending: MetalBlendConfiguration) -> Self {
+        self.color_attachments.push(ColorAttachmentKey { pixel_format, blending });
+        self
+    }
+    /// Sets the pixel format of the depth attachment.
+    pub fn with_depth_pixel_format(mut self, pixel_format: MTLPixelFormat) -> Self {
+        self.depth_pixel_format = Some(pixel_format);
+        self
+    }
+    /// Sets the pixel format of the stencil attachment.
+    pub fn with_stencil_pixel_format(mut self, pixel_format: MTLPixelFormat) -> Self {
+        self.stencil_pixel_format = Some(pixel_format);
+        self
+    }
+    /// Sets the number of samples in each pixel.
+    pub fn with_sample_count(mut self, sample_count: NSUInteger) -> Self {
+        self.sample_count = sample_count;
+        self
+    }
+    /// Sets how vertex attributes are read.
+    pub fn with_vertex_descriptor(mut self, vertex_descriptor: MetalVertexDescriptor) -> Self {
+        self.vertex_descriptor = Some(vertex_descriptor);
+        self
+    }
+    /// Creates a descriptor with these settings, looking the functions up in the library.
+    pub fn new_descriptor(&self, library: &MetalLibrary) -> Result<MetalRenderPipelineDescriptor, MetalError> {
+        let mut descriptor = MetalRenderPipelineDescriptor::new();
+        descriptor.set_vertex_function(&library.new_function_with_name(&self.vertex_function)?);
+        if let Some(fragment_function) = &self.fragment_function {
+            descriptor.set_fragment_function(&library.new_function_with_name(fragment_function)?);
+        }
+        for (index, attachment) in self.color_attachments.iter().enumerate() {
+            descriptor.set_color_attachment_pixel_format(index as NSUInteger, attachment.pixel_format);
+            descriptor.set_color_attachment_blending(index as NSUInteger, &attachment.blending)?;
+        }
+        if let Some(pixel_format) = self.depth_pixel_format {
+            descriptor.set_depth_attachment_pixel_format(pixel_format);
+        }
+        if let Some(pixel_format) = self.stencil_pixel_format {
+            descriptor.set_stencil_attachment_pixel_format(pixel_format);
+        }
+        descriptor.set_sample_count(self.sample_count);
+        if let Some(vertex_descriptor) = &self.vertex_descriptor {
+            descriptor.set_vertex_descriptor(vertex_descriptor);
+        }
+        descriptor.set_support_indirect_command_buffers(self.support_indirect_command_buffers);
+        Ok(descriptor)
+    }
+}
+
+/// Everything that goes into a `MetalDepthStencilDescriptor`,
+/// as a value that can be compared and hashed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct DepthStencilKey {
+    /// The comparison between a fragment's depth and the stored depth.
+    pub depth_compare_function: MTLCompareFunction,
+    /// Whether passing fragments write their depth.
+    pub depth_write_enabled: bool,
+    /// The stencil test for front-facing primitives, if any.
+    pub front_face_stencil: Option<MetalStencilDescriptor>,
+    /// The stencil test for back-facing primitives, if any.
+    pub back_face_stencil: Option<MetalStencilDescriptor>,
+}
+impl Default for DepthStencilKey {
+    /// Metal's defaults: every fragment passes and nothing is written.
+    fn default() -> Self {
+        DepthStencilKey {
+            depth_compare_function: MTLCompareFunctionAlways,
+            depth_write_enabled: false,
+            front_face_stencil: None,
+            back_face_stencil: None,
+        }
+    }
+}
+impl DepthStencilKey {
+    /// A depth test with the given comparison, and no stencil test.
+    pub fn depth(depth_compare_function: MTLCompareFunction, depth_write_enabled: bool) -> Self {
+        DepthStencilKey { depth_compare_function, depth_write_enabled, ..Default::default() }
+    }
+    /// Uses the same stencil test for front- and back-facing primitives.
+    pub fn with_stencil(mut self, stencil: MetalStencilDescriptor) -> Self {
+        self.front_face_stencil = Some(stencil);
+        self.back_face_stencil = Some(stencil);
+        self
+    }
+    /// Creates a descriptor with these settings.
+    pub fn new_descriptor(&self) -> MetalDepthStencilDescriptor {
+        let mut descriptor = MetalDepthStencilDescriptor::new();
+        descriptor.set_depth_compare_function(self.depth_compare_function);
+        descriptor.set_depth_write_enabled(if self.depth_write_enabled { YES } else { NO });
+        if let Some(stencil) = &self.front_face_stencil {
+            descriptor.set_front_face_stencil(stencil);
+        }
+        if let Some(stencil) = &self.back_face_stencil {
+            descriptor.set_back_face_stencil(stencil);
+        }
+        descriptor
+    }
+}
+
+/// How well a `PipelineCache` has been doing.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct PipelineCacheStats {
+    /// Lookups that found a state already made.
+    pub hits: u64,
+    /// Lookups that had to make a state.
+    pub misses: u64,
+    /// States dropped to keep the cache within its capacity.
+    pub evictions: u64,
+}
+impl PipelineCacheStats {
+    /// The fraction of lookups that were hits (0 if there have been none).
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 { 0.0 } else { self.hits as f64 / lookups as f64 }
+    }
+}
+
+/// States of one kind, dropping the least recently used beyond the capacity.
+#[derive(Debug)]
+struct StateCache<K, V> {
+    capacity: usize,
+    clock: u64,
+    entries: HashMap<K, (Rc<V>, u64)>,
+}
+impl<K: Eq + Hash + Clone, V> StateCache<K, V> {
+    fn new(capacity: usize) -> Self {
+        StateCache { capacity: capacity.max(1), clock: 0, entries: HashMap::new() }
+    }
+    fn get_or_create<E, F>(&mut self, key: &K, stats: &mut PipelineCacheStats, create: F) -> Result<Rc<V>, E>
+        where F: FnOnce(&K) -> Result<V, E> {
+        self.clock += 1;
+        if let Some((state, last_used)) = self.entries.get_mut(key) {
+            *last_used = self.clock;
+            stats.hits += 1;
+            return Ok(state.clone());
+        }
+        stats.misses += 1;
+        let state = Rc::new(create(key)?);
+        if self.entries.len() >= self.capacity {
+            let oldest = self.entries.iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+                stats.evictions += 1;
+            }
+        }
+        self.entries.insert(key.clone(), (state.clone(), self.clock));
+        Ok(state)
+    }
+}
+
+/// Makes each distinct render pipeline and depth stencil state only once,
+/// handing out shared references to the states it has already made.
+///
+/// Each kind of state is kept up to the capacity,
+/// beyond which the least recently used is dropped.
+/// The state types are generic so the bookkeeping can be tested without a GPU.
+pub struct PipelineCache<P = MetalRenderPipelineState, D = MetalDepthStencilState> {
+    render_pipelines: StateCache<RenderPipelineKey, P>,
+    depth_stencils: StateCache<DepthStencilKey, D>,
+    stats: PipelineCacheStats,
+    archive: Option<(MetalBinaryArchive, PathBuf)>,
+}
+impl<P, D> PipelineCache<P, D> {
+    /// An empty cache holding up to `capacity` states of each kind.
+    pub fn new(capacity: usize) -> Self {
+        PipelineCache {
+            render_pipelines: StateCache::new(capacity),
+            depth_stencils: StateCache::new(capacity),
+            stats: PipelineCacheStats::default(),
+            archive: None,
+        }
+    }
+    /// Gets the hit, miss and eviction counts so far.
+    pub fn stats(&self) -> PipelineCacheStats { self.stats }
+    /// Gets the number of render pipeline states held.
+    pub fn render_pipeline_count(&self) -> usize { self.render_pipelines.entries.len() }
+    /// Gets the number of depth stencil states held.
+    pub fn depth_stencil_count(&self) -> usize { self.depth_stencils.entries.len() }
+    /// Returns true if the cache holds a render pipeline state for the key.
+    pub fn contains_render_pipeline(&self, key: &RenderPipelineKey) -> bool {
+        self.render_pipelines.entries.contains_key(key)
+    }
+    /// Returns true if the cache holds a depth stencil state for the key.
+    pub fn contains_depth_stencil(&self, key: &DepthStencilKey) -> bool {
+        self.depth_stencils.entries.contains_key(key)
+    }
+    /// Drops every state held; the statistics are kept.
+    pub fn clear(&mut self) {
+        self.render_pipelines.entries.clear();
+        self.depth_stencils.entries.clear();
+    }
+    /// Gets the render pipeline state for the key,
+    /// calling `create` to make it if the cache does not hold one.
+    ///
+    /// If `create` fails, the error is passed on and nothing is cached.
+    pub fn get_or_create_render_pipeline<E, F>(&mut self, key: &RenderPipelineKey, create: F) -> Result<Rc<P>, E>
+        where F: FnOnce(&RenderPipelineKey) -> Result<P, E> {
+        self.render_pipelines.get_or_create(key, &mut self.stats, create)
+    }
+    /// Gets the depth stencil state for the key,
+    /// calling `create` to make it if the cache does not hold one.
+    ///
+    /// If `create` fails, the error is passed on and nothing is cached.
+    pub fn get_or_create_depth_stencil<E, F>(&mut self, key: &DepthStencilKey, create: F) -> Result<Rc<D>, E>
+        where F: FnOnce(&DepthStencilKey) -> Result<D, E> {
+        self.depth_stencils.get_or_create(key, &mut self.stats, create)
+    }
+}
+impl PipelineCache {
+    /// Keeps the compiled functions of every pipeline made from now on in a binary archive,
+    /// loading it from the given file if it exists.
+    ///
+    /// Call `save_binary_archive` to write it back, so later runs skip compiling them.
+    pub fn open_binary_archive(&mut self, device: &MetalDevice, path: &Path) -> Result<(), MetalError> {
+        let existing = if path.exists() { Some(path) } else { None };
+        let archive = device.new_binary_archive(existing)?;
+        self.archive = Some((archive, path.to_path_buf()));
+        Ok(())
+    }
+    /// Writes the binary archive back to its file, if one has been opened.
+    pub fn save_binary_archive(&self) -> Result<(), MetalError> {
+        match &self.archive {
+            Some((archive, path)) => archive.serialize_to_path(path),
+            None => Ok(()),
+        }
+    }
+    /// Gets the render pipeline state for the key,
+    /// making it from functions in the library if the cache does not hold one.
+    pub fn render_pipeline_state(
+        &mut self,
+        device: &mut MetalDevice,
+        library: &MetalLibrary,
+        key: &RenderPipelineKey
+    ) -> Result<Rc<MetalRenderPipelineState>, MetalError> {
+        let archive = &mut self.archive;
+        self.render_pipelines.get_or_create(key, &mut self.stats, |key| {
+            let mut descriptor = key.new_descriptor(library)?;
+            if let Some((archive, _)) = archive {
+                descriptor.set_binary_archives(&[archive]);
+                archive.add_render_pipeline_functions(&descriptor)?;
+            }
+            device.new_render_pipeline_state_with_descriptor(descriptor)
+        })
+    }
+    /// Gets the depth stencil state for the key,
+    /// making it if the cache does not hold one.
+    pub fn depth_stencil_state(&mut self, device: &MetalDevice, key: &DepthStencilKey) -> Result<Rc<MetalDepthStencilState>, MetalError> {
+        self.depth_stencils.get_or_create(key, &mut self.stats, |key| {
+            device.new_depth_stencil_state_with_descriptor(key.new_descriptor())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use cocoa::foundation::NSUInteger;
+    use core_animation::MTLPixelFormatBGRA8Unorm;
+    use std::collections::HashSet;
+
+    fn cube_pipeline() -> RenderPipelineKey {
+        RenderPipelineKey::new("vertex_project", Some("fragment_flatcolor"))
+            .with_color_attachment(MTLPixelFormatBGRA8Unorm, MetalBlendConfiguration::opaque())
+            .with_depth_pixel_format(MTLPixelFormatDepth32Float)
+    }
+
+    #[test]
+    fn test_key_equality_and_hashing() {
+        let variants = [
+            cube_pipeline(),
+            cube_pipeline().with_sample_count(4),
+            RenderPipelineKey::new("vertex_project", None)
+                .with_color_attachment(MTLPixelFormatBGRA8Unorm, MetalBlendConfiguration::opaque())
+                .with_depth_pixel_format(MTLPixelFormatDepth32Float),
+            RenderPipelineKey::new("vertex_project", Some("fragment_flatcolor"))
+                .with_color_attachment(MTLPixelFormatBGRA8Unorm, MetalBlendConfiguration::alpha())
+                .with_depth_pixel_format(MTLPixelFormatDepth32Float),
+            cube_pipeline().with_vertex_descriptor(MetalVertexDescriptor::default()
+                .with_attribute(0, MTLVertexFormatFloat4, 0, 0)
+                .with_layout(MetalVertexBufferLayout::per_vertex(0, 32))),
+        ];
+        let mut distinct: HashSet<RenderPipelineKey> = variants.iter().cloned().collect();
+        assert_eq!(variants.len(), distinct.len());
+        assert!(!distinct.insert(cube_pipeline()));
+
+        let depth_keys = [
+            DepthStencilKey::default(),
+            DepthStencilKey::depth(MTLCompareFunctionLess, true),
+            DepthStencilKey::depth(MTLCompareFunctionLess, false),
+            DepthStencilKey::depth(MTLCompareFunctionLess, true).with_stencil(MetalStencilDescriptor::write_reference()),
+        ];
+        let mut distinct: HashSet<DepthStencilKey> = depth_keys.iter().cloned().collect();
+        assert_eq!(depth_keys.len(), distinct.len());
+        assert!(!distinct.insert(DepthStencilKey::depth(MTLCompareFunctionLess, true)));
+    }
+
+    #[test]
+    fn test_deduplicates_creation() {
+        let mut cache: PipelineCache<String, u32> = PipelineCache::new(8);
+        let mut created = 0;
+        for _ in 0..3 {
+            let state = cache.get_or_create_render_pipeline(&cube_pipeline(), |key| {
+                created += 1;
+                Ok::<_, MetalError>(key.vertex_function.clone())
+            }).unwrap();
+            assert_eq!("vertex_project", *state);
+        }
+        assert_eq!(1, created);
+        let depth = DepthStencilKey::depth(MTLCompareFunctionLess, true);
+        cache.get_or_create_depth_stencil(&depth, |_| Ok::<_, MetalError>(1)).unwrap();
+        cache.get_or_create_depth_stencil(&depth, |_| Ok::<_, MetalError>(2)).unwrap();
+        assert_eq!(1, cache.render_pipeline_count());
+        assert_eq!(1, cache.depth_stencil_count());
+        assert_eq!(PipelineCacheStats { hits: 3, misses: 2, evictions: 0 }, cache.stats());
+        assert!((cache.stats().hit_rate() - 0.6).abs() < 1e-9);
+
+        // A failed creation is reported and not cached.
+        let failing = cube_pipeline().with_sample_count(4);
+        let result = cache.get_or_create_render_pipeline(&failing, |_| Err(MetalError::DepthStencilStateCreation));
+        assert_eq!(Err(MetalError::DepthStencilStateCreation), result);
+        assert!(!cache.contains_render_pipeline(&failing));
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache: PipelineCache<NSUInteger, ()> = PipelineCache::new(2);
+        let key = |sample_count| cube_pipeline().with_sample_count(sample_count);
+        let create = |key: &RenderPipelineKey| Ok::<_, MetalError>(key.sample_count);
+        cache.get_or_create_render_pipeline(&key(1), create).unwrap();
+        cache.get_or_create_render_pipeline(&key(2), create).unwrap();
+        // Using 1 again makes 2 the least recently used.
+        cache.get_or_create_render_pipeline(&key(1), create).unwrap();
+        cache.get_or_create_render_pipeline(&key(4), create).unwrap();
+        assert_eq!(2, cache.render_pipeline_count());
+        assert!(cache.contains_render_pipeline(&key(1)));
+        assert!(!cache.contains_render_pipeline(&key(2)));
+        assert!(cache.contains_render_pipeline(&key(4)));
+        assert_eq!(PipelineCacheStats { hits: 1, misses: 3, evictions: 1 }, cache.stats());
+
+        cache.clear();
+        assert_eq!(0, cache.render_pipeline_count());
+        assert_eq!(1, cache.stats().evictions);
+    }
+}
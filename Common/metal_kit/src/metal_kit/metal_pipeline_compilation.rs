@@ -0,0 +1,265 @@
+//
+//  metal_pipeline_compilation.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Rust closures and futures for render pipeline states compiled on Metal's own threads,
+//! and the placeholder a renderer keeps until its pipeline is ready
+
+use cocoa::base::{id, nil};
+use std::future::Future;
+use std::os::raw::c_void;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use crate::metal_kit::metal_callback::{run_callback, Completion};
+use crate::metal_kit::metal_error::{MetalError, NSErrorInfo};
+use crate::metal_kit::metal_render_pipeline_state::MetalRenderPipelineState;
+
+/// Called by the device (through GlueLib) with the user data from `callback_into_user_data`,
+/// made from a handler taking the pipeline state or the error.
+/// Metal calls each completion handler exactly once, so the handler is freed exactly once.
+pub(crate) extern "C" fn render_pipeline_handler_trampoline(state: id, error: id, user_data: *const c_void) {
+    let result = if state == nil {
+        Err(MetalError::RenderPipelineStateCreation(NSErrorInfo::from_ns_error_or_unknown(error)))
+    } else {
+        Ok(MetalRenderPipelineState::from(state))
+    };
+    unsafe { run_callback(user_data, result) }
+}
+
+/// A future that resolves to a pipeline state once Metal has compiled it,
+/// made by `MetalDevice::compile_render_pipeline_state`.
+pub struct PipelineCompilation<T = MetalRenderPipelineState> {
+    completion: Completion<Result<T, MetalError>>,
+}
+impl<T: Send + 'static> PipelineCompilation<T> {
+    /// Creates a future together with the handler that completes it.
+    pub(crate) fn new() -> (Self, impl FnOnce(Result<T, MetalError>) + Send + 'static) {
+        let (completion, handler) = Completion::new();
+        (PipelineCompilation { completion }, handler)
+    }
+}
+impl<T> PipelineCompilation<T> {
+    /// Returns true once the compilation has finished, whether or not it worked,
+    /// even after the result has been taken.
+    pub fn is_complete(&self) -> bool { self.completion.is_complete() }
+    /// Takes the result if the compilation has finished, without waiting.
+    pub fn try_take(&mut self) -> Option<Result<T, MetalError>> { self.completion.try_take() }
+}
+impl<T> Future for PipelineCompilation<T> {
+    type Output = Result<T, MetalError>;
+    fn poll(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.get_mut().completion).poll(context)
+    }
+}
+
+/// What the batch future and the completion handlers share.
+struct BatchState<T> {
+    results: Vec<Option<Result<T, MetalError>>>,
+    remaining: usize,
+    waker: Option<Waker>,
+}
+
+/// A future that resolves to every pipeline state in a batch once Metal has compiled them all,
+/// made by `MetalDevice::compile_render_pipeline_states`.
+///
+/// The results are in the same order as the descriptors, whatever order they finished in.
+pub struct PipelineBatch<T = MetalRenderPipelineState> {
+    state: Arc<Mutex<BatchState<T>>>,
+}
+impl<T: Send + 'static> PipelineBatch<T> {
+    /// Creates a future for `count` pipelines, together with the handler
+    /// that reports the result for the pipeline at an index.
+    ///
+    /// Reports after the first for an index are ignored.
+    pub(crate) fn new(count: usize) -> (Self, impl Fn(usize, Result<T, MetalError>) + Clone + Send + Sync + 'static) {
+        let results = (0..count).map(|_| None).collect();
+        let state = Arc::new(Mutex::new(BatchState { results, remaining: count, waker: None }));
+        let handler_state = state.clone();
+        let handler = move |index: usize, result: Result<T, MetalError>| {
+            let waker = {
+                let mut state = handler_state.lock().unwrap_or_else(|error| error.into_inner());
+                match state.results.get_mut(index) {
+                    Some(slot) if slot.is_none() => *slot = Some(result),
+                    _ => return,
+                }
+                state.remaining -= 1;
+                if state.remaining == 0 { state.waker.take() } else { None }
+            };
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        };
+        (PipelineBatch { state }, handler)
+    }
+}
+impl<T> PipelineBatch<T> {
+    /// Gets the number of pipelines in the batch.
+    pub fn len(&self) -> usize {
+        self.state.lock().map(|state| state.results.len()).unwrap_or(0)
+    }
+    /// Returns true if the batch has no pipelines.
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+    /// Gets the number of pipelines that have finished compiling, whether or not they worked.
+    pub fn completed_count(&self) -> usize {
+        self.state.lock().map(|state| state.results.len() - state.remaining).unwrap_or(0)
+    }
+    /// Returns true once every pipeline in the batch has finished compiling.
+    pub fn is_complete(&self) -> bool {
+        self.state.lock().map(|state| state.remaining == 0).unwrap_or(true)
+    }
+}
+impl<T> Future for PipelineBatch<T> {
+    type Output = Vec<Result<T, MetalError>>;
+    fn poll(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap_or_else(|error| error.into_inner());
+        if state.remaining > 0 {
+            state.waker = Some(context.waker().clone());
+            return Poll::Pending;
+        }
+        let results = state.results.iter_mut().filter_map(|slot| slot.take()).collect();
+        Poll::Ready(results)
+    }
+}
+
+/// Where a `PendingPipeline` has got to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PipelineStatus {
+    /// Metal is still compiling the pipeline.
+    Compiling,
+    /// The pipeline can be drawn with.
+    Ready,
+    /// The pipeline could not be compiled.
+    Failed,
+}
+
+/// The three states of a pending pipeline.
+enum PendingState<T> {
+    Compiling(PipelineCompilation<T>),
+    Ready(T),
+    Failed(MetalError),
+}
+
+/// A placeholder for a pipeline state that may still be compiling,
+/// so a renderer can start straight away and skip its draws until the pipeline is ready:
+/// each frame, draw only if `poll_ready` gives back a pipeline.
+pub struct PendingPipeline<T = MetalRenderPipelineState> {
+    state: PendingState<T>,
+}
+impl<T> PendingPipeline<T> {
+    /// A placeholder that becomes ready when the compilation finishes.
+    pub fn new(compilation: PipelineCompilation<T>) -> Self {
+        PendingPipeline { state: PendingState::Compiling(compilation) }
+    }
+    /// A placeholder for a pipeline that is already compiled.
+    pub fn ready(pipeline: T) -> Self {
+        PendingPipeline { state: PendingState::Ready(pipeline) }
+    }
+    /// Picks up the compiled pipeline if the compilation has finished since the last call.
+    fn update(&mut self) {
+        if let PendingState::Compiling(compilation) = &mut self.state {
+            match compilation.try_take() {
+                Some(Ok(pipeline)) => self.state = PendingState::Ready(pipeline),
+                Some(Err(error)) => self.state = PendingState::Failed(error),
+                None => {}
+            }
+        }
+    }
+    /// Gets where the pipeline has got to.
+    pub fn status(&mut self) -> PipelineStatus {
+        self.update();
+        match self.state {
+            PendingState::Compiling(_) => PipelineStatus::Compiling,
+            PendingState::Ready(_) => PipelineStatus::Ready,
+            PendingState::Failed(_) => PipelineStatus::Failed,
+        }
+    }
+    /// Gets the pipeline if it is ready to draw with;
+    /// `None` while it is still compiling or if it failed.
+    pub fn poll_ready(&mut self) -> Option<&T> {
+        self.update();
+        match &self.state {
+            PendingState::Ready(pipeline) => Some(pipeline),
+            _ => None,
+        }
+    }
+    /// Gets the error if the pipeline could not be compiled.
+    pub fn error(&mut self) -> Option<&MetalError> {
+        self.update();
+        match &self.state {
+            PendingState::Failed(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use crate::metal_kit::metal_callback::noop_waker;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    fn failure() -> MetalError {
+        MetalError::RenderPipelineStateCreation(NSErrorInfo::new("CompilerError", 2, "Function vertex_main not found"))
+    }
+
+    #[test]
+    fn test_pending_pipeline_becomes_ready() {
+        let waker = noop_waker();
+        let mut context = Context::from_waker(&waker);
+        let (mut compilation, handler) = PipelineCompilation::<&str>::new();
+        assert_eq!(Poll::Pending, Pin::new(&mut compilation).poll(&mut context));
+        handler(Ok("cube"));
+        assert!(compilation.is_complete());
+        assert_eq!(Poll::Ready(Ok("cube")), Pin::new(&mut compilation).poll(&mut context));
+        assert!(compilation.is_complete());
+        assert_eq!(None, compilation.try_take());
+
+        let (compilation, handler) = PipelineCompilation::<&str>::new();
+        let mut pending = PendingPipeline::new(compilation);
+        assert_eq!(PipelineStatus::Compiling, pending.status());
+        assert_eq!(None, pending.poll_ready());
+        handler(Ok("cube"));
+        assert_eq!(Some(&"cube"), pending.poll_ready());
+        assert_eq!(PipelineStatus::Ready, pending.status());
+        assert_eq!(None, pending.error());
+
+        let (compilation, handler) = PipelineCompilation::<&str>::new();
+        let mut pending = PendingPipeline::new(compilation);
+        handler(Err(failure()));
+        assert_eq!(None, pending.poll_ready());
+        assert_eq!(PipelineStatus::Failed, pending.status());
+        assert_eq!(Some(&failure()), pending.error());
+
+        assert_eq!(PipelineStatus::Ready, PendingPipeline::ready("cube").status());
+    }
+
+    #[test]
+    fn test_batch_keeps_descriptor_order() {
+        let waker = noop_waker();
+        let mut context = Context::from_waker(&waker);
+        let (mut batch, report) = PipelineBatch::<usize>::new(3);
+        assert_eq!(3, batch.len());
+        report(2, Ok(20));
+        report(0, Err(failure()));
+        report(2, Ok(99));
+        assert_eq!(2, batch.completed_count());
+        assert!(!batch.is_complete());
+        assert_eq!(Poll::Pending, Pin::new(&mut batch).poll(&mut context));
+
+        let thread_report = report.clone();
+        std::thread::spawn(move || thread_report(1, Ok(10))).join().unwrap();
+        assert!(batch.is_complete());
+        assert_eq!(Poll::Ready(vec![Err(failure()), Ok(10), Ok(20)]), Pin::new(&mut batch).poll(&mut context));
+
+        let (mut empty, _) = PipelineBatch::<usize>::new(0);
+        assert!(empty.is_empty() && empty.is_complete());
+        assert_eq!(Poll::Ready(vec![]), Pin::new(&mut empty).poll(&mut context));
+    }
+}
@@ -0,0 +1,459 @@
+//
+//  metal_pipeline_reflection.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! What a render pipeline's functions take, copied out of MTLRenderPipelineReflection,
+//! and checks that Rust structs match the buffers the shaders expect
+
+use objc::msg_send;
+use objc::sel;
+use objc::sel_impl;
+use cocoa::base::{id, nil};
+use cocoa::foundation::{NSInteger, NSUInteger};
+use objc::runtime::{BOOL, NO};
+use std::fmt::{Display, Formatter};
+use std::mem::size_of;
+use crate::metal_kit::metal_argument_layout::{MTLBindingAccess, MTLDataType};
+use crate::metal_kit::metal_error::MetalError;
+use crate::metal_kit::ns_string::rust_string_from_ns_string;
+
+// From MTLRenderPipeline.h:
+// typedef NS_OPTIONS(NSUInteger, MTLPipelineOption) {
+//     MTLPipelineOptionNone               = 0,
+//     MTLPipelineOptionArgumentInfo       = 1 << 0,
+//     MTLPipelineOptionBindingInfo        = 1 << 0,
+//     MTLPipelineOptionBufferTypeInfo     = 1 << 1,
+//     ...
+// } API_AVAILABLE(macos(10.11), ios(8.0));
+/// What to report when creating a pipeline.
+pub type MTLPipelineOption = NSUInteger;
+/// Reports nothing.
+#[allow(non_upper_case_globals)]
+pub const MTLPipelineOptionNone: MTLPipelineOption = 0;
+/// Reports the arguments each function takes.
+#[allow(non_upper_case_globals)]
+pub const MTLPipelineOptionBindingInfo: MTLPipelineOption = 1 << 0;
+/// Reports the types of the data in buffer arguments.
+#[allow(non_upper_case_globals)]
+pub const MTLPipelineOptionBufferTypeInfo: MTLPipelineOption = 1 << 1;
+//
+// From MTLArgument.h:
+// typedef NS_ENUM(NSInteger, MTLBindingType) {
+//     MTLBindingTypeBuffer = 0,
+//     MTLBindingTypeThreadgroupMemory = 1,
+//     MTLBindingTypeTexture = 2,
+//     MTLBindingTypeSampler = 3,
+//     ...
+// } API_AVAILABLE(macos(11.0), ios(14.0));
+/// The kind of resource a function argument is bound to.
+pub type MTLBindingType = NSInteger;
+/// A buffer.
+#[allow(non_upper_case_globals)]
+pub const MTLBindingTypeBuffer: MTLBindingType = 0;
+/// Threadgroup memory.
+#[allow(non_upper_case_globals)]
+pub const MTLBindingTypeThreadgroupMemory: MTLBindingType = 1;
+/// A texture.
+#[allow(non_upper_case_globals)]
+pub const MTLBindingTypeTexture: MTLBindingType = 2;
+/// A sampler.
+#[allow(non_upper_case_globals)]
+pub const MTLBindingTypeSampler: MTLBindingType = 3;
+
+/// The data types reflection reports for structs and arrays.
+#[allow(non_upper_case_globals)]
+const MTLDataTypeStruct: MTLDataType = 1;
+#[allow(non_upper_case_globals)]
+const MTLDataTypeArray: MTLDataType = 2;
+
+/// A member of a struct in a buffer argument.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructMemberReflection {
+    /// The member's name in the shader.
+    pub name: String,
+    /// The member's offset in the struct, in bytes.
+    pub offset: NSUInteger,
+    /// The member's type, or the type of each element for an array.
+    pub data_type: MTLDataType,
+    /// The number of elements for an array, or 0.
+    pub array_length: NSUInteger,
+    /// The members of a struct (or of each element of an array of structs).
+    pub members: Vec<StructMemberReflection>,
+}
+
+/// The data a buffer argument points to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BufferReflection {
+    /// The size of the data, in bytes.
+    pub data_size: NSUInteger,
+    /// The alignment of the data, in bytes.
+    pub alignment: NSUInteger,
+    /// The type of the data.
+    pub data_type: MTLDataType,
+    /// The members, if the data is a struct.
+    pub members: Vec<StructMemberReflection>,
+}
+
+/// An argument a pipeline function takes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArgumentReflection {
+    /// The argument's name in the shader.
+    pub name: String,
+    /// The kind of resource bound to the argument.
+    pub binding_type: MTLBindingType,
+    /// The `[[buffer(n)]]`, `[[texture(n)]]` or `[[sampler(n)]]` index.
+    pub index: NSUInteger,
+    /// How the function uses the argument.
+    pub access: MTLBindingAccess,
+    /// Whether the function actually uses the argument.
+    pub is_used: bool,
+    /// What the argument points to, for buffers.
+    pub buffer: Option<BufferReflection>,
+}
+
+/// The arguments taken by the functions of a render pipeline.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PipelineReflection {
+    /// The vertex function's arguments.
+    pub vertex_arguments: Vec<ArgumentReflection>,
+    /// The fragment function's arguments.
+    pub fragment_arguments: Vec<ArgumentReflection>,
+}
+impl PipelineReflection {
+    /// Copies the bindings out of an MTLRenderPipelineReflection.
+    pub(crate) fn from_objc(reflection: id) -> Self {
+        if reflection == nil {
+            return PipelineReflection::default();
+        }
+        let vertex_bindings: id = unsafe { msg_send![reflection, vertexBindings] };
+        let fragment_bindings: id = unsafe { msg_send![reflection, fragmentBindings] };
+        PipelineReflection {
+            vertex_arguments: objects_in_array(vertex_bindings).into_iter().map(argument_from_binding).collect(),
+            fragment_arguments: objects_in_array(fragment_bindings).into_iter().map(argument_from_binding).collect(),
+        }
+    }
+    /// Gets the vertex function's buffer argument at the given index.
+    pub fn vertex_buffer(&self, index: NSUInteger) -> Option<&ArgumentReflection> {
+        find_buffer(&self.vertex_arguments, index)
+    }
+    /// Gets the fragment function's buffer argument at the given index.
+    pub fn fragment_buffer(&self, index: NSUInteger) -> Option<&ArgumentReflection> {
+        find_buffer(&self.fragment_arguments, index)
+    }
+    /// Checks the vertex function's buffer at the given index matches the expected layout.
+    pub fn check_vertex_buffer(&self, index: NSUInteger, expected: &ExpectedBufferLayout) -> Result<(), MetalError> {
+        check_buffer("vertex", self.vertex_buffer(index), index, expected)
+    }
+    /// Checks the fragment function's buffer at the given index matches the expected layout.
+    pub fn check_fragment_buffer(&self, index: NSUInteger, expected: &ExpectedBufferLayout) -> Result<(), MetalError> {
+        check_buffer("fragment", self.fragment_buffer(index), index, expected)
+    }
+}
+
+/// A member the Rust side expects in a buffer's struct.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectedStructMember {
+    /// The member's name in the shader.
+    pub name: String,
+    /// The offset of the matching Rust field, in bytes.
+    pub offset: NSUInteger,
+    /// The type the shader should give the member.
+    pub data_type: MTLDataType,
+}
+
+/// How the Rust side lays out the data it puts in a buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpectedBufferLayout {
+    /// The size of the Rust type, in bytes.
+    pub data_size: NSUInteger,
+    /// The members to check; members the shader has but that aren't listed are reported.
+    pub members: Vec<ExpectedStructMember>,
+}
+impl ExpectedBufferLayout {
+    /// Expects the data to be the size of `T`.
+    ///
+    /// Alignment isn't compared: a buffer is bound at an offset we choose,
+    /// and a Rust type aligned less strictly than the shader's only matters for arrays,
+    /// where it shows up as a difference in size.
+    pub fn of<T>() -> Self {
+        ExpectedBufferLayout { data_size: size_of::<T>() as NSUInteger, members: Vec::new() }
+    }
+    /// Adds a member to check.
+    pub fn with_member(mut self, name: &str, offset: NSUInteger, data_type: MTLDataType) -> Self {
+        self.members.push(ExpectedStructMember { name: name.to_string(), offset, data_type });
+        self
+    }
+}
+
+/// One way a buffer argument differs from the layout the Rust side expects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PipelineLayoutMismatch {
+    /// The function has no buffer at the index.
+    MissingArgument,
+    /// The data is a different size.
+    DataSize {
+        /// The size of the Rust type.
+        expected: NSUInteger,
+        /// The size the shader uses.
+        actual: NSUInteger,
+    },
+    /// An expected member isn't in the shader's struct.
+    MissingMember(String),
+    /// The shader's struct has a member that wasn't expected.
+    UnexpectedMember(String),
+    /// A member is at a different offset.
+    MemberOffset {
+        /// The member's name.
+        name: String,
+        /// The offset of the Rust field.
+        expected: NSUInteger,
+        /// The offset the shader uses.
+        actual: NSUInteger,
+    },
+    /// A member has a different type.
+    MemberDataType {
+        /// The member's name.
+        name: String,
+        /// The type expected.
+        expected: MTLDataType,
+        /// The type the shader uses.
+        actual: MTLDataType,
+    },
+}
+impl Display for PipelineLayoutMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PipelineLayoutMismatch::MissingArgument =>
+                write!(f, "no buffer argument"),
+            PipelineLayoutMismatch::DataSize { expected, actual } =>
+                write!(f, "size {} bytes, expected {}", actual, expected),
+            PipelineLayoutMismatch::MissingMember(name) =>
+                write!(f, "no member {}", name),
+            PipelineLayoutMismatch::UnexpectedMember(name) =>
+                write!(f, "unexpected member {}", name),
+            PipelineLayoutMismatch::MemberOffset { name, expected, actual } =>
+                write!(f, "{} at offset {}, expected {}", name, actual, expected),
+            PipelineLayoutMismatch::MemberDataType { name, expected, actual } =>
+                write!(f, "{} has data type {}, expected {}", name, actual, expected),
+        }
+    }
+}
+
+/// Lists the ways a buffer's data differs from the expected layout.
+pub fn compare_buffer_layout(expected: &ExpectedBufferLayout, actual: &BufferReflection) -> Vec<PipelineLayoutMismatch> {
+    let mut mismatches = Vec::new();
+    if expected.data_size != actual.data_size {
+        mismatches.push(PipelineLayoutMismatch::DataSize { expected: expected.data_size, actual: actual.data_size });
+    }
+    if expected.members.is_empty() {
+        return mismatches;
+    }
+    for member in expected.members.iter() {
+        match actual.members.iter().find(|actual| actual.name == member.name) {
+            None => mismatches.push(PipelineLayoutMismatch::MissingMember(member.name.clone())),
+            Some(actual) => {
+                if actual.offset != member.offset {
+                    mismatches.push(PipelineLayoutMismatch::MemberOffset {
+                        name: member.name.clone(),
+                        expected: member.offset,
+                        actual: actual.offset,
+                    });
+                }
+                if actual.data_type != member.data_type {
+                    mismatches.push(PipelineLayoutMismatch::MemberDataType {
+                        name: member.name.clone(),
+                        expected: member.data_type,
+                        actual: actual.data_type,
+                    });
+                }
+            }
+        }
+    }
+    for actual in actual.members.iter() {
+        if !expected.members.iter().any(|member| member.name == actual.name) {
+            mismatches.push(PipelineLayoutMismatch::UnexpectedMember(actual.name.clone()));
+        }
+    }
+    mismatches
+}
+
+fn find_buffer(arguments: &[ArgumentReflection], index: NSUInteger) -> Option<&ArgumentReflection> {
+    arguments.iter().find(|argument| argument.binding_type == MTLBindingTypeBuffer && argument.index == index)
+}
+
+fn check_buffer(
+    stage: &str,
+    argument: Option<&ArgumentReflection>,
+    index: NSUInteger,
+    expected: &ExpectedBufferLayout
+) -> Result<(), MetalError> {
+    let mismatches = match argument.and_then(|argument| argument.buffer.as_ref()) {
+        Some(buffer) => compare_buffer_layout(expected, buffer),
+        None => vec![PipelineLayoutMismatch::MissingArgument],
+    };
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+    Err(MetalError::PipelineLayoutMismatch { stage: stage.to_string(), index, mismatches })
+}
+
+/// Gets the objects in an NSArray (none for nil).
+fn objects_in_array(array: id) -> Vec<id> {
+    if array == nil {
+        return Vec::new();
+    }
+    let count: NSUInteger = unsafe { msg_send![array, count] };
+    (0..count).map(|index| unsafe { msg_send![array, objectAtIndex:index] }).collect()
+}
+
+fn argument_from_binding(binding: id) -> ArgumentReflection {
+    let name: id = unsafe { msg_send![binding, name] };
+    let binding_type: MTLBindingType = unsafe { msg_send![binding, type] };
+    let used: BOOL = unsafe { msg_send![binding, isUsed] };
+    let buffer = if binding_type == MTLBindingTypeBuffer {
+        let struct_type: id = unsafe { msg_send![binding, bufferStructType] };
+        Some(BufferReflection {
+            data_size: unsafe { msg_send![binding, bufferDataSize] },
+            alignment: unsafe { msg_send![binding, bufferAlignment] },
+            data_type: unsafe { msg_send![binding, bufferDataType] },
+            members: members_of_struct_type(struct_type),
+        })
+    } else {
+        None
+    };
+    ArgumentReflection {
+        name: rust_string_from_ns_string(name),
+        binding_type,
+        index: unsafe { msg_send![binding, index] },
+        access: unsafe { msg_send![binding, access] },
+        is_used: used != NO,
+        buffer,
+    }
+}
+
+fn members_of_struct_type(struct_type: id) -> Vec<StructMemberReflection> {
+    if struct_type == nil {
+        return Vec::new();
+    }
+    let members: id = unsafe { msg_send![struct_type, members] };
+    objects_in_array(members).into_iter().map(|member| {
+        let name: id = unsafe { msg_send![member, name] };
+        let mut data_type: MTLDataType = unsafe { msg_send![member, dataType] };
+        let mut array_length = 0;
+        let mut nested = nil;
+        if data_type == MTLDataTypeStruct {
+            nested = unsafe { msg_send![member, structType] };
+        } else if data_type == MTLDataTypeArray {
+            let array_type: id = unsafe { msg_send![member, arrayType] };
+            array_length = unsafe { msg_send![array_type, arrayLength] };
+            data_type = unsafe { msg_send![array_type, elementType] };
+            if data_type == MTLDataTypeStruct {
+                nested = unsafe { msg_send![array_type, elementStructType] };
+            }
+        }
+        StructMemberReflection {
+            name: rust_string_from_ns_string(name),
+            offset: unsafe { msg_send![member, offset] },
+            data_type,
+            array_length,
+            members: members_of_struct_type(nested),
+        }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn member(name: &str, offset: u64, data_type: MTLDataType) -> StructMemberReflection {
+        StructMemberReflection { name: name.to_string(), offset, data_type, array_length: 0, members: Vec::new() }
+    }
+
+    /// What reflection gives for `struct Vertex { float4 position; float4 color; }` at buffer 0
+    /// and `struct Uniforms { float4x4 modelViewProjectionMatrix; }` at buffer 1.
+    fn reflection() -> PipelineReflection {
+        let buffer = |name: &str, index, data_size, members| ArgumentReflection {
+            name: name.to_string(),
+            binding_type: MTLBindingTypeBuffer,
+            index,
+            access: MTLBindingAccessReadOnly,
+            is_used: true,
+            buffer: Some(BufferReflection { data_size, alignment: 16, data_type: 1, members }),
+        };
+        PipelineReflection {
+            vertex_arguments: vec![
+                buffer("vertices", 0, 32, vec![member("position", 0, MTLDataTypeFloat4), member("color", 16, MTLDataTypeFloat4)]),
+                buffer("uniforms", 1, 64, vec![member("modelViewProjectionMatrix", 0, MTLDataTypeFloat4x4)]),
+            ],
+            fragment_arguments: Vec::new(),
+        }
+    }
+
+    #[repr(C)]
+    struct Vertex {
+        position: [f32; 4],
+        color: [f32; 4],
+    }
+
+    #[test]
+    fn test_matching_layouts_pass() {
+        let reflection = reflection();
+        let vertex = ExpectedBufferLayout::of::<Vertex>()
+            .with_member("position", 0, MTLDataTypeFloat4)
+            .with_member("color", 16, MTLDataTypeFloat4);
+        assert_eq!(Ok(()), reflection.check_vertex_buffer(0, &vertex));
+        // Alignment isn't compared, so a 4-byte aligned matrix is fine.
+        assert_eq!(Ok(()), reflection.check_vertex_buffer(1, &ExpectedBufferLayout::of::<[[f32; 4]; 4]>()));
+        assert_eq!("uniforms", reflection.vertex_buffer(1).unwrap().name);
+    }
+
+    #[test]
+    fn test_mismatches_are_all_reported() {
+        let reflection = reflection();
+        let swapped = ExpectedBufferLayout::of::<[f32; 12]>()
+            .with_member("color", 0, MTLDataTypeFloat4)
+            .with_member("normal", 32, MTLDataTypeFloat3)
+            .with_member("position", 16, MTLDataTypeFloat3);
+        let buffer = reflection.vertex_buffer(0).unwrap().buffer.as_ref().unwrap();
+        assert_eq!(
+            vec![
+                PipelineLayoutMismatch::DataSize { expected: 48, actual: 32 },
+                PipelineLayoutMismatch::MemberOffset { name: String::from("color"), expected: 0, actual: 16 },
+                PipelineLayoutMismatch::MissingMember(String::from("normal")),
+                PipelineLayoutMismatch::MemberOffset { name: String::from("position"), expected: 16, actual: 0 },
+                PipelineLayoutMismatch::MemberDataType {
+                    name: String::from("position"),
+                    expected: MTLDataTypeFloat3,
+                    actual: MTLDataTypeFloat4,
+                },
+            ],
+            compare_buffer_layout(&swapped, buffer)
+        );
+        let partial = ExpectedBufferLayout::of::<Vertex>().with_member("position", 0, MTLDataTypeFloat4);
+        assert_eq!(
+            vec![PipelineLayoutMismatch::UnexpectedMember(String::from("color"))],
+            compare_buffer_layout(&partial, buffer)
+        );
+    }
+
+    #[test]
+    fn test_missing_buffer_is_an_error() {
+        let error = reflection().check_fragment_buffer(1, &ExpectedBufferLayout::of::<u32>()).unwrap_err();
+        assert_eq!(
+            MetalError::PipelineLayoutMismatch {
+                stage: String::from("fragment"),
+                index: 1,
+                mismatches: vec![PipelineLayoutMismatch::MissingArgument],
+            },
+            error
+        );
+        assert_eq!("Pipeline fragment buffer 1 does not match: no buffer argument", error.to_string());
+
+        let error = reflection().check_vertex_buffer(1, &ExpectedBufferLayout::of::<[f32; 4]>()).unwrap_err();
+        assert_eq!("Pipeline vertex buffer 1 does not match: size 64 bytes, expected 16", error.to_string());
+    }
+}
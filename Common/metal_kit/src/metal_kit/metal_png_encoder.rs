@@ -0,0 +1,164 @@
+//
+//  metal_png_encoder.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! A minimal PNG encoder for 8-bit RGBA images,
+//! so screenshots can be written without an image crate
+
+/// The eight bytes every PNG file starts with.
+pub const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// The most bytes a stored (uncompressed) deflate block can hold.
+const MAX_STORED_BLOCK_LENGTH: usize = 65535;
+
+/// Encodes an image as a PNG with 8-bit RGBA pixels.
+///
+/// `rgba` holds `width * height` pixels, row by row from the top, with no padding.
+/// The image data is stored rather than compressed, which keeps the encoder small;
+/// files are about the size of the raw pixels.
+///
+/// # Panics
+/// Panics if `rgba` is not exactly `width * height * 4` bytes long.
+pub fn encode_png_rgba8(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+    let row_length = width as usize * 4;
+    assert_eq!(row_length * height as usize, rgba.len(), "RGBA pixels do not match the image size");
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // Bit depth 8, color type 6 (RGBA), deflate compression, adaptive filtering, no interlace.
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    // Each row starts with its filter type, which is always 0 (none) here.
+    let mut scanlines = Vec::with_capacity((row_length + 1) * height as usize);
+    if row_length > 0 {
+        for row in rgba.chunks_exact(row_length) {
+            scanlines.push(0);
+            scanlines.extend_from_slice(row);
+        }
+    }
+
+    let mut png = PNG_SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib_stored(&scanlines));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+/// Appends a chunk: its length, type, data, and the CRC of the type and data.
+fn write_chunk(png: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(chunk_type);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wraps the data in a zlib stream of stored deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let block_count = data.len().div_ceil(MAX_STORED_BLOCK_LENGTH).max(1);
+    let mut stream = Vec::with_capacity(data.len() + 5 * block_count + 6);
+    // Deflate with a 32K window, no preset dictionary; the header is a multiple of 31.
+    stream.extend_from_slice(&[0x78, 0x01]);
+    let mut blocks = data.chunks(MAX_STORED_BLOCK_LENGTH).peekable();
+    if blocks.peek().is_none() {
+        stream.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = if blocks.peek().is_none() { 1 } else { 0 };
+        let length = block.len() as u16;
+        stream.push(last);
+        stream.extend_from_slice(&length.to_le_bytes());
+        stream.extend_from_slice(&(!length).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+/// The CRC-32 (as used by PNG and zip) of the data.
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+/// The Adler-32 checksum zlib streams end with.
+pub(crate) fn adler32(data: &[u8]) -> u32 {
+    const MODULUS: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % MODULUS;
+        b = (b + a) % MODULUS;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{adler32, crc32, zlib_stored};
+    use crate::*;
+
+    /// Splits a PNG into its chunks, checking each one's CRC.
+    fn chunks(png: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let mut chunks = Vec::new();
+        let mut rest = &png[8..];
+        while !rest.is_empty() {
+            let length = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+            let body = &rest[4..8 + length];
+            let crc = u32::from_be_bytes([rest[8 + length], rest[9 + length], rest[10 + length], rest[11 + length]]);
+            assert_eq!(crc32(body), crc);
+            chunks.push((String::from_utf8(body[..4].to_vec()).unwrap(), body[4..].to_vec()));
+            rest = &rest[12 + length..];
+        }
+        chunks
+    }
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(0xcbf4_3926, crc32(b"123456789"));
+        assert_eq!(0xae42_6082, crc32(b"IEND"));
+        assert_eq!(0, crc32(b""));
+        assert_eq!(0x11e6_0398, adler32(b"Wikipedia"));
+        assert_eq!(1, adler32(b""));
+    }
+
+    #[test]
+    fn test_png_layout() {
+        let pixels = [255, 0, 0, 255, 0, 255, 0, 128];
+        let png = encode_png_rgba8(2, 1, &pixels);
+        assert_eq!(PNG_SIGNATURE, png[..8]);
+        let chunks = chunks(&png);
+        let names: Vec<&str> = chunks.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(vec!["IHDR", "IDAT", "IEND"], names);
+        assert_eq!(vec![0, 0, 0, 2, 0, 0, 0, 1, 8, 6, 0, 0, 0], chunks[0].1);
+        // zlib header, one final stored block of 9 bytes, the filtered row, then the Adler-32.
+        let mut expected = vec![0x78, 0x01, 1, 9, 0, 0xf6, 0xff, 0];
+        expected.extend_from_slice(&pixels);
+        expected.extend_from_slice(&adler32(&[0, 255, 0, 0, 255, 0, 255, 0, 128]).to_be_bytes());
+        assert_eq!(expected, chunks[1].1);
+        assert!(chunks[2].1.is_empty());
+    }
+
+    #[test]
+    fn test_large_data_is_split_into_stored_blocks() {
+        let data = vec![7u8; 65535 + 10];
+        let stream = zlib_stored(&data);
+        assert_eq!(2 + 5 + 65535 + 5 + 10 + 4, stream.len());
+        // The first block is full and not final; the second is final and holds the rest.
+        assert_eq!([0, 0xff, 0xff, 0, 0], stream[2..7]);
+        assert_eq!([1, 10, 0, 0xf5, 0xff], stream[7 + 65535..12 + 65535]);
+        assert_eq!([0x78, 0x01, 1, 0, 0, 0xff, 0xff, 0, 0, 0, 1], zlib_stored(&[])[..]);
+        assert_eq!(0, (0x78 * 256 + 0x01) % 31);
+    }
+}
@@ -13,10 +13,26 @@ use objc::sel;
 use objc::sel_impl;
 use cocoa::base::{id, nil};
 use crate::metal_kit::metal_render_pipeline_state::MetalRenderPipelineState;
-use cocoa::foundation::NSUInteger;
+use cocoa::foundation::{NSInteger, NSUInteger};
 use crate::metal_kit::metal_buffer::MetalBuffer;
-use objc::runtime::{objc_release, objc_retain};
+use objc::runtime::{objc_release, objc_retain, NO, YES};
 use crate::MetalDepthStencilState;
+use crate::metal_kit::metal_draw_arguments::{
+    MTLDrawPrimitivesIndirectArguments,
+    MTLDrawIndexedPrimitivesIndirectArguments,
+    validate_indirect_buffer_range,
+};
+use crate::metal_kit::metal_error::MetalError;
+use crate::metal_kit::metal_debug_group::MetalDebugGroups;
+use crate::metal_kit::metal_argument_encoder::{MetalResource, MTLRenderStages, MTLResourceUsage};
+use crate::metal_kit::metal_heap::MetalHeap;
+use crate::metal_kit::metal_fence::MetalFence;
+use crate::metal_kit::metal_counter_sample_buffer::MetalCounterSampleBuffer;
+use crate::metal_kit::metal_viewport::{MetalScissorRect, MetalViewport};
+use crate::metal_kit::metal_visibility_query_pool::VisibilityQuery;
+use crate::metal_kit::metal_indirect_command_buffer::{ns_range, MetalIndirectCommandBuffer};
+use std::ops::Range;
+use crate::metal_kit::ns_string::{get_objc_label, ns_string_from_str, set_objc_label};
 
 // From System/Library/Frameworks/Metal.framework/Headers/MTLRenderCommandEncoder.h:
 // typedef NS_ENUM(NSUInteger, MTLWinding) {
@@ -50,6 +66,49 @@ pub const MTLCullModeFront: MTLCullMode = 1;
 #[allow(non_upper_case_globals)]
 pub const MTLCullModeBack: MTLCullMode = 2;
 //
+// typedef NS_ENUM(NSUInteger, MTLTriangleFillMode) {
+//     MTLTriangleFillModeFill = 0,
+//     MTLTriangleFillModeLines = 1,
+// } API_AVAILABLE(macos(10.11), ios(8.0));
+/// How triangles are rasterized.
+pub type MTLTriangleFillMode = NSUInteger;
+/// Rasterizes the whole of each triangle.
+#[allow(non_upper_case_globals)]
+pub const MTLTriangleFillModeFill: MTLTriangleFillMode = 0;
+/// Rasterizes only the edges of each triangle, for wireframe views.
+#[allow(non_upper_case_globals)]
+pub const MTLTriangleFillModeLines: MTLTriangleFillMode = 1;
+//
+// typedef NS_ENUM(NSUInteger, MTLDepthClipMode) {
+//     MTLDepthClipModeClip = 0,
+//     MTLDepthClipModeClamp = 1,
+// } API_AVAILABLE(macos(10.11), ios(9.0));
+/// How fragments outside the near and far planes are handled.
+pub type MTLDepthClipMode = NSUInteger;
+/// Clips fragments outside the near and far planes.
+#[allow(non_upper_case_globals)]
+pub const MTLDepthClipModeClip: MTLDepthClipMode = 0;
+/// Clamps the depth of fragments outside the near and far planes, instead of clipping them.
+#[allow(non_upper_case_globals)]
+pub const MTLDepthClipModeClamp: MTLDepthClipMode = 1;
+//
+// typedef NS_ENUM(NSUInteger, MTLVisibilityResultMode) {
+//     MTLVisibilityResultModeDisabled = 0,
+//     MTLVisibilityResultModeBoolean = 1,
+//     MTLVisibilityResultModeCounting = 2,
+// } API_AVAILABLE(macos(10.11), ios(8.0));
+/// What the following draws write to the render pass's visibility result buffer.
+pub type MTLVisibilityResultMode = NSUInteger;
+/// Does not monitor whether samples pass the depth and stencil tests.
+#[allow(non_upper_case_globals)]
+pub const MTLVisibilityResultModeDisabled: MTLVisibilityResultMode = 0;
+/// Writes a non-zero value if any samples pass the depth and stencil tests.
+#[allow(non_upper_case_globals)]
+pub const MTLVisibilityResultModeBoolean: MTLVisibilityResultMode = 1;
+/// Counts the samples that pass the depth and stencil tests.
+#[allow(non_upper_case_globals)]
+pub const MTLVisibilityResultModeCounting: MTLVisibilityResultMode = 2;
+//
 // typedef NS_ENUM(NSUInteger, MTLPrimitiveType) {
 //     MTLPrimitiveTypePoint = 0,
 //     MTLPrimitiveTypeLine = 1,
@@ -59,11 +118,23 @@ pub const MTLCullModeBack: MTLCullMode = 2;
 // } API_AVAILABLE(macos(10.11), ios(8.0));
 /// The geometric primitive type for drawing commands.
 pub type MTLPrimitiveType = NSUInteger;
+/// Rasterize a point at each vertex.
+#[allow(non_upper_case_globals)]
+pub const MTLPrimitiveTypePoint:MTLPrimitiveType = 0;
+/// Rasterize a line between each separate pair of vertices.
+#[allow(non_upper_case_globals)]
+pub const MTLPrimitiveTypeLine:MTLPrimitiveType = 1;
+/// Rasterize a line between each pair of adjacent vertices.
+#[allow(non_upper_case_globals)]
+pub const MTLPrimitiveTypeLineStrip:MTLPrimitiveType = 2;
 /// For every separate set of three vertices, rasterize a triangle.
 /// If the number of vertices is not a multiple of three,
 /// either one or two vertices is ignored.
 #[allow(non_upper_case_globals)]
 pub const MTLPrimitiveTypeTriangle:MTLPrimitiveType = 3;
+/// For every three adjacent vertices, rasterize a triangle.
+#[allow(non_upper_case_globals)]
+pub const MTLPrimitiveTypeTriangleStrip:MTLPrimitiveType = 4;
 
 // From System/Library/Frameworks/Metal.framework/Headers/MTLStageInputOutputDescriptor.h:
 // typedef NS_ENUM(NSUInteger, MTLIndexType) {
@@ -99,6 +170,10 @@ impl Drop for MetalRenderCommandEncoder {
     fn drop(&mut self) { unsafe { objc_release(self.encoder) } }
 }
 impl MetalRenderCommandEncoder {
+    /// Sets the label used to identify the encoder in debugging tools.
+    pub fn set_label(&mut self, label: &str) { set_objc_label(self.encoder, label) }
+    /// Gets the label (empty if none was set).
+    pub fn get_label(&self) -> String { get_objc_label(self.encoder) }
     /// Sets the current render pipeline state object.
     #[inline]
     pub fn set_render_pipeline_state(&mut self, pipeline: &MetalRenderPipelineState) {
@@ -109,11 +184,45 @@ impl MetalRenderCommandEncoder {
     pub fn set_vertex_buffer(&mut self, vertex_buffer: &MetalBuffer, offset:NSUInteger, index: NSUInteger) {
         unsafe { msg_send![self.encoder, setVertexBuffer:vertex_buffer.to_objc() offset:offset atIndex:index] }
     }
+    /// Sets a buffer for the fragment function.
+    #[inline]
+    pub fn set_fragment_buffer(&mut self, fragment_buffer: &MetalBuffer, offset: NSUInteger, index: NSUInteger) {
+        unsafe { msg_send![self.encoder, setFragmentBuffer:fragment_buffer.to_objc() offset:offset atIndex:index] }
+    }
+    /// Makes a resource that the shaders reach through an argument buffer resident
+    /// for the draws that follow.
+    pub fn use_resource<R: MetalResource>(&mut self, resource: &R, usage: MTLResourceUsage, stages: MTLRenderStages) {
+        unsafe { msg_send![self.encoder, useResource:MetalResource::to_objc(resource) usage:usage stages:stages] }
+    }
+    /// Makes all the resources in a heap resident for the draws that follow,
+    /// for the shaders to read through argument buffers.
+    pub fn use_heap(&mut self, heap: &MetalHeap, stages: MTLRenderStages) {
+        unsafe { msg_send![self.encoder, useHeap:heap.to_objc() stages:stages] }
+    }
+    /// Updates the fence once the given stages of the draws encoded so far have finished.
+    pub fn update_fence(&mut self, fence: &MetalFence, after_stages: MTLRenderStages) {
+        unsafe { msg_send![self.encoder, updateFence:fence.to_objc() afterStages:after_stages] }
+    }
+    /// Makes the given stages of the following draws wait until another pass has updated the fence.
+    pub fn wait_for_fence(&mut self, fence: &MetalFence, before_stages: MTLRenderStages) {
+        unsafe { msg_send![self.encoder, waitForFence:fence.to_objc() beforeStages:before_stages] }
+    }
     /// Sets the depth and stencil test state.
     #[inline]
     pub fn set_depth_stencil_state(&mut self, state: &MetalDepthStencilState) {
         unsafe { msg_send![self.encoder, setDepthStencilState:state.to_objc()] }
     }
+    /// Sets the value compared with the stored stencil value
+    /// by the stencil test, for both front- and back-facing primitives.
+    #[inline]
+    pub fn set_stencil_reference_value(&mut self, reference_value: u32) {
+        unsafe { msg_send![self.encoder, setStencilReferenceValue:reference_value] }
+    }
+    /// Sets separate stencil reference values for front- and back-facing primitives.
+    #[inline]
+    pub fn set_stencil_front_and_back_reference_values(&mut self, front_reference_value: u32, back_reference_value: u32) {
+        unsafe { msg_send![self.encoder, setStencilFrontReferenceValue:front_reference_value backReferenceValue:back_reference_value] }
+    }
     /// Sets the winding order of front-facing primitives.
     #[inline]
     pub fn set_front_facing_winding(&mut self, winding: MTLWinding) {
@@ -124,6 +233,70 @@ impl MetalRenderCommandEncoder {
     pub fn set_cull_mode(&mut self, cull_mode: MTLCullMode) {
         unsafe { msg_send![self.encoder, setCullMode:cull_mode] }
     }
+    /// Sets the area of the attachment that the following draws render to.
+    #[inline]
+    pub fn set_viewport(&mut self, viewport: MetalViewport) {
+        unsafe { msg_send![self.encoder, setViewport:viewport] }
+    }
+    /// Sets one viewport for each index a vertex function can select with `[[viewport_array_index]]`.
+    pub fn set_viewports(&mut self, viewports: &[MetalViewport]) {
+        let count = viewports.len() as NSUInteger;
+        unsafe { msg_send![self.encoder, setViewports:viewports.as_ptr() count:count] }
+    }
+    /// Sets the area of the attachment outside which fragments are discarded.
+    ///
+    /// The rectangle must lie within the attachment; see `MetalScissorRect::clamped_to`.
+    #[inline]
+    pub fn set_scissor_rect(&mut self, rect: MetalScissorRect) {
+        unsafe { msg_send![self.encoder, setScissorRect:rect] }
+    }
+    /// Sets one scissor rectangle for each viewport.
+    pub fn set_scissor_rects(&mut self, rects: &[MetalScissorRect]) {
+        let count = rects.len() as NSUInteger;
+        unsafe { msg_send![self.encoder, setScissorRects:rects.as_ptr() count:count] }
+    }
+    /// Sets the bias added to each fragment's depth,
+    /// made of a constant plus a scale of the triangle's slope, and limited to the clamp
+    /// (no limit if zero).
+    #[inline]
+    pub fn set_depth_bias(&mut self, depth_bias: f32, slope_scale: f32, clamp: f32) {
+        unsafe { msg_send![self.encoder, setDepthBias:depth_bias slopeScale:slope_scale clamp:clamp] }
+    }
+    /// Specifies whether to clip or clamp fragments outside the near and far planes.
+    #[inline]
+    pub fn set_depth_clip_mode(&mut self, mode: MTLDepthClipMode) {
+        unsafe { msg_send![self.encoder, setDepthClipMode:mode] }
+    }
+    /// Specifies whether to fill triangles or draw only their edges.
+    #[inline]
+    pub fn set_triangle_fill_mode(&mut self, mode: MTLTriangleFillMode) {
+        unsafe { msg_send![self.encoder, setTriangleFillMode:mode] }
+    }
+    /// Sets the constant color used by blend factors such as `MTLBlendFactorBlendColor`.
+    #[inline]
+    pub fn set_blend_color(&mut self, red: f32, green: f32, blue: f32, alpha: f32) {
+        unsafe { msg_send![self.encoder, setBlendColorRed:red green:green blue:blue alpha:alpha] }
+    }
+    /// Specifies whether, and how, the following draws record whether samples pass,
+    /// at the given offset (a multiple of 8 bytes) in the render pass's visibility result buffer.
+    #[inline]
+    pub fn set_visibility_result_mode(&mut self, mode: MTLVisibilityResultMode, offset: NSUInteger) {
+        unsafe { msg_send![self.encoder, setVisibilityResultMode:mode offset:offset] }
+    }
+    /// Starts recording the following draws into the query's slot;
+    /// turn recording off with `MTLVisibilityResultModeDisabled` once they are encoded.
+    #[inline]
+    pub fn begin_visibility_query(&mut self, mode: MTLVisibilityResultMode, query: &VisibilityQuery) {
+        self.set_visibility_result_mode(mode, query.offset())
+    }
+    /// Encodes a command that writes the counter set's values into the sample buffer at the index,
+    /// optionally waiting for the commands before it to finish first.
+    ///
+    /// The device has to support sampling at `MTLCounterSamplingPointAtDrawBoundary`; see `supports_counter_sampling`.
+    pub fn sample_counters_in_buffer(&mut self, sample_buffer: &MetalCounterSampleBuffer, sample_index: NSUInteger, barrier: bool) {
+        let barrier = if barrier { YES } else { NO };
+        unsafe { msg_send![self.encoder, sampleCountersInBuffer:sample_buffer.to_objc() atSampleIndex:sample_index withBarrier:barrier] }
+    }
     /// Encodes a command to render one instance of primitives using vertex data in contiguous array elements.
     #[inline]
     pub fn draw_primitives(&mut self, primitive: NSUInteger, vertex_start: NSUInteger, vertex_count: NSUInteger) {
@@ -148,9 +321,173 @@ impl MetalRenderCommandEncoder {
                  indexBufferOffset:index_buffer_offset
         ] }
     }
+    /// Encodes a command to render a number of instances of primitives
+    /// using vertex data in contiguous array elements.
+    #[inline]
+    pub fn draw_primitives_with_instance_count(
+        &mut self,
+        primitive_type: MTLPrimitiveType,
+        vertex_start: NSUInteger,
+        vertex_count: NSUInteger,
+        instance_count: NSUInteger
+    ) {
+        unsafe { msg_send![self.encoder,
+            drawPrimitives:primitive_type
+               vertexStart:vertex_start
+               vertexCount:vertex_count
+             instanceCount:instance_count
+        ] }
+    }
+    /// Encodes a command to render a number of instances of primitives
+    /// using vertex data in contiguous array elements,
+    /// starting from the given instance.
+    #[inline]
+    pub fn draw_primitives_with_instance_count_and_base_instance(
+        &mut self,
+        primitive_type: MTLPrimitiveType,
+        vertex_start: NSUInteger,
+        vertex_count: NSUInteger,
+        instance_count: NSUInteger,
+        base_instance: NSUInteger
+    ) {
+        unsafe { msg_send![self.encoder,
+            drawPrimitives:primitive_type
+               vertexStart:vertex_start
+               vertexCount:vertex_count
+             instanceCount:instance_count
+              baseInstance:base_instance
+        ] }
+    }
+    /// Encodes a command to render a number of instances of primitives
+    /// using an index list specified in a buffer.
+    #[inline]
+    pub fn draw_indexed_primitives_with_instance_count(
+        &mut self,
+        primitive_type: MTLPrimitiveType,
+        index_count: NSUInteger,
+        index_type: MTLIndexType,
+        index_buffer: &MetalBuffer,
+        index_buffer_offset: NSUInteger,
+        instance_count: NSUInteger
+    ) {
+        unsafe { msg_send![self.encoder,
+             drawIndexedPrimitives:primitive_type
+                        indexCount:index_count
+                         indexType:index_type
+                       indexBuffer:index_buffer.to_objc()
+                 indexBufferOffset:index_buffer_offset
+                     instanceCount:instance_count
+        ] }
+    }
+    /// Encodes a command to render a number of instances of primitives
+    /// using an index list specified in a buffer,
+    /// adding `base_vertex` to each index and starting from the given instance.
+    #[inline]
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_indexed_primitives_with_instance_count_and_base_vertex_and_base_instance(
+        &mut self,
+        primitive_type: MTLPrimitiveType,
+        index_count: NSUInteger,
+        index_type: MTLIndexType,
+        index_buffer: &MetalBuffer,
+        index_buffer_offset: NSUInteger,
+        instance_count: NSUInteger,
+        base_vertex: NSInteger,
+        base_instance: NSUInteger
+    ) {
+        unsafe { msg_send![self.encoder,
+             drawIndexedPrimitives:primitive_type
+                        indexCount:index_count
+                         indexType:index_type
+                       indexBuffer:index_buffer.to_objc()
+                 indexBufferOffset:index_buffer_offset
+                     instanceCount:instance_count
+                        baseVertex:base_vertex
+                      baseInstance:base_instance
+        ] }
+    }
+    /// Encodes a command to render primitives
+    /// using the `MTLDrawPrimitivesIndirectArguments` at the given offset in a buffer.
+    ///
+    /// Fails, without encoding anything, if the buffer does not hold the arguments.
+    pub fn draw_primitives_indirect(
+        &mut self,
+        primitive_type: MTLPrimitiveType,
+        indirect_buffer: &MetalBuffer,
+        indirect_buffer_offset: NSUInteger
+    ) -> Result<(), MetalError> {
+        validate_indirect_buffer_range::<MTLDrawPrimitivesIndirectArguments>(
+            indirect_buffer.get_length(),
+            indirect_buffer_offset
+        )?;
+        unsafe { msg_send![self.encoder,
+                     drawPrimitives:primitive_type
+                     indirectBuffer:indirect_buffer.to_objc()
+               indirectBufferOffset:indirect_buffer_offset
+        ] }
+        Ok(())
+    }
+    /// Encodes a command to render primitives using an index list specified in a buffer
+    /// and the `MTLDrawIndexedPrimitivesIndirectArguments` at the given offset in another buffer.
+    ///
+    /// Fails, without encoding anything, if the indirect buffer does not hold the arguments.
+    pub fn draw_indexed_primitives_indirect(
+        &mut self,
+        primitive_type: MTLPrimitiveType,
+        index_type: MTLIndexType,
+        index_buffer: &MetalBuffer,
+        index_buffer_offset: NSUInteger,
+        indirect_buffer: &MetalBuffer,
+        indirect_buffer_offset: NSUInteger
+    ) -> Result<(), MetalError> {
+        validate_indirect_buffer_range::<MTLDrawIndexedPrimitivesIndirectArguments>(
+            indirect_buffer.get_length(),
+            indirect_buffer_offset
+        )?;
+        unsafe { msg_send![self.encoder,
+              drawIndexedPrimitives:primitive_type
+                          indexType:index_type
+                        indexBuffer:index_buffer.to_objc()
+                  indexBufferOffset:index_buffer_offset
+                     indirectBuffer:indirect_buffer.to_objc()
+               indirectBufferOffset:indirect_buffer_offset
+        ] }
+        Ok(())
+    }
+    /// Encodes a command to execute a range of the commands in an indirect command buffer.
+    ///
+    /// Use `get_counter().encoded_range()` on the buffer for the commands encoded from the CPU.
+    pub fn execute_commands_in_buffer(
+        &mut self,
+        buffer: &MetalIndirectCommandBuffer,
+        range: Range<NSUInteger>
+    ) -> Result<(), MetalError> {
+        buffer.get_counter().check_range(&range)?;
+        unsafe { msg_send![self.encoder, executeCommandsInBuffer:buffer.to_objc() withRange:ns_range(&range)] }
+        Ok(())
+    }
     /// Declares that all command generation from the encoder is completed.
     #[inline]
     pub fn end_encoding(&mut self) {
         unsafe { msg_send![self.encoder, endEncoding] }
     }
 }
+impl MetalDebugGroups for MetalRenderCommandEncoder {
+    fn push_debug_group(&mut self, name: &str) {
+        let name = ns_string_from_str(name);
+        unsafe {
+            let _:() = msg_send![self.encoder, pushDebugGroup:name];
+            objc_release(name);
+        }
+    }
+    fn pop_debug_group(&mut self) {
+        unsafe { msg_send![self.encoder, popDebugGroup] }
+    }
+    fn insert_debug_signpost(&mut self, name: &str) {
+        let name = ns_string_from_str(name);
+        unsafe {
+            let _:() = msg_send![self.encoder, insertDebugSignpost:name];
+            objc_release(name);
+        }
+    }
+}
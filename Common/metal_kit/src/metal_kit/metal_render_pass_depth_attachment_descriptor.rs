@@ -40,6 +40,10 @@ impl MetalRenderPassDepthAttachment {
     pub fn set_texture(&mut self, texture: &MetalTexture) {
         unsafe { msg_send![self.attachment, setTexture:texture.to_objc()] }
     }
+    /// Sets the texture the multisample depth texture is resolved into at the end of the pass.
+    pub fn set_resolve_texture(&mut self, texture: &MetalTexture) {
+        unsafe { msg_send![self.attachment, setResolveTexture:texture.to_objc()] }
+    }
     /// Sets the depth to use when the depth render target is cleared.
     pub fn set_clear_depth(&mut self, clear_depth: c_double) {
         unsafe { msg_send![self.attachment, setClearDepth:clear_depth]}
@@ -16,7 +16,10 @@ use cocoa::base::{id, nil};
 use objc::runtime::{objc_retain, objc_release};
 use cocoa::foundation::NSUInteger;
 use crate::MetalClearColor;
+use crate::metal_kit::metal_buffer::MetalBuffer;
+use crate::metal_kit::metal_counter_sample_buffer::{MetalCounterSampleBuffer, MTLCounterDontSample};
 use crate::metal_kit::metal_render_pass_depth_attachment_descriptor::MetalRenderPassDepthAttachment;
+use crate::metal_kit::metal_render_pass_stencil_attachment_descriptor::MetalRenderPassStencilAttachment;
 
 // From System/Library/Frameworks/Metal.framework/Headers/MTLRenderPass.h:
 // typedef NS_ENUM(NSUInteger, MTLLoadAction) {
@@ -24,9 +27,18 @@ use crate::metal_kit::metal_render_pass_depth_attachment_descriptor::MetalRender
 //     MTLLoadActionLoad = 1,
 //     MTLLoadActionClear = 2,
 // } API_AVAILABLE(macos(10.11), ios(8.0));
+/// The action performed at the start of a rendering pass for a render command encoder.
+pub type MTLLoadAction = NSUInteger;
+#[allow(non_upper_case_globals)]
+/// Each pixel in the attachment is allowed to take on any value
+/// at the start of the rendering pass.
+pub const MTLLoadActionDontCare: MTLLoadAction = 0;
+#[allow(non_upper_case_globals)]
+/// The existing contents of the texture are preserved.
+pub const MTLLoadActionLoad: MTLLoadAction = 1;
 #[allow(non_upper_case_globals)]
 /// A value is written to every pixel in the specified attachment.
-pub const MTLLoadActionClear: NSUInteger = 2;
+pub const MTLLoadActionClear: MTLLoadAction = 2;
 // typedef NS_ENUM(NSUInteger, MTLStoreAction) {
 //     MTLStoreActionDontCare = 0,
 //     MTLStoreActionStore = 1,
@@ -35,13 +47,23 @@ pub const MTLLoadActionClear: NSUInteger = 2;
 //     MTLStoreActionUnknown API_AVAILABLE(macos(10.12), ios(10.0)) = 4,
 //     MTLStoreActionCustomSampleDepthStore API_AVAILABLE(macos(10.13), ios(11.0)) = 5,
 // } API_AVAILABLE(macos(10.11), ios(8.0));
+/// The action performed at the end of a rendering pass for a render command encoder.
+pub type MTLStoreAction = NSUInteger;
 #[allow(non_upper_case_globals)]
 /// Each pixel in the attachment is allowed to take on any value
 /// at the start of the rendering pass.
-pub const MTLStoreActionDontCare: NSUInteger = 0;
+pub const MTLStoreActionDontCare: MTLStoreAction = 0;
 #[allow(non_upper_case_globals)]
 /// The final results of the rendering pass are stored in the attachment.
-pub const MTLStoreActionStore: NSUInteger = 1;
+pub const MTLStoreActionStore: MTLStoreAction = 1;
+#[allow(non_upper_case_globals)]
+/// The multisample values are resolved into the resolve texture,
+/// and the multisample values themselves are discarded.
+pub const MTLStoreActionMultisampleResolve: MTLStoreAction = 2;
+#[allow(non_upper_case_globals)]
+/// The multisample values are both stored in the attachment
+/// and resolved into the resolve texture.
+pub const MTLStoreActionStoreAndMultisampleResolve: MTLStoreAction = 3;
 
 /// Rust wrapper for a group of render targets
 /// that hold the results of a render pass.
@@ -93,6 +115,12 @@ impl MetalRenderPassDescriptor {
         unsafe { msg_send![color_attachment, setClearColor:clear_color] }
     }
     /// Sets, for the render target at the given index,
+    /// the texture the multisample texture is resolved into at the end of the pass.
+    pub fn set_color_attachments_resolve_texture(&mut self, index: NSUInteger, resolve_texture: id) {
+        let color_attachment = self.get_color_attachments_for_index(index);
+        unsafe { msg_send![color_attachment, setResolveTexture:resolve_texture] }
+    }
+    /// Sets, for the render target at the given index,
     /// the action performed by this attachment at the end of a rendering pass
     /// for a render command encoder.
     pub fn set_color_attachments_store_action(&mut self, index: NSUInteger, action: NSUInteger) {
@@ -113,9 +141,45 @@ impl MetalRenderPassDescriptor {
         let color_attachment = unsafe { msg_send![color_attachments, objectAtIndexedSubscript:index] };
         color_attachment
     }
+    /// Sets the buffer that draws write their visibility results into.
+    pub fn set_visibility_result_buffer(&mut self, buffer: &MetalBuffer) {
+        unsafe { msg_send![self.descriptor, setVisibilityResultBuffer:buffer.to_objc()] }
+    }
+    /// Gets the buffer that draws write their visibility results into.
+    pub fn get_visibility_result_buffer(&self) -> MetalBuffer {
+        let buffer: id = unsafe { msg_send![self.descriptor, visibilityResultBuffer] };
+        MetalBuffer::from(buffer)
+    }
+    /// Sets, for the sample buffer attachment at the given index, the buffer and the sample indices
+    /// to take counter samples at when the pass starts its vertex stage and ends its fragment stage
+    /// (`MTLCounterDontSample` to skip one).
+    ///
+    /// The device has to support `MTLCounterSamplingPointAtStageBoundary`.
+    pub fn set_sample_buffer_attachment(
+        &mut self,
+        index: NSUInteger,
+        sample_buffer: &MetalCounterSampleBuffer,
+        start_of_vertex_sample_index: NSUInteger,
+        end_of_fragment_sample_index: NSUInteger
+    ) {
+        let attachments: id = unsafe { msg_send![self.descriptor, sampleBufferAttachments] };
+        let attachment: id = unsafe { msg_send![attachments, objectAtIndexedSubscript:index] };
+        unsafe {
+            let _:() = msg_send![attachment, setSampleBuffer:sample_buffer.to_objc()];
+            let _:() = msg_send![attachment, setStartOfVertexSampleIndex:start_of_vertex_sample_index];
+            let _:() = msg_send![attachment, setEndOfVertexSampleIndex:MTLCounterDontSample];
+            let _:() = msg_send![attachment, setStartOfFragmentSampleIndex:MTLCounterDontSample];
+            let _:() = msg_send![attachment, setEndOfFragmentSampleIndex:end_of_fragment_sample_index];
+        }
+    }
     /// Gets the depth attachment for this render pass.
     pub fn get_depth_attachment(&self) -> MetalRenderPassDepthAttachment {
         let depth_attachment:id = unsafe { msg_send![self.descriptor, depthAttachment] };
         MetalRenderPassDepthAttachment::from(depth_attachment)
     }
+    /// Gets the stencil attachment for this render pass.
+    pub fn get_stencil_attachment(&self) -> MetalRenderPassStencilAttachment {
+        let stencil_attachment:id = unsafe { msg_send![self.descriptor, stencilAttachment] };
+        MetalRenderPassStencilAttachment::from(stencil_attachment)
+    }
 }
@@ -0,0 +1,57 @@
+//
+//  metal_render_pass_stencil_attachment_descriptor.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Thin wrappers for MTLRenderPassStencilAttachmentDescriptor
+
+use objc::msg_send;
+use objc::sel;
+use objc::sel_impl;
+use cocoa::base::{id, nil};
+use objc::runtime::{objc_retain, objc_release};
+use crate::MetalTexture;
+use cocoa::foundation::NSUInteger;
+
+/// A stencil render target that serves as the output destination
+/// for stencil pixels generated by a render pass.
+pub struct MetalRenderPassStencilAttachment {
+    attachment: id
+}
+impl Default for MetalRenderPassStencilAttachment {
+    fn default() -> Self { MetalRenderPassStencilAttachment { attachment: nil } }
+}
+impl From<id> for MetalRenderPassStencilAttachment {
+    fn from(attachment:id) -> Self {
+        let attachment = unsafe { objc_retain(attachment) };
+        MetalRenderPassStencilAttachment { attachment }
+    }
+}
+impl Drop for MetalRenderPassStencilAttachment {
+    fn drop(&mut self) { unsafe { objc_release(self.attachment) } }
+}
+
+impl MetalRenderPassStencilAttachment {
+    /// Sets the texture object associated with this attachment.
+    ///
+    /// For combined formats such as Depth32Float_Stencil8
+    /// this is the same texture as the depth attachment's.
+    pub fn set_texture(&mut self, texture: &MetalTexture) {
+        unsafe { msg_send![self.attachment, setTexture:texture.to_objc()] }
+    }
+    /// Sets the value to use when the stencil render target is cleared.
+    pub fn set_clear_stencil(&mut self, clear_stencil: u32) {
+        unsafe { msg_send![self.attachment, setClearStencil:clear_stencil] }
+    }
+    /// Sets the load action associated with this attachment.
+    pub fn set_load_action(&mut self, load_action:NSUInteger) {
+        unsafe { msg_send![self.attachment, setLoadAction:load_action] }
+    }
+    /// Sets the store action associated with this attachment.
+    pub fn set_store_action(&mut self, store_action:NSUInteger) {
+        unsafe { msg_send![self.attachment, setStoreAction:store_action] }
+    }
+}
@@ -14,8 +14,15 @@ use objc::sel;
 use objc::sel_impl;
 use cocoa::base::{id, nil};
 use objc::runtime::{objc_retain, objc_release};
-use cocoa::foundation::{NSUInteger, NSAutoreleasePool};
+use cocoa::foundation::{NSArray, NSUInteger, NSAutoreleasePool};
 use core_animation::MTLPixelFormat;
+use crate::metal_kit::metal_function::MetalFunction;
+use crate::metal_kit::metal_blend_configuration::MetalBlendConfiguration;
+use crate::metal_kit::metal_error::MetalError;
+use crate::metal_kit::metal_binary_archive::MetalBinaryArchive;
+use crate::metal_kit::metal_vertex_descriptor::MetalVertexDescriptor;
+use cocoa::base::{YES, NO};
+use crate::metal_kit::ns_string::{get_objc_label, set_objc_label};
 
 /// Rust wrapper for an argument of options
 /// you pass to a device to get a render pipeline state object.
@@ -38,6 +45,10 @@ impl Drop for MetalRenderPipelineDescriptor {
 }
 
 impl MetalRenderPipelineDescriptor {
+    /// Sets the label used to identify the pipeline state made from this descriptor in debugging tools.
+    pub fn set_label(&mut self, label: &str) { set_objc_label(self.descriptor, label) }
+    /// Gets the label (empty if none was set).
+    pub fn get_label(&self) -> String { get_objc_label(self.descriptor) }
     /// Creates a new MetalRenderPipelineDescriptor
     pub fn new() -> Self {
         let pool = unsafe { NSAutoreleasePool::new(nil) };
@@ -47,6 +58,8 @@ impl MetalRenderPipelineDescriptor {
         unsafe { pool.drain() };
         descriptor
     }
+    /// Returns the underlying Objective C descriptor.
+    pub fn to_objc(&self) -> id { self.descriptor }
     /// Sets, for the render target at the given index,
     /// the pixel format of the color attachment’s texture.
     pub fn set_color_attachment_pixel_format(&mut self, index: NSUInteger, pixel_format: MTLPixelFormat) {
@@ -58,20 +71,78 @@ impl MetalRenderPipelineDescriptor {
             pool.drain();
         }
     }
+    /// Sets, for the render target at the given index,
+    /// how the fragment color is blended with the attachment's color.
+    ///
+    /// The configuration is checked first, and nothing is changed if it is invalid.
+    pub fn set_color_attachment_blending(&mut self, index: NSUInteger, blending: &MetalBlendConfiguration) -> Result<(), MetalError> {
+        blending.validate()?;
+        unsafe {
+            let pool = NSAutoreleasePool::new(nil);
+            let colour_attachments_array: id = msg_send![self.descriptor, colorAttachments];
+            let colour_attachment_element: id = msg_send![colour_attachments_array, objectAtIndexedSubscript:index];
+            let enabled = if blending.blending_enabled { YES } else { NO };
+            let _:() = msg_send![colour_attachment_element, setBlendingEnabled:enabled];
+            let _:() = msg_send![colour_attachment_element, setRgbBlendOperation:blending.rgb_blend_operation];
+            let _:() = msg_send![colour_attachment_element, setAlphaBlendOperation:blending.alpha_blend_operation];
+            let _:() = msg_send![colour_attachment_element, setSourceRGBBlendFactor:blending.source_rgb_blend_factor];
+            let _:() = msg_send![colour_attachment_element, setSourceAlphaBlendFactor:blending.source_alpha_blend_factor];
+            let _:() = msg_send![colour_attachment_element, setDestinationRGBBlendFactor:blending.destination_rgb_blend_factor];
+            let _:() = msg_send![colour_attachment_element, setDestinationAlphaBlendFactor:blending.destination_alpha_blend_factor];
+            let _:() = msg_send![colour_attachment_element, setWriteMask:blending.write_mask];
+            pool.drain();
+        }
+        Ok(())
+    }
     /// Sets the pixel format of the attachment that stores depth data.
     pub fn set_depth_attachment_pixel_format(&mut self, pixel_format: MTLPixelFormat) {
         unsafe { msg_send![self.descriptor, setDepthAttachmentPixelFormat:pixel_format] }
     }
+    /// Sets the pixel format of the attachment that stores stencil data.
+    ///
+    /// For combined formats such as Depth32Float_Stencil8
+    /// this is the same as the depth attachment pixel format.
+    pub fn set_stencil_attachment_pixel_format(&mut self, pixel_format: MTLPixelFormat) {
+        unsafe { msg_send![self.descriptor, setStencilAttachmentPixelFormat:pixel_format] }
+    }
+    /// Sets the number of samples in each pixel of the attachments.
+    /// This must match the sample count of the textures the pipeline renders into.
+    pub fn set_sample_count(&mut self, sample_count: NSUInteger) {
+        unsafe { msg_send![self.descriptor, setRasterSampleCount:sample_count] }
+    }
+    /// Sets whether the pipeline can be used by commands in an indirect command buffer.
+    pub fn set_support_indirect_command_buffers(&mut self, supported: bool) {
+        unsafe { msg_send![self.descriptor, setSupportIndirectCommandBuffers:if supported { YES } else { NO }] }
+    }
+    /// Sets how the vertex function's `[[stage_in]]` attributes are read from the vertex buffers.
+    pub fn set_vertex_descriptor(&mut self, vertex_descriptor: &MetalVertexDescriptor) {
+        let vertex_descriptor = vertex_descriptor.new_objc();
+        unsafe {
+            let _:() = msg_send![self.descriptor, setVertexDescriptor:vertex_descriptor];
+            objc_release(vertex_descriptor);
+        }
+    }
+    /// Sets the archives searched for already compiled functions
+    /// before the pipeline's functions are compiled.
+    pub fn set_binary_archives(&mut self, archives: &[&MetalBinaryArchive]) {
+        let archives: Vec<id> = archives.iter().map(|archive| archive.to_objc()).collect();
+        unsafe {
+            let pool = NSAutoreleasePool::new(nil);
+            let archives = NSArray::arrayWithObjects(nil, &archives);
+            let _:() = msg_send![self.descriptor, setBinaryArchives:archives];
+            pool.drain();
+        }
+    }
     /// Sets a programmable function that processes individual vertices in a rendering pass.
-    pub fn set_vertex_function(&mut self, vertex_function: id) {
+    pub fn set_vertex_function(&mut self, vertex_function: &MetalFunction) {
         unsafe {
-            let _:() = msg_send![self.descriptor, setVertexFunction:vertex_function];
+            let _:() = msg_send![self.descriptor, setVertexFunction:vertex_function.to_objc()];
         }
     }
     /// Sets a programmable function that processes individual fragments in a rendering pass.
-    pub fn set_fragment_function(&mut self, fragment_function: id) {
+    pub fn set_fragment_function(&mut self, fragment_function: &MetalFunction) {
         unsafe {
-            let _:() = msg_send![self.descriptor, setFragmentFunction:fragment_function];
+            let _:() = msg_send![self.descriptor, setFragmentFunction:fragment_function.to_objc()];
         }
     }
 }
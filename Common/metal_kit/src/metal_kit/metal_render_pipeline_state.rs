@@ -10,6 +10,7 @@
 
 use cocoa::base::{id, nil};
 use objc::runtime::{objc_release, objc_retain};
+use crate::metal_kit::ns_string::get_objc_label;
 
 /// Rust wrapper for an object that contains
 /// the graphics functions and configuration state
@@ -31,8 +32,15 @@ impl From<id> for MetalRenderPipelineState {
 impl Drop for MetalRenderPipelineState {
     fn drop(&mut self) { unsafe { objc_release(self.state) } }
 }
+// Pipeline states are immutable once made, and Metal documents them as safe
+// to share between threads, so they can be handed back from Metal's compiler threads.
+unsafe impl Send for MetalRenderPipelineState {}
+unsafe impl Sync for MetalRenderPipelineState {}
 
 impl MetalRenderPipelineState {
+    /// Gets the label used to identify the pipeline state in debugging tools,
+    /// copied from the descriptor it was made from (empty if none was set).
+    pub fn get_label(&self) -> String { get_objc_label(self.state) }
     /// Returns the underlying objective c pipeline state
     pub fn to_objc(&self) -> id { self.state }
 }
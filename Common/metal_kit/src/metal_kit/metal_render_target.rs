@@ -0,0 +1,204 @@
+//
+//  metal_render_target.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Bookkeeping for the textures a view renders into,
+//! including the choice of sample count for multisample anti-aliasing
+
+use cocoa::base::NO;
+use cocoa::foundation::NSUInteger;
+use core_animation::MTLPixelFormat;
+use crate::metal_kit::metal_texture::MTLTextureUsageRenderTarget;
+use crate::metal_kit::metal_texture_descriptor::{
+    MetalTextureDescriptor,
+    MTLStorageModePrivate,
+    MTLTextureType,
+    MTLTextureType2D,
+    MTLTextureType2DMultisample,
+};
+use crate::metal_kit::metal_render_pass_descriptor::{MTLStoreActionMultisampleResolve, MTLStoreActionStore};
+
+/// Chooses the number of samples per pixel to use:
+/// the largest power of two, no bigger than the one requested,
+/// that the device supports.
+///
+/// Falls back to 1 (no multisampling), which every device supports.
+pub fn choose_sample_count<F: Fn(NSUInteger) -> bool>(requested: NSUInteger, is_supported: F) -> NSUInteger {
+    let mut sample_count = if requested <= 1 { 1 } else { 1 << (usize::BITS - 1 - requested.leading_zeros()) };
+    while sample_count > 1 && !is_supported(sample_count) {
+        sample_count /= 2;
+    }
+    sample_count
+}
+
+/// What a render target texture has to look like.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MetalRenderTargetSpec {
+    /// The width in pixels.
+    pub width: NSUInteger,
+    /// The height in pixels.
+    pub height: NSUInteger,
+    /// The pixel format.
+    pub pixel_format: MTLPixelFormat,
+    /// The number of samples in each pixel (1 for no multisampling).
+    pub sample_count: NSUInteger,
+}
+impl MetalRenderTargetSpec {
+    /// Creates a specification from its parts.
+    pub fn new(width: NSUInteger, height: NSUInteger, pixel_format: MTLPixelFormat, sample_count: NSUInteger) -> Self {
+        MetalRenderTargetSpec { width, height, pixel_format, sample_count }
+    }
+    /// Returns true if the texture has more than one sample per pixel.
+    pub fn is_multisampled(&self) -> bool { self.sample_count > 1 }
+    /// Returns the texture type that matches the sample count.
+    pub fn texture_type(&self) -> MTLTextureType {
+        if self.is_multisampled() { MTLTextureType2DMultisample } else { MTLTextureType2D }
+    }
+    /// Returns the store action for a color attachment with this texture:
+    /// multisample textures are resolved, others are stored.
+    pub fn color_store_action(&self) -> NSUInteger {
+        if self.is_multisampled() { MTLStoreActionMultisampleResolve } else { MTLStoreActionStore }
+    }
+    /// Returns true if no texture can be made, because a dimension is zero.
+    pub fn is_empty(&self) -> bool { self.width == 0 || self.height == 0 }
+    /// Creates a descriptor for a private, render-target-only texture with this specification.
+    pub fn new_texture_descriptor(&self) -> MetalTextureDescriptor {
+        let mut descriptor = MetalTextureDescriptor::texture_2d_descriptor_with_pixel_format_and_width_and_height_and_mipmapped(
+            self.pixel_format,
+            self.width,
+            self.height,
+            NO
+        );
+        descriptor.set_texture_type(self.texture_type());
+        descriptor.set_sample_count(self.sample_count);
+        descriptor.set_usage(MTLTextureUsageRenderTarget);
+        descriptor.set_storage_mode(MTLStorageModePrivate);
+        descriptor
+    }
+}
+
+/// Holds a render target texture together with the specification it was made to,
+/// so it is only remade when the specification changes (e.g. the view is resized).
+#[derive(Debug, Default)]
+pub struct MetalRenderTargetCache<T> {
+    spec: Option<MetalRenderTargetSpec>,
+    texture: T,
+}
+impl<T: Default> MetalRenderTargetCache<T> {
+    /// Gets the current texture (the default value if none has been made).
+    pub fn texture(&self) -> &T { &self.texture }
+    /// Gets the specification the current texture was made to, if any.
+    pub fn spec(&self) -> Option<&MetalRenderTargetSpec> { self.spec.as_ref() }
+    /// Returns true if the current texture does not match the given specification.
+    pub fn needs_rebuild(&self, spec: &MetalRenderTargetSpec) -> bool {
+        self.spec.as_ref() != Some(spec)
+    }
+    /// Makes sure the texture matches the given specification,
+    /// calling `create` to make a new one if it does not.
+    ///
+    /// Returns true if a new texture was made.
+    /// If `create` fails, the cache is cleared and the error is passed on,
+    /// so the next update tries again.
+    pub fn update<E, F>(&mut self, spec: MetalRenderTargetSpec, create: F) -> Result<bool, E>
+        where F: FnOnce(&MetalRenderTargetSpec) -> Result<T, E> {
+        if !self.needs_rebuild(&spec) {
+            return Ok(false);
+        }
+        self.clear();
+        self.texture = create(&spec)?;
+        self.spec = Some(spec);
+        Ok(true)
+    }
+    /// Drops the current texture.
+    pub fn clear(&mut self) {
+        self.spec = None;
+        self.texture = T::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_choose_sample_count() {
+        let apple_gpu = |count| count == 1 || count == 2 || count == 4;
+        assert_eq!(4, choose_sample_count(4, apple_gpu));
+        assert_eq!(4, choose_sample_count(8, apple_gpu));
+        assert_eq!(2, choose_sample_count(3, apple_gpu));
+        assert_eq!(1, choose_sample_count(1, apple_gpu));
+        assert_eq!(1, choose_sample_count(0, apple_gpu));
+
+        let only_single = |count| count == 1;
+        assert_eq!(1, choose_sample_count(4, only_single));
+
+        let eight_not_four = |count| count == 8;
+        assert_eq!(8, choose_sample_count(8, eight_not_four));
+        assert_eq!(1, choose_sample_count(4, eight_not_four));
+    }
+
+    #[test]
+    fn test_spec_derived_settings() {
+        let single = MetalRenderTargetSpec::new(640, 480, MTLPixelFormatDepth32Float, 1);
+        assert!(!single.is_multisampled());
+        assert_eq!(MTLTextureType2D, single.texture_type());
+        assert_eq!(MTLStoreActionStore, single.color_store_action());
+
+        let multi = MetalRenderTargetSpec { sample_count: 4, ..single };
+        assert!(multi.is_multisampled());
+        assert_eq!(MTLTextureType2DMultisample, multi.texture_type());
+        assert_eq!(MTLStoreActionMultisampleResolve, multi.color_store_action());
+
+        assert!(MetalRenderTargetSpec::new(0, 480, MTLPixelFormatDepth32Float, 1).is_empty());
+    }
+
+    #[test]
+    fn test_cache_rebuilds_only_on_change() {
+        let created = Cell::new(0);
+        let create = |spec: &MetalRenderTargetSpec| -> Result<u32, ()> {
+            created.set(created.get() + 1);
+            Ok(spec.width as u32)
+        };
+        let mut cache: MetalRenderTargetCache<u32> = MetalRenderTargetCache::default();
+        assert_eq!(0, *cache.texture());
+        assert_eq!(None, cache.spec());
+
+        let spec = MetalRenderTargetSpec::new(640, 480, MTLPixelFormatDepth32Float, 4);
+        assert_eq!(Ok(true), cache.update(spec, create));
+        assert_eq!(Ok(false), cache.update(spec, create));
+        assert_eq!(1, created.get());
+        assert_eq!(640, *cache.texture());
+
+        // Resize, then change the sample count.
+        let resized = MetalRenderTargetSpec { width: 800, ..spec };
+        assert_eq!(Ok(true), cache.update(resized, create));
+        assert_eq!(800, *cache.texture());
+        let single = MetalRenderTargetSpec { sample_count: 1, ..resized };
+        assert!(cache.needs_rebuild(&single));
+        assert_eq!(Ok(true), cache.update(single, create));
+        assert_eq!(3, created.get());
+        assert_eq!(Some(&single), cache.spec());
+    }
+
+    #[test]
+    fn test_cache_retries_after_failure() {
+        let mut cache: MetalRenderTargetCache<u32> = MetalRenderTargetCache::default();
+        let spec = MetalRenderTargetSpec::new(640, 480, MTLPixelFormatDepth32Float, 1);
+        assert_eq!(Ok(true), cache.update(spec, |_| Ok::<u32, &str>(7)));
+
+        let bigger = MetalRenderTargetSpec { width: 1 << 20, ..spec };
+        assert_eq!(Err("too big"), cache.update(bigger, |_| Err("too big")));
+        assert_eq!(0, *cache.texture());
+        assert_eq!(None, cache.spec());
+        assert!(cache.needs_rebuild(&spec));
+
+        cache.clear();
+        assert_eq!(Ok(true), cache.update(spec, |_| Ok::<u32, &str>(9)));
+        assert_eq!(9, *cache.texture());
+    }
+}
@@ -0,0 +1,314 @@
+//
+//  metal_resource_options.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! The CPU cache mode, storage mode and hazard tracking mode of a new resource,
+//! packed into MTLResourceOptions the way MTLResource.h does it
+
+use cocoa::foundation::NSUInteger;
+use crate::metal_kit::metal_device::MTLResourceOptions;
+use crate::metal_kit::metal_error::MetalError;
+use crate::metal_kit::metal_texture_descriptor::{
+    MTLStorageMode,
+    MTLStorageModeShared,
+    MTLStorageModeManaged,
+    MTLStorageModePrivate,
+    MTLStorageModeMemoryless,
+};
+
+// From MTLResource.h:
+// typedef NS_ENUM(NSUInteger, MTLCPUCacheMode)
+// {
+//     MTLCPUCacheModeDefaultCache = 0,
+//     MTLCPUCacheModeWriteCombined = 1,
+// } API_AVAILABLE(macos(10.11), ios(8.0));
+/// How the CPU maps a resource's memory.
+pub type MTLCPUCacheMode = NSUInteger;
+/// The default cache mode, which keeps CPU reads and writes in order.
+#[allow(non_upper_case_globals)]
+pub const MTLCPUCacheModeDefaultCache: MTLCPUCacheMode = 0;
+/// A write-combined cache mode, for resources the CPU writes but never reads.
+#[allow(non_upper_case_globals)]
+pub const MTLCPUCacheModeWriteCombined: MTLCPUCacheMode = 1;
+
+// typedef NS_ENUM(NSUInteger, MTLHazardTrackingMode)
+// {
+//     MTLHazardTrackingModeDefault = 0,
+//     MTLHazardTrackingModeUntracked = 1,
+//     MTLHazardTrackingModeTracked = 2,
+// } API_AVAILABLE(macos(10.15), ios(13.0));
+/// Whether Metal stops the GPU reading and writing a resource at the same time.
+pub type MTLHazardTrackingMode = NSUInteger;
+/// The default for the kind of resource: tracked, except for resources made from a heap.
+#[allow(non_upper_case_globals)]
+pub const MTLHazardTrackingModeDefault: MTLHazardTrackingMode = 0;
+/// The app is responsible for avoiding hazards, e.g. with fences.
+#[allow(non_upper_case_globals)]
+pub const MTLHazardTrackingModeUntracked: MTLHazardTrackingMode = 1;
+/// Metal avoids hazards itself.
+#[allow(non_upper_case_globals)]
+pub const MTLHazardTrackingModeTracked: MTLHazardTrackingMode = 2;
+
+// #define MTLResourceCPUCacheModeShift            0
+// #define MTLResourceCPUCacheModeMask             (0xfUL << MTLResourceCPUCacheModeShift)
+//
+// #define MTLResourceStorageModeShift             4
+// #define MTLResourceStorageModeMask              (0xfUL << MTLResourceStorageModeShift)
+//
+// #define MTLResourceHazardTrackingModeShift      8
+// #define MTLResourceHazardTrackingModeMask       (0x3UL << MTLResourceHazardTrackingModeShift)
+/// Where the CPU cache mode sits in the resource options.
+#[allow(non_upper_case_globals)]
+pub const MTLResourceCPUCacheModeShift: NSUInteger = 0;
+/// The bits of the resource options holding the CPU cache mode.
+#[allow(non_upper_case_globals)]
+pub const MTLResourceCPUCacheModeMask: NSUInteger = 0xf << MTLResourceCPUCacheModeShift;
+/// Where the storage mode sits in the resource options.
+#[allow(non_upper_case_globals)]
+pub const MTLResourceStorageModeShift: NSUInteger = 4;
+/// The bits of the resource options holding the storage mode.
+#[allow(non_upper_case_globals)]
+pub const MTLResourceStorageModeMask: NSUInteger = 0xf << MTLResourceStorageModeShift;
+/// Where the hazard tracking mode sits in the resource options.
+#[allow(non_upper_case_globals)]
+pub const MTLResourceHazardTrackingModeShift: NSUInteger = 8;
+/// The bits of the resource options holding the hazard tracking mode.
+#[allow(non_upper_case_globals)]
+pub const MTLResourceHazardTrackingModeMask: NSUInteger = 0x3 << MTLResourceHazardTrackingModeShift;
+
+/// Where one mode sits in the resource options, and the largest value it can take.
+struct ModeField {
+    name: &'static str,
+    largest: NSUInteger,
+    mask: NSUInteger,
+    shift: NSUInteger,
+}
+const CPU_CACHE_MODE_FIELD: ModeField = ModeField {
+    name: "CPU cache",
+    largest: MTLCPUCacheModeWriteCombined,
+    mask: MTLResourceCPUCacheModeMask,
+    shift: MTLResourceCPUCacheModeShift,
+};
+const STORAGE_MODE_FIELD: ModeField = ModeField {
+    name: "storage",
+    largest: MTLStorageModeMemoryless,
+    mask: MTLResourceStorageModeMask,
+    shift: MTLResourceStorageModeShift,
+};
+const HAZARD_TRACKING_MODE_FIELD: ModeField = ModeField {
+    name: "hazard tracking",
+    largest: MTLHazardTrackingModeTracked,
+    mask: MTLResourceHazardTrackingModeMask,
+    shift: MTLResourceHazardTrackingModeShift,
+};
+
+/// Typed resource options: a CPU cache mode, a storage mode and a hazard tracking mode,
+/// each packed into its own field of `MTLResourceOptions`.
+///
+/// The modes are numbers rather than flags, so there is no `|`:
+/// build options with `from_modes`, or start from `SHARED` or `PRIVATE` and replace fields
+/// with `with_storage_mode` and friends. Every constructor checks the result,
+/// so a `MetalResourceOptions` always holds modes Metal has, in a combination it accepts.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MetalResourceOptions(NSUInteger);
+impl Default for MetalResourceOptions {
+    /// Default cache, shared storage, default hazard tracking: all zero.
+    fn default() -> Self { MetalResourceOptions::SHARED }
+}
+impl MetalResourceOptions {
+    /// Memory shared by the CPU and GPU, with the default cache and hazard tracking modes.
+    pub const SHARED: Self = MetalResourceOptions(MTLStorageModeShared << MTLResourceStorageModeShift);
+    /// GPU-only memory, with the default cache and hazard tracking modes.
+    pub const PRIVATE: Self = MetalResourceOptions(MTLStorageModePrivate << MTLResourceStorageModeShift);
+
+    /// Packs the three modes into resource options, checking the result.
+    pub fn from_modes(
+        cpu_cache_mode: MTLCPUCacheMode,
+        storage_mode: MTLStorageMode,
+        hazard_tracking_mode: MTLHazardTrackingMode
+    ) -> Result<Self, MetalError> {
+        MetalResourceOptions::default()
+            .with_field(&CPU_CACHE_MODE_FIELD, cpu_cache_mode)?
+            .with_field(&STORAGE_MODE_FIELD, storage_mode)?
+            .with_field(&HAZARD_TRACKING_MODE_FIELD, hazard_tracking_mode)?
+            .checked()
+    }
+    /// Turns raw resource options from Metal into typed ones, checking them.
+    pub fn from_raw(options: MTLResourceOptions) -> Result<Self, MetalError> {
+        let known = MTLResourceCPUCacheModeMask | MTLResourceStorageModeMask | MTLResourceHazardTrackingModeMask;
+        let decoded = MetalResourceOptions(options);
+        if options & !known != 0
+            || decoded.cpu_cache_mode() > CPU_CACHE_MODE_FIELD.largest
+            || decoded.storage_mode() > STORAGE_MODE_FIELD.largest
+            || decoded.hazard_tracking_mode() > HAZARD_TRACKING_MODE_FIELD.largest {
+            return Err(MetalError::InvalidResourceOptions(options));
+        }
+        decoded.checked()
+    }
+    /// Gets the raw value to pass to Metal.
+    pub fn to_raw(self) -> MTLResourceOptions { self.0 }
+    /// Gets the CPU cache mode.
+    pub fn cpu_cache_mode(self) -> MTLCPUCacheMode {
+        (self.0 & MTLResourceCPUCacheModeMask) >> MTLResourceCPUCacheModeShift
+    }
+    /// Gets the storage mode.
+    pub fn storage_mode(self) -> MTLStorageMode {
+        (self.0 & MTLResourceStorageModeMask) >> MTLResourceStorageModeShift
+    }
+    /// Gets the hazard tracking mode.
+    pub fn hazard_tracking_mode(self) -> MTLHazardTrackingMode {
+        (self.0 & MTLResourceHazardTrackingModeMask) >> MTLResourceHazardTrackingModeShift
+    }
+    /// Returns these options with the CPU cache mode replaced, checking the result.
+    pub fn with_cpu_cache_mode(self, mode: MTLCPUCacheMode) -> Result<Self, MetalError> {
+        self.with_field(&CPU_CACHE_MODE_FIELD, mode)?.checked()
+    }
+    /// Returns these options with the storage mode replaced, checking the result.
+    pub fn with_storage_mode(self, mode: MTLStorageMode) -> Result<Self, MetalError> {
+        self.with_field(&STORAGE_MODE_FIELD, mode)?.checked()
+    }
+    /// Returns these options with the hazard tracking mode replaced, checking the result.
+    pub fn with_hazard_tracking_mode(self, mode: MTLHazardTrackingMode) -> Result<Self, MetalError> {
+        self.with_field(&HAZARD_TRACKING_MODE_FIELD, mode)?.checked()
+    }
+    /// Returns true if the CPU can read or write the resource's contents.
+    pub fn is_cpu_accessible(self) -> bool {
+        let storage_mode = self.storage_mode();
+        storage_mode == MTLStorageModeShared || storage_mode == MTLStorageModeManaged
+    }
+    /// Checks that the modes make sense together, passing the options through if they do:
+    /// write combining only applies to memory the CPU can see,
+    /// and managed storage does not exist on iOS.
+    fn checked(self) -> Result<Self, MetalError> {
+        let invalid = Err(MetalError::InvalidResourceOptions(self.0));
+        if self.cpu_cache_mode() == MTLCPUCacheModeWriteCombined && !self.is_cpu_accessible() {
+            return invalid;
+        }
+        if cfg!(target_os = "ios") && self.storage_mode() == MTLStorageModeManaged {
+            return invalid;
+        }
+        Ok(self)
+    }
+    /// Replaces the field with the mode, if it is one Metal has.
+    fn with_field(self, field: &ModeField, mode: NSUInteger) -> Result<Self, MetalError> {
+        if mode > field.largest {
+            return Err(MetalError::InvalidResourceMode { name: field.name.to_string(), mode });
+        }
+        Ok(MetalResourceOptions((self.0 & !field.mask) | (mode << field.shift)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_encodings_match_mtl_resource_h() {
+        assert_eq!(0, MetalResourceOptions::default().to_raw());
+        assert_eq!(MTLResourceCPUCacheModeDefaultCache, MetalResourceOptions::SHARED.to_raw());
+        assert_eq!(0x20, MetalResourceOptions::PRIVATE.to_raw());
+        let raw = |cpu_cache_mode, storage_mode, hazard_tracking_mode| {
+            MetalResourceOptions::from_modes(cpu_cache_mode, storage_mode, hazard_tracking_mode).map(|options| options.to_raw())
+        };
+        assert_eq!(Ok(0x01), raw(MTLCPUCacheModeWriteCombined, MTLStorageModeShared, MTLHazardTrackingModeDefault));
+        assert_eq!(Ok(0x30), raw(MTLCPUCacheModeDefaultCache, MTLStorageModeMemoryless, MTLHazardTrackingModeDefault));
+        assert_eq!(Ok(0x100), raw(MTLCPUCacheModeDefaultCache, MTLStorageModeShared, MTLHazardTrackingModeUntracked));
+        assert_eq!(Ok(0x200), raw(MTLCPUCacheModeDefaultCache, MTLStorageModeShared, MTLHazardTrackingModeTracked));
+
+        let upload = MetalResourceOptions::SHARED
+            .with_cpu_cache_mode(MTLCPUCacheModeWriteCombined)
+            .and_then(|options| options.with_hazard_tracking_mode(MTLHazardTrackingModeUntracked))
+            .unwrap();
+        assert_eq!(0x101, upload.to_raw());
+        assert_eq!(Ok(upload), MetalResourceOptions::from_raw(0x101));
+    }
+
+    #[cfg(not(target_os = "ios"))]
+    #[test]
+    fn test_managed_storage_is_encoded() {
+        let managed = MetalResourceOptions::from_modes(
+            MTLCPUCacheModeDefaultCache,
+            MTLStorageModeManaged,
+            MTLHazardTrackingModeDefault
+        ).unwrap();
+        assert_eq!(0x10, managed.to_raw());
+        assert_eq!(Ok(managed), MetalResourceOptions::from_raw(0x10));
+        assert_eq!(MTLStorageModeManaged, managed.storage_mode());
+        assert!(managed.is_cpu_accessible());
+    }
+
+    #[cfg(target_os = "ios")]
+    #[test]
+    fn test_managed_storage_is_rejected_on_ios() {
+        assert_eq!(
+            Err(MetalError::InvalidResourceOptions(0x10)),
+            MetalResourceOptions::from_modes(MTLCPUCacheModeDefaultCache, MTLStorageModeManaged, MTLHazardTrackingModeDefault)
+        );
+        assert_eq!(Err(MetalError::InvalidResourceOptions(0x10)), MetalResourceOptions::from_raw(0x10));
+        assert_eq!(
+            Err(MetalError::InvalidResourceOptions(0x10)),
+            MetalResourceOptions::SHARED.with_storage_mode(MTLStorageModeManaged)
+        );
+    }
+
+    #[test]
+    fn test_modes_round_trip_through_fields() {
+        let options = MetalResourceOptions::from_modes(
+            MTLCPUCacheModeDefaultCache,
+            MTLStorageModePrivate,
+            MTLHazardTrackingModeTracked
+        ).unwrap();
+        assert_eq!(0x220, options.to_raw());
+        assert_eq!(MTLCPUCacheModeDefaultCache, options.cpu_cache_mode());
+        assert_eq!(MTLStorageModePrivate, options.storage_mode());
+        assert_eq!(MTLHazardTrackingModeTracked, options.hazard_tracking_mode());
+        assert!(!options.is_cpu_accessible());
+
+        // Replacing a field does not leave old bits behind.
+        let options = options.with_storage_mode(MTLStorageModeShared).unwrap();
+        assert_eq!(MTLStorageModeShared, options.storage_mode());
+        assert_eq!(0x200, options.to_raw());
+        assert!(options.is_cpu_accessible());
+
+        // Memoryless decodes as memoryless, not as managed or private.
+        let memoryless = MetalResourceOptions::from_raw(0x30).unwrap();
+        assert_eq!(MTLStorageModeMemoryless, memoryless.storage_mode());
+        assert!(!memoryless.is_cpu_accessible());
+    }
+
+    #[test]
+    fn test_invalid_options_are_rejected() {
+        // Bits outside the three fields.
+        assert_eq!(Err(MetalError::InvalidResourceOptions(0x400)), MetalResourceOptions::from_raw(0x400));
+        // Modes Metal does not have.
+        assert_eq!(Err(MetalError::InvalidResourceOptions(0x2)), MetalResourceOptions::from_raw(0x2));
+        assert_eq!(Err(MetalError::InvalidResourceOptions(0x40)), MetalResourceOptions::from_raw(0x40));
+        assert_eq!(Err(MetalError::InvalidResourceOptions(0x300)), MetalResourceOptions::from_raw(0x300));
+        assert_eq!(
+            Err(MetalError::InvalidResourceMode { name: "hazard tracking".to_string(), mode: 3 }),
+            MetalResourceOptions::SHARED.with_hazard_tracking_mode(3)
+        );
+        // Write combining memory the CPU cannot see.
+        assert_eq!(
+            Err(MetalError::InvalidResourceOptions(0x21)),
+            MetalResourceOptions::from_modes(MTLCPUCacheModeWriteCombined, MTLStorageModePrivate, MTLHazardTrackingModeDefault)
+        );
+        assert_eq!(
+            Err(MetalError::InvalidResourceOptions(0x31)),
+            MetalResourceOptions::PRIVATE
+                .with_storage_mode(MTLStorageModeShared)
+                .and_then(|options| options.with_cpu_cache_mode(MTLCPUCacheModeWriteCombined))
+                .and_then(|options| options.with_storage_mode(MTLStorageModeMemoryless))
+        );
+        assert!(MetalResourceOptions::from_modes(MTLCPUCacheModeWriteCombined, MTLStorageModeShared, MTLHazardTrackingModeDefault).is_ok());
+        // A mode too big for its field is reported, rather than spilling into the next field.
+        assert_eq!(
+            Err(MetalError::InvalidResourceMode { name: "storage".to_string(), mode: 0x10 }),
+            MetalResourceOptions::from_modes(MTLCPUCacheModeDefaultCache, 0x10, MTLHazardTrackingModeDefault)
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//
+//  metal_sampler_state.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Thin wrappers for MTLSamplerDescriptor and MTLSamplerState
+
+use objc::msg_send;
+use objc::sel;
+use objc::sel_impl;
+use objc::class;
+use cocoa::base::{id, nil};
+use cocoa::foundation::NSUInteger;
+use objc::runtime::{objc_release, objc_retain, BOOL};
+use crate::metal_kit::metal_argument_encoder::MTLResourceID;
+use crate::metal_kit::ns_string::{get_objc_label, set_objc_label};
+
+// From MTLSampler.h:
+// typedef NS_ENUM(NSUInteger, MTLSamplerMinMagFilter) {
+//     MTLSamplerMinMagFilterNearest = 0,
+//     MTLSamplerMinMagFilterLinear = 1,
+// } API_AVAILABLE(macos(10.11), ios(8.0));
+/// How to pick a colour from the texels nearest a sample.
+pub type MTLSamplerMinMagFilter = NSUInteger;
+/// Takes the colour of the nearest texel.
+#[allow(non_upper_case_globals)]
+pub const MTLSamplerMinMagFilterNearest: MTLSamplerMinMagFilter = 0;
+/// Blends the colours of the nearest texels.
+#[allow(non_upper_case_globals)]
+pub const MTLSamplerMinMagFilterLinear: MTLSamplerMinMagFilter = 1;
+//
+// typedef NS_ENUM(NSUInteger, MTLSamplerMipFilter) {
+//     MTLSamplerMipFilterNotMipmapped = 0,
+//     MTLSamplerMipFilterNearest = 1,
+//     MTLSamplerMipFilterLinear = 2,
+// } API_AVAILABLE(macos(10.11), ios(8.0));
+/// How to pick a colour from the mipmap levels nearest a sample.
+pub type MTLSamplerMipFilter = NSUInteger;
+/// Always samples the base level.
+#[allow(non_upper_case_globals)]
+pub const MTLSamplerMipFilterNotMipmapped: MTLSamplerMipFilter = 0;
+/// Samples the nearest mipmap level.
+#[allow(non_upper_case_globals)]
+pub const MTLSamplerMipFilterNearest: MTLSamplerMipFilter = 1;
+/// Blends samples from the two nearest mipmap levels.
+#[allow(non_upper_case_globals)]
+pub const MTLSamplerMipFilterLinear: MTLSamplerMipFilter = 2;
+//
+// typedef NS_ENUM(NSUInteger, MTLSamplerAddressMode) {
+//     MTLSamplerAddressModeClampToEdge = 0,
+//     MTLSamplerAddressModeMirrorClampToEdge API_AVAILABLE(macos(10.11), ios(14.0)) = 1,
+//     MTLSamplerAddressModeRepeat = 2,
+//     MTLSamplerAddressModeMirrorRepeat = 3,
+//     MTLSamplerAddressModeClampToZero = 4,
+//     MTLSamplerAddressModeClampToBorderColor API_AVAILABLE(macos(10.12), ios(14.0)) = 5,
+// } API_AVAILABLE(macos(10.11), ios(8.0));
+/// What to sample for coordinates outside the texture.
+pub type MTLSamplerAddressMode = NSUInteger;
+/// Uses the texel on the nearest edge.
+#[allow(non_upper_case_globals)]
+pub const MTLSamplerAddressModeClampToEdge: MTLSamplerAddressMode = 0;
+/// Mirrors the texture once, then uses the texel on the nearest edge.
+#[allow(non_upper_case_globals)]
+pub const MTLSamplerAddressModeMirrorClampToEdge: MTLSamplerAddressMode = 1;
+/// Repeats the texture.
+#[allow(non_upper_case_globals)]
+pub const MTLSamplerAddressModeRepeat: MTLSamplerAddressMode = 2;
+/// Repeats the texture, mirroring every other copy.
+#[allow(non_upper_case_globals)]
+pub const MTLSamplerAddressModeMirrorRepeat: MTLSamplerAddressMode = 3;
+/// Uses transparent black (or opaque black for textures without alpha).
+#[allow(non_upper_case_globals)]
+pub const MTLSamplerAddressModeClampToZero: MTLSamplerAddressMode = 4;
+/// Uses the sampler's border colour.
+#[allow(non_upper_case_globals)]
+pub const MTLSamplerAddressModeClampToBorderColor: MTLSamplerAddressMode = 5;
+
+/// Rust wrapper for the settings used to create a sampler state.
+pub struct MetalSamplerDescriptor {
+    descriptor: id,
+}
+impl Default for MetalSamplerDescriptor {
+    fn default() -> Self {
+        MetalSamplerDescriptor { descriptor: nil }
+    }
+}
+impl From<id> for MetalSamplerDescriptor {
+    fn from(descriptor: id) -> Self {
+        let descriptor = unsafe { objc_retain(descriptor) };
+        MetalSamplerDescriptor { descriptor }
+    }
+}
+impl Drop for MetalSamplerDescriptor {
+    fn drop(&mut self) { unsafe { objc_release(self.descriptor) } }
+}
+impl MetalSamplerDescriptor {
+    /// Creates a descriptor for a nearest-texel sampler that clamps to the edge.
+    pub fn new() -> Self {
+        let class = class!(MTLSamplerDescriptor);
+        let descriptor: id = unsafe { msg_send![class, new] };
+        let result = MetalSamplerDescriptor::from(descriptor);
+        unsafe { objc_release(descriptor) };
+        result
+    }
+    /// Returns the underlying Objective C descriptor.
+    pub fn to_objc(&self) -> id { self.descriptor }
+    /// Sets the label used to identify the sampler state made from this descriptor in debugging tools.
+    pub fn set_label(&mut self, label: &str) { set_objc_label(self.descriptor, label) }
+    /// Gets the label (empty if none was set).
+    pub fn get_label(&self) -> String { get_objc_label(self.descriptor) }
+    /// Sets the filter used when the texture is drawn smaller than its size.
+    pub fn set_min_filter(&mut self, filter: MTLSamplerMinMagFilter) {
+        unsafe { msg_send![self.descriptor, setMinFilter:filter] }
+    }
+    /// Sets the filter used when the texture is drawn larger than its size.
+    pub fn set_mag_filter(&mut self, filter: MTLSamplerMinMagFilter) {
+        unsafe { msg_send![self.descriptor, setMagFilter:filter] }
+    }
+    /// Sets the filter used between mipmap levels.
+    pub fn set_mip_filter(&mut self, filter: MTLSamplerMipFilter) {
+        unsafe { msg_send![self.descriptor, setMipFilter:filter] }
+    }
+    /// Sets the address mode for the width (s) coordinate.
+    pub fn set_s_address_mode(&mut self, mode: MTLSamplerAddressMode) {
+        unsafe { msg_send![self.descriptor, setSAddressMode:mode] }
+    }
+    /// Sets the address mode for the height (t) coordinate.
+    pub fn set_t_address_mode(&mut self, mode: MTLSamplerAddressMode) {
+        unsafe { msg_send![self.descriptor, setTAddressMode:mode] }
+    }
+    /// Sets the address mode for the depth (r) coordinate.
+    pub fn set_r_address_mode(&mut self, mode: MTLSamplerAddressMode) {
+        unsafe { msg_send![self.descriptor, setRAddressMode:mode] }
+    }
+    /// Sets whether the sampler can be placed in an argument buffer.
+    pub fn set_support_argument_buffers(&mut self, supported: BOOL) {
+        unsafe { msg_send![self.descriptor, setSupportArgumentBuffers:supported] }
+    }
+}
+
+/// Rust wrapper for an object that says how a shader samples textures.
+pub struct MetalSamplerState {
+    state: id,
+}
+impl Default for MetalSamplerState {
+    fn default() -> Self {
+        MetalSamplerState { state: nil }
+    }
+}
+impl From<id> for MetalSamplerState {
+    fn from(state: id) -> Self {
+        let state = unsafe { objc_retain(state) };
+        MetalSamplerState { state }
+    }
+}
+impl Drop for MetalSamplerState {
+    fn drop(&mut self) { unsafe { objc_release(self.state) } }
+}
+impl MetalSamplerState {
+    /// Returns the underlying Objective C sampler state.
+    pub fn to_objc(&self) -> id { self.state }
+    /// Gets the label used to identify the sampler state in debugging tools,
+    /// copied from the descriptor it was made from (empty if none was set).
+    pub fn get_label(&self) -> String { get_objc_label(self.state) }
+    /// Gets the ID to write into a Tier 2 argument buffer in place of the sampler.
+    ///
+    /// The descriptor must have supported argument buffers.
+    pub fn get_gpu_resource_id(&self) -> MTLResourceID {
+        unsafe { msg_send![self.state, gpuResourceID] }
+    }
+}
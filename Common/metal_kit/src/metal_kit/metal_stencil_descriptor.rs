@@ -0,0 +1,265 @@
+//
+//  metal_stencil_descriptor.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! The stencil test and operations for one face of a primitive,
+//! kept as a plain Rust value until they are applied to a depth stencil descriptor
+
+use objc::class;
+use objc::msg_send;
+use objc::sel;
+use objc::sel_impl;
+use cocoa::base::id;
+use cocoa::foundation::NSUInteger;
+use crate::metal_kit::metal_depth_stencil_descriptor::{
+    MTLCompareFunction,
+    MTLCompareFunctionNever,
+    MTLCompareFunctionLess,
+    MTLCompareFunctionEqual,
+    MTLCompareFunctionLessEqual,
+    MTLCompareFunctionGreater,
+    MTLCompareFunctionNotEqual,
+    MTLCompareFunctionGreaterEqual,
+    MTLCompareFunctionAlways,
+};
+
+// From MTLDepthStencil.h:
+// typedef NS_ENUM(NSUInteger, MTLStencilOperation) {
+//     MTLStencilOperationKeep = 0,
+//     MTLStencilOperationZero = 1,
+//     MTLStencilOperationReplace = 2,
+//     MTLStencilOperationIncrementClamp = 3,
+//     MTLStencilOperationDecrementClamp = 4,
+//     MTLStencilOperationInvert = 5,
+//     MTLStencilOperationIncrementWrap = 6,
+//     MTLStencilOperationDecrementWrap = 7,
+// } API_AVAILABLE(macos(10.11), ios(8.0));
+/// The operation performed on a currently stored stencil value
+/// when a comparison test passes or fails.
+pub type MTLStencilOperation = NSUInteger;
+/// Keep the current stencil value.
+#[allow(non_upper_case_globals)]
+pub const MTLStencilOperationKeep: MTLStencilOperation = 0;
+/// Set the stencil value to zero.
+#[allow(non_upper_case_globals)]
+pub const MTLStencilOperationZero: MTLStencilOperation = 1;
+/// Replace the stencil value with the stencil reference value.
+#[allow(non_upper_case_globals)]
+pub const MTLStencilOperationReplace: MTLStencilOperation = 2;
+/// If the current stencil value is not the maximum representable value, increase it by 1.
+#[allow(non_upper_case_globals)]
+pub const MTLStencilOperationIncrementClamp: MTLStencilOperation = 3;
+/// If the current stencil value is not zero, decrease it by 1.
+#[allow(non_upper_case_globals)]
+pub const MTLStencilOperationDecrementClamp: MTLStencilOperation = 4;
+/// Perform a logical bitwise invert operation on the current stencil value.
+#[allow(non_upper_case_globals)]
+pub const MTLStencilOperationInvert: MTLStencilOperation = 5;
+/// Increase the stencil value by 1, wrapping the maximum value round to zero.
+#[allow(non_upper_case_globals)]
+pub const MTLStencilOperationIncrementWrap: MTLStencilOperation = 6;
+/// Decrease the stencil value by 1, wrapping zero round to the maximum value.
+#[allow(non_upper_case_globals)]
+pub const MTLStencilOperationDecrementWrap: MTLStencilOperation = 7;
+
+/// The largest value an 8-bit stencil attachment can hold.
+const STENCIL_MAX: u32 = 0xff;
+
+/// The stencil test and the operations done with its result,
+/// for one face (front or back) of a primitive.
+///
+/// Besides being applied to a `MetalDepthStencilDescriptor`,
+/// the value can work out, on the CPU, what the GPU would do to a stencil value,
+/// so stencil set-ups can be checked in unit tests.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MetalStencilDescriptor {
+    /// The comparison between the masked reference value and the masked stored value.
+    pub stencil_compare_function: MTLCompareFunction,
+    /// The operation done when the stencil test fails.
+    pub stencil_failure_operation: MTLStencilOperation,
+    /// The operation done when the stencil test passes but the depth test fails.
+    pub depth_failure_operation: MTLStencilOperation,
+    /// The operation done when both the stencil and the depth tests pass.
+    pub depth_stencil_pass_operation: MTLStencilOperation,
+    /// The bits of the reference and stored values that take part in the test.
+    pub read_mask: u32,
+    /// The bits of the stored value that the operations can change.
+    pub write_mask: u32,
+}
+impl Default for MetalStencilDescriptor {
+    /// Metal's defaults: the test always passes and the stored value is kept.
+    fn default() -> Self {
+        MetalStencilDescriptor {
+            stencil_compare_function: MTLCompareFunctionAlways,
+            stencil_failure_operation: MTLStencilOperationKeep,
+            depth_failure_operation: MTLStencilOperationKeep,
+            depth_stencil_pass_operation: MTLStencilOperationKeep,
+            read_mask: 0xffff_ffff,
+            write_mask: 0xffff_ffff,
+        }
+    }
+}
+
+impl MetalStencilDescriptor {
+    /// Returns a descriptor that writes the reference value
+    /// wherever a fragment is drawn, e.g. to mark out a mirror or portal.
+    pub fn write_reference() -> Self {
+        MetalStencilDescriptor {
+            depth_stencil_pass_operation: MTLStencilOperationReplace,
+            ..Self::default()
+        }
+    }
+    /// Returns a descriptor that only lets fragments through
+    /// where the stored value matches the reference value,
+    /// leaving the stored value untouched.
+    pub fn equal_to_reference() -> Self {
+        MetalStencilDescriptor {
+            stencil_compare_function: MTLCompareFunctionEqual,
+            ..Self::default()
+        }
+    }
+    /// Works out whether a fragment passes the stencil test,
+    /// given the reference value set on the encoder and the value stored in the attachment.
+    #[allow(non_upper_case_globals)]
+    pub fn test(&self, reference: u32, stored: u32) -> bool {
+        let reference = reference & self.read_mask & STENCIL_MAX;
+        let stored = stored & self.read_mask & STENCIL_MAX;
+        match self.stencil_compare_function {
+            MTLCompareFunctionNever => false,
+            MTLCompareFunctionLess => reference < stored,
+            MTLCompareFunctionEqual => reference == stored,
+            MTLCompareFunctionLessEqual => reference <= stored,
+            MTLCompareFunctionGreater => reference > stored,
+            MTLCompareFunctionNotEqual => reference != stored,
+            MTLCompareFunctionGreaterEqual => reference >= stored,
+            _ => true,
+        }
+    }
+    /// Works out the value left in the stencil attachment after a fragment is processed,
+    /// given the reference value, the value stored in the attachment
+    /// and whether the fragment passed the depth test.
+    #[allow(non_upper_case_globals)]
+    pub fn updated_value(&self, reference: u32, stored: u32, depth_passed: bool) -> u32 {
+        let operation = if !self.test(reference, stored) {
+            self.stencil_failure_operation
+        } else if !depth_passed {
+            self.depth_failure_operation
+        } else {
+            self.depth_stencil_pass_operation
+        };
+        let stored = stored & STENCIL_MAX;
+        let new_value = match operation {
+            MTLStencilOperationZero => 0,
+            MTLStencilOperationReplace => reference & STENCIL_MAX,
+            MTLStencilOperationIncrementClamp => (stored + 1).min(STENCIL_MAX),
+            MTLStencilOperationDecrementClamp => stored.saturating_sub(1),
+            MTLStencilOperationInvert => !stored & STENCIL_MAX,
+            MTLStencilOperationIncrementWrap => (stored + 1) & STENCIL_MAX,
+            MTLStencilOperationDecrementWrap => stored.wrapping_sub(1) & STENCIL_MAX,
+            _ => stored,
+        };
+        (stored & !self.write_mask) | (new_value & self.write_mask)
+    }
+    /// Creates a new MTLStencilDescriptor with these settings.
+    /// The caller is responsible for releasing it.
+    pub(crate) fn new_objc(&self) -> id {
+        let class = class!(MTLStencilDescriptor);
+        unsafe {
+            let descriptor: id = msg_send![class, new];
+            let _:() = msg_send![descriptor, setStencilCompareFunction:self.stencil_compare_function];
+            let _:() = msg_send![descriptor, setStencilFailureOperation:self.stencil_failure_operation];
+            let _:() = msg_send![descriptor, setDepthFailureOperation:self.depth_failure_operation];
+            let _:() = msg_send![descriptor, setDepthStencilPassOperation:self.depth_stencil_pass_operation];
+            let _:() = msg_send![descriptor, setReadMask:self.read_mask];
+            let _:() = msg_send![descriptor, setWriteMask:self.write_mask];
+            descriptor
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_default_always_passes_and_keeps() {
+        let stencil = MetalStencilDescriptor::default();
+        for stored in [0, 1, 0x80, 0xff].iter() {
+            assert!(stencil.test(0, *stored));
+            assert_eq!(*stored, stencil.updated_value(0x42, *stored, true));
+            assert_eq!(*stored, stencil.updated_value(0x42, *stored, false));
+        }
+    }
+
+    #[test]
+    fn test_mask_then_draw_inside() {
+        // First pass marks the region, second pass draws only inside it.
+        let mark = MetalStencilDescriptor::write_reference();
+        let stored = mark.updated_value(1, 0, true);
+        assert_eq!(1, stored);
+        // Fragments hidden by depth leave the mark out.
+        assert_eq!(0, mark.updated_value(1, 0, false));
+
+        let inside = MetalStencilDescriptor::equal_to_reference();
+        assert!(inside.test(1, stored));
+        assert!(!inside.test(1, 0));
+        assert_eq!(stored, inside.updated_value(1, stored, true));
+    }
+
+    #[test]
+    fn test_compare_functions_use_reference_on_the_left() {
+        let stencil = |function| MetalStencilDescriptor { stencil_compare_function: function, ..Default::default() };
+        assert!(!stencil(MTLCompareFunctionNever).test(1, 1));
+        assert!(stencil(MTLCompareFunctionLess).test(1, 2));
+        assert!(!stencil(MTLCompareFunctionLess).test(2, 1));
+        assert!(stencil(MTLCompareFunctionLessEqual).test(2, 2));
+        assert!(stencil(MTLCompareFunctionGreater).test(3, 2));
+        assert!(stencil(MTLCompareFunctionGreaterEqual).test(2, 2));
+        assert!(stencil(MTLCompareFunctionNotEqual).test(2, 3));
+        assert!(!stencil(MTLCompareFunctionEqual).test(2, 3));
+    }
+
+    #[test]
+    fn test_read_mask_limits_compared_bits() {
+        let stencil = MetalStencilDescriptor {
+            read_mask: 0x0f,
+            ..MetalStencilDescriptor::equal_to_reference()
+        };
+        assert!(stencil.test(0x03, 0xf3));
+        assert!(!stencil.test(0x03, 0xf4));
+    }
+
+    #[test]
+    fn test_operations_on_8_bit_values() {
+        let operation = |operation| MetalStencilDescriptor { depth_stencil_pass_operation: operation, ..Default::default() };
+        assert_eq!(0, operation(MTLStencilOperationZero).updated_value(7, 5, true));
+        assert_eq!(0xff, operation(MTLStencilOperationIncrementClamp).updated_value(0, 0xff, true));
+        assert_eq!(0, operation(MTLStencilOperationIncrementWrap).updated_value(0, 0xff, true));
+        assert_eq!(0, operation(MTLStencilOperationDecrementClamp).updated_value(0, 0, true));
+        assert_eq!(0xff, operation(MTLStencilOperationDecrementWrap).updated_value(0, 0, true));
+        assert_eq!(0xf0, operation(MTLStencilOperationInvert).updated_value(0, 0x0f, true));
+        assert_eq!(0x34, operation(MTLStencilOperationReplace).updated_value(0x1234, 0, true));
+    }
+
+    #[test]
+    fn test_failure_operations_and_write_mask() {
+        let stencil = MetalStencilDescriptor {
+            stencil_compare_function: MTLCompareFunctionEqual,
+            stencil_failure_operation: MTLStencilOperationZero,
+            depth_failure_operation: MTLStencilOperationIncrementClamp,
+            depth_stencil_pass_operation: MTLStencilOperationInvert,
+            read_mask: 0xff,
+            write_mask: 0x0f,
+        };
+        // Stencil test fails: zero, but only the low bits may change.
+        assert_eq!(0x50, stencil.updated_value(1, 0x55, true));
+        // Stencil passes, depth fails: increment.
+        assert_eq!(0x02, stencil.updated_value(1, 0x01, false));
+        // Both pass: invert the low bits.
+        assert_eq!(0x0e, stencil.updated_value(1, 0x01, true));
+    }
+}
@@ -12,15 +12,38 @@ use objc::msg_send;
 use objc::sel;
 use objc::sel_impl;
 use cocoa::base::{id, nil};
-use objc::runtime::{objc_release, objc_retain};
+use objc::runtime::{objc_release, objc_retain, BOOL, NO};
 use cocoa::foundation::NSUInteger;
+use core_animation::MTLPixelFormat;
+use crate::metal_kit::ns_string::{get_objc_label, set_objc_label};
+use crate::metal_kit::metal_argument_encoder::MTLResourceID;
+use crate::metal_kit::metal_error::MetalError;
+use crate::metal_kit::metal_indirect_command_buffer::ns_range;
+use crate::metal_kit::metal_texture_descriptor::{MTLStorageMode, MTLStorageModeManaged, MTLStorageModeShared, MTLTextureType};
+use crate::metal_kit::metal_texture_layout::{
+    mip_level_size,
+    pixel_format_bytes_per_pixel,
+    region_within_level,
+    texture_slice_count,
+};
+use crate::metal_kit::metal_device_capabilities::MTLSize;
+use std::ops::Range;
 
 // From MTLTexture.h:
 // typedef NS_OPTIONS(NSUInteger, MTLTextureUsage)
 // {
-//  ...
+// MTLTextureUsageUnknown         = 0x0000,
+// MTLTextureUsageShaderRead      = 0x0001,
+// MTLTextureUsageShaderWrite     = 0x0002,
 // MTLTextureUsageRenderTarget    = 0x0004,
+// MTLTextureUsagePixelFormatView = 0x0010,
 // }
+/// An option for reading or sampling from the texture in a shader.
+#[allow(non_upper_case_globals)]
+pub const MTLTextureUsageShaderRead:NSUInteger      = 0x0001;
+/// An option for writing to the texture in a shader.
+#[allow(non_upper_case_globals)]
+pub const MTLTextureUsageShaderWrite:NSUInteger     = 0x0002;
 /// An option for rendering to the texture in a render pass.
 ///
 /// Set this option if you use the given texture as a color,
@@ -29,6 +52,48 @@ use cocoa::foundation::NSUInteger;
 /// of a MTLRenderPassAttachmentDescriptor.
 #[allow(non_upper_case_globals)]
 pub const MTLTextureUsageRenderTarget:NSUInteger    = 0x0004;
+/// An option for creating views of the texture with a different pixel format.
+#[allow(non_upper_case_globals)]
+pub const MTLTextureUsagePixelFormatView:NSUInteger = 0x0010;
+
+// From MTLTypes.h:
+// typedef struct {
+//     NSUInteger x, y, z;
+// } MTLOrigin;
+/// The position of a pixel in a texture.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct MTLOrigin {
+    /// The x coordinate.
+    pub x: NSUInteger,
+    /// The y coordinate.
+    pub y: NSUInteger,
+    /// The z coordinate (0 unless 3D).
+    pub z: NSUInteger,
+}
+//
+// typedef struct {
+//     MTLOrigin origin;
+//     MTLSize   size;
+// } MTLRegion;
+/// A rectangular block of pixels in a texture.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct MTLRegion {
+    /// The first pixel of the block.
+    pub origin: MTLOrigin,
+    /// The size of the block.
+    pub size: MTLSize,
+}
+impl MTLRegion {
+    /// A two-dimensional region.
+    pub fn new_2d(x: NSUInteger, y: NSUInteger, width: NSUInteger, height: NSUInteger) -> Self {
+        MTLRegion {
+            origin: MTLOrigin { x, y, z: 0 },
+            size: MTLSize { width, height, depth: 1 },
+        }
+    }
+}
 
 /// Rust wrapper for a resource that holds formatted image data.
 pub struct MetalTexture {
@@ -50,12 +115,20 @@ impl Drop for MetalTexture {
 }
 
 impl MetalTexture {
+    /// Sets the label used to identify the texture in debugging tools.
+    pub fn set_label(&mut self, label: &str) { set_objc_label(self.texture, label) }
+    /// Gets the label (empty if none was set).
+    pub fn get_label(&self) -> String { get_objc_label(self.texture) }
     /// Returns true if the underlying Objective C object is nil.
     pub fn is_null(&self) -> bool {
         self.texture.is_null()
     }
     /// Returns the underlying Objective C texture.
     pub fn to_objc(&self) -> id { self.texture }
+    /// Gets the ID to write into a Tier 2 argument buffer in place of the texture.
+    pub fn get_gpu_resource_id(&self) -> MTLResourceID {
+        unsafe { msg_send![self.texture, gpuResourceID] }
+    }
     /// Gets the width of the texture image for the base level mipmap, in pixels.
     pub fn get_width(&self) -> NSUInteger {
         unsafe { msg_send![self.texture, width] }
@@ -64,4 +137,120 @@ impl MetalTexture {
     pub fn get_height(&self) ->  NSUInteger {
         unsafe { msg_send![self.texture, height] }
     }
+    /// Gets the format that determines how a pixel is written to, stored, and read from the texture.
+    pub fn get_pixel_format(&self) -> MTLPixelFormat {
+        unsafe { msg_send![self.texture, pixelFormat] }
+    }
+    /// Gets the depth of the texture image for the base level mipmap, in pixels (1 unless 3D).
+    pub fn get_depth(&self) -> NSUInteger {
+        unsafe { msg_send![self.texture, depth] }
+    }
+    /// Gets the dimension and arrangement of texture image data.
+    pub fn get_texture_type(&self) -> MTLTextureType {
+        unsafe { msg_send![self.texture, textureType] }
+    }
+    /// Gets the number of elements in an array texture.
+    pub fn get_array_length(&self) -> NSUInteger {
+        unsafe { msg_send![self.texture, arrayLength] }
+    }
+    /// Gets the number of mipmap levels.
+    pub fn get_mipmap_level_count(&self) -> NSUInteger {
+        unsafe { msg_send![self.texture, mipmapLevelCount] }
+    }
+    /// Gets the number of samples in each pixel.
+    pub fn get_sample_count(&self) -> NSUInteger {
+        unsafe { msg_send![self.texture, sampleCount] }
+    }
+    /// Gets where the texture's memory lives.
+    pub fn get_storage_mode(&self) -> MTLStorageMode {
+        unsafe { msg_send![self.texture, storageMode] }
+    }
+    /// Returns true if the texture can only be used as a render target, e.g. a drawable's texture.
+    pub fn is_framebuffer_only(&self) -> bool {
+        let framebuffer_only: BOOL = unsafe { msg_send![self.texture, isFramebufferOnly] };
+        framebuffer_only != NO
+    }
+    /// Gets the number of slices: array elements, cube faces, or 1.
+    pub fn get_slice_count(&self) -> NSUInteger {
+        texture_slice_count(self.get_texture_type(), self.get_array_length())
+    }
+    /// Creates a texture that shares this texture's memory,
+    /// reading and writing it as a different (but compatible) pixel format.
+    ///
+    /// Changing between formats of different sizes needs `MTLTextureUsagePixelFormatView`.
+    pub fn new_texture_view_with_pixel_format(&self, pixel_format: MTLPixelFormat) -> Result<MetalTexture, MetalError> {
+        let view: id = unsafe { msg_send![self.texture, newTextureViewWithPixelFormat:pixel_format] };
+        if view == nil {
+            return Err(MetalError::TextureViewCreation(pixel_format));
+        }
+        Ok(MetalTexture::from_new_object(view))
+    }
+    /// Creates a texture that shares some of this texture's mipmap levels and slices,
+    /// possibly as a different pixel format and texture type,
+    /// e.g. one face of a cube as a 2D texture, or a cube array as 2D array.
+    pub fn new_texture_view(
+        &self,
+        pixel_format: MTLPixelFormat,
+        texture_type: MTLTextureType,
+        levels: Range<NSUInteger>,
+        slices: Range<NSUInteger>
+    ) -> Result<MetalTexture, MetalError> {
+        let level_count = self.get_mipmap_level_count();
+        let slice_count = self.get_slice_count();
+        if levels.start >= levels.end || levels.end > level_count || slices.start >= slices.end || slices.end > slice_count {
+            return Err(MetalError::InvalidTextureViewRange { levels, slices, level_count, slice_count });
+        }
+        let view: id = unsafe { msg_send![self.texture, newTextureViewWithPixelFormat:pixel_format
+                                                                         textureType:texture_type
+                                                                              levels:ns_range(&levels)
+                                                                              slices:ns_range(&slices)] };
+        if view == nil {
+            return Err(MetalError::TextureViewCreation(pixel_format));
+        }
+        Ok(MetalTexture::from_new_object(view))
+    }
+    /// Copies a region one pixel deep from a mipmap level of the texture (its first slice)
+    /// into memory, `bytes_per_row` apart (0 to pack them tightly).
+    ///
+    /// The CPU has to be able to see the texture, so it cannot be private or framebuffer only;
+    /// use a `TextureReadback` for those. Managed textures have to be synchronized first.
+    pub fn get_bytes(&self, region: MTLRegion, mipmap_level: NSUInteger, bytes_per_row: NSUInteger) -> Result<Vec<u8>, MetalError> {
+        let storage_mode = self.get_storage_mode();
+        if (storage_mode != MTLStorageModeShared && storage_mode != MTLStorageModeManaged) || self.is_framebuffer_only() {
+            return Err(MetalError::TextureContentsUnavailable);
+        }
+        let level_size = if mipmap_level < self.get_mipmap_level_count() {
+            let base_size = MTLSize { width: self.get_width(), height: self.get_height(), depth: self.get_depth() };
+            mip_level_size(base_size, mipmap_level)
+        } else {
+            MTLSize { width: 0, height: 0, depth: 0 }
+        };
+        if !region_within_level(region, level_size) {
+            return Err(MetalError::InvalidTextureRegion { region, mipmap_level, level_size });
+        }
+        let pixel_format = self.get_pixel_format();
+        let bytes_per_pixel = pixel_format_bytes_per_pixel(pixel_format)
+            .ok_or(MetalError::UnsupportedPixelFormat(pixel_format))?;
+        let row_length = region.size.width * bytes_per_pixel;
+        let bytes_per_row = if bytes_per_row == 0 { row_length } else { bytes_per_row };
+        if bytes_per_row < row_length {
+            return Err(MetalError::BufferTooSmall { required: row_length, length: bytes_per_row });
+        }
+        let length = bytes_per_row
+            .checked_mul(region.size.height)
+            .ok_or(MetalError::BufferLengthOverflow(region.size.height as usize))?;
+        let mut bytes = vec![0u8; length as usize];
+        unsafe { msg_send![self.texture, getBytes:bytes.as_mut_ptr()
+                                        bytesPerRow:bytes_per_row
+                                         fromRegion:region
+                                        mipmapLevel:mipmap_level] }
+        Ok(bytes)
+    }
+    /// Wraps a texture returned from a `new...` method,
+    /// balancing the retain the method has already done for us.
+    fn from_new_object(texture: id) -> Self {
+        let result = MetalTexture::from(texture);
+        unsafe { objc_release(texture) };
+        result
+    }
 }
@@ -13,31 +13,260 @@ use objc::msg_send;
 use objc::sel;
 use objc::sel_impl;
 use cocoa::base::{id, nil};
-use objc::runtime::{objc_retain, objc_release, BOOL};
+use objc::runtime::{objc_retain, objc_release, BOOL, NO};
 use core_animation::MTLPixelFormat;
 use cocoa::foundation::NSUInteger;
+use crate::metal_kit::metal_texture_layout::mipmap_level_count;
 
 // From MTLPixelFormat.h:
 // typedef NS_ENUM(NSUInteger, MTLPixelFormat)
 // {
+// ...
+//     MTLPixelFormatR8Unorm                         = 10,
+// ...
+//     MTLPixelFormatR32Float  = 55,
+// ...
+//     MTLPixelFormatRGBA8Unorm      = 70,
+//     MTLPixelFormatRGBA8Unorm_sRGB API_AVAILABLE(macos(10.11), ios(8.0)) = 71,
+// ...
+//     MTLPixelFormatBGRA8Unorm      = 80,
+//     MTLPixelFormatBGRA8Unorm_sRGB API_AVAILABLE(macos(10.11), ios(8.0)) = 81,
+// ...
+//     MTLPixelFormatRGBA16Float     = 115,
+// ...
+//     MTLPixelFormatRGBA32Float     = 125,
+// ...
+//     /* Depth */
+//     MTLPixelFormatDepth16Unorm API_AVAILABLE(macos(10.12), ios(13.0)) = 250,
+//     MTLPixelFormatDepth32Float  = 252,
+//
+//     /* Stencil */
+//     MTLPixelFormatStencil8        = 253,
+//
+//     /* Depth Stencil */
+//     MTLPixelFormatDepth24Unorm_Stencil8  API_AVAILABLE(macos(10.11), macCatalyst(13.0)) API_UNAVAILABLE(ios) = 255,
+//     MTLPixelFormatDepth32Float_Stencil8  API_AVAILABLE(macos(10.11), ios(9.0)) = 260,
+//
+//     MTLPixelFormatX32_Stencil8  API_AVAILABLE(macos(10.12), ios(10.0)) = 261,
+//     MTLPixelFormatX24_Stencil8  API_AVAILABLE(macos(10.12), macCatalyst(13.0)) API_UNAVAILABLE(ios) = 262,
+// ...
+// }
+#[allow(non_upper_case_globals)]
+/// An 8-bit pixel format with one normalized unsigned integer component.
+pub const MTLPixelFormatR8Unorm:MTLPixelFormat  = 10;
+#[allow(non_upper_case_globals)]
+/// A 32-bit pixel format with one 32-bit floating-point component.
+pub const MTLPixelFormatR32Float:MTLPixelFormat  = 55;
+#[allow(non_upper_case_globals)]
+/// A 32-bit pixel format with four 8-bit normalized unsigned integer components in RGBA order.
+pub const MTLPixelFormatRGBA8Unorm:MTLPixelFormat  = 70;
+#[allow(non_upper_case_globals)]
+/// A 32-bit pixel format with four 8-bit normalized unsigned integer components in RGBA order,
+/// with conversion between sRGB and linear space.
+pub const MTLPixelFormatRGBA8Unorm_sRGB:MTLPixelFormat  = 71;
+#[allow(non_upper_case_globals)]
+/// A 32-bit pixel format with four 8-bit normalized unsigned integer components in BGRA order.
+///
+/// The same value as `core_animation::MTLPixelFormatBGRA8Unorm`, but usable in patterns.
+pub const MTLPixelFormatBGRA8Unorm:MTLPixelFormat  = 80;
+#[allow(non_upper_case_globals)]
+/// A 32-bit pixel format with four 8-bit normalized unsigned integer components in BGRA order,
+/// with conversion between sRGB and linear space.
+pub const MTLPixelFormatBGRA8Unorm_sRGB:MTLPixelFormat  = 81;
+#[allow(non_upper_case_globals)]
+/// A 64-bit pixel format with four 16-bit floating-point components in RGBA order.
+pub const MTLPixelFormatRGBA16Float:MTLPixelFormat  = 115;
+#[allow(non_upper_case_globals)]
+/// A 128-bit pixel format with four 32-bit floating-point components in RGBA order.
+pub const MTLPixelFormatRGBA32Float:MTLPixelFormat  = 125;
+#[allow(non_upper_case_globals)]
+/// A 16-bit depth pixel format with one normalized unsigned integer component.
+pub const MTLPixelFormatDepth16Unorm:MTLPixelFormat  = 250;
 #[allow(non_upper_case_globals)]
 /// A 32-bit depth pixel format with one 32-bit floating-point component,
 /// typically used for a depth render target.
 pub const MTLPixelFormatDepth32Float:MTLPixelFormat  = 252;
+#[allow(non_upper_case_globals)]
+/// An 8-bit stencil pixel format with one unsigned integer component.
+pub const MTLPixelFormatStencil8:MTLPixelFormat  = 253;
+#[allow(non_upper_case_globals)]
+/// A 32-bit combined depth and stencil pixel format
+/// with a 24-bit normalized depth component and an 8-bit stencil component.
+///
+/// Only some Macs support this format:
+/// check `MetalDevice::is_depth24_stencil8_pixel_format_supported` first.
+pub const MTLPixelFormatDepth24Unorm_Stencil8:MTLPixelFormat  = 255;
+#[allow(non_upper_case_globals)]
+/// A 40-bit combined depth and stencil pixel format
+/// with a 32-bit floating-point depth component and an 8-bit stencil component.
+pub const MTLPixelFormatDepth32Float_Stencil8:MTLPixelFormat  = 260;
+#[allow(non_upper_case_globals)]
+/// A stencil pixel format used to read the stencil value
+/// from a texture with a Depth32Float_Stencil8 format.
+pub const MTLPixelFormatX32_Stencil8:MTLPixelFormat  = 261;
+#[allow(non_upper_case_globals)]
+/// A stencil pixel format used to read the stencil value
+/// from a texture with a Depth24Unorm_Stencil8 format.
+pub const MTLPixelFormatX24_Stencil8:MTLPixelFormat  = 262;
+
+//
+// From MTLTexture.h:
+// typedef NS_ENUM(NSUInteger, MTLTextureType)
+// {
+//     MTLTextureType1D = 0,
+//     MTLTextureType1DArray = 1,
+//     MTLTextureType2D = 2,
+//     MTLTextureType2DArray = 3,
+//     MTLTextureType2DMultisample = 4,
+//     MTLTextureTypeCube = 5,
+//     MTLTextureTypeCubeArray API_AVAILABLE(macos(10.11), ios(11.0)) = 6,
+//     MTLTextureType3D = 7,
+//     MTLTextureType2DMultisampleArray API_AVAILABLE(macos(10.14), ios(14.0)) = 8,
+//     MTLTextureTypeTextureBuffer API_AVAILABLE(macos(10.14), ios(12.0)) = 9
+// } API_AVAILABLE(macos(10.11), ios(8.0));
+/// The dimension of each image, including whether multiple images are arranged into an array or a cube.
+pub type MTLTextureType = NSUInteger;
+#[allow(non_upper_case_globals)]
+/// A one-dimensional texture image.
+pub const MTLTextureType1D:MTLTextureType = 0;
+#[allow(non_upper_case_globals)]
+/// An array of one-dimensional texture images.
+pub const MTLTextureType1DArray:MTLTextureType = 1;
+#[allow(non_upper_case_globals)]
+/// A two-dimensional texture image.
+pub const MTLTextureType2D:MTLTextureType = 2;
+#[allow(non_upper_case_globals)]
+/// An array of two-dimensional texture images.
+pub const MTLTextureType2DArray:MTLTextureType = 3;
+#[allow(non_upper_case_globals)]
+/// A two-dimensional texture image that uses more than one sample for each pixel.
+pub const MTLTextureType2DMultisample:MTLTextureType = 4;
+#[allow(non_upper_case_globals)]
+/// A cube texture with six two-dimensional images.
+pub const MTLTextureTypeCube:MTLTextureType = 5;
+#[allow(non_upper_case_globals)]
+/// An array of cube textures, each with six two-dimensional images.
+pub const MTLTextureTypeCubeArray:MTLTextureType = 6;
+#[allow(non_upper_case_globals)]
+/// A three-dimensional texture image.
+pub const MTLTextureType3D:MTLTextureType = 7;
+#[allow(non_upper_case_globals)]
+/// An array of two-dimensional texture images that use more than one sample for each pixel.
+pub const MTLTextureType2DMultisampleArray:MTLTextureType = 8;
+#[allow(non_upper_case_globals)]
+/// A texture buffer.
+pub const MTLTextureTypeTextureBuffer:MTLTextureType = 9;
+
+//
+// typedef NS_ENUM(uint8_t, MTLTextureSwizzle) {
+//     MTLTextureSwizzleZero = 0,
+//     MTLTextureSwizzleOne = 1,
+//     MTLTextureSwizzleRed = 2,
+//     MTLTextureSwizzleGreen = 3,
+//     MTLTextureSwizzleBlue = 4,
+//     MTLTextureSwizzleAlpha = 5,
+// } API_AVAILABLE(macos(10.15), ios(13.0));
+/// Where a channel read from a texture takes its value from.
+pub type MTLTextureSwizzle = u8;
+#[allow(non_upper_case_globals)]
+/// The channel reads as zero.
+pub const MTLTextureSwizzleZero:MTLTextureSwizzle = 0;
+#[allow(non_upper_case_globals)]
+/// The channel reads as one.
+pub const MTLTextureSwizzleOne:MTLTextureSwizzle = 1;
+#[allow(non_upper_case_globals)]
+/// The channel reads the texture's red channel.
+pub const MTLTextureSwizzleRed:MTLTextureSwizzle = 2;
+#[allow(non_upper_case_globals)]
+/// The channel reads the texture's green channel.
+pub const MTLTextureSwizzleGreen:MTLTextureSwizzle = 3;
+#[allow(non_upper_case_globals)]
+/// The channel reads the texture's blue channel.
+pub const MTLTextureSwizzleBlue:MTLTextureSwizzle = 4;
+#[allow(non_upper_case_globals)]
+/// The channel reads the texture's alpha channel.
+pub const MTLTextureSwizzleAlpha:MTLTextureSwizzle = 5;
+
+// typedef struct {
+//     MTLTextureSwizzle red, green, blue, alpha;
+// } MTLTextureSwizzleChannels;
+/// Where each channel read from a texture takes its value from,
+/// e.g. to sample a one-channel texture as grey.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct MetalTextureSwizzleChannels {
+    /// The source of the red channel.
+    pub red: MTLTextureSwizzle,
+    /// The source of the green channel.
+    pub green: MTLTextureSwizzle,
+    /// The source of the blue channel.
+    pub blue: MTLTextureSwizzle,
+    /// The source of the alpha channel.
+    pub alpha: MTLTextureSwizzle,
+}
+impl Default for MetalTextureSwizzleChannels {
+    /// Each channel reads itself.
+    fn default() -> Self {
+        MetalTextureSwizzleChannels::new(MTLTextureSwizzleRed, MTLTextureSwizzleGreen, MTLTextureSwizzleBlue, MTLTextureSwizzleAlpha)
+    }
+}
+impl MetalTextureSwizzleChannels {
+    /// Swizzles reading the given sources.
+    pub fn new(red: MTLTextureSwizzle, green: MTLTextureSwizzle, blue: MTLTextureSwizzle, alpha: MTLTextureSwizzle) -> Self {
+        MetalTextureSwizzleChannels { red, green, blue, alpha }
+    }
+}
+
+/// Returns true if textures with the given pixel format have a depth component,
+/// and so can be used as a depth attachment.
+#[allow(non_upper_case_globals)]
+pub fn pixel_format_has_depth(pixel_format: MTLPixelFormat) -> bool {
+    matches!(pixel_format,
+        MTLPixelFormatDepth16Unorm
+        | MTLPixelFormatDepth32Float
+        | MTLPixelFormatDepth24Unorm_Stencil8
+        | MTLPixelFormatDepth32Float_Stencil8)
+}
+/// Returns true if textures with the given pixel format have a stencil component,
+/// and so can be used as a stencil attachment.
+#[allow(non_upper_case_globals)]
+pub fn pixel_format_has_stencil(pixel_format: MTLPixelFormat) -> bool {
+    matches!(pixel_format,
+        MTLPixelFormatStencil8
+        | MTLPixelFormatDepth24Unorm_Stencil8
+        | MTLPixelFormatDepth32Float_Stencil8
+        | MTLPixelFormatX32_Stencil8
+        | MTLPixelFormatX24_Stencil8)
+}
 //
 // From MTLResource.h:
 // typedef NS_ENUM(NSUInteger, MTLStorageMode)
 // {
-// ...
+//   MTLStorageModeShared  = 0,
+//   MTLStorageModeManaged API_AVAILABLE(macos(10.11), macCatalyst(13.0)) API_UNAVAILABLE(ios) = 1,
 //   MTLStorageModePrivate = 2,
-// ...
-// }
+//   MTLStorageModeMemoryless API_AVAILABLE(macos(11.0), macCatalyst(14.0), ios(10.0)) = 3,
+// } API_AVAILABLE(macos(10.11), ios(9.0));
+/// The location and access permissions of a resource's memory.
+pub type MTLStorageMode = NSUInteger;
+#[allow(non_upper_case_globals)]
+/// The resource is stored in system memory
+/// that both the CPU and the GPU can access.
+pub const MTLStorageModeShared:MTLStorageMode = 0;
+#[allow(non_upper_case_globals)]
+/// The CPU and GPU may keep separate copies of the resource (macOS only),
+/// so changes made by the CPU must be flagged with `didModifyRange:`.
+pub const MTLStorageModeManaged:MTLStorageMode = 1;
 #[allow(non_upper_case_globals)]
 /// This mode allows the texture resource data
 /// to be kept entirely to GPU (or driver) private memory
 /// that will never be accessed by the CPU directly, so no
 ///  coherency of any kind must be maintained.
-pub const MTLStorageModePrivate:NSUInteger = 2;
+pub const MTLStorageModePrivate:MTLStorageMode = 2;
+#[allow(non_upper_case_globals)]
+/// The resource only lives in tile memory for the length of a render pass,
+/// e.g. a depth or multisample attachment that is never stored (Apple GPUs only).
+pub const MTLStorageModeMemoryless:MTLStorageMode = 3;
 
 /// Rust wrapper for an object that you use
 /// to configure new Metal texture objects.
@@ -78,6 +307,149 @@ impl MetalTextureDescriptor {
                                                                                 mipmapped:mipmapped] };
         MetalTextureDescriptor::from(descriptor)
     }
+    // + (MTLTextureDescriptor *)textureCubeDescriptorWithPixelFormat:(MTLPixelFormat)pixelFormat
+    //                                                            size:(NSUInteger)size
+    //                                                       mipmapped:(BOOL)mipmapped;
+    /// Creates a texture descriptor object for a cube texture whose faces are `size` pixels square.
+    pub fn texture_cube_descriptor_with_pixel_format_and_size_and_mipmapped(
+        pixel_format: MTLPixelFormat,
+        size: NSUInteger,
+        mipmapped: BOOL
+    ) -> Self {
+        let class = class!(MTLTextureDescriptor);
+        let descriptor:id = unsafe { msg_send![class, textureCubeDescriptorWithPixelFormat:pixel_format
+                                                                                      size:size
+                                                                                 mipmapped:mipmapped] };
+        MetalTextureDescriptor::from(descriptor)
+    }
+    /// Creates a texture descriptor object for an array of `array_length` cube textures.
+    pub fn texture_cube_array_descriptor(
+        pixel_format: MTLPixelFormat,
+        size: NSUInteger,
+        array_length: NSUInteger,
+        mipmapped: BOOL
+    ) -> Self {
+        let mut descriptor = Self::texture_cube_descriptor_with_pixel_format_and_size_and_mipmapped(pixel_format, size, mipmapped);
+        descriptor.set_texture_type(MTLTextureTypeCubeArray);
+        descriptor.set_array_length(array_length);
+        descriptor
+    }
+    /// Creates a texture descriptor object for an array of `array_length` 2D textures.
+    pub fn texture_2d_array_descriptor(
+        pixel_format: MTLPixelFormat,
+        width: NSUInteger,
+        height: NSUInteger,
+        array_length: NSUInteger,
+        mipmapped: BOOL
+    ) -> Self {
+        let mut descriptor = Self::texture_2d_descriptor_with_pixel_format_and_width_and_height_and_mipmapped(
+            pixel_format, width, height, mipmapped
+        );
+        descriptor.set_texture_type(MTLTextureType2DArray);
+        descriptor.set_array_length(array_length);
+        descriptor
+    }
+    /// Creates a texture descriptor object for a 3D texture.
+    pub fn texture_3d_descriptor(
+        pixel_format: MTLPixelFormat,
+        width: NSUInteger,
+        height: NSUInteger,
+        depth: NSUInteger,
+        mipmapped: BOOL
+    ) -> Self {
+        let mut descriptor = Self::texture_2d_descriptor_with_pixel_format_and_width_and_height_and_mipmapped(
+            pixel_format, width, height, NO
+        );
+        descriptor.set_texture_type(MTLTextureType3D);
+        descriptor.set_depth(depth);
+        if mipmapped != NO {
+            descriptor.set_mipmap_level_count(mipmap_level_count(width, height, depth));
+        }
+        descriptor
+    }
+    /// Creates a texture descriptor object for a 1D texture.
+    pub fn texture_1d_descriptor(pixel_format: MTLPixelFormat, width: NSUInteger, mipmapped: BOOL) -> Self {
+        let mut descriptor = Self::texture_2d_descriptor_with_pixel_format_and_width_and_height_and_mipmapped(
+            pixel_format, width, 1, mipmapped
+        );
+        descriptor.set_texture_type(MTLTextureType1D);
+        descriptor
+    }
+    /// Returns the underlying Objective C descriptor.
+    pub fn to_objc(&self) -> id { self.descriptor }
+    /// Sets the format that determines how a pixel is written to, stored, and read from the texture.
+    pub fn set_pixel_format(&mut self, pixel_format: MTLPixelFormat) {
+        unsafe { msg_send![self.descriptor, setPixelFormat:pixel_format] }
+    }
+    /// Sets the width of the texture image for the base level mipmap, in pixels.
+    pub fn set_width(&mut self, width: NSUInteger) {
+        unsafe { msg_send![self.descriptor, setWidth:width] }
+    }
+    /// Sets the height of the texture image for the base level mipmap, in pixels.
+    pub fn set_height(&mut self, height: NSUInteger) {
+        unsafe { msg_send![self.descriptor, setHeight:height] }
+    }
+    /// Sets the depth of a 3D texture image for the base level mipmap, in pixels.
+    pub fn set_depth(&mut self, depth: NSUInteger) {
+        unsafe { msg_send![self.descriptor, setDepth:depth] }
+    }
+    /// Gets the depth of the texture image for the base level mipmap, in pixels (1 unless 3D).
+    pub fn get_depth(&self) -> NSUInteger {
+        unsafe { msg_send![self.descriptor, depth] }
+    }
+    /// Sets the number of elements in an array texture (for cube arrays, the number of cubes).
+    pub fn set_array_length(&mut self, array_length: NSUInteger) {
+        unsafe { msg_send![self.descriptor, setArrayLength:array_length] }
+    }
+    /// Gets the number of elements in an array texture.
+    pub fn get_array_length(&self) -> NSUInteger {
+        unsafe { msg_send![self.descriptor, arrayLength] }
+    }
+    /// Sets the number of mipmap levels; see `mipmap_level_count` for a full chain.
+    pub fn set_mipmap_level_count(&mut self, mipmap_level_count: NSUInteger) {
+        unsafe { msg_send![self.descriptor, setMipmapLevelCount:mipmap_level_count] }
+    }
+    /// Gets the number of mipmap levels.
+    pub fn get_mipmap_level_count(&self) -> NSUInteger {
+        unsafe { msg_send![self.descriptor, mipmapLevelCount] }
+    }
+    /// Sets where each channel read from the texture takes its value from.
+    pub fn set_swizzle(&mut self, swizzle: MetalTextureSwizzleChannels) {
+        unsafe { msg_send![self.descriptor, setSwizzle:swizzle] }
+    }
+    /// Gets where each channel read from the texture takes its value from.
+    pub fn get_swizzle(&self) -> MetalTextureSwizzleChannels {
+        unsafe { msg_send![self.descriptor, swizzle] }
+    }
+    /// Gets the width of the texture image for the base level mipmap, in pixels.
+    pub fn get_width(&self) -> NSUInteger {
+        unsafe { msg_send![self.descriptor, width] }
+    }
+    /// Gets the height of the texture image for the base level mipmap, in pixels.
+    pub fn get_height(&self) -> NSUInteger {
+        unsafe { msg_send![self.descriptor, height] }
+    }
+    /// Gets the format that determines how a pixel is written to, stored, and read from the texture.
+    pub fn get_pixel_format(&self) -> MTLPixelFormat {
+        unsafe { msg_send![self.descriptor, pixelFormat] }
+    }
+    /// Sets the dimension and arrangement of texture image data.
+    pub fn set_texture_type(&mut self, texture_type: MTLTextureType) {
+        unsafe { msg_send![self.descriptor, setTextureType:texture_type] }
+    }
+    /// Gets the dimension and arrangement of texture image data.
+    pub fn get_texture_type(&self) -> MTLTextureType {
+        unsafe { msg_send![self.descriptor, textureType] }
+    }
+    /// Sets the number of samples in each pixel.
+    /// Anything above 1 needs the 2D multisample (or multisample array) texture type.
+    pub fn set_sample_count(&mut self, sample_count: NSUInteger) {
+        unsafe { msg_send![self.descriptor, setSampleCount:sample_count] }
+    }
+    /// Gets the number of samples in each pixel.
+    pub fn get_sample_count(&self) -> NSUInteger {
+        unsafe { msg_send![self.descriptor, sampleCount] }
+    }
     /// Set options that determine how you can use the texture.
     pub fn set_usage(&mut self, usage: NSUInteger) {
         unsafe { msg_send![self.descriptor, setUsage:usage] }
@@ -87,3 +459,27 @@ impl MetalTextureDescriptor {
         unsafe { msg_send![self.descriptor, setStorageMode:storage_mode] }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use core_animation::MTLPixelFormatBGRA8Unorm;
+
+    #[test]
+    fn test_depth_and_stencil_components() {
+        assert!(pixel_format_has_depth(MTLPixelFormatDepth32Float));
+        assert!(!pixel_format_has_stencil(MTLPixelFormatDepth32Float));
+
+        assert!(!pixel_format_has_depth(MTLPixelFormatStencil8));
+        assert!(pixel_format_has_stencil(MTLPixelFormatStencil8));
+
+        for format in [MTLPixelFormatDepth32Float_Stencil8, MTLPixelFormatDepth24Unorm_Stencil8].iter() {
+            assert!(pixel_format_has_depth(*format));
+            assert!(pixel_format_has_stencil(*format));
+        }
+
+        assert!(!pixel_format_has_depth(MTLPixelFormatX32_Stencil8));
+        assert!(!pixel_format_has_depth(MTLPixelFormatBGRA8Unorm));
+        assert!(!pixel_format_has_stencil(MTLPixelFormatBGRA8Unorm));
+    }
+}
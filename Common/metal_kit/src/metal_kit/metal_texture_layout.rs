@@ -0,0 +1,209 @@
+//
+//  metal_texture_layout.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! The arithmetic of mipmap levels and slices,
+//! for sizing uploads and checking texture view ranges without a GPU
+
+use cocoa::foundation::NSUInteger;
+use core_animation::MTLPixelFormat;
+use crate::metal_kit::metal_device_capabilities::MTLSize;
+use crate::metal_kit::metal_error::MetalError;
+use crate::metal_kit::metal_texture::MTLRegion;
+use crate::metal_kit::metal_texture_descriptor::{
+    MTLPixelFormatBGRA8Unorm,
+    MTLPixelFormatBGRA8Unorm_sRGB,
+    MTLPixelFormatDepth16Unorm,
+    MTLPixelFormatDepth32Float,
+    MTLPixelFormatR32Float,
+    MTLPixelFormatR8Unorm,
+    MTLPixelFormatRGBA16Float,
+    MTLPixelFormatRGBA32Float,
+    MTLPixelFormatRGBA8Unorm,
+    MTLPixelFormatRGBA8Unorm_sRGB,
+    MTLPixelFormatStencil8,
+    MTLTextureType,
+    MTLTextureType1DArray,
+    MTLTextureType2DArray,
+    MTLTextureType2DMultisampleArray,
+    MTLTextureTypeCube,
+    MTLTextureTypeCubeArray,
+};
+
+/// The number of faces, and so slices, in each cube of a cube texture.
+pub const CUBE_FACE_COUNT: NSUInteger = 6;
+
+/// Returns the number of levels in a full mipmap chain for a texture of the given size,
+/// down to and including the 1×1×1 level.
+pub fn mipmap_level_count(width: NSUInteger, height: NSUInteger, depth: NSUInteger) -> NSUInteger {
+    let largest = width.max(height).max(depth).max(1);
+    (NSUInteger::BITS - largest.leading_zeros()) as NSUInteger
+}
+
+/// Returns the size of the given mipmap level of a texture whose base level has the given size.
+/// Each dimension is halved per level, rounding down, but never goes below 1.
+pub fn mip_level_size(size: MTLSize, level: NSUInteger) -> MTLSize {
+    let halve = |dimension: NSUInteger| dimension.checked_shr(level as u32).unwrap_or(0).max(1);
+    MTLSize { width: halve(size.width), height: halve(size.height), depth: halve(size.depth) }
+}
+
+/// Returns the number of slices in a texture of the given type:
+/// one per array element, six per cube, and one for everything else
+/// (the depth of a 3D texture is not sliced).
+#[allow(non_upper_case_globals)]
+pub fn texture_slice_count(texture_type: MTLTextureType, array_length: NSUInteger) -> NSUInteger {
+    match texture_type {
+        MTLTextureType1DArray | MTLTextureType2DArray | MTLTextureType2DMultisampleArray => array_length,
+        MTLTextureTypeCube => CUBE_FACE_COUNT,
+        MTLTextureTypeCubeArray => CUBE_FACE_COUNT * array_length,
+        _ => 1,
+    }
+}
+
+/// Returns true if the region lies inside a mipmap level of the given size,
+/// and is only one depth plane deep.
+pub fn region_within_level(region: MTLRegion, level_size: MTLSize) -> bool {
+    let fits = |origin: NSUInteger, length: NSUInteger, limit: NSUInteger| {
+        origin.checked_add(length).map_or(false, |end| end <= limit)
+    };
+    region.size.depth == 1
+        && fits(region.origin.x, region.size.width, level_size.width)
+        && fits(region.origin.y, region.size.height, level_size.height)
+        && fits(region.origin.z, region.size.depth, level_size.depth)
+}
+
+/// Returns the slice holding the given face (0 to 5: +X, -X, +Y, -Y, +Z, -Z)
+/// of the given cube in a cube or cube array texture.
+pub fn cube_slice(cube: NSUInteger, face: NSUInteger) -> NSUInteger {
+    cube * CUBE_FACE_COUNT + face
+}
+
+/// Returns the number of bytes in one pixel of the common uncompressed pixel formats,
+/// or None for formats this does not know.
+#[allow(non_upper_case_globals)]
+pub fn pixel_format_bytes_per_pixel(pixel_format: MTLPixelFormat) -> Option<NSUInteger> {
+    match pixel_format {
+        MTLPixelFormatR8Unorm | MTLPixelFormatStencil8 => Some(1),
+        MTLPixelFormatDepth16Unorm => Some(2),
+        MTLPixelFormatRGBA8Unorm
+        | MTLPixelFormatRGBA8Unorm_sRGB
+        | MTLPixelFormatBGRA8Unorm
+        | MTLPixelFormatBGRA8Unorm_sRGB
+        | MTLPixelFormatR32Float
+        | MTLPixelFormatDepth32Float => Some(4),
+        MTLPixelFormatRGBA16Float => Some(8),
+        MTLPixelFormatRGBA32Float => Some(16),
+        _ => None,
+    }
+}
+
+/// Returns the number of bytes in one row of a slice of the given width.
+pub fn bytes_per_row(width: NSUInteger, bytes_per_pixel: NSUInteger) -> Result<NSUInteger, MetalError> {
+    width.checked_mul(bytes_per_pixel)
+        .ok_or(MetalError::TextureSizeOverflow(MTLSize { width, height: 1, depth: 1 }))
+}
+
+/// Returns the number of bytes in one slice of the given mipmap level,
+/// which is what `replaceRegion:...bytesPerImage:` and blits between buffers and textures expect.
+/// For a 3D texture this covers every depth plane of the level.
+pub fn slice_size_in_bytes(size: MTLSize, level: NSUInteger, bytes_per_pixel: NSUInteger) -> Result<NSUInteger, MetalError> {
+    let level_size = mip_level_size(size, level);
+    level_size.width.checked_mul(bytes_per_pixel)
+        .and_then(|row| row.checked_mul(level_size.height))
+        .and_then(|plane| plane.checked_mul(level_size.depth))
+        .ok_or(MetalError::TextureSizeOverflow(size))
+}
+
+/// Returns the number of bytes needed to hold every level of every slice of a texture,
+/// packed with no row padding.
+pub fn texture_size_in_bytes(
+    size: MTLSize,
+    level_count: NSUInteger,
+    slice_count: NSUInteger,
+    bytes_per_pixel: NSUInteger
+) -> Result<NSUInteger, MetalError> {
+    let mut per_slice: NSUInteger = 0;
+    for level in 0..level_count {
+        per_slice = per_slice.checked_add(slice_size_in_bytes(size, level, bytes_per_pixel)?)
+            .ok_or(MetalError::TextureSizeOverflow(size))?;
+    }
+    per_slice.checked_mul(slice_count).ok_or(MetalError::TextureSizeOverflow(size))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use cocoa::foundation::NSUInteger;
+
+    fn size(width: NSUInteger, height: NSUInteger, depth: NSUInteger) -> MTLSize {
+        MTLSize { width, height, depth }
+    }
+
+    #[test]
+    fn test_mipmap_level_counts() {
+        assert_eq!(1, mipmap_level_count(1, 1, 1));
+        assert_eq!(1, mipmap_level_count(0, 0, 0));
+        assert_eq!(9, mipmap_level_count(256, 256, 1));
+        assert_eq!(10, mipmap_level_count(512, 3, 1));
+        assert_eq!(10, mipmap_level_count(300, 600, 1));
+        assert_eq!(7, mipmap_level_count(16, 16, 64));
+
+        let base = size(300, 40, 1);
+        assert_eq!(size(150, 20, 1), mip_level_size(base, 1));
+        assert_eq!(size(9, 1, 1), mip_level_size(base, 5));
+        assert_eq!(size(1, 1, 1), mip_level_size(base, 8));
+        assert_eq!(size(1, 1, 1), mip_level_size(base, 200));
+        assert_eq!(size(8, 8, 2), mip_level_size(size(32, 32, 8), 2));
+    }
+
+    #[test]
+    fn test_slice_counts() {
+        assert_eq!(1, texture_slice_count(MTLTextureType2D, 1));
+        assert_eq!(1, texture_slice_count(MTLTextureType3D, 1));
+        assert_eq!(4, texture_slice_count(MTLTextureType2DArray, 4));
+        assert_eq!(6, texture_slice_count(MTLTextureTypeCube, 1));
+        assert_eq!(18, texture_slice_count(MTLTextureTypeCubeArray, 3));
+        assert_eq!(0, cube_slice(0, 0));
+        assert_eq!(11, cube_slice(1, 5));
+    }
+
+    #[test]
+    fn test_slice_and_texture_sizes() {
+        assert_eq!(Some(4), pixel_format_bytes_per_pixel(MTLPixelFormatBGRA8Unorm));
+        assert_eq!(Some(16), pixel_format_bytes_per_pixel(MTLPixelFormatRGBA32Float));
+        assert_eq!(None, pixel_format_bytes_per_pixel(MTLPixelFormatDepth32Float_Stencil8));
+        assert_eq!(Ok(1024), bytes_per_row(256, 4));
+
+        let base = size(4, 4, 1);
+        assert_eq!(Ok(64), slice_size_in_bytes(base, 0, 4));
+        assert_eq!(Ok(16), slice_size_in_bytes(base, 1, 4));
+        assert_eq!(Ok(4), slice_size_in_bytes(base, 2, 4));
+        // A mipmapped cube of 4×4 faces: (64 + 16 + 4) bytes for each of 6 faces.
+        assert_eq!(Ok(504), texture_size_in_bytes(base, 3, 6, 4));
+        // A 3D texture's slice covers every depth plane.
+        assert_eq!(Ok(8 * 8 * 8 * 2), slice_size_in_bytes(size(8, 8, 8), 0, 2));
+        assert_eq!(Ok(4 * 4 * 4 * 2), slice_size_in_bytes(size(8, 8, 8), 1, 2));
+        // Sizes too big for memory are reported rather than wrapping around.
+        let huge = size(1 << 40, 1 << 40, 1);
+        assert_eq!(Err(MetalError::TextureSizeOverflow(huge)), slice_size_in_bytes(huge, 0, 4));
+        assert_eq!(Err(MetalError::TextureSizeOverflow(base)), texture_size_in_bytes(base, 3, NSUInteger::MAX, 4));
+        assert!(bytes_per_row(NSUInteger::MAX, 16).is_err());
+
+        let level_size = mip_level_size(size(64, 32, 1), 1);
+        assert!(region_within_level(MTLRegion::new_2d(0, 0, 32, 16), level_size));
+        assert!(region_within_level(MTLRegion::new_2d(31, 15, 1, 1), level_size));
+        assert!(!region_within_level(MTLRegion::new_2d(31, 15, 2, 1), level_size));
+        assert!(!region_within_level(MTLRegion::new_2d(NSUInteger::MAX, 0, 2, 1), level_size));
+        let mut deep = MTLRegion::new_2d(0, 0, 4, 4);
+        deep.size.depth = 2;
+        assert!(!region_within_level(deep, size(8, 8, 8)));
+        deep.size.depth = 1;
+        deep.origin.z = 7;
+        assert!(region_within_level(deep, size(8, 8, 8)));
+        assert!(!region_within_level(deep, size(8, 8, 4)));
+    }
+}
@@ -0,0 +1,297 @@
+//
+//  metal_texture_readback.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Copying rendered pixels back to the CPU, converting them to 8-bit RGBA,
+//! and saving them as PNG screenshots
+
+use cocoa::base::{id, nil};
+use cocoa::foundation::NSUInteger;
+use core_animation::MTLPixelFormat;
+use objc::msg_send;
+use objc::sel;
+use objc::sel_impl;
+use std::fs;
+use std::path::Path;
+use crate::metal_kit::metal_buffer::MetalBuffer;
+use crate::metal_kit::metal_command_buffer::MetalCommandBuffer;
+use crate::metal_kit::metal_device::MetalDevice;
+use crate::metal_kit::metal_device_capabilities::MTLSize;
+use crate::metal_kit::metal_error::MetalError;
+use crate::metal_kit::metal_png_encoder::encode_png_rgba8;
+use crate::metal_kit::metal_resource_options::MetalResourceOptions;
+use crate::metal_kit::metal_texture::{MTLOrigin, MetalTexture};
+use crate::metal_kit::metal_texture_descriptor::{
+    MTLPixelFormatBGRA8Unorm,
+    MTLPixelFormatBGRA8Unorm_sRGB,
+    MTLPixelFormatRGBA16Float,
+    MTLPixelFormatRGBA8Unorm,
+    MTLPixelFormatRGBA8Unorm_sRGB,
+};
+use crate::metal_kit::metal_texture_layout::{bytes_per_row, pixel_format_bytes_per_pixel};
+
+/// Converts a 16-bit (half precision) float to an `f32`.
+pub fn half_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((half >> 10) & 0x1f) as i32;
+    let mantissa = (half & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+/// Converts a value to an 8-bit normalized value, clamping it to 0...1 (NaN becomes 0).
+pub fn unorm8_from_f32(value: f32) -> u8 {
+    if value.is_nan() {
+        return 0;
+    }
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Converts BGRA8 pixels to RGBA8 by swapping the red and blue channels.
+pub fn bgra8_to_rgba8(bgra: &[u8]) -> Vec<u8> {
+    bgra.chunks_exact(4).flat_map(|pixel| [pixel[2], pixel[1], pixel[0], pixel[3]]).collect()
+}
+
+/// Applies the sRGB transfer function to a linear color value,
+/// giving the encoded value an sRGB image stores.
+pub fn linear_to_srgb(linear: f32) -> f32 {
+    if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Converts RGBA16Float pixels (little-endian halves) to sRGB-encoded RGBA8.
+///
+/// Half float drawables hold linear (extended sRGB) values, so the color channels
+/// go through the sRGB transfer function before being clamped to 0...1; alpha stays linear.
+pub fn rgba16f_to_rgba8(rgba16f: &[u8]) -> Vec<u8> {
+    rgba16f.chunks_exact(2)
+        .map(|half| half_to_f32(u16::from_le_bytes([half[0], half[1]])))
+        .enumerate()
+        .map(|(channel, value)| if channel % 4 == 3 { unorm8_from_f32(value) } else { unorm8_from_f32(linear_to_srgb(value)) })
+        .collect()
+}
+
+/// Returns true if `pixels_to_rgba8` can convert pixels of the given format.
+#[allow(non_upper_case_globals)]
+pub fn can_convert_to_rgba8(pixel_format: MTLPixelFormat) -> bool {
+    matches!(pixel_format,
+        MTLPixelFormatBGRA8Unorm
+        | MTLPixelFormatBGRA8Unorm_sRGB
+        | MTLPixelFormatRGBA8Unorm
+        | MTLPixelFormatRGBA8Unorm_sRGB
+        | MTLPixelFormatRGBA16Float)
+}
+
+/// Converts tightly packed pixels of the given format to RGBA8.
+///
+/// sRGB formats are copied as they are, as PNG files are sRGB too.
+#[allow(non_upper_case_globals)]
+pub fn pixels_to_rgba8(pixel_format: MTLPixelFormat, pixels: &[u8]) -> Result<Vec<u8>, MetalError> {
+    match pixel_format {
+        MTLPixelFormatRGBA8Unorm | MTLPixelFormatRGBA8Unorm_sRGB => Ok(pixels.to_vec()),
+        MTLPixelFormatBGRA8Unorm | MTLPixelFormatBGRA8Unorm_sRGB => Ok(bgra8_to_rgba8(pixels)),
+        MTLPixelFormatRGBA16Float => Ok(rgba16f_to_rgba8(pixels)),
+        _ => Err(MetalError::UnsupportedPixelFormat(pixel_format)),
+    }
+}
+
+/// An image of 8-bit RGBA pixels, row by row from the top.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    /// The width in pixels.
+    pub width: u32,
+    /// The height in pixels.
+    pub height: u32,
+    /// The pixels, four bytes each, with no padding between rows.
+    pub pixels: Vec<u8>,
+}
+impl RgbaImage {
+    /// Converts rows of pixels of the given format, `bytes_per_row` apart, to an RGBA8 image.
+    pub fn from_pixels(
+        width: u32,
+        height: u32,
+        bytes_per_row: NSUInteger,
+        pixel_format: MTLPixelFormat,
+        bytes: &[u8]
+    ) -> Result<Self, MetalError> {
+        let bytes_per_pixel = pixel_format_bytes_per_pixel(pixel_format)
+            .filter(|_| can_convert_to_rgba8(pixel_format))
+            .ok_or(MetalError::UnsupportedPixelFormat(pixel_format))?;
+        let row_length = (width as NSUInteger * bytes_per_pixel) as usize;
+        if height > 0 && (bytes_per_row as usize) < row_length {
+            return Err(MetalError::BufferTooSmall { required: row_length as NSUInteger, length: bytes_per_row });
+        }
+        let required = if height == 0 { 0 } else { bytes_per_row as usize * (height as usize - 1) + row_length };
+        if bytes.len() < required {
+            return Err(MetalError::BufferTooSmall { required: required as NSUInteger, length: bytes.len() as NSUInteger });
+        }
+        let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+        for row in 0..height as usize {
+            let start = row * bytes_per_row as usize;
+            pixels.extend(pixels_to_rgba8(pixel_format, &bytes[start..start + row_length])?);
+        }
+        Ok(RgbaImage { width, height, pixels })
+    }
+    /// Gets the pixel at the given position, as [red, green, blue, alpha].
+    pub fn pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let start = (y as usize * self.width as usize + x as usize) * 4;
+        Some([self.pixels[start], self.pixels[start + 1], self.pixels[start + 2], self.pixels[start + 3]])
+    }
+    /// Encodes the image as a PNG file.
+    pub fn encode_png(&self) -> Vec<u8> { encode_png_rgba8(self.width, self.height, &self.pixels) }
+    /// Writes the image to the given path as a PNG file.
+    pub fn write_png(&self, path: &Path) -> Result<(), MetalError> {
+        fs::write(path, self.encode_png())
+            .map_err(|error| MetalError::ImageExport(format!("{}: {}", path.display(), error)))
+    }
+}
+
+/// The pixels of a texture, copied by the GPU into a shared buffer
+/// that the CPU can read once the command buffer has completed.
+///
+/// This works for textures the CPU cannot read directly, such as private textures,
+/// and drawables (as long as the layer is not framebuffer only).
+pub struct TextureReadback {
+    buffer: MetalBuffer,
+    width: NSUInteger,
+    height: NSUInteger,
+    bytes_per_row: NSUInteger,
+    pixel_format: MTLPixelFormat,
+}
+// The buffer is only read after the GPU has finished writing it,
+// typically from the command buffer's completed handler.
+unsafe impl Send for TextureReadback {}
+impl TextureReadback {
+    /// Encodes a copy of the base level of the texture (its first slice)
+    /// into a new shared buffer, at the end of the command buffer.
+    pub fn encode(command_buffer: &MetalCommandBuffer, texture: &MetalTexture) -> Result<Self, MetalError> {
+        let pixel_format = texture.get_pixel_format();
+        let bytes_per_pixel = pixel_format_bytes_per_pixel(pixel_format)
+            .ok_or(MetalError::UnsupportedPixelFormat(pixel_format))?;
+        let width = texture.get_width();
+        let height = texture.get_height();
+        let bytes_per_row = bytes_per_row(width, bytes_per_pixel)?;
+        let length = bytes_per_row.checked_mul(height)
+            .ok_or(MetalError::TextureSizeOverflow(MTLSize { width, height, depth: 1 }))?;
+        let device: id = unsafe { msg_send![texture.to_objc(), device] };
+        let mut device = MetalDevice::from(device);
+        let buffer = device.new_buffer_with_length_and_options(length, MetalResourceOptions::SHARED);
+        if buffer.to_objc() == nil {
+            return Err(MetalError::BufferCreation(length));
+        }
+        let mut blit = command_buffer.blit_command_encoder();
+        blit.set_label("Texture readback");
+        blit.copy_from_texture_to_buffer(
+            texture,
+            0,
+            0,
+            MTLOrigin::default(),
+            MTLSize { width, height, depth: 1 },
+            &buffer,
+            0,
+            bytes_per_row,
+            length
+        );
+        blit.end_encoding();
+        Ok(TextureReadback { buffer, width, height, bytes_per_row, pixel_format })
+    }
+    /// Gets the pixel format the pixels were copied in.
+    pub fn get_pixel_format(&self) -> MTLPixelFormat { self.pixel_format }
+    /// Converts the copied pixels to an RGBA8 image.
+    ///
+    /// Only call this once the command buffer has completed.
+    pub fn to_image(&self) -> Result<RgbaImage, MetalError> {
+        let contents = self.buffer.get_contents() as *const u8;
+        if contents.is_null() {
+            return Err(MetalError::BufferContentsUnavailable);
+        }
+        let bytes = unsafe { std::slice::from_raw_parts(contents, self.buffer.get_length() as usize) };
+        RgbaImage::from_pixels(self.width as u32, self.height as u32, self.bytes_per_row, self.pixel_format, bytes)
+    }
+    /// Converts the copied pixels and writes them to the given path as a PNG file.
+    ///
+    /// Only call this once the command buffer has completed.
+    pub fn write_png(&self, path: &Path) -> Result<(), MetalError> {
+        self.to_image()?.write_png(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_half_floats() {
+        assert_eq!(0.0, half_to_f32(0x0000));
+        assert_eq!(1.0, half_to_f32(0x3c00));
+        assert_eq!(-2.0, half_to_f32(0xc000));
+        assert_eq!(0.5, half_to_f32(0x3800));
+        assert_eq!(65504.0, half_to_f32(0x7bff));
+        assert_eq!(2f32.powi(-24), half_to_f32(0x0001));
+        assert_eq!(f32::INFINITY, half_to_f32(0x7c00));
+        assert!(half_to_f32(0x7e00).is_nan());
+
+        assert_eq!(0, unorm8_from_f32(-1.0));
+        assert_eq!(128, unorm8_from_f32(0.5));
+        assert_eq!(255, unorm8_from_f32(4.0));
+        assert_eq!(0, unorm8_from_f32(f32::NAN));
+    }
+
+    #[test]
+    fn test_pixel_conversions() {
+        assert_eq!(vec![3, 2, 1, 4, 30, 20, 10, 40], bgra8_to_rgba8(&[1, 2, 3, 4, 10, 20, 30, 40]));
+        // (1.0, 0.5, 0.0, 2.0) as little-endian halves: linear 0.5 is sRGB 188, alpha is not encoded.
+        let half_pixel = [0x00, 0x3c, 0x00, 0x38, 0x00, 0x00, 0x00, 0x40];
+        assert_eq!(vec![255, 188, 0, 255], rgba16f_to_rgba8(&half_pixel));
+        assert_eq!(Ok(vec![255, 188, 0, 255]), pixels_to_rgba8(MTLPixelFormatRGBA16Float, &half_pixel));
+        // (0.5, 0.5, 0.5, 0.5): only the color channels are encoded.
+        assert_eq!(vec![188, 188, 188, 128], rgba16f_to_rgba8(&[0x00, 0x38, 0x00, 0x38, 0x00, 0x38, 0x00, 0x38]));
+        assert_eq!(0.0, linear_to_srgb(0.0));
+        assert_eq!(12.92 * 0.002, linear_to_srgb(0.002));
+        assert!((linear_to_srgb(1.0) - 1.0).abs() < 1e-6);
+        assert!((linear_to_srgb(0.214) - 0.5).abs() < 1e-3);
+        assert_eq!(Ok(vec![3, 2, 1, 4]), pixels_to_rgba8(MTLPixelFormatBGRA8Unorm, &[1, 2, 3, 4]));
+        assert_eq!(Ok(vec![1, 2, 3, 4]), pixels_to_rgba8(MTLPixelFormatRGBA8Unorm, &[1, 2, 3, 4]));
+        assert_eq!(
+            Err(MetalError::UnsupportedPixelFormat(MTLPixelFormatDepth32Float)),
+            pixels_to_rgba8(MTLPixelFormatDepth32Float, &[0, 0, 0, 0])
+        );
+    }
+
+    #[test]
+    fn test_images_drop_row_padding() {
+        // Two rows of two BGRA pixels, 12 bytes apart.
+        let bytes = [
+            0, 0, 255, 255,  0, 255, 0, 255,  9, 9, 9, 9,
+            255, 0, 0, 255,  1, 2, 3, 4,      9, 9, 9, 9,
+        ];
+        let image = RgbaImage::from_pixels(2, 2, 12, MTLPixelFormatBGRA8Unorm, &bytes).unwrap();
+        assert_eq!(16, image.pixels.len());
+        assert_eq!(Some([255, 0, 0, 255]), image.pixel(0, 0));
+        assert_eq!(Some([0, 255, 0, 255]), image.pixel(1, 0));
+        assert_eq!(Some([0, 0, 255, 255]), image.pixel(0, 1));
+        assert_eq!(Some([3, 2, 1, 4]), image.pixel(1, 1));
+        assert_eq!(None, image.pixel(2, 0));
+        assert_eq!(PNG_SIGNATURE, image.encode_png()[..8]);
+        // The last row does not need its padding.
+        assert!(RgbaImage::from_pixels(2, 2, 12, MTLPixelFormatBGRA8Unorm, &bytes[..20]).is_ok());
+        assert_eq!(
+            Err(MetalError::BufferTooSmall { required: 20, length: 19 }),
+            RgbaImage::from_pixels(2, 2, 12, MTLPixelFormatBGRA8Unorm, &bytes[..19])
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//
+//  metal_typed_buffer.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! A buffer holding a known number of values of one type,
+//! with bounds-checked access from the CPU
+
+use cocoa::base::nil;
+use cocoa::foundation::NSUInteger;
+use std::marker::PhantomData;
+use std::mem::{align_of, size_of};
+use std::ops::Range;
+use crate::metal_kit::metal_buffer::MetalBuffer;
+use crate::metal_kit::metal_buffer_storage::BufferStorage;
+use crate::metal_kit::metal_device::MetalDevice;
+use crate::metal_kit::metal_error::MetalError;
+use crate::metal_kit::metal_resource_options::MetalResourceOptions;
+
+/// A buffer of `len()` values of type `T`, laid out one after the other.
+///
+/// The contents have to be visible to the CPU (shared or managed storage).
+/// Writes made through `write_at` and `write_slice_at` tell a managed buffer what changed;
+/// after changing values through `as_mut_slice`, call `did_modify` yourself.
+pub struct TypedBuffer<T: Copy, S: BufferStorage = MetalBuffer> {
+    storage: S,
+    count: usize,
+    element: PhantomData<T>,
+}
+impl<T: Copy, S: BufferStorage + Default> Default for TypedBuffer<T, S> {
+    /// An empty buffer, holding no values.
+    fn default() -> Self {
+        TypedBuffer { storage: S::default(), count: 0, element: PhantomData }
+    }
+}
+impl<T: Copy> TypedBuffer<T> {
+    /// Creates a Metal buffer holding a copy of the given values.
+    ///
+    /// Metal cannot make empty buffers, so there must be at least one byte of values.
+    pub fn new_with_slice(device: &mut MetalDevice, values: &[T], options: MetalResourceOptions) -> Result<Self, MetalError> {
+        let buffer = device.new_buffer_with_bytes_and_options(values, options)?;
+        Self::with_storage(buffer, values.len())
+    }
+    /// Creates a zero-filled Metal buffer with room for `count` values.
+    ///
+    /// Metal cannot make empty buffers, so the values must take up at least one byte.
+    pub fn new_with_length(device: &mut MetalDevice, count: usize, options: MetalResourceOptions) -> Result<Self, MetalError> {
+        let length = Self::byte_length(count)?;
+        if length == 0 {
+            return Err(MetalError::EmptyBuffer);
+        }
+        let buffer = device.new_buffer_with_length_and_options(length, options);
+        if buffer.to_objc() == nil {
+            return Err(MetalError::BufferCreation(length));
+        }
+        Self::with_storage(buffer, count)
+    }
+}
+impl<T: Copy, S: BufferStorage> TypedBuffer<T, S> {
+    /// Returns the number of bytes taken up by `count` values,
+    /// or an error if that does not fit in memory.
+    pub fn byte_length(count: usize) -> Result<NSUInteger, MetalError> {
+        size_of::<T>()
+            .checked_mul(count)
+            .map(|length| length as NSUInteger)
+            .ok_or(MetalError::BufferLengthOverflow(count))
+    }
+    /// Treats existing storage as holding `count` values.
+    pub fn with_storage(storage: S, count: usize) -> Result<Self, MetalError> {
+        let contents = storage.contents();
+        if contents.is_null() {
+            return Err(MetalError::BufferContentsUnavailable);
+        }
+        if contents as usize & (align_of::<T>() - 1) != 0 {
+            return Err(MetalError::InvalidBufferAlignment(align_of::<T>() as NSUInteger));
+        }
+        let required = Self::byte_length(count)?;
+        let length = storage.length();
+        if length < required {
+            return Err(MetalError::BufferTooSmall { required, length });
+        }
+        Ok(TypedBuffer { storage, count, element: PhantomData })
+    }
+    /// Gets the storage, e.g. to bind the Metal buffer to an encoder.
+    pub fn get_storage(&self) -> &S { &self.storage }
+    /// Gets the storage mutably, e.g. to set the buffer's label.
+    pub fn get_storage_mut(&mut self) -> &mut S { &mut self.storage }
+    /// Gets the number of values in the buffer.
+    pub fn len(&self) -> usize { self.count }
+    /// Returns true if the buffer holds no values.
+    pub fn is_empty(&self) -> bool { self.count == 0 }
+    /// Gets the offset in bytes of the value at the given index,
+    /// e.g. to bind the buffer from that value onwards.
+    pub fn byte_offset(&self, index: usize) -> Result<NSUInteger, MetalError> {
+        if index > self.count {
+            return Err(MetalError::BufferIndexOutOfBounds { index, count: self.count });
+        }
+        Self::byte_length(index)
+    }
+    /// Gets the values in the buffer.
+    pub fn as_slice(&self) -> &[T] {
+        if self.count == 0 {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(self.storage.contents() as *const T, self.count) }
+    }
+    /// Gets the values in the buffer to change.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        if self.count == 0 {
+            return &mut [];
+        }
+        unsafe { std::slice::from_raw_parts_mut(self.storage.contents() as *mut T, self.count) }
+    }
+    /// Writes a value at the given index.
+    pub fn write_at(&mut self, index: usize, value: T) -> Result<(), MetalError> {
+        self.write_slice_at(index, &[value])
+    }
+    /// Writes the values into the buffer, starting at the given index.
+    pub fn write_slice_at(&mut self, start: usize, values: &[T]) -> Result<(), MetalError> {
+        let end = start.saturating_add(values.len());
+        if end > self.count {
+            let index = if start >= self.count { start } else { end - 1 };
+            return Err(MetalError::BufferIndexOutOfBounds { index, count: self.count });
+        }
+        self.as_mut_slice()[start..end].copy_from_slice(values);
+        self.did_modify(start..end)
+    }
+    /// Tells a managed buffer that the values in the given range have been changed.
+    pub fn did_modify(&self, range: Range<usize>) -> Result<(), MetalError> {
+        if range.end > self.count || range.start > range.end {
+            return Err(MetalError::BufferIndexOutOfBounds { index: range.end.max(range.start), count: self.count });
+        }
+        self.storage.did_modify_range(Self::byte_length(range.start)?, Self::byte_length(range.len())?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[derive(Debug, Copy, Clone, Default, PartialEq)]
+    #[repr(C)]
+    struct Vertex {
+        position: [f32; 4],
+        color: [f32; 4],
+    }
+
+    fn vertex(value: f32) -> Vertex {
+        Vertex { position: [value; 4], color: [1.0; 4] }
+    }
+
+    #[test]
+    fn test_new_buffer_is_zero_filled_and_sized() {
+        let buffer: TypedBuffer<Vertex, HostBuffer> = TypedBuffer::with_storage(HostBuffer::new(100), 3).unwrap();
+        assert_eq!(3, buffer.len());
+        assert!(!buffer.is_empty());
+        assert_eq!(&[Vertex::default(); 3], buffer.as_slice());
+        assert_eq!(Ok(64), buffer.byte_offset(2));
+        assert_eq!(Ok(96), buffer.byte_offset(3));
+        assert_eq!(Err(MetalError::BufferIndexOutOfBounds { index: 4, count: 3 }), buffer.byte_offset(4));
+    }
+
+    #[test]
+    fn test_writes_are_bounds_checked_and_reported() {
+        let mut buffer: TypedBuffer<Vertex, HostBuffer> = TypedBuffer::with_storage(HostBuffer::new(96), 3).unwrap();
+        assert_eq!(Ok(()), buffer.write_at(1, vertex(2.0)));
+        assert_eq!(Err(MetalError::BufferIndexOutOfBounds { index: 3, count: 3 }), buffer.write_at(3, vertex(3.0)));
+        assert_eq!(
+            Err(MetalError::BufferIndexOutOfBounds { index: 3, count: 3 }),
+            buffer.write_slice_at(2, &[vertex(4.0), vertex(5.0)])
+        );
+        assert_eq!(Ok(()), buffer.write_slice_at(1, &[vertex(4.0), vertex(5.0)]));
+        assert_eq!(&[Vertex::default(), vertex(4.0), vertex(5.0)], buffer.as_slice());
+        assert_eq!(vec![32..64, 32..96], buffer.get_storage().take_modified_ranges());
+
+        buffer.as_mut_slice()[0] = vertex(6.0);
+        assert_eq!(Ok(()), buffer.did_modify(0..1));
+        assert_eq!(Err(MetalError::BufferIndexOutOfBounds { index: 4, count: 3 }), buffer.did_modify(0..4));
+        assert_eq!(vec![0..32], buffer.get_storage().take_modified_ranges());
+    }
+
+    #[test]
+    fn test_storage_must_fit_and_be_visible() {
+        type Buffer = TypedBuffer<Vertex, HostBuffer>;
+        assert_eq!(
+            Err(MetalError::BufferTooSmall { required: 96, length: 95 }),
+            Buffer::with_storage(HostBuffer::new(95), 3).map(|_| ())
+        );
+        let empty = Buffer::with_storage(HostBuffer::new(0), 0).unwrap();
+        assert!(empty.is_empty());
+        assert!(empty.as_slice().is_empty());
+        assert_eq!(Ok(()), empty.did_modify(0..0));
+        assert!(empty.get_storage().take_modified_ranges().is_empty());
+
+        let mut default: TypedBuffer<Vertex, HostBuffer> = TypedBuffer::default();
+        assert!(default.as_mut_slice().is_empty());
+        assert_eq!(Err(MetalError::BufferIndexOutOfBounds { index: 0, count: 0 }), default.write_at(0, vertex(1.0)));
+
+        // A count whose byte length wraps around must not pass the length check.
+        assert_eq!(Err(MetalError::BufferLengthOverflow(usize::MAX / 16)), Buffer::byte_length(usize::MAX / 16));
+        assert_eq!(
+            Err(MetalError::BufferLengthOverflow(usize::MAX / 16)),
+            Buffer::with_storage(HostBuffer::new(96), usize::MAX / 16).map(|_| ())
+        );
+    }
+}
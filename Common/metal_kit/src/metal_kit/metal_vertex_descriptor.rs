@@ -0,0 +1,162 @@
+//
+//  metal_vertex_descriptor.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! How a render pipeline reads vertex attributes from its buffers,
+//! kept as a plain Rust value until it is applied to a descriptor
+
+use objc::class;
+use objc::msg_send;
+use objc::sel;
+use objc::sel_impl;
+use cocoa::base::id;
+use cocoa::foundation::NSUInteger;
+
+// From MTLVertexDescriptor.h:
+// typedef NS_ENUM(NSUInteger, MTLVertexFormat) {
+//     MTLVertexFormatInvalid = 0,
+//     MTLVertexFormatUChar4Normalized = 9,
+//     MTLVertexFormatHalf2 = 25,
+//     MTLVertexFormatHalf4 = 27,
+//     MTLVertexFormatFloat = 28,
+//     MTLVertexFormatFloat2 = 29,
+//     MTLVertexFormatFloat3 = 30,
+//     MTLVertexFormatFloat4 = 31,
+//     MTLVertexFormatInt = 32,
+//     MTLVertexFormatUInt = 36,
+//     ...
+// } API_AVAILABLE(macos(10.11), ios(8.0));
+/// The format of a vertex attribute in its buffer.
+pub type MTLVertexFormat = NSUInteger;
+/// No format: the attribute is not read.
+#[allow(non_upper_case_globals)]
+pub const MTLVertexFormatInvalid: MTLVertexFormat = 0;
+/// Four unsigned 8-bit values, normalized to [0, 1].
+#[allow(non_upper_case_globals)]
+pub const MTLVertexFormatUChar4Normalized: MTLVertexFormat = 9;
+/// Two half-precision floats.
+#[allow(non_upper_case_globals)]
+pub const MTLVertexFormatHalf2: MTLVertexFormat = 25;
+/// Four half-precision floats.
+#[allow(non_upper_case_globals)]
+pub const MTLVertexFormatHalf4: MTLVertexFormat = 27;
+/// One float.
+#[allow(non_upper_case_globals)]
+pub const MTLVertexFormatFloat: MTLVertexFormat = 28;
+/// Two floats.
+#[allow(non_upper_case_globals)]
+pub const MTLVertexFormatFloat2: MTLVertexFormat = 29;
+/// Three floats.
+#[allow(non_upper_case_globals)]
+pub const MTLVertexFormatFloat3: MTLVertexFormat = 30;
+/// Four floats.
+#[allow(non_upper_case_globals)]
+pub const MTLVertexFormatFloat4: MTLVertexFormat = 31;
+/// One signed 32-bit integer.
+#[allow(non_upper_case_globals)]
+pub const MTLVertexFormatInt: MTLVertexFormat = 32;
+/// One unsigned 32-bit integer.
+#[allow(non_upper_case_globals)]
+pub const MTLVertexFormatUInt: MTLVertexFormat = 36;
+//
+// typedef NS_ENUM(NSUInteger, MTLVertexStepFunction) {
+//     MTLVertexStepFunctionConstant = 0,
+//     MTLVertexStepFunctionPerVertex = 1,
+//     MTLVertexStepFunctionPerInstance = 2,
+//     ...
+// } API_AVAILABLE(macos(10.11), ios(8.0));
+/// How often the pipeline moves on to the next element of a vertex buffer.
+pub type MTLVertexStepFunction = NSUInteger;
+/// Every vertex reads the same element.
+#[allow(non_upper_case_globals)]
+pub const MTLVertexStepFunctionConstant: MTLVertexStepFunction = 0;
+/// Each vertex reads the next element.
+#[allow(non_upper_case_globals)]
+pub const MTLVertexStepFunctionPerVertex: MTLVertexStepFunction = 1;
+/// Each instance (or every `step_rate` instances) reads the next element.
+#[allow(non_upper_case_globals)]
+pub const MTLVertexStepFunctionPerInstance: MTLVertexStepFunction = 2;
+
+/// Where one attribute (`[[attribute(n)]]` in the shader) is found.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MetalVertexAttribute {
+    /// The attribute index in the shader.
+    pub index: NSUInteger,
+    /// The format of the attribute in the buffer.
+    pub format: MTLVertexFormat,
+    /// The offset of the attribute in each element of the buffer.
+    pub offset: NSUInteger,
+    /// The index of the vertex buffer the attribute is read from.
+    pub buffer_index: NSUInteger,
+}
+
+/// How the elements of one vertex buffer are laid out and stepped through.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MetalVertexBufferLayout {
+    /// The index of the vertex buffer.
+    pub buffer_index: NSUInteger,
+    /// The distance in bytes between elements.
+    pub stride: NSUInteger,
+    /// How often the pipeline moves on to the next element.
+    pub step_function: MTLVertexStepFunction,
+    /// The number of instances that share an element, for per-instance stepping.
+    pub step_rate: NSUInteger,
+}
+impl MetalVertexBufferLayout {
+    /// A buffer with one element per vertex.
+    pub fn per_vertex(buffer_index: NSUInteger, stride: NSUInteger) -> Self {
+        MetalVertexBufferLayout { buffer_index, stride, step_function: MTLVertexStepFunctionPerVertex, step_rate: 1 }
+    }
+    /// A buffer with one element per instance.
+    pub fn per_instance(buffer_index: NSUInteger, stride: NSUInteger) -> Self {
+        MetalVertexBufferLayout { buffer_index, stride, step_function: MTLVertexStepFunctionPerInstance, step_rate: 1 }
+    }
+}
+
+/// The vertex attributes a pipeline reads, and the layouts of the buffers they come from.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct MetalVertexDescriptor {
+    /// The attributes, in any order.
+    pub attributes: Vec<MetalVertexAttribute>,
+    /// The buffer layouts, in any order.
+    pub layouts: Vec<MetalVertexBufferLayout>,
+}
+impl MetalVertexDescriptor {
+    /// Adds an attribute.
+    pub fn with_attribute(mut self, index: NSUInteger, format: MTLVertexFormat, offset: NSUInteger, buffer_index: NSUInteger) -> Self {
+        self.attributes.push(MetalVertexAttribute { index, format, offset, buffer_index });
+        self
+    }
+    /// Adds a buffer layout.
+    pub fn with_layout(mut self, layout: MetalVertexBufferLayout) -> Self {
+        self.layouts.push(layout);
+        self
+    }
+    /// Creates a new MTLVertexDescriptor with these settings.
+    /// The caller is responsible for releasing it.
+    pub(crate) fn new_objc(&self) -> id {
+        let class = class!(MTLVertexDescriptor);
+        unsafe {
+            let descriptor: id = msg_send![class, new];
+            let attributes: id = msg_send![descriptor, attributes];
+            for attribute in &self.attributes {
+                let element: id = msg_send![attributes, objectAtIndexedSubscript:attribute.index];
+                let _:() = msg_send![element, setFormat:attribute.format];
+                let _:() = msg_send![element, setOffset:attribute.offset];
+                let _:() = msg_send![element, setBufferIndex:attribute.buffer_index];
+            }
+            let layouts: id = msg_send![descriptor, layouts];
+            for layout in &self.layouts {
+                let element: id = msg_send![layouts, objectAtIndexedSubscript:layout.buffer_index];
+                let _:() = msg_send![element, setStride:layout.stride];
+                let _:() = msg_send![element, setStepFunction:layout.step_function];
+                let _:() = msg_send![element, setStepRate:layout.step_rate];
+            }
+            descriptor
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//
+//  metal_viewport.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Viewports and scissor rectangles, laid out as Metal expects them
+
+use cocoa::foundation::NSUInteger;
+
+// From System/Library/Frameworks/Metal.framework/Headers/MTLRenderCommandEncoder.h:
+// typedef struct {
+//     double originX, originY, width, height, znear, zfar;
+// } MTLViewport;
+/// The area of the attachment that normalized device coordinates are mapped to,
+/// and the depth range they are mapped to.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(C)]
+pub struct MetalViewport {
+    /// The left edge, in pixels.
+    pub origin_x: f64,
+    /// The top edge, in pixels.
+    pub origin_y: f64,
+    /// The width, in pixels.
+    pub width: f64,
+    /// The height, in pixels.
+    pub height: f64,
+    /// The depth that the near clipping plane is mapped to.
+    pub znear: f64,
+    /// The depth that the far clipping plane is mapped to.
+    pub zfar: f64,
+}
+impl MetalViewport {
+    /// A viewport over the given area, with the full depth range.
+    pub fn new(origin_x: f64, origin_y: f64, width: f64, height: f64) -> Self {
+        MetalViewport { origin_x, origin_y, width, height, znear: 0.0, zfar: 1.0 }
+    }
+    /// A viewport over the whole of an attachment of the given size.
+    pub fn full(width: NSUInteger, height: NSUInteger) -> Self {
+        MetalViewport::new(0.0, 0.0, width as f64, height as f64)
+    }
+    /// Returns the viewport with the given depth range.
+    pub fn with_depth_range(self, znear: f64, zfar: f64) -> Self {
+        MetalViewport { znear, zfar, ..self }
+    }
+}
+
+// From System/Library/Frameworks/Metal.framework/Headers/MTLRenderCommandEncoder.h:
+// typedef struct {
+//     NSUInteger x, y, width, height;
+// } MTLScissorRect;
+/// The area of the attachment that fragments are kept in; the rest are discarded.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct MetalScissorRect {
+    /// The left edge, in pixels.
+    pub x: NSUInteger,
+    /// The top edge, in pixels.
+    pub y: NSUInteger,
+    /// The width, in pixels.
+    pub width: NSUInteger,
+    /// The height, in pixels.
+    pub height: NSUInteger,
+}
+impl MetalScissorRect {
+    /// A scissor rectangle over the given area.
+    pub fn new(x: NSUInteger, y: NSUInteger, width: NSUInteger, height: NSUInteger) -> Self {
+        MetalScissorRect { x, y, width, height }
+    }
+    /// A scissor rectangle over the whole of an attachment of the given size.
+    pub fn full(width: NSUInteger, height: NSUInteger) -> Self {
+        MetalScissorRect::new(0, 0, width, height)
+    }
+    /// Returns the part of the rectangle inside an attachment of the given size.
+    ///
+    /// Metal rejects scissor rectangles that reach outside the attachment,
+    /// so rectangles worked out from window or UI coordinates should be clamped first.
+    /// A rectangle wholly outside the attachment becomes an empty one at its edge.
+    pub fn clamped_to(&self, width: NSUInteger, height: NSUInteger) -> Self {
+        let x = self.x.min(width);
+        let y = self.y.min(height);
+        MetalScissorRect {
+            x,
+            y,
+            width: self.width.min(width - x),
+            height: self.height.min(height - y),
+        }
+    }
+    /// Returns true if the rectangle covers no pixels.
+    pub fn is_empty(&self) -> bool { self.width == 0 || self.height == 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use cocoa::foundation::NSUInteger;
+    use crate::*;
+
+    #[test]
+    fn test_viewport_constructors() {
+        let viewport = MetalViewport::full(800, 600);
+        assert_eq!(MetalViewport::new(0.0, 0.0, 800.0, 600.0), viewport);
+        assert_eq!((0.0, 1.0), (viewport.znear, viewport.zfar));
+        let viewport = MetalViewport::new(400.0, 0.0, 400.0, 600.0).with_depth_range(1.0, 0.0);
+        assert_eq!(400.0, viewport.origin_x);
+        assert_eq!((1.0, 0.0), (viewport.znear, viewport.zfar));
+    }
+
+    #[test]
+    fn test_scissor_inside_attachment_is_unchanged() {
+        let rect = MetalScissorRect::new(10, 20, 100, 50);
+        assert_eq!(rect, rect.clamped_to(800, 600));
+        assert_eq!(MetalScissorRect::full(800, 600), MetalScissorRect::full(800, 600).clamped_to(800, 600));
+        assert!(!rect.is_empty());
+    }
+
+    #[test]
+    fn test_scissor_is_clamped_to_attachment() {
+        assert_eq!(MetalScissorRect::new(700, 500, 100, 100), MetalScissorRect::new(700, 500, 300, 200).clamped_to(800, 600));
+        assert_eq!(MetalScissorRect::full(800, 600), MetalScissorRect::new(0, 0, NSUInteger::MAX, NSUInteger::MAX).clamped_to(800, 600));
+        let outside = MetalScissorRect::new(900, 650, 10, 10).clamped_to(800, 600);
+        assert_eq!(MetalScissorRect::new(800, 600, 0, 0), outside);
+        assert!(outside.is_empty());
+    }
+}
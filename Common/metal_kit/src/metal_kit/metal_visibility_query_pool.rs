@@ -0,0 +1,273 @@
+//
+//  metal_visibility_query_pool.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Occlusion query slots in a visibility result buffer,
+//! handed out per frame and read back once the frame's work has completed
+
+use cocoa::base::nil;
+use cocoa::foundation::NSUInteger;
+use std::mem::size_of;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::metal_kit::metal_buffer::MetalBuffer;
+use crate::metal_kit::metal_buffer_storage::BufferStorage;
+use crate::metal_kit::metal_device::MetalDevice;
+use crate::metal_kit::metal_error::MetalError;
+use crate::metal_kit::metal_resource_options::MetalResourceOptions;
+
+/// Metal writes each visibility result as a 64-bit value, at a multiple of 8 bytes.
+pub const VISIBILITY_RESULT_SIZE: NSUInteger = size_of::<u64>() as NSUInteger;
+
+/// One query slot, to pass to `begin_visibility_query` before the draws it counts.
+///
+/// Only a pool hands out queries, and a query is only valid until its frame is recycled.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct VisibilityQuery {
+    frame: usize,
+    index: usize,
+    generation: u64,
+    offset: NSUInteger,
+}
+impl VisibilityQuery {
+    /// Gets the frame the slot belongs to.
+    pub fn frame(&self) -> usize { self.frame }
+    /// Gets the index of the slot within its frame.
+    pub fn index(&self) -> usize { self.index }
+    /// Gets the offset of the slot in the visibility result buffer, in bytes.
+    pub fn offset(&self) -> NSUInteger { self.offset }
+}
+
+/// A visibility result buffer split into one group of query slots per frame in flight.
+///
+/// Each frame calls `begin_frame()`, which recycles the oldest group,
+/// then `allocate_query()` for each object whose visibility it wants to know.
+/// After committing, it calls `end_frame()`, and when the command buffer has completed,
+/// `complete_frame()` with the same frame index (this only needs `&self`);
+/// the frame's results can then be read until the group is recycled.
+pub struct VisibilityQueryPool<S: BufferStorage = MetalBuffer> {
+    storage: S,
+    queries_per_frame: usize,
+    current_frame: usize,
+    allocated: Vec<usize>,
+    generations: Vec<u64>,
+    in_flight: Vec<AtomicBool>,
+}
+impl VisibilityQueryPool {
+    /// Creates a shared Metal buffer with `queries_per_frame` slots for each of `frame_count` frames.
+    pub fn new(device: &mut MetalDevice, frame_count: usize, queries_per_frame: usize) -> Result<Self, MetalError> {
+        let length = Self::required_length(frame_count, queries_per_frame)?;
+        let buffer = device.new_buffer_with_length_and_options(length, MetalResourceOptions::SHARED);
+        if buffer.to_objc() == nil {
+            return Err(MetalError::BufferCreation(length));
+        }
+        Self::with_storage(buffer, frame_count, queries_per_frame)
+    }
+}
+impl<S: BufferStorage> VisibilityQueryPool<S> {
+    /// Returns the number of bytes needed for the given numbers of frames and queries.
+    pub fn required_length(frame_count: usize, queries_per_frame: usize) -> Result<NSUInteger, MetalError> {
+        if frame_count == 0 {
+            return Err(MetalError::InvalidSlotCount(frame_count));
+        }
+        if queries_per_frame == 0 {
+            return Err(MetalError::InvalidSlotCount(queries_per_frame));
+        }
+        Self::slots_length(frame_count, queries_per_frame)
+    }
+    /// Splits existing storage into `queries_per_frame` slots for each of `frame_count` frames.
+    ///
+    /// The pool starts before its first frame, so call `begin_frame()` before allocating.
+    pub fn with_storage(storage: S, frame_count: usize, queries_per_frame: usize) -> Result<Self, MetalError> {
+        let required = Self::required_length(frame_count, queries_per_frame)?;
+        let length = storage.length();
+        if length < required || storage.contents().is_null() {
+            return Err(MetalError::BufferTooSmall { required, length });
+        }
+        Ok(VisibilityQueryPool {
+            storage,
+            queries_per_frame,
+            current_frame: frame_count - 1,
+            allocated: vec![0; frame_count],
+            generations: vec![0; frame_count],
+            in_flight: (0..frame_count).map(|_| AtomicBool::new(false)).collect(),
+        })
+    }
+    /// Gets the storage, e.g. to set it as a render pass's visibility result buffer.
+    pub fn get_storage(&self) -> &S { &self.storage }
+    /// Gets the number of frames that can be in flight at once.
+    pub fn get_frame_count(&self) -> usize { self.allocated.len() }
+    /// Gets the number of query slots each frame has.
+    pub fn get_queries_per_frame(&self) -> usize { self.queries_per_frame }
+    /// Gets the index of the frame that queries are being allocated for.
+    pub fn current_frame(&self) -> usize { self.current_frame }
+    /// Gets the number of queries allocated for the given frame.
+    pub fn allocated_count(&self, frame: usize) -> usize { self.allocated.get(frame).copied().unwrap_or(0) }
+    /// Returns true if the given frame has ended but has not yet completed.
+    pub fn is_in_flight(&self, frame: usize) -> bool {
+        self.in_flight.get(frame).map_or(false, |flag| flag.load(Ordering::Acquire))
+    }
+    /// Moves on to the next frame's group of slots, clearing its old results,
+    /// and returns its index.
+    ///
+    /// Fails if the GPU may still be writing to that group.
+    pub fn begin_frame(&mut self) -> Result<usize, MetalError> {
+        let frame = (self.current_frame + 1) % self.allocated.len();
+        if self.is_in_flight(frame) {
+            return Err(MetalError::VisibilityFrameInFlight(frame));
+        }
+        let offset = self.frame_offset(frame)?;
+        let length = Self::slots_length(1, self.queries_per_frame)?;
+        unsafe { self.storage.contents().add(offset as usize).write_bytes(0, length as usize) }
+        self.storage.did_modify_range(offset, length);
+        self.allocated[frame] = 0;
+        self.generations[frame] += 1;
+        self.current_frame = frame;
+        Ok(frame)
+    }
+    /// Hands out the next free slot of the current frame.
+    pub fn allocate_query(&mut self) -> Result<VisibilityQuery, MetalError> {
+        let frame = self.current_frame;
+        let index = self.allocated[frame];
+        if index == self.queries_per_frame || self.is_in_flight(frame) {
+            return Err(MetalError::VisibilityQueryPoolExhausted(self.queries_per_frame));
+        }
+        let offset = self.frame_offset(frame)? + Self::slots_length(1, index)?;
+        self.allocated[frame] += 1;
+        Ok(VisibilityQuery { frame, index, generation: self.generations[frame], offset })
+    }
+    /// Marks the current frame as handed to the GPU, and returns its index.
+    pub fn end_frame(&mut self) -> usize {
+        self.in_flight[self.current_frame].store(true, Ordering::Release);
+        self.current_frame
+    }
+    /// Marks the given frame as completed by the GPU, so its results can be read.
+    /// Call this from the frame's command buffer completed handler, or after waiting for it.
+    pub fn complete_frame(&self, frame: usize) {
+        if let Some(flag) = self.in_flight.get(frame) {
+            flag.store(false, Ordering::Release);
+        }
+    }
+    /// Reads the result of one query: in boolean mode, non-zero if any samples passed;
+    /// in counting mode, the number that did.
+    ///
+    /// Fails if the query's frame has been recycled since the query was allocated.
+    pub fn result(&self, query: &VisibilityQuery) -> Result<u64, MetalError> {
+        let frame_offset = self.frame_offset(query.frame)?;
+        if self.generations[query.frame] != query.generation {
+            return Err(MetalError::StaleVisibilityQuery(query.frame));
+        }
+        if self.is_in_flight(query.frame) {
+            return Err(MetalError::VisibilityFrameInFlight(query.frame));
+        }
+        let count = self.allocated_count(query.frame);
+        if query.index >= count {
+            return Err(MetalError::BufferIndexOutOfBounds { index: query.index, count });
+        }
+        let offset = frame_offset + Self::slots_length(1, query.index)?;
+        Ok(unsafe { (self.storage.contents().add(offset as usize) as *const u64).read() })
+    }
+    /// Reads the results of all the queries allocated for the given frame, in allocation order.
+    pub fn results(&self, frame: usize) -> Result<Vec<u64>, MetalError> {
+        let offset = self.frame_offset(frame)?;
+        if self.is_in_flight(frame) {
+            return Err(MetalError::VisibilityFrameInFlight(frame));
+        }
+        let first = unsafe { self.storage.contents().add(offset as usize) as *const u64 };
+        Ok((0..self.allocated_count(frame)).map(|index| unsafe { first.add(index).read() }).collect())
+    }
+    /// Gets the offset in bytes of the first slot of the given frame.
+    fn frame_offset(&self, frame: usize) -> Result<NSUInteger, MetalError> {
+        let count = self.get_frame_count();
+        if frame >= count {
+            return Err(MetalError::BufferIndexOutOfBounds { index: frame, count });
+        }
+        Self::slots_length(frame, self.queries_per_frame)
+    }
+    /// Gets the number of bytes taken by `queries_per_frame` slots for each of `frame_count` frames.
+    fn slots_length(frame_count: usize, queries_per_frame: usize) -> Result<NSUInteger, MetalError> {
+        frame_count.checked_mul(queries_per_frame)
+            .and_then(|slots| (slots as NSUInteger).checked_mul(VISIBILITY_RESULT_SIZE))
+            .ok_or(MetalError::BufferLengthOverflow(frame_count.saturating_mul(queries_per_frame)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    fn pool(frame_count: usize, queries_per_frame: usize) -> VisibilityQueryPool<HostBuffer> {
+        let length = VisibilityQueryPool::<HostBuffer>::required_length(frame_count, queries_per_frame).unwrap();
+        VisibilityQueryPool::with_storage(HostBuffer::new(length as usize), frame_count, queries_per_frame).unwrap()
+    }
+
+    #[test]
+    fn test_queries_are_allocated_per_frame() {
+        let mut pool = pool(3, 2);
+        assert_eq!(Ok(0), pool.begin_frame());
+        let first = pool.allocate_query().unwrap();
+        assert_eq!((0, 0, 0), (first.frame(), first.index(), first.offset()));
+        let second = pool.allocate_query().unwrap();
+        assert_eq!((0, 1, 8), (second.frame(), second.index(), second.offset()));
+        assert_eq!(Err(MetalError::VisibilityQueryPoolExhausted(2)), pool.allocate_query());
+        assert_eq!(0, pool.end_frame());
+        assert_eq!(Ok(1), pool.begin_frame());
+        let third = pool.allocate_query().unwrap();
+        assert_eq!((1, 0, 16), (third.frame(), third.index(), third.offset()));
+        assert_eq!(2, pool.allocated_count(0));
+        assert_eq!(1, pool.allocated_count(1));
+        assert_eq!(Err(MetalError::InvalidSlotCount(0)), VisibilityQueryPool::<HostBuffer>::required_length(2, 0));
+        assert_eq!(
+            Err(MetalError::BufferLengthOverflow(usize::MAX / 16 * 4)),
+            VisibilityQueryPool::<HostBuffer>::required_length(usize::MAX / 16, 4)
+        );
+    }
+
+    #[test]
+    fn test_frames_in_flight_are_not_recycled() {
+        let mut pool = pool(2, 4);
+        assert_eq!(Ok(0), pool.begin_frame());
+        pool.end_frame();
+        assert_eq!(Ok(1), pool.begin_frame());
+        pool.end_frame();
+        assert!(pool.is_in_flight(0));
+        assert_eq!(Err(MetalError::VisibilityFrameInFlight(0)), pool.begin_frame());
+        assert_eq!(Err(MetalError::VisibilityFrameInFlight(1)), pool.results(1));
+        pool.complete_frame(0);
+        assert_eq!(Ok(0), pool.begin_frame());
+        assert!(pool.is_in_flight(1));
+        assert_eq!(0, pool.allocated_count(0));
+    }
+
+    #[test]
+    fn test_results_are_read_back_and_cleared_on_reuse() {
+        let mut pool = pool(2, 3);
+        pool.begin_frame().unwrap();
+        let visible = pool.allocate_query().unwrap();
+        let hidden = pool.allocate_query().unwrap();
+        let frame = pool.end_frame();
+        // Stand in for the GPU, which counts the samples that pass.
+        unsafe { (pool.get_storage().contents().add(visible.offset() as usize) as *mut u64).write(120) };
+        assert_eq!(Err(MetalError::VisibilityFrameInFlight(frame)), pool.result(&visible));
+        pool.complete_frame(frame);
+        assert_eq!(Ok(120), pool.result(&visible));
+        assert_eq!(Ok(0), pool.result(&hidden));
+        assert_eq!(Ok(vec![120, 0]), pool.results(frame));
+
+        pool.begin_frame().unwrap();
+        pool.end_frame();
+        assert_eq!(Ok(0), pool.begin_frame());
+        assert_eq!(Ok(vec![]), pool.results(0));
+        assert_eq!(Err(MetalError::BufferIndexOutOfBounds { index: 2, count: 2 }), pool.results(2));
+        assert_eq!(0..24, pool.get_storage().take_modified_ranges().pop().unwrap());
+        let reused = pool.allocate_query().unwrap();
+        assert_eq!(visible.offset(), reused.offset());
+        assert_eq!(Ok(0), pool.result(&reused));
+        // The queries of the recycled frame no longer read its slots.
+        assert_eq!(Err(MetalError::StaleVisibilityQuery(0)), pool.result(&visible));
+        assert_eq!(Err(MetalError::StaleVisibilityQuery(0)), pool.result(&hidden));
+    }
+}
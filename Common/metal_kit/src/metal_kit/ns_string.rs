@@ -0,0 +1,64 @@
+//
+//  ns_string.rs
+//
+//  Created by TR Solutions on 2026-10-19.
+//  Copyright © 2026 TR Solutions Pte. Ltd.
+//  Licensed under Apache 2.0 and MIT
+//  See appropriate LICENCE files for details.
+//
+//! Helpers for moving strings and paths between Rust and Foundation
+
+use objc::msg_send;
+use objc::sel;
+use objc::sel_impl;
+use objc::class;
+use cocoa::base::{id, nil};
+use cocoa::foundation::NSString;
+use objc::runtime::objc_release;
+use std::ffi::CStr;
+use std::path::Path;
+
+/// Creates a new NSString with the contents of the given Rust string.
+pub(crate) fn ns_string_from_str(string: &str) -> id {
+    unsafe { NSString::alloc(nil).init_str(string) }
+}
+
+/// Copies the contents of the given NSString into an owned Rust string.
+///
+/// A nil NSString gives an empty Rust string.
+pub(crate) fn rust_string_from_ns_string(string: id) -> String {
+    if string == nil {
+        return String::new();
+    }
+    let bytes = unsafe { string.UTF8String() };
+    if bytes.is_null() {
+        return String::new();
+    }
+    unsafe { CStr::from_ptr(bytes) }.to_string_lossy().into_owned()
+}
+
+/// Sets the `label` property that Metal objects show in debugging tools.
+pub(crate) fn set_objc_label(object: id, label: &str) {
+    let label = ns_string_from_str(label);
+    unsafe {
+        let _:() = msg_send![object, setLabel:label];
+        objc_release(label);
+    }
+}
+
+/// Gets the `label` property of a Metal object (empty if none was set).
+pub(crate) fn get_objc_label(object: id) -> String {
+    let label: id = unsafe { msg_send![object, label] };
+    rust_string_from_ns_string(label)
+}
+
+/// Creates an autoreleased NSURL for the file at the given path.
+pub(crate) fn file_url_from_path(path: &Path) -> id {
+    let path_string = ns_string_from_str(&path.to_string_lossy());
+    let url_class = class!(NSURL);
+    unsafe {
+        let url: id = msg_send![url_class, fileURLWithPath:path_string];
+        objc_release(path_string);
+        url
+    }
+}